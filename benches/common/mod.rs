@@ -0,0 +1,15 @@
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+/// Runs a future to completion, the synthetic transports of the benchmarks never wait.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            return output;
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Measures the transmit path of QoS 0 publishes of various sizes.
+//!
+//! - `send`: [`Client::send`](miniqtt::Client::send) with a contiguous payload.
+//! - `vectored`: [`Client::publish_vectored`](miniqtt::Client::publish_vectored), with the
+//!   payload split into four slices.
+//! - `staged`: Staging through a [`Staging`] slot, which serializes the publish into the slot,
+//!   and sending it with [`Client::send_staged`](miniqtt::Client::send_staged).
+//!
+//! Before measuring, the amount of writes and bytes per publish are printed for every method.
+//! Every write is a call into the transport, usually a syscall or a transfer to the network
+//! stack, fewer writes per publish means better coalescing.
+
+use std::cell::Cell;
+use std::convert::Infallible;
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use miniqtt::client::Staging;
+
+mod common;
+
+/// Payload sizes of the measured publishes.
+const SIZES: [usize; 4] = [16, 256, 1024, 4096];
+
+/// Writes and bytes written to a [`Counting`] transport.
+#[derive(Debug, Default)]
+struct Counters {
+    writes: Cell<usize>,
+    bytes: Cell<usize>,
+}
+
+/// A transport discarding all written data, counting writes and bytes.
+struct Counting<'a>(&'a Counters);
+
+impl embedded_io_async::ErrorType for Counting<'_> {
+    type Error = Infallible;
+}
+
+impl embedded_io_async::Read for Counting<'_> {
+    async fn read(&mut self, _buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(0)
+    }
+}
+
+impl embedded_io_async::Write for Counting<'_> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.0.writes.set(self.0.writes.get() + 1);
+        self.0.bytes.set(self.0.bytes.get() + buf.len());
+        Ok(buf.len())
+    }
+}
+
+type Client<'a> = miniqtt::Client<Counting<'a>, [u8; 16]>;
+
+fn client(counters: &Counters) -> Client<'_> {
+    miniqtt::Client::new(miniqtt::Connection::new(Counting(counters), [0; 16]))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Method {
+    Send,
+    Vectored,
+    Staged,
+}
+
+impl Method {
+    const ALL: [Self; 3] = [Self::Send, Self::Vectored, Self::Staged];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Send => "send",
+            Self::Vectored => "vectored",
+            Self::Staged => "staged",
+        }
+    }
+
+    fn publish(self, client: &mut Client<'_>, staging: &Staging<8192>, payload: &[u8]) {
+        common::block_on(async {
+            match self {
+                Self::Send => client.send("sensor/value", payload).await.unwrap(),
+                Self::Vectored => {
+                    let (head, tail) = payload.split_at(payload.len() / 2);
+                    let (a, b) = head.split_at(head.len() / 2);
+                    let (c, d) = tail.split_at(tail.len() / 2);
+                    client
+                        .publish_vectored("sensor/value", &[a, b, c, d])
+                        .await
+                        .unwrap()
+                }
+                Self::Staged => {
+                    staging
+                        .handle()
+                        .try_publish_qos0("sensor/value", payload)
+                        .unwrap();
+                    assert!(client.send_staged(staging).await.unwrap());
+                }
+            }
+        })
+    }
+}
+
+fn bench_publish(c: &mut Criterion) {
+    let staging = Staging::new();
+
+    for size in SIZES {
+        let payload = vec![b'x'; size];
+        for method in Method::ALL {
+            let counters = Counters::default();
+            method.publish(&mut client(&counters), &staging, &payload);
+            println!(
+                "publish/{}/{size}: {} writes, {} bytes",
+                method.name(),
+                counters.writes.get(),
+                counters.bytes.get()
+            );
+        }
+    }
+
+    let mut group = c.benchmark_group("publish");
+    for size in SIZES {
+        let payload = vec![b'x'; size];
+        group.throughput(Throughput::Bytes(size as u64));
+
+        for method in Method::ALL {
+            let counters = Counters::default();
+            let mut client = client(&counters);
+            group.bench_function(BenchmarkId::new(method.name(), size), |b| {
+                b.iter(|| method.publish(&mut client, &staging, black_box(&payload)))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_publish);
+criterion_main!(benches);
@@ -0,0 +1,233 @@
+//! Compares strategies of managing the receive buffer, on a synthetic stream of publishes.
+//!
+//! - `compaction/*`: The client, with the [`Compaction`] strategies it implements.
+//! - `two_phase`: A model reading the fixed header first and then exactly the rest of the
+//!   packet, no data is ever moved, but it takes at least two reads per packet.
+//! - `ring`: A model of a ring buffer, data is only moved for packets wrapping around the end
+//!   of the buffer, which are copied into a scratch buffer to be parsed.
+//!
+//! The models parse packets with [`miniqtt::protocol::decode`], the same parser used by the
+//! client.
+
+use std::convert::Infallible;
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use miniqtt::client::{Compaction, Error};
+use miniqtt::protocol::decode;
+use miniqtt::protocol::v5::AnyPacket;
+
+mod common;
+
+/// Size of the receive buffer of all strategies.
+const BUFFER: usize = 1024;
+/// Amount of publishes in the stream.
+const PUBLISHES: usize = 10_000;
+
+/// Generates QoS 0 publishes with varying topic and payload sizes.
+fn stream() -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut seed = 0x2545_f491u32;
+    for i in 0..PUBLISHES {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+
+        let topic = format!("sensor/{}/value", i % 64);
+        let payload = vec![b'x'; (seed % 400) as usize];
+        let length = 2 + topic.len() + 1 + payload.len();
+
+        data.push(0x30);
+        // Variable byte integer, packets are smaller than 16 KiB.
+        match length {
+            0..128 => data.push(length as u8),
+            _ => data.extend([(length % 128) as u8 | 0x80, (length / 128) as u8]),
+        }
+        data.extend((topic.len() as u16).to_be_bytes());
+        data.extend(topic.as_bytes());
+        data.push(0);
+        data.extend(payload);
+    }
+    data
+}
+
+/// A transport returning the stream in TCP sized segments, then closing the connection.
+struct Segments<'a> {
+    data: &'a [u8],
+    segment: usize,
+}
+
+impl<'a> Segments<'a> {
+    fn new(data: &'a [u8], segment: usize) -> Self {
+        Self { data, segment }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let len = buf.len().min(self.segment).min(self.data.len());
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        len
+    }
+}
+
+impl embedded_io_async::ErrorType for Segments<'_> {
+    type Error = Infallible;
+}
+
+impl embedded_io_async::Read for Segments<'_> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(Segments::read(self, buf))
+    }
+}
+
+impl embedded_io_async::Write for Segments<'_> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(buf.len())
+    }
+}
+
+/// Returns the length of the packet starting with `data`, if the fixed header is complete.
+fn packet_len(data: &[u8]) -> Option<usize> {
+    let mut length = 0;
+    for (i, &byte) in data.iter().enumerate().skip(1).take(4) {
+        length |= usize::from(byte & 0x7f) << (7 * (i - 1));
+        if byte & 0x80 == 0 {
+            return Some(i + 1 + length);
+        }
+    }
+    None
+}
+
+fn consume(packet: AnyPacket<'_>) -> usize {
+    match packet {
+        AnyPacket::Publish(publish) => black_box(publish.payload).len(),
+        _ => unreachable!("the stream only contains publishes"),
+    }
+}
+
+fn receive_client(data: &[u8], segment: usize, compaction: Compaction) -> usize {
+    let connection = miniqtt::Connection::builder(Segments::new(data, segment), [0; BUFFER])
+        .compaction(compaction)
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+
+    common::block_on(async {
+        let mut received = 0;
+        loop {
+            match client.receive().await {
+                Ok(message) => received += black_box(message.payload).len(),
+                Err(Error::Disconnected) => return received,
+                Err(err) => panic!("receive failed: {err:?}"),
+            }
+        }
+    })
+}
+
+fn receive_two_phase(data: &[u8], segment: usize) -> usize {
+    let mut transport = Segments::new(data, segment);
+    let mut buffer = [0; BUFFER];
+
+    let mut received = 0;
+    loop {
+        // The fixed header is between 2 and 5 bytes, read byte by byte after the first two.
+        let mut filled = 0;
+        let len = loop {
+            let want = if filled < 2 { 2 } else { filled + 1 };
+            let r = transport.read(&mut buffer[filled..want]);
+            if r == 0 {
+                return received;
+            }
+            filled += r;
+            if let Some(len) = packet_len(&buffer[..filled]) {
+                break len;
+            }
+        };
+        while filled < len {
+            filled += transport.read(&mut buffer[filled..len]);
+        }
+
+        let (_, packet) = decode(&buffer[..len]).unwrap();
+        received += consume(packet);
+    }
+}
+
+fn receive_ring(data: &[u8], segment: usize) -> usize {
+    let mut transport = Segments::new(data, segment);
+    let mut buffer = [0; BUFFER];
+    let mut scratch = [0; BUFFER];
+    // Absolute positions, the index into the buffer is the position modulo its length.
+    let (mut head, mut tail) = (0usize, 0usize);
+
+    let mut received = 0;
+    loop {
+        // Parse all complete packets.
+        loop {
+            let start = head % BUFFER;
+            let available = tail - head;
+            let contiguous = available.min(BUFFER - start);
+
+            // Only the fixed header may need to be copied to learn the packet length.
+            let mut header = [0; 5];
+            for (i, byte) in header.iter_mut().enumerate().take(available) {
+                *byte = buffer[(head + i) % BUFFER];
+            }
+            let Some(len) = packet_len(&header[..available.min(5)]) else {
+                break;
+            };
+            if len > available {
+                break;
+            }
+
+            let packet = match len <= contiguous {
+                true => &buffer[start..start + len],
+                false => {
+                    scratch[..contiguous].copy_from_slice(&buffer[start..]);
+                    scratch[contiguous..len].copy_from_slice(&buffer[..len - contiguous]);
+                    &scratch[..len]
+                }
+            };
+            let (_, packet) = decode(packet).unwrap();
+            received += consume(packet);
+            head += len;
+        }
+
+        let end = tail % BUFFER;
+        let free = BUFFER - (tail - head);
+        let r = transport.read(&mut buffer[end..BUFFER.min(end + free)]);
+        if r == 0 {
+            return received;
+        }
+        tail += r;
+    }
+}
+
+fn bench_receive(c: &mut Criterion) {
+    let data = stream();
+    let expected = receive_client(&data, usize::MAX, Compaction::Eager);
+
+    let mut group = c.benchmark_group("receive");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    for segment in [64, 1460] {
+        let strategies: [(&str, &dyn Fn() -> usize); 4] = [
+            ("compaction/eager", &|| {
+                receive_client(&data, segment, Compaction::Eager)
+            }),
+            ("compaction/lazy", &|| {
+                receive_client(&data, segment, Compaction::Lazy)
+            }),
+            ("two_phase", &|| receive_two_phase(&data, segment)),
+            ("ring", &|| receive_ring(&data, segment)),
+        ];
+
+        for (name, receive) in strategies {
+            assert_eq!(receive(), expected, "{name} must receive all payloads");
+            group.bench_function(BenchmarkId::new(name, segment), |b| b.iter(receive));
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_receive);
+criterion_main!(benches);
@@ -0,0 +1,110 @@
+//! Command line arguments and connection setup shared by the `miniqtt-pub` and `miniqtt-sub`
+//! examples.
+use std::error::Error;
+
+use embedded_io_adapters::tokio_1::FromTokio;
+use miniqtt::protocol::{QoS, v5};
+use tokio::net::TcpStream;
+
+pub type Client<'a> = miniqtt::Client<FromTokio<&'a mut TcpStream>, Vec<u8>>;
+
+pub struct Args {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub keep_alive: u16,
+    pub will_topic: Option<String>,
+    pub will_payload: String,
+    pub will_qos: QoS,
+    pub will_retain: bool,
+    pub topic: Option<String>,
+    pub qos: QoS,
+}
+
+impl Args {
+    /// Parses the options shared by the examples.
+    ///
+    /// Arguments which are not shared are passed to `extra`, together with a function which
+    /// returns the value of the argument. `extra` returns `false` for unknown arguments.
+    pub fn parse<F>(client_id: &str, mut extra: F) -> Result<Self, Box<dyn Error>>
+    where
+        F: FnMut(&str, &mut dyn FnMut() -> Result<String, String>) -> Result<bool, Box<dyn Error>>,
+    {
+        let mut result = Self {
+            host: "127.0.0.1".to_owned(),
+            port: 1883,
+            client_id: client_id.to_owned(),
+            username: None,
+            password: None,
+            keep_alive: 60,
+            will_topic: None,
+            will_payload: String::new(),
+            will_qos: QoS::AtMostOnce,
+            will_retain: false,
+            topic: None,
+            qos: QoS::AtMostOnce,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {arg}"))
+            };
+            match arg.as_str() {
+                "-h" => result.host = value()?,
+                "-p" => result.port = value()?.parse()?,
+                "-i" => result.client_id = value()?,
+                "-u" => result.username = Some(value()?),
+                "-P" => result.password = Some(value()?),
+                "-k" => result.keep_alive = value()?.parse()?,
+                "--will-topic" => result.will_topic = Some(value()?),
+                "--will-payload" => result.will_payload = value()?,
+                "--will-qos" => result.will_qos = parse_qos(&value()?)?,
+                "--will-retain" => result.will_retain = true,
+                "-t" => result.topic = Some(value()?),
+                "-q" => result.qos = parse_qos(&value()?)?,
+                _ if extra(&arg, &mut value)? => {}
+                _ => return Err(format!("unknown argument {arg}").into()),
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Connects `client` with the configured client id, credentials, keep alive and will.
+    pub async fn connect(&self, client: &mut Client<'_>) -> Result<(), Box<dyn Error>> {
+        let mut connect = client.connect(&self.client_id).keep_alive(self.keep_alive);
+        if let Some(username) = &self.username {
+            connect = connect.with_username(username.as_str());
+        }
+        if let Some(password) = &self.password {
+            connect = connect.with_password(password.as_str());
+        }
+        if let Some(topic) = &self.will_topic {
+            connect = connect.with_will(v5::Will {
+                retain: self.will_retain,
+                qos: self.will_qos,
+                properties: &[],
+                topic,
+                payload: self.will_payload.as_bytes(),
+            });
+        }
+        if !connect.await?.successful() {
+            return Err("connection refused by broker".into());
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_qos(qos: &str) -> Result<QoS, Box<dyn Error>> {
+    match qos {
+        "0" => Ok(QoS::AtMostOnce),
+        "1" => Ok(QoS::AtLeastOnce),
+        "2" => Ok(QoS::ExactlyOnce),
+        qos => Err(format!("unsupported qos {qos}").into()),
+    }
+}
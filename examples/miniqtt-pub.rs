@@ -0,0 +1,59 @@
+//! Publishes a single message, similar to `mosquitto_pub`.
+//!
+//! Usage: `cargo run --example miniqtt-pub --features std -- -t <topic> -m <message> [options]`
+//!
+//! Options:
+//!  -h <host>                 broker host, defaults to `127.0.0.1`
+//!  -p <port>                 broker port, defaults to `1883`
+//!  -i <id>                   client id, defaults to `miniqtt-pub`
+//!  -u <username>             username used for authentication
+//!  -P <password>             password used for authentication
+//!  -k <seconds>              keep alive interval, defaults to `60`
+//!  --will-topic <topic>      topic of the will message, published if the client goes away
+//!  --will-payload <payload>  payload of the will message, defaults to an empty payload
+//!  --will-qos <qos>          quality of service of the will message, defaults to `0`
+//!  --will-retain             retain the will message
+//!  -q <qos>                  quality of service, `0`, `1` or `2`, defaults to `0`
+//!  -r                        retain the message
+//!
+//! TLS is not supported, the example connects through plain TCP. Use a TLS transport, e.g. from
+//! `tokio-rustls`, or a local TLS proxy like `stunnel` to connect to a TLS listener.
+use std::error::Error;
+
+use tokio::net::TcpStream;
+
+mod common;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::builder().format_timestamp(None).init();
+
+    let mut message = None;
+    let mut retain = false;
+    let args = common::Args::parse("miniqtt-pub", |arg, value| {
+        match arg {
+            "-m" => message = Some(value()?),
+            "-r" => retain = true,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let topic = args.topic.as_deref().ok_or("missing topic (-t)")?;
+    let message = message.ok_or("missing message (-m)")?;
+
+    let mut stream = TcpStream::connect((args.host.as_str(), args.port)).await?;
+    let stream = embedded_io_adapters::tokio_1::FromTokio::new(&mut stream);
+
+    let connection = miniqtt::Connection::new(stream, Vec::new());
+    let mut client = miniqtt::Client::new(connection);
+    args.connect(&mut client).await?;
+
+    client
+        .publish(topic, message.as_bytes())
+        .qos(args.qos)
+        .retain(retain)
+        .await?;
+    client.disconnect().await?;
+
+    Ok(())
+}
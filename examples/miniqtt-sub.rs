@@ -0,0 +1,60 @@
+//! Subscribes to a topic and prints all received messages, similar to `mosquitto_sub -v`.
+//!
+//! Usage: `cargo run --example miniqtt-sub --features std -- -t <topic> [options]`
+//!
+//! Options:
+//!  -h <host>                 broker host, defaults to `127.0.0.1`
+//!  -p <port>                 broker port, defaults to `1883`
+//!  -i <id>                   client id, defaults to `miniqtt-sub`
+//!  -u <username>             username used for authentication
+//!  -P <password>             password used for authentication
+//!  -k <seconds>              keep alive interval, defaults to `60`
+//!  -q <qos>                  maximum quality of service of the subscription, defaults to `0`
+//!  --will-topic <topic>      topic of the will message, published if the client goes away
+//!  --will-payload <payload>  payload of the will message, defaults to an empty payload
+//!  --will-qos <qos>          quality of service of the will message, defaults to `0`
+//!  --will-retain             retain the will message
+//!
+//! TLS is not supported, the example connects through plain TCP. Use a TLS transport, e.g. from
+//! `tokio-rustls`, or a local TLS proxy like `stunnel` to connect to a TLS listener.
+use std::error::Error;
+use std::time::Duration;
+
+use miniqtt::client::{ReceiveOr, StdClock};
+use tokio::net::TcpStream;
+
+mod common;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::builder().format_timestamp(None).init();
+
+    let args = common::Args::parse("miniqtt-sub", |_, _| Ok(false))?;
+    let topic = args.topic.as_deref().ok_or("missing topic (-t)")?;
+
+    let mut stream = TcpStream::connect((args.host.as_str(), args.port)).await?;
+    let stream = embedded_io_adapters::tokio_1::FromTokio::new(&mut stream);
+
+    // The clock tells when a PINGREQ is due, which keeps the idle connection alive.
+    let connection = miniqtt::Connection::builder(stream, Vec::new())
+        .with_clock(&StdClock)
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+    args.connect(&mut client).await?;
+
+    client.subscribe(topic).qos(args.qos).await?;
+    loop {
+        let next_ping_in = client.next_ping_in().unwrap_or(Duration::from_secs(60));
+        if let ReceiveOr::Message(message) =
+            client.receive_or(tokio::time::sleep(next_ping_in)).await?
+        {
+            println!(
+                "{} {}",
+                message.topic,
+                String::from_utf8_lossy(message.payload)
+            );
+        }
+
+        client.keep_alive_tick().await?;
+    }
+}
@@ -0,0 +1,141 @@
+//! Proves the packet decoding paths and staging a publish cannot panic.
+//!
+//! Every checked function holds a guard, which references an undefined symbol when it is
+//! dropped. The guard is only dropped when the function unwinds, if the optimizer cannot remove
+//! all panics from the function, the example fails to link and names the function which may
+//! panic.
+//!
+//! Usage: `RUSTFLAGS="--cfg no_panic" cargo build --profile no-panic --example no-panic`
+//!
+//! The check requires optimizations and link time optimization, without `--cfg no_panic` the
+//! guards are disabled and the example only decodes a few sample packets.
+//!
+//! Covered are the parsers of all received packets, the [`Decoder`] and
+//! [`Handle::try_publish_qos0`](miniqtt::client::Handle::try_publish_qos0), which stages a
+//! publish from interrupt context.
+//!
+//! Not covered are the receive path of a connection and
+//! [`Encoder::encode`](miniqtt::protocol::codec::Encoder::encode). Both are built from async
+//! state machines, which contain a panic for being polled after completion, and optimizations
+//! do not remove it. The receive path frames and parses packets with the checked decoder and
+//! only accesses its buffer through checked slicing.
+use std::hint::black_box;
+
+use miniqtt::client::Staging;
+use miniqtt::protocol::codec::Decoder;
+use miniqtt::protocol::types::FixedHeader;
+use miniqtt::protocol::{self, Parse, v5};
+
+/// Defines a function, which fails to link if `$body` may panic.
+macro_rules! no_panic {
+    ($name:ident, |$data:ident| $body:expr) => {
+        #[inline(never)]
+        fn $name($data: &[u8]) -> usize {
+            struct Guard;
+
+            impl Drop for Guard {
+                fn drop(&mut self) {
+                    #[cfg(no_panic)]
+                    {
+                        unsafe extern "C" {
+                            #[link_name = concat!("miniqtt_", stringify!($name), "_may_panic")]
+                            fn may_panic() -> !;
+                        }
+                        unsafe { may_panic() }
+                    }
+                }
+            }
+
+            let guard = Guard;
+            let result = $body;
+            core::mem::forget(guard);
+            result
+        }
+    };
+}
+
+/// Returns the length of a successfully parsed packet `T`, or `0`.
+fn parse<'a, T: Parse<'a>>(data: &'a [u8]) -> usize {
+    T::parse(data).map_or(0, |(len, _)| len)
+}
+
+no_panic!(fixed_header, |data| parse::<FixedHeader>(data));
+no_panic!(conn_ack, |data| parse::<v5::ConnAck>(data));
+no_panic!(publish, |data| parse::<v5::Publish>(data));
+no_panic!(pub_ack, |data| parse::<v5::PubAck>(data));
+no_panic!(pub_rec, |data| parse::<v5::PubRec>(data));
+no_panic!(pub_rel, |data| parse::<v5::PubRel>(data));
+no_panic!(pub_comp, |data| parse::<v5::PubComp>(data));
+no_panic!(ping_resp, |data| parse::<v5::PingResp>(data));
+no_panic!(disconnect, |data| parse::<v5::Disconnect>(data));
+#[cfg(feature = "auth")]
+no_panic!(auth, |data| parse::<v5::Auth>(data));
+#[cfg(feature = "subscribe")]
+no_panic!(sub_ack, |data| parse::<v5::SubAck>(data));
+#[cfg(feature = "unsubscribe")]
+no_panic!(unsub_ack, |data| parse::<v5::UnsubAck>(data));
+no_panic!(decode, |data| protocol::decode(data)
+    .map_or(0, |(len, _)| len));
+no_panic!(frame_len, |data| {
+    let decoder = Decoder::new().max_packet_size(1024);
+    decoder.frame_len(data).ok().flatten().unwrap_or(0)
+});
+no_panic!(decoder, |data| {
+    let decoder = Decoder::new().max_packet_size(1024);
+    match decoder.decode::<v5::Publish>(data) {
+        Ok(Some((len, _))) => len,
+        _ => 0,
+    }
+});
+no_panic!(staging, |data| {
+    let staging = Staging::<64>::new();
+    match staging.handle().try_publish_qos0("a/b", data) {
+        Ok(()) => data.len(),
+        Err(_) => 0,
+    }
+});
+
+/// A checked function, returns the length of the parsed or staged packet or `0`.
+type Check = fn(&[u8]) -> usize;
+
+fn main() {
+    let checks: &[(&str, Check)] = &[
+        ("fixed header", fixed_header),
+        ("CONNACK", conn_ack),
+        ("PUBLISH", publish),
+        ("PUBACK", pub_ack),
+        ("PUBREC", pub_rec),
+        ("PUBREL", pub_rel),
+        ("PUBCOMP", pub_comp),
+        ("PINGRESP", ping_resp),
+        ("DISCONNECT", disconnect),
+        #[cfg(feature = "auth")]
+        ("AUTH", auth),
+        #[cfg(feature = "subscribe")]
+        ("SUBACK", sub_ack),
+        #[cfg(feature = "unsubscribe")]
+        ("UNSUBACK", unsub_ack),
+        ("decode", decode),
+        ("frame length", frame_len),
+        ("decoder", decoder),
+        ("staged publish", staging),
+    ];
+
+    let packets: &[&[u8]] = &[
+        &[0x20, 0x03, 0x00, 0x00, 0x00],
+        &[0x30, 0x07, 0x00, 0x03, b'a', b'/', b'b', 0x00, b'x'],
+        &[0x40, 0x02, 0x00, 0x01],
+        &[0xd0, 0x00],
+        &[0xe0, 0x01, 0x8e],
+        &[0xff, 0xff, 0xff, 0xff, 0xff],
+        &[],
+    ];
+
+    for (name, check) in checks {
+        let parsed = packets
+            .iter()
+            .filter(|packet| check(black_box(packet)) > 0)
+            .count();
+        println!("{name}: parsed {parsed} of {} packets", packets.len());
+    }
+}
@@ -0,0 +1,55 @@
+//! MQTT over a QUIC stream.
+//!
+//! Usage: `cargo run --example quic --features std -- <address> <server name> <ca certificate>`
+use std::sync::Arc;
+
+use embedded_io_adapters::tokio_1::FromTokio;
+use miniqtt::transport::Duplex;
+use quinn::crypto::rustls::QuicClientConfig;
+use quinn::rustls::pki_types::CertificateDer;
+use quinn::rustls::pki_types::pem::PemObject;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::builder().format_timestamp(None).init();
+
+    let mut args = std::env::args().skip(1);
+    let addr = args
+        .next()
+        .as_deref()
+        .unwrap_or("127.0.0.1:14567")
+        .parse()?;
+    let server_name = args.next().unwrap_or_else(|| "localhost".to_owned());
+    let ca = args.next().unwrap_or_else(|| "ca.pem".to_owned());
+
+    let mut roots = quinn::rustls::RootCertStore::empty();
+    roots.add(CertificateDer::from_pem_file(ca)?)?;
+
+    let mut crypto = quinn::rustls::ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    crypto.alpn_protocols = vec![b"mqtt".to_vec()];
+
+    let mut endpoint = quinn::Endpoint::client("0.0.0.0:0".parse()?)?;
+    endpoint.set_default_client_config(quinn::ClientConfig::new(Arc::new(
+        QuicClientConfig::try_from(crypto)?,
+    )));
+
+    let quic = endpoint.connect(addr, &server_name)?.await?;
+    let (send, recv) = quic.open_bi().await?;
+
+    // A QUIC stream is split into a send and a receive half, joined into a single transport.
+    let stream = Duplex::new(FromTokio::new(recv), FromTokio::new(send));
+
+    let connection = miniqtt::Connection::new(stream, [0; 128]);
+    let mut client = miniqtt::Client::new(connection);
+
+    client.connect("miniqtt").await?;
+    client.send("miniqtt", b"hello from quic").await?;
+    client.disconnect().await?;
+
+    quic.close(0u32.into(), b"");
+    endpoint.wait_idle().await;
+
+    Ok(())
+}
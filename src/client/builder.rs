@@ -0,0 +1,192 @@
+use crate::client::watermark::Watermarks;
+use crate::client::{Clock, Connection, DeadLetter, Recorder, Stats, Watermark};
+#[cfg(doc)]
+use crate::client::{Error, MessageGuard, Publish};
+use crate::log;
+use crate::protocol::MIN_RX_BUFFER_SIZE;
+use crate::protocol::codec::TrailingData;
+use crate::protocol::types::PacketId;
+use crate::traits::Buffer;
+
+/// When data of released packets is removed from the receive buffer of a [`Connection`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Compaction {
+    /// Moves the remaining data to the start of the buffer, whenever a packet is received.
+    #[default]
+    Eager,
+    /// Only moves the remaining data, once the buffer runs out of space.
+    ///
+    /// This avoids copying data, when many small packets are received at once.
+    Lazy,
+    /// Moves the remaining data, once more than the given amount of bytes at the start of the
+    /// buffer were consumed, or the buffer runs out of space.
+    ///
+    /// A middle ground between [`Self::Eager`] and [`Self::Lazy`], the amount of copied data is
+    /// bounded while the buffer keeps room for the next packet.
+    Threshold(usize),
+}
+
+/// Builder for a [`Connection`].
+///
+/// Created with [`Connection::builder`].
+pub struct ConnectionBuilder<C, B> {
+    connection: Connection<C, B>,
+}
+
+impl<C, B> ConnectionBuilder<C, B> {
+    pub(super) fn new(connection: Connection<C, B>) -> Self {
+        Self { connection }
+    }
+
+    /// Limits the size of received packets, including their fixed header.
+    ///
+    /// Receiving a larger packet fails with [`Error::PacketTooLarge`]. This bounds the growth of
+    /// resizable buffers. The limit should also be announced to the server with the
+    /// [`MaximumPacketSize`](crate::protocol::v5::ConnectProperty::MaximumPacketSize)
+    /// property, which prevents the server from sending larger packets.
+    ///
+    /// Unlimited by default.
+    pub fn max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.connection.decoder = self.connection.decoder.max_packet_size(max_packet_size);
+        self
+    }
+
+    /// Configures how data following the parsed fields of a received packet is handled.
+    ///
+    /// Rejected trailing data fails receiving with [`Error::Protocol`]. Ignoring it tolerates
+    /// servers which append data to acknowledgements, at the cost of hiding parser bugs.
+    ///
+    /// Defaults to [`TrailingData::Reject`].
+    pub fn trailing_data(mut self, trailing_data: TrailingData) -> Self {
+        self.connection.decoder = self.connection.decoder.trailing_data(trailing_data);
+        self
+    }
+
+    /// Configures when released packets are removed from the receive buffer.
+    ///
+    /// Defaults to [`Compaction::Eager`].
+    pub fn compaction(mut self, compaction: Compaction) -> Self {
+        self.connection.compaction = compaction;
+        self
+    }
+
+    /// Allows publishing to topics reserved for the server, starting with `$`.
+    ///
+    /// Servers usually reject or silently drop publishes to reserved topics, like `$SYS`.
+    /// Publishing to them fails with [`Error::ReservedTopic`], unless explicitly allowed.
+    /// Subscribing to reserved topics is always possible.
+    ///
+    /// Not allowed by default.
+    pub fn allow_reserved_topics(mut self, allow: bool) -> Self {
+        self.connection.allow_reserved_topics = allow;
+        self
+    }
+
+    /// Omits payloads of sent and received packets from logs.
+    ///
+    /// Payloads are replaced with their size. Credentials, like passwords, are never logged.
+    pub fn redact_payloads(mut self, redact: bool) -> Self {
+        self.connection.redact_payloads = redact;
+        self
+    }
+
+    /// Reports when the receive buffer fills past one of the `thresholds`.
+    ///
+    /// Thresholds are configured in percent of the buffer capacity, `callback` is invoked
+    /// whenever data read from the connection fills the buffer past a threshold. This can be used
+    /// to detect undersized buffers, before they cause [`Error::InsufficientBufferSize`] errors.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use miniqtt::client::Watermark;
+    /// # fn example<C>(stream: C) {
+    /// fn report(watermark: Watermark) {
+    ///     println!("receive buffer {}% full", watermark.threshold);
+    /// }
+    ///
+    /// let connection = miniqtt::Connection::builder(stream, [0; 128])
+    ///     .with_watermarks(&[75, 90], report)
+    ///     .build();
+    /// # }
+    /// ```
+    pub fn with_watermarks(mut self, thresholds: &'static [u8], callback: fn(Watermark)) -> Self {
+        self.connection.watermarks = Some(Watermarks {
+            thresholds,
+            callback,
+        });
+        self
+    }
+
+    /// Reports [detached](Publish::detach) publishes, which are not delivered.
+    ///
+    /// `callback` is invoked with the packet identifier returned by the publish and the reason
+    /// of the PUBACK, when the server rejects a publish. Publishes which are still
+    /// unacknowledged when the client connects again are reported as [`DeadLetter::Lost`].
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use miniqtt::client::DeadLetter;
+    /// # use miniqtt::protocol::types::PacketId;
+    /// # fn example<C>(stream: C) {
+    /// fn dead_letter(identifier: PacketId, reason: DeadLetter) {
+    ///     println!("publish {identifier} not delivered: {reason:?}");
+    /// }
+    ///
+    /// let connection = miniqtt::Connection::builder(stream, [0; 128])
+    ///     .with_dead_letter(dead_letter)
+    ///     .build();
+    /// # }
+    /// ```
+    pub fn with_dead_letter(mut self, callback: fn(PacketId, DeadLetter)) -> Self {
+        self.connection.detached.dead_letter = Some(callback);
+        self
+    }
+
+    /// Records the most recent frames sent and received on this connection.
+    ///
+    /// Frames which could not be parsed are recorded as well. See [`Recorder`].
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.connection.recorder = Some(recorder);
+        self
+    }
+
+    /// Continues counting from previously recorded `stats`.
+    ///
+    /// This keeps the [`Stats`] monotonic, when a client reconnects with a new connection.
+    pub fn with_stats(mut self, stats: Stats) -> Self {
+        self.connection.stats = stats;
+        self
+    }
+
+    /// Timestamps read and write activity with `clock`.
+    ///
+    /// See [`Connection::last_rx_at`] and [`Connection::last_tx_at`].
+    pub fn with_clock(mut self, clock: &'static dyn Clock) -> Self {
+        self.connection.clock = Some(clock);
+        self
+    }
+
+    /// Creates the configured connection.
+    ///
+    /// A receive buffer smaller than [`MIN_RX_BUFFER_SIZE`] is resized, a buffer which can not
+    /// be resized is logged, since it cannot hold the acknowledgements of the server.
+    pub fn build(mut self) -> Connection<C, B>
+    where
+        B: Buffer,
+    {
+        let buffer = &mut self.connection.rx_buffer;
+        while buffer.as_slice().len() < MIN_RX_BUFFER_SIZE {
+            if buffer.try_resize().is_err() {
+                log::error!(
+                    "receive buffer of {} bytes is smaller than the minimum of {MIN_RX_BUFFER_SIZE} bytes",
+                    buffer.as_slice().len()
+                );
+                break;
+            }
+        }
+
+        self.connection
+    }
+}
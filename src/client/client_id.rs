@@ -0,0 +1,88 @@
+use core::fmt;
+use core::ops::Deref;
+
+#[cfg(doc)]
+use crate::client::Client;
+
+/// A client identifier stored inline, with a capacity of `N` bytes.
+///
+/// Used to keep the identifier assigned by the server, see [`Client::assigned_client_id`],
+/// without borrowing from the receive buffer and without allocating.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClientId<const N: usize = 64> {
+    buffer: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> ClientId<N> {
+    /// Copies `id` into a new client identifier.
+    ///
+    /// Returns `None` if `id` is longer than `N` bytes.
+    pub fn new(id: &str) -> Option<Self> {
+        let mut buffer = [0; N];
+        buffer.get_mut(..id.len())?.copy_from_slice(id.as_bytes());
+        Some(Self {
+            buffer,
+            len: id.len(),
+        })
+    }
+
+    /// Returns the client identifier as string.
+    pub fn as_str(&self) -> &str {
+        let id = &self.buffer[..self.len];
+
+        #[cfg(not(feature = "forbid-unsafe"))]
+        {
+            // SAFETY: the buffer is only ever initialized from a `str`, up to `len`.
+            unsafe { core::str::from_utf8_unchecked(id) }
+        }
+        #[cfg(feature = "forbid-unsafe")]
+        {
+            // The buffer is only ever initialized from a `str`, up to `len`.
+            core::str::from_utf8(id).unwrap_or_default()
+        }
+    }
+}
+
+impl<const N: usize> Deref for ClientId<N> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> AsRef<str> for ClientId<N> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> fmt::Debug for ClientId<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> fmt::Display for ClientId<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_id() {
+        let id = ClientId::<8>::new("auto-1").unwrap();
+        assert_eq!(id.as_str(), "auto-1");
+        assert_eq!(&*id, "auto-1");
+        assert_eq!(format!("{id:?}"), r#""auto-1""#);
+
+        assert_eq!(ClientId::<8>::new("12345678").unwrap().as_str(), "12345678");
+        assert_eq!(ClientId::<8>::new("123456789"), None);
+        assert_eq!(ClientId::<8>::new("").unwrap().as_str(), "");
+    }
+}
@@ -0,0 +1,76 @@
+use core::time::Duration;
+
+#[cfg(doc)]
+use crate::client::{Client, ConnectionBuilder};
+
+/// A monotonic clock, measuring the time since an arbitrary point, e.g. the boot of the device.
+///
+/// The client has no clock of its own, a clock is configured with
+/// [`ConnectionBuilder::with_clock`]. Any `fn() -> Duration` is a clock, which makes it easy to
+/// plug in the timer of a platform or runtime, e.g. `embassy_time::Instant` or an RTC. With the
+/// `std` feature, [`StdClock`] is based on [`std::time::Instant`].
+///
+/// The clock timestamps read and write activity and schedules keep alive pings, see
+/// [`Client::keep_alive_tick`].
+///
+/// # Examples:
+///
+/// ```
+/// # use core::time::Duration;
+/// # use miniqtt::client::Clock;
+/// struct Ticks;
+///
+/// impl Clock for Ticks {
+///     fn now(&self) -> Duration {
+///         // Time since boot, from the platform's timer.
+///         # Duration::ZERO
+///     }
+/// }
+///
+/// # fn example<C>(stream: C) {
+/// let connection = miniqtt::Connection::builder(stream, [0; 128])
+///     .with_clock(&Ticks)
+///     .build();
+/// # }
+/// ```
+///
+/// A function is used as clock through a static:
+///
+/// ```
+/// # use core::time::Duration;
+/// fn uptime() -> Duration {
+///     // e.g. `Duration::from_micros(embassy_time::Instant::now().as_micros())`
+///     # Duration::ZERO
+/// }
+///
+/// static CLOCK: fn() -> Duration = uptime;
+///
+/// # fn example<C>(stream: C) {
+/// let connection = miniqtt::Connection::builder(stream, [0; 128])
+///     .with_clock(&CLOCK)
+///     .build();
+/// # }
+/// ```
+pub trait Clock {
+    /// Returns the current time.
+    fn now(&self) -> Duration;
+}
+
+impl Clock for fn() -> Duration {
+    fn now(&self) -> Duration {
+        self()
+    }
+}
+
+/// A [`Clock`] based on [`std::time::Instant`], measuring the time since it was first used.
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdClock;
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now(&self) -> Duration {
+        static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        START.get_or_init(std::time::Instant::now).elapsed()
+    }
+}
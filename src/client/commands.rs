@@ -0,0 +1,381 @@
+use core::fmt;
+use core::future::poll_fn;
+use core::task::{Poll, Waker};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[cfg(doc)]
+use crate::client::Client;
+
+/// A request to the task which drives the [`Client`], see [`CommandQueue`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Publishes a QoS 0 message, like [`Client::send`].
+    Publish { topic: String, payload: Vec<u8> },
+    /// Subscribes to a topic filter, like [`Client::subscribe`].
+    #[cfg(feature = "subscribe")]
+    Subscribe(String),
+    /// Unsubscribes from a topic filter, like [`Client::unsubscribe`].
+    #[cfg(feature = "unsubscribe")]
+    Unsubscribe(String),
+}
+
+/// A bounded queue of [`Command`]s, sent from many tasks to the task which drives the
+/// [`Client`].
+///
+/// The client is owned by a single task, which receives messages and executes commands.
+/// Other tasks issue commands through cheaply cloneable [`CommandHandle`]s. Commands are
+/// executed in order, their results are only visible to the task driving the client.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::client::{CommandQueue, ReceiveOr};
+/// # async fn example<C>(
+/// #     mut client: miniqtt::Client<C, Vec<u8>>,
+/// # ) -> miniqtt::client::Result<(), C::Error>
+/// # where
+/// #     C: embedded_io_async::Read + embedded_io_async::Write,
+/// # {
+/// let queue = CommandQueue::new(16);
+///
+/// let handle = queue.handle();
+/// std::thread::spawn(move || {
+///     miniqtt::transport::block_on(handle.publish("greeting", b"hello"))
+/// });
+///
+/// loop {
+///     let command = match client.receive_or(queue.next()).await? {
+///         ReceiveOr::Message(message) => {
+///             println!("{:?}", message.payload);
+///             continue;
+///         }
+///         ReceiveOr::Other(Some(command)) => command,
+///         // All handles are dropped.
+///         ReceiveOr::Other(None) => break,
+///     };
+///
+///     client.execute(&command).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CommandQueue {
+    shared: Arc<Shared>,
+}
+
+impl CommandQueue {
+    /// Creates a new queue, which holds up to `capacity` commands.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is `0`.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must not be zero");
+
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    queue: VecDeque::with_capacity(capacity),
+                    capacity,
+                    handles: 0,
+                    closed: false,
+                    receiver: None,
+                    senders: Vec::new(),
+                }),
+            }),
+        }
+    }
+
+    /// Returns a new handle, to send commands to this queue.
+    pub fn handle(&self) -> CommandHandle {
+        self.shared.lock().handles += 1;
+        CommandHandle {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// Receives the next command.
+    ///
+    /// Returns `None` once all handles are dropped and no commands are left.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method *is* cancel safe.
+    pub async fn next(&self) -> Option<Command> {
+        poll_fn(|cx| {
+            let mut state = self.shared.lock();
+
+            if let Some(command) = state.queue.pop_front() {
+                // Space was freed, wake all waiting senders, one of them gets the space.
+                for waker in state.senders.drain(..) {
+                    waker.wake();
+                }
+                return Poll::Ready(Some(command));
+            }
+            if state.handles == 0 {
+                return Poll::Ready(None);
+            }
+
+            state.receiver = Some(cx.waker().clone());
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Receives a command, without waiting.
+    pub fn try_next(&self) -> Option<Command> {
+        let mut state = self.shared.lock();
+        let command = state.queue.pop_front()?;
+        for waker in state.senders.drain(..) {
+            waker.wake();
+        }
+        Some(command)
+    }
+}
+
+impl Drop for CommandQueue {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.closed = true;
+        state.queue.clear();
+        for waker in state.senders.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Sends [`Command`]s to a [`CommandQueue`].
+///
+/// Handles are cheap to clone and can be used from many tasks and threads.
+#[derive(Debug)]
+pub struct CommandHandle {
+    shared: Arc<Shared>,
+}
+
+impl CommandHandle {
+    /// Queues a command, waits while the queue is full.
+    ///
+    /// Fails if the [`CommandQueue`] was dropped, returning the command.
+    pub async fn send(&self, command: Command) -> Result<(), Command> {
+        let mut command = Some(command);
+        poll_fn(|cx| {
+            // The command is put back while waiting for space.
+            match self.try_send(command.take().unwrap()) {
+                Ok(()) => Poll::Ready(Ok(())),
+                Err(TrySendError::Closed(command)) => Poll::Ready(Err(command)),
+                Err(TrySendError::Full(full)) => {
+                    let mut state = self.shared.lock();
+                    // The queue may have been drained, since the command was rejected.
+                    if state.queue.len() < state.capacity || state.closed {
+                        cx.waker().wake_by_ref();
+                    } else {
+                        state.senders.push(cx.waker().clone());
+                    }
+                    command = Some(full);
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    /// Queues a command, without waiting.
+    pub fn try_send(&self, command: Command) -> Result<(), TrySendError> {
+        let mut state = self.shared.lock();
+        if state.closed {
+            return Err(TrySendError::Closed(command));
+        }
+        if state.queue.len() >= state.capacity {
+            return Err(TrySendError::Full(command));
+        }
+
+        state.queue.push_back(command);
+        if let Some(waker) = state.receiver.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Queues a [`Command::Publish`], see [`Self::send`].
+    pub async fn publish(&self, topic: &str, payload: &[u8]) -> Result<(), Command> {
+        self.send(Command::Publish {
+            topic: topic.into(),
+            payload: payload.into(),
+        })
+        .await
+    }
+
+    /// Queues a [`Command::Subscribe`], see [`Self::send`].
+    #[cfg(feature = "subscribe")]
+    pub async fn subscribe(&self, topic: &str) -> Result<(), Command> {
+        self.send(Command::Subscribe(topic.into())).await
+    }
+
+    /// Queues a [`Command::Unsubscribe`], see [`Self::send`].
+    #[cfg(feature = "unsubscribe")]
+    pub async fn unsubscribe(&self, topic: &str) -> Result<(), Command> {
+        self.send(Command::Unsubscribe(topic.into())).await
+    }
+}
+
+impl Clone for CommandHandle {
+    fn clone(&self) -> Self {
+        self.shared.lock().handles += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl Drop for CommandHandle {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.handles -= 1;
+        if state.handles == 0
+            && let Some(waker) = state.receiver.take()
+        {
+            waker.wake();
+        }
+    }
+}
+
+/// Returned when a command cannot be queued, see [`CommandHandle::try_send`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrySendError {
+    /// The queue is full, contains the rejected command.
+    Full(Command),
+    /// The [`CommandQueue`] was dropped, contains the rejected command.
+    Closed(Command),
+}
+
+impl fmt::Display for TrySendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("command queue is full"),
+            Self::Closed(_) => f.write_str("command queue is closed"),
+        }
+    }
+}
+
+impl core::error::Error for TrySendError {}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // The state is consistent after every operation, a panic cannot leave it half updated.
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    queue: VecDeque<Command>,
+    capacity: usize,
+    /// The amount of live handles.
+    handles: usize,
+    /// Whether the queue was dropped.
+    closed: bool,
+    /// Waker of the task waiting for the next command.
+    receiver: Option<Waker>,
+    /// Wakers of tasks waiting for space in the queue.
+    senders: Vec<Waker>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::block_on;
+
+    fn publish(payload: &[u8]) -> Command {
+        Command::Publish {
+            topic: "a".into(),
+            payload: payload.into(),
+        }
+    }
+
+    #[test]
+    fn test_queue_in_order() {
+        let queue = CommandQueue::new(2);
+        let handle = queue.handle();
+        let other = handle.clone();
+
+        assert_eq!(handle.try_send(publish(b"1")), Ok(()));
+        assert_eq!(other.try_send(publish(b"2")), Ok(()));
+        assert_eq!(
+            handle.try_send(publish(b"3")),
+            Err(TrySendError::Full(publish(b"3")))
+        );
+
+        assert_eq!(queue.try_next(), Some(publish(b"1")));
+        assert_eq!(handle.try_send(publish(b"3")), Ok(()));
+        assert_eq!(queue.try_next(), Some(publish(b"2")));
+        assert_eq!(queue.try_next(), Some(publish(b"3")));
+        assert_eq!(queue.try_next(), None);
+    }
+
+    #[test]
+    fn test_queue_ends_without_handles() {
+        let queue = CommandQueue::new(1);
+        let handle = queue.handle();
+        block_on(handle.publish("a", b"1")).unwrap();
+        drop(handle);
+
+        assert_eq!(block_on(queue.next()), Some(publish(b"1")));
+        assert_eq!(block_on(queue.next()), None);
+    }
+
+    #[test]
+    fn test_queue_closed() {
+        let queue = CommandQueue::new(1);
+        let handle = queue.handle();
+        drop(queue);
+
+        assert_eq!(
+            handle.try_send(publish(b"1")),
+            Err(TrySendError::Closed(publish(b"1")))
+        );
+        assert_eq!(block_on(handle.publish("a", b"1")), Err(publish(b"1")));
+    }
+
+    #[test]
+    fn test_queue_threads() {
+        let queue = CommandQueue::new(1);
+
+        let senders: Vec<_> = (0..4u8)
+            .map(|i| {
+                let handle = queue.handle();
+                std::thread::spawn(move || {
+                    for j in 0..10 {
+                        block_on(handle.publish("a", &[i, j])).unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        let mut received = Vec::new();
+        while let Some(command) = block_on(queue.next()) {
+            received.push(command);
+        }
+        for sender in senders {
+            sender.join().unwrap();
+        }
+
+        assert_eq!(received.len(), 40);
+        for i in 0..4u8 {
+            let payloads: Vec<_> = received
+                .iter()
+                .filter_map(|command| match command {
+                    Command::Publish { payload, .. } if payload[0] == i => Some(payload[1]),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(payloads, (0..10).collect::<Vec<_>>());
+        }
+    }
+}
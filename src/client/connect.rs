@@ -1,11 +1,20 @@
+use core::fmt;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+#[cfg(feature = "auth")]
+use crate::client::message::Receive;
+use crate::client::utils::{LazyMakeFuture, TakeOptions};
 #[cfg(doc)]
-use crate::client::Client;
-use crate::client::MakeFuture;
-use crate::client::utils::LazyMakeFuture;
-use crate::protocol::v5;
+use crate::client::{Client, Error};
+use crate::client::{ClientId, MakeFuture, Presence, TimeSync};
+use crate::log;
+#[cfg(feature = "auth")]
+use crate::log::LogPacket;
+use crate::log::Redacted;
+#[cfg(feature = "auth")]
+use crate::protocol::{PacketError, PacketParse, ParseResult};
+use crate::protocol::{QoS, v5};
 
 pin_project_lite::pin_project! {
     /// Future returned by [`Client::connect`].
@@ -15,7 +24,7 @@ pin_project_lite::pin_project! {
     /// This future is *not* cancel safe.
     #[must_use = "futures do nothing unless you `.await` or poll them"]
     pub struct Connect<'a, T> {
-        packet: v5::Connect<'a>,
+        options: ConnectOptions<'a>,
         #[pin]
         inner: T,
     }
@@ -27,10 +36,35 @@ impl<'a, T> Connect<'a, T> {
     /// Clients must periodically send a keep-alive message with this interval to inform the server
     /// about its presence.
     ///
+    /// A keep alive of `0` disables the keep alive mechanism. The server may assign a different
+    /// keep alive, see [`Client::keep_alive`].
+    ///
+    /// The keep alive is clamped to the [bounds](Self::keep_alive_bounds).
+    ///
     /// Note: The client currently does not implement automatic keep alive messages and these must
-    /// be generated by the user.
+    /// be generated by the user, see [`Client::ping_due_in`].
     pub fn keep_alive(mut self, keep_alive: u16) -> Self {
-        self.packet.keep_alive = keep_alive;
+        let (min, max) = self.options.keep_alive_bounds;
+        self.options.packet.keep_alive = keep_alive.clamp(min, max);
+        self
+    }
+
+    /// Configures the lowest and highest keep alive in seconds, which is acceptable.
+    ///
+    /// Some brokers reject keep alive intervals outside of their limits. The requested
+    /// [keep alive](Self::keep_alive) is clamped to the bounds. A keep alive assigned by the
+    /// server must be used by the client, even if it is outside of the bounds, this is reported
+    /// with [`ConnectResponse::keep_alive_out_of_bounds`].
+    ///
+    /// Unbounded by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is greater than `max`.
+    pub fn keep_alive_bounds(mut self, min: u16, max: u16) -> Self {
+        assert!(min <= max, "keep alive bounds: min must not exceed max");
+        self.options.keep_alive_bounds = (min, max);
+        self.options.packet.keep_alive = self.options.packet.keep_alive.clamp(min, max);
         self
     }
 
@@ -42,7 +76,24 @@ impl<'a, T> Connect<'a, T> {
     /// session resumption. This may lead to hanging connection or lost messages while the client
     /// was not connected.
     pub fn resume_session(mut self, resume: bool) -> Self {
-        self.packet.clean_start = !resume;
+        self.options.packet.clean_start = !resume;
+        self
+    }
+
+    /// Whether an already connected client disconnects, before connecting again.
+    ///
+    /// Sending a second CONNECT on a connection is a protocol error. By default, connecting a
+    /// [connected](Client::is_connected) client fails with [`Error::AlreadyConnected`]. With
+    /// `reconnect`, the client first sends a DISCONNECT, this requires a transport which
+    /// re-establishes the network connection once it is closed by the server.
+    ///
+    /// The DISCONNECT is a [normal disconnection](v5::DisconnectReason::NormalDisconnection),
+    /// the server discards the will message. Data which was buffered but not yet received is
+    /// discarded with the previous connection.
+    ///
+    /// Defaults to `false`.
+    pub fn reconnect(mut self, reconnect: bool) -> Self {
+        self.options.reconnect = reconnect;
         self
     }
 
@@ -51,36 +102,176 @@ impl<'a, T> Connect<'a, T> {
     where
         S: Into<Option<&'a str>>,
     {
-        self.packet.username = username.into();
+        self.options.packet.username = username.into();
         self
     }
 
     /// Configures the password for authentication with the broker.
-    pub fn with_password<S>(mut self, password: S) -> Self
+    ///
+    /// The password is binary data, it is not required to be valid UTF-8. This accepts strings
+    /// as well as raw bytes, e.g. tokens or certificates, see [`Password`].
+    pub fn with_password<P>(mut self, password: P) -> Self
     where
-        S: Into<Option<&'a str>>,
+        P: Into<Password<'a>>,
     {
-        self.packet.password = password.into();
+        self.options.packet.password = password.into().0;
+        self
+    }
+
+    /// Configures the will message, which the server publishes when the connection is closed
+    /// without a disconnect.
+    #[cfg(feature = "will")]
+    pub fn with_will(mut self, will: v5::Will<'a>) -> Self {
+        self.options.packet.will = Some(will);
+        self
+    }
+
+    /// Announces the presence of the client on a topic.
+    ///
+    /// After a successful connect, the client publishes a retained online message. The offline
+    /// message is configured as retained [will](Self::with_will), which the server publishes
+    /// when the client goes away without a disconnect.
+    ///
+    /// See [`Presence`].
+    #[cfg(feature = "will")]
+    pub fn with_presence(mut self, presence: Presence<'a>) -> Self {
+        self.options.packet.will = Some(presence.will());
+        self.options.presence = Some(presence);
         self
     }
 
     /// Configures additional properties for the connection.
     pub fn with_properties(mut self, properties: &'a [v5::ConnectProperty<'a>]) -> Self {
-        self.packet.properties = properties;
+        self.options.packet.properties = properties;
+        self
+    }
+
+    /// Authenticates with the server through enhanced authentication.
+    ///
+    /// The CONNECT carries the [method](Authenticator::method) and the initial data of the
+    /// `authenticator`. As long as the server responds with an AUTH packet which
+    /// [continues](v5::AuthReason::ContinueAuthentication) the authentication, the challenge is
+    /// answered by the `authenticator`, until the server completes the connect with a CONNACK.
+    ///
+    /// Spec: [4.12](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901256)
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use miniqtt::client::Authenticator;
+    /// # async fn example<C>(
+    /// #     client: &mut miniqtt::Client<C, [u8; 128]>,
+    /// # ) -> miniqtt::client::Result<(), C::Error>
+    /// # where
+    /// #     C: embedded_io_async::Read + embedded_io_async::Write,
+    /// # {
+    /// struct Token<'a>(&'a [u8]);
+    ///
+    /// impl Authenticator for Token<'_> {
+    ///     fn method(&self) -> &'static str {
+    ///         "TOKEN"
+    ///     }
+    ///
+    ///     fn respond(&mut self, challenge: Option<&[u8]>) -> Option<&[u8]> {
+    ///         match challenge {
+    ///             None => Some(self.0),
+    ///             Some(_) => None,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut token = Token(b"secret");
+    /// let response = client
+    ///     .connect("miniqtt")
+    ///     .with_authenticator(&mut token)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "auth")]
+    pub fn with_authenticator(mut self, authenticator: &'a mut dyn Authenticator) -> Self {
+        self.options.authenticator = Some(authenticator);
+        self
+    }
+
+    /// Reads the time of the broker from the CONNACK, see [`ConnectResponse::broker_time`].
+    ///
+    /// Brokers which only include the time on request, need the [request](TimeSync::request)
+    /// in the [properties](Self::with_properties).
+    pub fn with_time_sync(mut self, time_sync: TimeSync<'a>) -> Self {
+        self.options.time_sync = Some(time_sync);
         self
     }
 }
 
+/// The password of a [`Connect`], see [`Connect::with_password`].
+///
+/// Created from a string or raw bytes, an optional string configures no password if it is
+/// `None`.
+#[derive(Clone, Copy, Default)]
+pub struct Password<'a>(Option<&'a [u8]>);
+
+impl fmt::Debug for Password<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Password")
+            .field(&self.0.map(|_| Redacted))
+            .finish()
+    }
+}
+
+impl<'a> From<&'a str> for Password<'a> {
+    fn from(password: &'a str) -> Self {
+        Self(Some(password.as_bytes()))
+    }
+}
+
+impl<'a> From<Option<&'a str>> for Password<'a> {
+    fn from(password: Option<&'a str>) -> Self {
+        Self(password.map(str::as_bytes))
+    }
+}
+
+impl<'a> From<&'a [u8]> for Password<'a> {
+    fn from(password: &'a [u8]) -> Self {
+        Self(Some(password))
+    }
+}
+
+impl<'a, const N: usize> From<&'a [u8; N]> for Password<'a> {
+    fn from(password: &'a [u8; N]) -> Self {
+        Self(Some(password))
+    }
+}
+
+/// The options of a [`Connect`], passed to the future which sends it.
+pub struct ConnectOptions<'a> {
+    pub(super) packet: v5::Connect<'a>,
+    pub(super) presence: Option<Presence<'a>>,
+    pub(super) time_sync: Option<TimeSync<'a>>,
+    pub(super) keep_alive_bounds: (u16, u16),
+    pub(super) reconnect: bool,
+    pub(super) authenticator: Option<Authentication<'a>>,
+}
+
+impl TakeOptions for ConnectOptions<'_> {
+    fn take(&mut self) -> Self {
+        Self {
+            authenticator: self.authenticator.take(),
+            ..*self
+        }
+    }
+}
+
 impl Connect<'_, ()> {
     pub(super) fn new<'a, M, F, O>(
         client_id: &'a str,
         m: M,
-    ) -> Connect<'a, impl MakeFuture<v5::Connect<'a>, Output = O>>
+    ) -> Connect<'a, impl MakeFuture<ConnectOptions<'a>, Output = O>>
     where
-        M: FnOnce(v5::Connect<'a>) -> F,
+        M: FnOnce(ConnectOptions<'a>) -> F,
         F: Future<Output = O>,
     {
-        let packet = v5::Connect {
+        let packet: v5::Connect<'a> = v5::Connect {
             client_id,
             keep_alive: 0,
             clean_start: true,
@@ -91,7 +282,14 @@ impl Connect<'_, ()> {
         };
 
         Connect {
-            packet,
+            options: ConnectOptions {
+                packet,
+                presence: None,
+                time_sync: None,
+                keep_alive_bounds: (0, u16::MAX),
+                reconnect: false,
+                authenticator: None,
+            },
             inner: LazyMakeFuture::new(m),
         }
     }
@@ -99,37 +297,229 @@ impl Connect<'_, ()> {
 
 impl<'a, M> Future for Connect<'a, M>
 where
-    M: MakeFuture<v5::Connect<'a>>,
+    M: MakeFuture<ConnectOptions<'a>>,
 {
     type Output = M::Output;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
-        this.inner.poll(&*this.packet, cx)
+        this.inner.poll(this.options, cx)
+    }
+}
+
+/// Implements a method of enhanced authentication, e.g. SCRAM, Kerberos or a vendor specific
+/// method.
+///
+/// Used with [`Connect::with_authenticator`]. The authenticator keeps the state of the
+/// exchange, e.g. the nonces of a SCRAM exchange, and owns the data it responds with.
+///
+/// Spec: [4.12](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901256)
+#[cfg(feature = "auth")]
+pub trait Authenticator {
+    /// Returns the name of the authentication method, e.g. `SCRAM-SHA-1`.
+    fn method(&self) -> &'static str;
+
+    /// Returns the authentication data sent to the server.
+    ///
+    /// Called without a `challenge` for the data sent with the CONNECT, afterwards for every
+    /// challenge the server sends with an AUTH packet. Returning `None` aborts the exchange,
+    /// the connect fails with [`Error::AuthenticationAborted`].
+    fn respond(&mut self, challenge: Option<&[u8]>) -> Option<&[u8]>;
+
+    /// Verifies the authentication data the server sends with a successful CONNACK, e.g. the
+    /// server signature of a SCRAM exchange.
+    ///
+    /// Returning `false` fails the connect with [`Error::AuthenticationAborted`]. Accepts the
+    /// server by default.
+    fn finish(&mut self, data: Option<&[u8]>) -> bool {
+        let _ = data;
+        true
+    }
+}
+
+#[cfg(feature = "auth")]
+impl<A> Authenticator for &mut A
+where
+    A: Authenticator + ?Sized,
+{
+    fn method(&self) -> &'static str {
+        (**self).method()
+    }
+
+    fn respond(&mut self, challenge: Option<&[u8]>) -> Option<&[u8]> {
+        (**self).respond(challenge)
+    }
+
+    fn finish(&mut self, data: Option<&[u8]>) -> bool {
+        (**self).finish(data)
+    }
+}
+
+/// The enhanced authentication of a connect.
+#[cfg(feature = "auth")]
+pub(super) type Authentication<'a> = &'a mut dyn Authenticator;
+
+/// Without the `auth` feature, a connect never uses enhanced authentication.
+#[cfg(not(feature = "auth"))]
+pub(super) type Authentication<'a> = core::convert::Infallible;
+
+/// A packet the server responds with to a CONNECT, when using enhanced authentication.
+#[cfg(feature = "auth")]
+#[derive(Debug)]
+pub(super) enum ConnectReply<'a> {
+    ConnAck(v5::ConnAck<'a>),
+    Auth(v5::Auth<'a>),
+}
+
+#[cfg(feature = "auth")]
+impl LogPacket for ConnectReply<'_> {}
+
+#[cfg(feature = "auth")]
+impl Receive for ConnectReply<'static> {
+    type Packet<'a> = ConnectReply<'a>;
+}
+
+#[cfg(feature = "auth")]
+impl<'a> PacketParse<'a> for ConnectReply<'a> {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let (len, packet) = <v5::AnyPacket as PacketParse>::parse(data)?;
+
+        let reply = match packet {
+            v5::AnyPacket::ConnAck(ack) => Self::ConnAck(ack),
+            v5::AnyPacket::Auth(auth) => Self::Auth(auth),
+            _ => return Err(PacketError::ProtocolError.into()),
+        };
+
+        Ok((len, reply))
     }
 }
 
 /// The response from the server to a [`Connect`] request.
 #[derive(Debug)]
 pub struct ConnectResponse {
-    pub(super) ack: v5::ConnAck,
+    pub(super) ack_flags: u8,
+    pub(super) reason: v5::ConnAckReason,
+    pub(super) server_keep_alive: Option<u16>,
+    pub(super) session_expiry_interval: Option<u32>,
+    pub(super) assigned_client_id: Option<ClientId>,
+    broker_time: Option<u64>,
+    receive_maximum: u16,
+    maximum_qos: QoS,
+    retain_available: bool,
+    maximum_packet_size: Option<u32>,
+    topic_alias_maximum: u16,
+    pub(super) keep_alive_out_of_bounds: bool,
 }
 
-// TODO: this is all a bit lack-luster, the reason isn't exposed, properties aren't exposed,
-// error handling sucks, because clients need to explicitly check for success.
+// TODO: error handling sucks, because clients need to explicitly check for success.
 //
 // There should probably a good way to turn this into some kind of Result<> where clients can
 // just `?` the response.
 impl ConnectResponse {
+    pub(super) fn new(ack: &v5::ConnAck<'_>, time_sync: Option<TimeSync<'_>>) -> Self {
+        Self {
+            ack_flags: ack.ack_flags,
+            reason: ack.reason,
+            server_keep_alive: ack.server_keep_alive,
+            session_expiry_interval: ack.session_expiry_interval,
+            assigned_client_id: ack.assigned_client_identifier.and_then(|id| {
+                match ClientId::new(id) {
+                    Some(id) => Some(id),
+                    None => {
+                        log::debug!("assigned client identifier too long: {id}");
+                        None
+                    }
+                }
+            }),
+            broker_time: time_sync.and_then(|time_sync| time_sync.read_connack(&ack.properties)),
+            receive_maximum: ack.properties.receive_maximum(),
+            maximum_qos: ack.properties.maximum_qos(),
+            retain_available: ack.properties.retain_available(),
+            maximum_packet_size: ack.properties.maximum_packet_size(),
+            topic_alias_maximum: ack.properties.topic_alias_maximum(),
+            keep_alive_out_of_bounds: false,
+        }
+    }
+
     /// Returns whether the connection was successful.
     ///
     /// After an unsuccessful connect attempt, the connection must be closed.
     pub fn successful(&self) -> bool {
-        self.ack.reason == v5::ConnAckReason::Success
+        self.reason == v5::ConnAckReason::Success
+    }
+
+    /// Returns the reason of the server's response.
+    pub fn reason(&self) -> v5::ConnAckReason {
+        self.reason
     }
 
     /// Whether the server resumed an existing client session.
     pub fn session_present(&self) -> bool {
-        self.ack.ack_flags & 1 == 1
+        self.ack_flags & 1 == 1
+    }
+
+    /// Returns the keep alive assigned by the server, see [`Client::keep_alive`].
+    pub fn server_keep_alive(&self) -> Option<u16> {
+        self.server_keep_alive
+    }
+
+    /// Whether the keep alive assigned by the server is outside of the
+    /// [configured bounds](Connect::keep_alive_bounds).
+    ///
+    /// The client must use the keep alive assigned by the server regardless, the application
+    /// may decide to disconnect instead.
+    pub fn keep_alive_out_of_bounds(&self) -> bool {
+        self.keep_alive_out_of_bounds
+    }
+
+    /// Returns the client identifier assigned by the server.
+    ///
+    /// The server assigns an identifier, when the client connects with an empty identifier.
+    /// Identifiers which exceed the capacity of a [`ClientId`] are dropped.
+    pub fn assigned_client_id(&self) -> Option<&ClientId> {
+        self.assigned_client_id.as_ref()
+    }
+
+    /// Returns the amount of QoS 1 and QoS 2 publishes the server processes concurrently.
+    ///
+    /// See [`v5::ConnAckProperties::receive_maximum`].
+    pub fn receive_maximum(&self) -> u16 {
+        self.receive_maximum
+    }
+
+    /// Returns the highest QoS the server supports.
+    ///
+    /// See [`v5::ConnAckProperties::maximum_qos`].
+    pub fn maximum_qos(&self) -> QoS {
+        self.maximum_qos
+    }
+
+    /// Returns whether the server supports retained messages.
+    ///
+    /// See [`v5::ConnAckProperties::retain_available`].
+    pub fn retain_available(&self) -> bool {
+        self.retain_available
+    }
+
+    /// Returns the maximum size of a packet the server accepts, `None` if there is no limit.
+    ///
+    /// See [`v5::ConnAckProperties::maximum_packet_size`].
+    pub fn maximum_packet_size(&self) -> Option<u32> {
+        self.maximum_packet_size
+    }
+
+    /// Returns the highest topic alias the server accepts.
+    ///
+    /// See [`v5::ConnAckProperties::topic_alias_maximum`].
+    pub fn topic_alias_maximum(&self) -> u16 {
+        self.topic_alias_maximum
+    }
+
+    /// Returns the time of the broker in milliseconds since the unix epoch.
+    ///
+    /// Only available if the connect was configured [with a time sync](Connect::with_time_sync)
+    /// and the server included the time in its response.
+    pub fn broker_time(&self) -> Option<u64> {
+        self.broker_time
     }
 }
@@ -0,0 +1,191 @@
+use crate::protocol::v5;
+
+/// A filter for redelivered messages, keyed on their topic and Correlation Data.
+///
+/// Upstream systems of request/response and command topics may deliver the same request more
+/// than once, e.g. after a retry, even if every single publish is delivered exactly once. The
+/// filter remembers the topic and Correlation Data of the `N` most recently seen messages and
+/// reports messages carrying the same Correlation Data on the same topic again as duplicates,
+/// preventing a command from being executed twice.
+///
+/// Only a 64 bit fingerprint of the topic and Correlation Data is kept, the filter needs no
+/// buffer for the data itself. The least recently seen entry is evicted once the window is full.
+/// Messages without Correlation Data are never considered duplicates.
+///
+/// The fingerprint is not a cryptographic hash. Two different messages may share a
+/// fingerprint, the second one is then wrongly reported as a duplicate. By chance this is
+/// negligibly unlikely, but a publisher which chooses the Correlation Data can craft a collision
+/// on purpose and suppress a request of another publisher on the same topic. Only filter
+/// messages of trusted publishers.
+///
+/// Spec: [3.3.2.3.6](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901115)
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::client::Dedup;
+/// # async fn example<C>(client: &mut miniqtt::Client<C, Vec<u8>>) -> miniqtt::client::Result<(), C::Error>
+/// # where
+/// #     C: embedded_io_async::Read + embedded_io_async::Write,
+/// # {
+/// let mut dedup = Dedup::<16>::new();
+///
+/// loop {
+///     let message = client.receive().await?;
+///     if dedup.is_duplicate(&message) {
+///         continue;
+///     }
+///     // Actuate the command.
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Dedup<const N: usize> {
+    /// Fingerprints, ordered from the most to the least recently seen.
+    entries: [u64; N],
+    len: usize,
+}
+
+impl<const N: usize> Dedup<N> {
+    /// Creates a new, empty filter.
+    pub const fn new() -> Self {
+        Self {
+            entries: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Returns `true` if the Correlation Data of `message` was already seen on its topic.
+    ///
+    /// The topic and Correlation Data of the message are remembered, see [`Self::insert`].
+    pub fn is_duplicate(&mut self, message: &v5::Publish<'_>) -> bool {
+        match message.properties.correlation_data() {
+            Some(correlation_data) => !self.insert(message.topic, correlation_data),
+            None => false,
+        }
+    }
+
+    /// Remembers `correlation_data` on `topic` as the most recently seen entry.
+    ///
+    /// Returns `true` if the entry was newly inserted and `false` if it was already contained.
+    pub fn insert(&mut self, topic: &str, correlation_data: &[u8]) -> bool {
+        let fingerprint = fingerprint(topic, correlation_data);
+
+        let (position, inserted) = match self.position(fingerprint) {
+            Some(position) => (position, false),
+            None if self.len < N => {
+                self.len += 1;
+                (self.len - 1, true)
+            }
+            // Evicts the least recently seen entry.
+            None => (self.len.saturating_sub(1), true),
+        };
+
+        if position < self.len {
+            self.entries[..=position].rotate_right(1);
+            self.entries[0] = fingerprint;
+        }
+        inserted
+    }
+
+    /// Returns whether `correlation_data` was seen on `topic`, without updating the filter.
+    pub fn contains(&self, topic: &str, correlation_data: &[u8]) -> bool {
+        self.position(fingerprint(topic, correlation_data))
+            .is_some()
+    }
+
+    /// Returns the amount of remembered entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no entries are remembered.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Forgets all entries.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn position(&self, fingerprint: u64) -> Option<usize> {
+        self.entries[..self.len]
+            .iter()
+            .position(|&entry| entry == fingerprint)
+    }
+}
+
+impl<const N: usize> Default for Dedup<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the 64 bit FNV-1a hash of `topic` and `correlation_data`.
+///
+/// The topic is prefixed with its length, a topic ending in the start of the Correlation Data
+/// does not collide with a shorter topic.
+fn fingerprint(topic: &str, correlation_data: &[u8]) -> u64 {
+    let len = (topic.len() as u64).to_be_bytes();
+    len.iter()
+        .chain(topic.as_bytes())
+        .chain(correlation_data)
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_evicts_least_recently_seen() {
+        let mut dedup = Dedup::<2>::new();
+
+        assert!(dedup.insert("t", b"a"));
+        assert!(dedup.insert("t", b"b"));
+        // Seeing `a` again makes `b` the least recently seen entry.
+        assert!(!dedup.insert("t", b"a"));
+        assert!(dedup.insert("t", b"c"));
+        assert_eq!(dedup.len(), 2);
+
+        assert!(dedup.contains("t", b"a"));
+        assert!(!dedup.contains("t", b"b"));
+        assert!(dedup.contains("t", b"c"));
+
+        dedup.clear();
+        assert!(dedup.is_empty());
+        assert!(dedup.insert("t", b"a"));
+    }
+
+    #[test]
+    fn test_dedup_keyed_on_topic() {
+        let mut dedup = Dedup::<4>::new();
+
+        assert!(dedup.insert("a", b"1"));
+        // The same Correlation Data on another topic is a different request.
+        assert!(dedup.insert("b", b"1"));
+        assert!(!dedup.insert("a", b"1"));
+        // The boundary between topic and Correlation Data is part of the key.
+        assert!(dedup.insert("a1", b""));
+        assert!(!dedup.contains("", b"a1"));
+    }
+
+    #[test]
+    fn test_dedup_empty_window() {
+        let mut dedup = Dedup::<0>::new();
+
+        assert!(dedup.insert("t", b"a"));
+        assert!(dedup.insert("t", b"a"));
+        assert!(dedup.is_empty());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_ne!(fingerprint("t", b"ab"), fingerprint("t", b"ba"));
+        assert_ne!(fingerprint("a", b"b"), fingerprint("ab", b""));
+        assert_ne!(fingerprint("a", b"b"), fingerprint("b", b"a"));
+    }
+}
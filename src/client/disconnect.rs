@@ -0,0 +1,77 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+#[cfg(doc)]
+use crate::client::Client;
+use crate::client::MakeFuture;
+use crate::client::utils::LazyMakeFuture;
+use crate::protocol::v5;
+
+pin_project_lite::pin_project! {
+    /// Future returned by [`Client::disconnect`].
+    ///
+    /// # Cancel safety
+    ///
+    /// This future is *not* cancel safe.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Disconnect<'a, T> {
+        options: DisconnectOptions<'a>,
+        #[pin]
+        inner: T,
+    }
+}
+
+/// The options of a [`Disconnect`], passed to the future which sends it.
+#[derive(Debug, Clone, Copy)]
+pub struct DisconnectOptions<'a> {
+    pub(super) reason: v5::DisconnectReason,
+    pub(super) properties: &'a [v5::DisconnectProperty<'a>],
+}
+
+impl<'a, T> Disconnect<'a, T> {
+    /// Configures the reason sent to the server.
+    ///
+    /// Defaults to [`v5::DisconnectReason::DisconnectWithWillMessage`], which asks the server to
+    /// publish the will message. Use [`v5::DisconnectReason::NormalDisconnection`] to disconnect
+    /// without publishing the will message.
+    pub fn reason(mut self, reason: v5::DisconnectReason) -> Self {
+        self.options.reason = reason;
+        self
+    }
+
+    /// Configures additional properties, e.g. a reason string or user properties.
+    pub fn with_properties(mut self, properties: &'a [v5::DisconnectProperty<'a>]) -> Self {
+        self.options.properties = properties;
+        self
+    }
+}
+
+impl Disconnect<'_, ()> {
+    pub(super) fn new<'a, M, F, O>(
+        m: M,
+    ) -> Disconnect<'a, impl MakeFuture<DisconnectOptions<'a>, Output = O>>
+    where
+        M: FnOnce(DisconnectOptions<'a>) -> F,
+        F: Future<Output = O>,
+    {
+        Disconnect {
+            options: DisconnectOptions {
+                reason: v5::DisconnectReason::DisconnectWithWillMessage,
+                properties: &[],
+            },
+            inner: LazyMakeFuture::new(m),
+        }
+    }
+}
+
+impl<'a, M> Future for Disconnect<'a, M>
+where
+    M: MakeFuture<DisconnectOptions<'a>>,
+{
+    type Output = M::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        this.inner.poll(this.options, cx)
+    }
+}
@@ -0,0 +1,90 @@
+use core::ops::{Deref, DerefMut};
+use core::pin::pin;
+use core::task::{Context, Waker};
+
+use crate::client::Client;
+use crate::log;
+use crate::protocol::v5;
+
+/// A [`Client`] which makes a best-effort attempt to disconnect from the server when dropped.
+///
+/// Dropping a connected client without disconnecting leaves the session on the broker
+/// stranded until the keep alive expires and causes the will message to be published. The guard
+/// sends a [`NormalDisconnection`](v5::DisconnectReason::NormalDisconnection), the server
+/// discards the will message.
+///
+/// Since a [`Drop`] implementation can not wait for the connection, the disconnect message is
+/// polled exactly once, with a no-op waker. It is only sent if it can be written immediately,
+/// which is usually the case for buffered transports like TCP sockets. A transport which accepts
+/// only part of the message leaves a partially written DISCONNECT on the wire, the server closes
+/// the connection with a malformed packet and publishes the will message. Nothing is sent if the
+/// client is not [connected](Client::is_connected).
+///
+/// Created with [`Client::disconnect_on_drop`].
+pub struct DisconnectOnDrop<C, B, T>
+where
+    C: embedded_io_async::Write,
+{
+    client: Option<Client<C, B, T>>,
+}
+
+impl<C, B, T> DisconnectOnDrop<C, B, T>
+where
+    C: embedded_io_async::Write,
+{
+    pub(super) fn new(client: Client<C, B, T>) -> Self {
+        Self {
+            client: Some(client),
+        }
+    }
+
+    /// Returns the wrapped client, without disconnecting it.
+    pub fn into_inner(mut self) -> Client<C, B, T> {
+        // The client is only ever taken here or on drop.
+        self.client.take().unwrap()
+    }
+}
+
+impl<C, B, T> Deref for DisconnectOnDrop<C, B, T>
+where
+    C: embedded_io_async::Write,
+{
+    type Target = Client<C, B, T>;
+
+    fn deref(&self) -> &Self::Target {
+        // The client is only ever taken when consuming the guard.
+        self.client.as_ref().unwrap()
+    }
+}
+
+impl<C, B, T> DerefMut for DisconnectOnDrop<C, B, T>
+where
+    C: embedded_io_async::Write,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // The client is only ever taken when consuming the guard.
+        self.client.as_mut().unwrap()
+    }
+}
+
+impl<C, B, T> Drop for DisconnectOnDrop<C, B, T>
+where
+    C: embedded_io_async::Write,
+{
+    fn drop(&mut self) {
+        let Some(client) = &mut self.client else {
+            return;
+        };
+        if !client.is_connected() {
+            return;
+        }
+
+        let packet: v5::Disconnect<'_, &[v5::DisconnectProperty<'_>]> =
+            v5::Disconnect::new(v5::DisconnectReason::NormalDisconnection, &[]);
+        let disconnect = pin!(client.connection.send(&packet));
+        let mut cx = Context::from_waker(Waker::noop());
+        if disconnect.poll(&mut cx).is_pending() {
+            log::debug!("failed to disconnect on drop, connection not ready");
+        }
+    }
+}
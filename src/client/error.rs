@@ -1,5 +1,10 @@
 use core::fmt;
 
+#[cfg(doc)]
+use crate::client::{Connect, ConnectionBuilder};
+use crate::protocol::v5;
+use crate::protocol::{QoS, ValidationError, WriteError};
+
 /// A MQTT Client error.
 #[derive(Debug)]
 pub enum Error<E> {
@@ -8,6 +13,8 @@ pub enum Error<E> {
     /// The connection may have been closed by the server or the client
     /// disconnected.
     Disconnected,
+    /// The client is already connected, see [`Connect::reconnect`].
+    AlreadyConnected,
     /// A protocol error.
     ///
     /// Protocol errors may happen when invalid data is received, a protocol error cannot be
@@ -15,6 +22,64 @@ pub enum Error<E> {
     Protocol,
     /// The connection buffer is not big enough to receive a package.
     InsufficientBufferSize,
+    /// The packet is too large to be sent or exceeds the configured maximum packet size.
+    PacketTooLarge,
+    /// The server did not respond in time.
+    Timeout,
+    /// The server does not support the MQTT protocol version used by the client.
+    ///
+    /// This is also returned for servers which only support MQTT 3.1.1.
+    UnsupportedProtocolVersion,
+    /// The server rejected a subscription.
+    ///
+    /// Some rejections are [transient](v5::SubAckReason::is_transient) and the subscription
+    /// may be retried.
+    #[cfg(feature = "subscribe")]
+    SubscriptionRejected(v5::SubAckReason),
+    /// The server rejected an unsubscribe.
+    #[cfg(feature = "unsubscribe")]
+    UnsubscribeRejected(v5::UnsubAckReason),
+    /// The server rejected a QoS 1 or QoS 2 publish.
+    PublishRejected(v5::PubAckReason),
+    /// The server received a QoS 2 publish, but did not complete the handshake.
+    PublishIncomplete(v5::PubCompReason),
+    /// The server closed the connection with a DISCONNECT, instead of the expected packet.
+    ///
+    /// Following receives and sends fail with [`Error::Disconnected`], until the client connects
+    /// again.
+    ServerDisconnect(v5::DisconnectReason),
+    /// A packet was only partially sent, the connection must be re-established.
+    InterruptedWrite,
+    /// The [`Authenticator`](crate::client::Authenticator) aborted the enhanced authentication,
+    /// or rejected the authentication data of the server.
+    ///
+    /// The server still expects a response or considers the client connected, the connection
+    /// must be closed.
+    #[cfg(feature = "auth")]
+    AuthenticationAborted,
+    /// Publishing to a topic reserved for the server, starting with `$`, is not allowed.
+    ///
+    /// See [`ConnectionBuilder::allow_reserved_topics`].
+    ReservedTopic,
+    /// The quality of service is not supported by the client.
+    UnsupportedQoS(QoS),
+    /// A topic or client identifier violates a limit of the protocol.
+    ///
+    /// See [`protocol::validate_topic`](crate::protocol::validate_topic).
+    Invalid(ValidationError),
+    /// Writing a packet to the connection failed.
+    ///
+    /// If no bytes were `written`, the packet was not sent and can be retried on the same
+    /// connection. Otherwise the packet was only partially sent and the connection must be
+    /// re-established.
+    Write {
+        /// The [type](crate::protocol::Packet::TYPE) of the packet.
+        packet_type: u8,
+        /// The amount of bytes of the packet which were written before the error occurred.
+        written: usize,
+        /// The error of the connection.
+        error: E,
+    },
     /// An underlying error occurred on the connection.
     Connection(E),
 }
@@ -25,17 +90,63 @@ impl<E> From<E> for Error<E> {
     }
 }
 
+impl<E> From<WriteError<E>> for Error<E> {
+    fn from(value: WriteError<E>) -> Self {
+        match value {
+            WriteError::Write(err) => Self::Connection(err),
+            WriteError::Overflow(_) => Self::PacketTooLarge,
+        }
+    }
+}
+
 impl<E> fmt::Display for Error<E>
 where
     E: fmt::Display,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Disconnected => write!(f, "The connection is closed!"),
+            Self::AlreadyConnected => write!(f, "The client is already connected!"),
             Self::Protocol => write!(f, "A protocol error occured!"),
             Self::InsufficientBufferSize => {
                 write!(f, "Buffer is not big enough to parse a received packet!")
             }
+            Self::PacketTooLarge => write!(f, "The packet is too large to be sent!"),
+            Self::Timeout => write!(f, "The server did not respond in time!"),
+            Self::UnsupportedProtocolVersion => {
+                write!(f, "The server does not support the protocol version!")
+            }
+            #[cfg(feature = "subscribe")]
+            Self::SubscriptionRejected(reason) => {
+                write!(f, "The server rejected the subscription: {reason:?}")
+            }
+            #[cfg(feature = "unsubscribe")]
+            Self::UnsubscribeRejected(reason) => {
+                write!(f, "The server rejected the unsubscribe: {reason:?}")
+            }
+            Self::PublishRejected(reason) => {
+                write!(f, "The server rejected the publish: {reason:?}")
+            }
+            Self::PublishIncomplete(reason) => {
+                write!(f, "The server did not complete the publish: {reason:?}")
+            }
+            Self::ServerDisconnect(reason) => {
+                write!(f, "The server closed the connection: {reason:?}")
+            }
+            Self::InterruptedWrite => write!(f, "A packet was only partially sent!"),
+            #[cfg(feature = "auth")]
+            Self::AuthenticationAborted => write!(f, "The authentication was aborted!"),
+            Self::ReservedTopic => write!(f, "Publishing to a reserved topic is not allowed!"),
+            Self::UnsupportedQoS(qos) => write!(f, "The QoS {qos:?} is not supported!"),
+            Self::Invalid(err) => write!(f, "Invalid value: {err}"),
+            Self::Write {
+                packet_type,
+                written,
+                error,
+            } => write!(
+                f,
+                "Failed to write packet of type {packet_type} after {written} bytes: {error}"
+            ),
             Self::Connection(err) => write!(f, "A connection error occured: {err}"),
         }
     }
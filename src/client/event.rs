@@ -0,0 +1,104 @@
+use core::fmt;
+
+use crate::client::message::Receive;
+use crate::client::{Connection, MessageGuard, Result};
+use crate::log::LogPacket;
+use crate::protocol::{PacketError, PacketParse, ParseResult, v5};
+use crate::traits::Buffer;
+
+#[cfg(doc)]
+use crate::client::Client;
+
+/// A packet received from the server, returned by [`Events`].
+///
+/// Besides messages, this includes packets which the server sends on its own and which
+/// [`Client::receive`] handles internally.
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// An application message, like returned by [`Client::receive`].
+    Publish(v5::Publish<'a>),
+    /// An AUTH packet, exchanged during extended authentication.
+    #[cfg(feature = "auth")]
+    Auth(v5::Auth<'a>),
+    /// The server closes the connection, the connection is closed after this packet.
+    Disconnect(v5::Disconnect<'a>),
+    /// The response to a PINGREQ.
+    PingResp(v5::PingResp),
+}
+
+impl LogPacket for Event<'_> {
+    fn fmt_without_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Publish(publish) => publish.fmt_without_payload(f),
+            event => fmt::Debug::fmt(event, f),
+        }
+    }
+}
+
+impl Receive for Event<'static> {
+    type Packet<'a> = Event<'a>;
+}
+
+impl<'a> PacketParse<'a> for Event<'a> {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let (len, packet) = <v5::AnyPacket as PacketParse>::parse(data)?;
+
+        let event = match packet {
+            v5::AnyPacket::Publish(publish) => Self::Publish(publish),
+            #[cfg(feature = "auth")]
+            v5::AnyPacket::Auth(auth) => Self::Auth(auth),
+            v5::AnyPacket::Disconnect(disconnect) => Self::Disconnect(disconnect),
+            v5::AnyPacket::PingResp(ping) => Self::PingResp(ping),
+            // Acknowledgements are only expected as a response to a request.
+            v5::AnyPacket::ConnAck(_) => return Err(PacketError::ProtocolError.into()),
+            v5::AnyPacket::PubAck(_) => return Err(PacketError::ProtocolError.into()),
+            v5::AnyPacket::PubComp(_) => return Err(PacketError::ProtocolError.into()),
+            v5::AnyPacket::PubRec(_) => return Err(PacketError::ProtocolError.into()),
+            // Releases of the server are answered by the connection itself.
+            v5::AnyPacket::PubRel(_) => return Err(PacketError::ProtocolError.into()),
+            #[cfg(feature = "subscribe")]
+            v5::AnyPacket::SubAck(_) => return Err(PacketError::ProtocolError.into()),
+            #[cfg(feature = "unsubscribe")]
+            v5::AnyPacket::UnsubAck(_) => return Err(PacketError::ProtocolError.into()),
+        };
+
+        Ok((len, event))
+    }
+}
+
+/// Receives every packet sent by the server as an [`Event`], see [`Client::events`].
+pub struct Events<'a, C, B> {
+    connection: &'a mut Connection<C, B>,
+}
+
+impl<'a, C, B> Events<'a, C, B> {
+    pub(super) fn new(connection: &'a mut Connection<C, B>) -> Self {
+        Self { connection }
+    }
+}
+
+impl<C, B> Events<'_, C, B>
+where
+    C: embedded_io_async::Read,
+    C: embedded_io_async::Write,
+    B: Buffer,
+{
+    /// Receives the next event from the server.
+    ///
+    /// The event borrows from the receive buffer, it must be dropped before the next event can
+    /// be received.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method *is* cancel safe.
+    pub async fn next(&mut self) -> Result<MessageGuard<'_, Event<'_>>, C::Error> {
+        self.connection.receive::<Event>().await
+    }
+
+    /// Receives an event which has already been read from the connection.
+    ///
+    /// Returns `None` if no complete event is buffered, see [`Client::try_receive`].
+    pub fn try_next(&mut self) -> Result<Option<MessageGuard<'_, Event<'_>>>, C::Error> {
+        self.connection.try_receive::<Event>()
+    }
+}
@@ -0,0 +1,158 @@
+use core::ops::Deref;
+
+#[cfg(doc)]
+use crate::client::Client;
+use crate::log::LogPacket;
+use crate::protocol::types::PacketId;
+use crate::protocol::{Packet, PacketParse, Parse, QoS, v5};
+
+/// A packet received from the server, borrowed from the receive buffer of the connection.
+///
+/// The guard keeps the connection borrowed, the next packet can only be received after the guard
+/// is dropped. Dropping the guard releases the packet from the receive buffer.
+///
+/// Releasing a QoS 1 or QoS 2 message acknowledges it, the PUBACK or PUBREC is sent before
+/// the next packet is received or sent.
+///
+/// If the guard is leaked (e.g. with [`core::mem::forget`]), the packet is never released and
+/// received again by the next [`Client::receive`].
+#[derive(Debug)]
+pub struct MessageGuard<'conn, T> {
+    packet: T,
+    /// Length of the packet in the receive buffer.
+    len: usize,
+    /// Release state of the connection, updated once the packet is released.
+    release: &'conn mut Release,
+}
+
+impl<'conn, T> MessageGuard<'conn, T> {
+    pub(super) fn new(packet: T, len: usize, release: &'conn mut Release) -> Self {
+        Self {
+            packet,
+            len,
+            release,
+        }
+    }
+}
+
+impl<T> Deref for MessageGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.packet
+    }
+}
+
+impl<T> Drop for MessageGuard<'_, T> {
+    fn drop(&mut self) {
+        self.release.position = Some(self.len);
+        if let Some(ack) = self.release.delivering.take() {
+            self.release.pending = Some(ack);
+        }
+    }
+}
+
+/// Tracks the packet handed out in a [`MessageGuard`], until it is released.
+#[derive(Debug, Default)]
+pub(crate) struct Release {
+    /// Position of the next packet in the receive buffer, set once the packet is released.
+    pub(super) position: Option<usize>,
+    /// Acknowledgement of the handed out message, sent once the message is released.
+    pub(super) delivering: Option<Ack>,
+    /// Acknowledgement of a released message, which still needs to be sent.
+    pub(super) pending: Option<Ack>,
+}
+
+/// Acknowledgement of a received QoS 1 or QoS 2 message.
+///
+/// Spec: [4.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901234)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Ack {
+    /// A QoS 1 message is acknowledged with a PUBACK.
+    PubAck(PacketId),
+    /// A QoS 2 message is acknowledged with a PUBREC, the server answers with a PUBREL.
+    PubRec(PacketId, v5::PubRecReason),
+}
+
+impl Ack {
+    /// Returns the acknowledgement required by the packet `data`, if it is a QoS 1 or QoS 2
+    /// `PUBLISH`.
+    pub(super) fn of(data: &[u8]) -> Option<Self> {
+        let first = *data.first()?;
+        if first >> 4 != <v5::Publish as Packet>::TYPE || (first >> 1) & 0b11 == 0 {
+            return None;
+        }
+
+        let (_, publish) = <v5::Publish as Parse>::parse(data).ok()?;
+        match (publish.qos, publish.identifier) {
+            (QoS::AtLeastOnce, Some(identifier)) => Some(Self::PubAck(identifier)),
+            (QoS::ExactlyOnce, Some(identifier)) => {
+                Some(Self::PubRec(identifier, v5::PubRecReason::Success))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An application message received from the server, returned by [`Client::receive`].
+///
+/// Dereferences to the received [`Publish`](v5::Publish), which exposes the topic, payload,
+/// quality of service, retain and duplicate flags, packet identifier and properties of the
+/// message. The message borrows from the receive buffer, see [`MessageGuard`].
+pub type Message<'conn> = MessageGuard<'conn, v5::Publish<'conn>>;
+
+/// Returned by [`Client::receive_or`].
+#[derive(Debug)]
+pub enum ReceiveOr<'conn, T> {
+    /// A message was received.
+    Message(Message<'conn>),
+    /// The other future completed first.
+    Other(T),
+}
+
+/// A packet which can be received, independent of the lifetime of the receive buffer.
+///
+/// Implemented for the `'static` variant of a packet, e.g. `SubAck<'static>`. Receiving names
+/// the packet for any lifetime, which allows parsing it without keeping the connection borrowed.
+pub(crate) trait Receive {
+    type Packet<'a>: PacketParse<'a> + LogPacket;
+}
+
+impl Receive for v5::ConnAck<'static> {
+    type Packet<'a> = v5::ConnAck<'a>;
+}
+
+impl Receive for v5::Publish<'static> {
+    type Packet<'a> = v5::Publish<'a>;
+}
+
+impl Receive for v5::PubAck<'static> {
+    type Packet<'a> = v5::PubAck<'a>;
+}
+
+impl Receive for v5::PubRec {
+    type Packet<'a> = v5::PubRec;
+}
+
+impl Receive for v5::PubComp {
+    type Packet<'a> = v5::PubComp;
+}
+
+impl Receive for v5::PingResp {
+    type Packet<'a> = v5::PingResp;
+}
+
+#[cfg(feature = "subscribe")]
+impl Receive for v5::SubAck<'static> {
+    type Packet<'a> = v5::SubAck<'a>;
+}
+
+#[cfg(feature = "unsubscribe")]
+impl Receive for v5::UnsubAck<'static> {
+    type Packet<'a> = v5::UnsubAck<'a>;
+}
+
+#[cfg(feature = "auth")]
+impl Receive for v5::Auth<'static> {
+    type Packet<'a> = v5::Auth<'a>;
+}
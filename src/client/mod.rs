@@ -1,40 +1,345 @@
-use core::sync::atomic::{AtomicU16, Ordering};
+use core::ops::Range;
+use core::pin::pin;
+use core::sync::atomic::AtomicU16;
+use core::time::Duration;
 
-use crate::log;
-use crate::protocol::types::FixedHeader;
-use crate::protocol::v5::TopicFilter;
-use crate::protocol::{Packet, PacketError, Parse, ParseError, QoS, v5};
-use crate::traits::{Buffer, Writable};
+use crate::entropy::EntropySource;
+use crate::log::{self, LogPacket, Logged};
+use crate::protocol::codec::{DecodeError, Decoder, Encoder};
+use crate::protocol::types::PacketId;
+use crate::protocol::{self, Packet, PacketParse, Parse, QoS, WriteError, v5};
+use crate::traits::Writable;
+pub use crate::traits::{Buffer, BufferNotResizable};
 
+mod builder;
+mod client_id;
+mod clock;
+#[cfg(feature = "std")]
+mod commands;
 mod connect;
+mod dedup;
+mod disconnect;
+#[cfg(feature = "std")]
+mod drop;
 mod error;
+mod event;
+mod message;
+mod pacing;
+mod packet_ids;
+mod ping;
+mod presence;
+mod publish;
+mod recorder;
+mod reply;
+mod staging;
+mod stats;
+#[cfg(feature = "subscribe")]
+mod subscribe;
+mod time;
+mod timeout;
 mod utils;
+mod watermark;
 
-pub use self::connect::{Connect, ConnectResponse};
+pub use self::builder::{Compaction, ConnectionBuilder};
+pub use self::client_id::ClientId;
+pub use self::clock::Clock;
+#[cfg(feature = "std")]
+pub use self::clock::StdClock;
+#[cfg(feature = "std")]
+pub use self::commands::{Command, CommandHandle, CommandQueue, TrySendError};
+#[cfg(feature = "auth")]
+pub use self::connect::Authenticator;
+use self::connect::ConnectOptions;
+#[cfg(feature = "auth")]
+use self::connect::{Authentication, ConnectReply};
+pub use self::connect::{Connect, ConnectResponse, Password};
+pub use self::dedup::Dedup;
+pub use self::disconnect::Disconnect;
+use self::disconnect::DisconnectOptions;
+#[cfg(feature = "std")]
+pub use self::drop::DisconnectOnDrop;
 pub use self::error::{Error, Result};
+pub use self::event::{Event, Events};
+use self::message::{Ack, Receive, Release};
+pub use self::message::{Message, MessageGuard, ReceiveOr};
+pub use self::pacing::{Chunked, Pacing, Plan};
+pub use self::packet_ids::{PacketIdWindow, WindowFull};
+#[cfg(feature = "embedded-hal-async-1")]
+use self::ping::DelayPings;
+use self::ping::{NoPings, Pings};
+#[cfg(feature = "will")]
+pub use self::presence::Presence;
+#[cfg(not(feature = "will"))]
+use self::presence::Presence;
+pub use self::publish::{DeadLetter, Publish};
+use self::publish::{Detached, PublishOptions};
+use self::recorder::Recording;
+pub use self::recorder::{Direction, Frame, Frames, Recorder};
+pub use self::reply::ReplyTo;
+pub use self::staging::{Handle, Staging, TryPublishError};
+pub use self::stats::{ReasonCounts, Stats, StatsReport};
+#[cfg(feature = "subscribe")]
+pub use self::subscribe::Subscribe;
+#[cfg(feature = "subscribe")]
+use self::subscribe::SubscribeOptions;
+pub use self::time::{BrokerTime, TimeSync};
+#[cfg(feature = "embedded-hal-async-1")]
+pub use self::timeout::DelayTimeout;
+pub use self::timeout::{NoTimeout, Timeout};
 pub use self::utils::MakeFuture;
+use self::utils::{Either, select};
+pub use self::watermark::Watermark;
+use self::watermark::Watermarks;
 
-pub struct Client<C, B> {
+pub struct Client<C, B, T = NoTimeout> {
     // TODO: connection should possibly a trait to make dealing with it easier, or make the Client
     // a trait.
     connection: Connection<C, B>,
     identifier: AtomicU16, // TODO: maybe we don't need the atomic here
+    timeout: T,
+    /// The effective keep alive interval in seconds.
+    keep_alive: u16,
+    /// The effective session expiry interval in seconds.
+    session_expiry: u32,
+    /// Whether the client successfully connected and did not yet disconnect.
+    connected: bool,
+    /// A connect which was started, but not yet acknowledged.
+    pending_connect: Option<PendingConnect>,
+    /// The client identifier last assigned by the server.
+    assigned_client_id: Option<ClientId>,
+}
+
+/// A connect which was started, but not yet acknowledged, see [`Client::pending_connect`].
+#[derive(Debug, Clone, Copy)]
+struct PendingConnect {
+    /// The requested keep alive interval in seconds.
+    keep_alive: u16,
+    /// The lowest and highest acceptable keep alive interval in seconds.
+    keep_alive_bounds: (u16, u16),
+    /// The requested session expiry interval in seconds.
+    session_expiry: u32,
+    /// Whether the connect packet was completely sent.
+    sent: bool,
+}
+
+/// A subscription which was sent, but whose result was not yet returned, see
+/// [`Client::pending_subscribe`].
+#[cfg(feature = "subscribe")]
+#[derive(Debug, Clone, Copy)]
+struct PendingSubscribe {
+    /// The packet identifier of the subscription.
+    identifier: PacketId,
+    /// The reason of the first topic filter, if the acknowledgement was already received while
+    /// receiving other packets.
+    reason: Option<v5::SubAckReason>,
 }
 
 impl<C, B> Client<C, B> {
     pub fn new(connection: Connection<C, B>) -> Self {
+        Self::with_timeout(connection, NoTimeout)
+    }
+}
+
+impl<C, B, T> Client<C, B, T> {
+    /// Creates a new client, which uses `timeout` to limit how long it waits for responses
+    /// from the server.
+    ///
+    /// Operations which do not complete in time fail with [`Error::Timeout`].
+    pub fn with_timeout(connection: Connection<C, B>, timeout: T) -> Self {
         Self {
             connection,
             identifier: AtomicU16::new(20_000),
+            timeout,
+            keep_alive: 0,
+            session_expiry: 0,
+            connected: false,
+            pending_connect: None,
+            assigned_client_id: None,
+        }
+    }
+
+    /// Returns whether the client is connected.
+    ///
+    /// A client is connected after a successful [connect](Self::connect), until it
+    /// [disconnects](Self::disconnect) or the server closes the connection.
+    pub fn is_connected(&self) -> bool {
+        self.connected && !self.connection.closed
+    }
+
+    /// Starts packet identifiers at a random value, instead of a fixed value.
+    ///
+    /// This makes identifiers less likely to collide with identifiers still in use from
+    /// a previous connection.
+    pub fn seed_identifiers<E>(&mut self, mut entropy: E)
+    where
+        E: EntropySource,
+    {
+        *self.identifier.get_mut() = (entropy.next_u32() as u16).max(1);
+    }
+
+    /// Wraps the client into a guard, which disconnects the client when dropped.
+    ///
+    /// See [`DisconnectOnDrop`].
+    #[cfg(feature = "std")]
+    pub fn disconnect_on_drop(self) -> DisconnectOnDrop<C, B, T>
+    where
+        C: embedded_io_async::Write,
+    {
+        DisconnectOnDrop::new(self)
+    }
+
+    /// Returns the frame recorder of the connection, if configured.
+    ///
+    /// See [`ConnectionBuilder::with_recorder`].
+    pub fn recorder(&self) -> Option<&Recorder> {
+        self.connection.recorder()
+    }
+
+    /// Returns the stats of the connection.
+    pub fn stats(&self) -> &Stats {
+        self.connection.stats()
+    }
+
+    /// Returns the amount of [detached](Publish::detach) publishes, which are not yet
+    /// acknowledged by the server.
+    ///
+    /// Acknowledgements are consumed whenever the client receives from the server, e.g. with
+    /// [`Self::receive`] or [`Self::ping`].
+    pub fn pending_acks(&self) -> usize {
+        self.connection.detached.pending()
+    }
+
+    /// Returns the time data was last received from the server.
+    ///
+    /// Requires a clock, see [`ConnectionBuilder::with_clock`]. Together with
+    /// [`Self::keep_alive_expired`], this detects an unreachable server.
+    pub fn last_rx_at(&self) -> Option<Duration> {
+        self.connection.last_rx_at()
+    }
+
+    /// Returns the time a packet was last sent to the server.
+    ///
+    /// Requires a clock, see [`ConnectionBuilder::with_clock`]. Together with
+    /// [`Self::ping_due_in`], this schedules the keep alive.
+    pub fn last_tx_at(&self) -> Option<Duration> {
+        self.connection.last_tx_at()
+    }
+
+    /// Returns the client identifier last assigned by the server.
+    ///
+    /// The server assigns an identifier, when the client connects with an empty identifier. The
+    /// identifier is kept across connects, to resume the session with it on a reconnect.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # async fn example<C>(
+    /// #     client: &mut miniqtt::Client<C, [u8; 128]>,
+    /// # ) -> miniqtt::client::Result<(), C::Error>
+    /// # where
+    /// #     C: embedded_io_async::Read + embedded_io_async::Write,
+    /// # {
+    /// // The identifier is copied, the client is borrowed mutably by the connect.
+    /// let client_id = client.assigned_client_id().copied();
+    /// let client_id = client_id.as_deref().unwrap_or("");
+    ///
+    /// client.connect(client_id).resume_session(true).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn assigned_client_id(&self) -> Option<&ClientId> {
+        self.assigned_client_id.as_ref()
+    }
+
+    /// Returns the effective keep alive interval in seconds.
+    ///
+    /// This is the keep alive requested when [connecting](Self::connect), unless the server
+    /// assigned a different keep alive. A value of `0` disables the keep alive mechanism.
+    pub fn keep_alive(&self) -> u16 {
+        self.keep_alive
+    }
+
+    /// Returns how long until the client must send a packet, to keep the connection alive.
+    ///
+    /// `since_last_sent` is the time since the client last sent a packet. A [ping](Self::ping)
+    /// must be sent, if no other packet is sent before the returned duration elapses.
+    ///
+    /// Returns `None` if the [keep alive](Self::keep_alive) is `0`, the client must not send
+    /// pings in this case.
+    pub fn ping_due_in(&self, since_last_sent: Duration) -> Option<Duration> {
+        match self.keep_alive {
+            0 => None,
+            keep_alive => {
+                Some(Duration::from_secs(u64::from(keep_alive)).saturating_sub(since_last_sent))
+            }
+        }
+    }
+
+    /// Returns how long until the client must send a PINGREQ, measured with the configured
+    /// [clock](ConnectionBuilder::with_clock).
+    ///
+    /// Like [`Self::ping_due_in`], with the time since the [last sent](Self::last_tx_at) packet
+    /// taken from the clock. Returns `None` if the keep alive is disabled or no clock is
+    /// configured.
+    pub fn next_ping_in(&self) -> Option<Duration> {
+        let clock = self.connection.clock?;
+        let since_last_sent = self
+            .connection
+            .last_tx
+            .map_or(Duration::MAX, |last_tx| clock.now().saturating_sub(last_tx));
+        self.ping_due_in(since_last_sent)
+    }
+
+    /// Returns whether the server is considered unreachable, because it did not send anything
+    /// for `since_last_received`.
+    ///
+    /// Like the server does for clients, the connection is considered broken after one and a
+    /// half times the [keep alive](Self::keep_alive). Never returns `true` if the keep alive
+    /// is `0`, the server must not be timed out in this case.
+    ///
+    /// Spec: [3.1.2.10](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901045)
+    pub fn keep_alive_expired(&self, since_last_received: Duration) -> bool {
+        match self.keep_alive {
+            0 => false,
+            keep_alive => since_last_received > Duration::from_millis(u64::from(keep_alive) * 1500),
+        }
+    }
+
+    /// Returns the effective session expiry interval in seconds.
+    ///
+    /// This is the interval requested with [`v5::ConnectProperty::SessionExpiryInterval`] when
+    /// [connecting](Self::connect), unless the server assigned a different interval. A value of
+    /// `0` ends the session with the network connection, `u32::MAX` means the session never
+    /// expires.
+    pub fn session_expiry_interval(&self) -> u32 {
+        self.session_expiry
+    }
+
+    /// Returns how long the server keeps the session after the network connection was closed
+    /// `disconnected_for` ago.
+    ///
+    /// Returns `None` if the session never expires. Reconnecting with
+    /// [`resume_session`](Connect::resume_session) before the returned duration elapses keeps
+    /// the session, including messages queued by the server.
+    ///
+    /// The client does not keep track of time, the caller measures how long the client has been
+    /// disconnected.
+    pub fn session_expires_in(&self, disconnected_for: Duration) -> Option<Duration> {
+        match self.session_expiry {
+            u32::MAX => None,
+            interval => {
+                Some(Duration::from_secs(u64::from(interval)).saturating_sub(disconnected_for))
+            }
         }
     }
 }
 
-impl<C, B> Client<C, B>
+impl<C, B, T> Client<C, B, T>
 where
     C: embedded_io_async::Read,
     C: embedded_io_async::Write,
     B: Buffer,
+    T: Timeout,
 {
     // TODO: maybe only connected clients should be able to be created via a builder.
     // TODO: sending methods could send the payload, then return a future which simply awaits
@@ -44,99 +349,1170 @@ where
 
     /// Initiates a connection with the MQTT broker.
     ///
+    /// Returns [`Error::UnsupportedProtocolVersion`] if the server does not support MQTT 5.
+    ///
     /// # Cancel safety
     ///
     /// The returned future is *not* cancel safe.
     pub fn connect<'a>(
         &mut self,
         client_id: &'a str,
-    ) -> Connect<'a, impl MakeFuture<v5::Connect<'a>, Output = Result<ConnectResponse, C::Error>>>
+    ) -> Connect<'a, impl MakeFuture<ConnectOptions<'a>, Output = Result<ConnectResponse, C::Error>>>
+    {
+        Connect::new(
+            client_id,
+            |options| async move { self.establish(options).await },
+        )
+    }
+
+    /// Sends the CONNECT and awaits the acknowledgement of the server, see [`Self::connect`].
+    async fn establish(
+        &mut self,
+        options: ConnectOptions<'_>,
+    ) -> Result<ConnectResponse, C::Error> {
+        let ConnectOptions {
+            packet,
+            presence,
+            time_sync,
+            keep_alive_bounds,
+            reconnect,
+            authenticator,
+        } = options;
+
+        protocol::validate_client_id(packet.client_id).map_err(Error::Invalid)?;
+        if self.is_connected() {
+            if !reconnect {
+                return Err(Error::AlreadyConnected);
+            }
+            log::debug!("already connected, disconnecting before connecting again");
+            // A planned reconnect must not publish the will message.
+            self.disconnect()
+                .reason(v5::DisconnectReason::NormalDisconnection)
+                .await?;
+            self.connection.discard_buffered();
+        }
+        if packet.client_id.len() > protocol::MAX_CLIENT_ID_COMPAT {
+            log::debug!(
+                "client identifier exceeds {} bytes, it may be rejected",
+                protocol::MAX_CLIENT_ID_COMPAT
+            );
+        }
+
+        let pending = PendingConnect {
+            keep_alive: packet.keep_alive,
+            keep_alive_bounds,
+            // Without properties, no session expiry interval is sent and the server uses `0`.
+            session_expiry: packet
+                .properties
+                .iter()
+                .filter(|_| cfg!(feature = "properties"))
+                .find_map(|property| match property {
+                    v5::ConnectProperty::SessionExpiryInterval(v) => Some(*v),
+                    _ => None,
+                })
+                .unwrap_or(0),
+            sent: false,
+        };
+        self.pending_connect = Some(pending);
+
+        self.connection.closed = false;
+        // Unanswered packets of the previous connection are sent again by the server.
+        self.connection.release.pending = None;
+        self.connection.pending_release = None;
+        let connection = &mut self.connection;
+        let pending_connect = &mut self.pending_connect;
+        let response = self
+            .timeout
+            .timeout(async move {
+                #[cfg(feature = "auth")]
+                let mut authenticator = authenticator;
+                #[cfg(feature = "auth")]
+                let auth_properties = match &mut authenticator {
+                    Some(authenticator) => {
+                        let method = authenticator.method();
+                        let data = authenticator
+                            .respond(None)
+                            .ok_or(Error::AuthenticationAborted)?;
+                        Some([
+                            v5::ConnectProperty::AuthenticationMethod(method),
+                            v5::ConnectProperty::AuthenticationData(data),
+                        ])
+                    }
+                    None => None,
+                };
+                #[cfg(not(feature = "auth"))]
+                let auth_properties: Option<[v5::ConnectProperty<'_>; 0]> = None;
+
+                let properties = [
+                    packet.properties,
+                    auth_properties.as_ref().map_or(&[][..], |p| &p[..]),
+                ];
+                let packet = v5::Connect {
+                    client_id: packet.client_id,
+                    keep_alive: packet.keep_alive,
+                    clean_start: packet.clean_start,
+                    will: packet.will,
+                    username: packet.username,
+                    password: packet.password,
+                    properties: &properties[..],
+                };
+
+                connection.send(&packet).await?;
+                *pending_connect = Some(PendingConnect {
+                    sent: true,
+                    ..pending
+                });
+
+                match authenticator {
+                    #[cfg(feature = "auth")]
+                    Some(authenticator) => {
+                        connection
+                            .authenticate_connect(authenticator, time_sync)
+                            .await
+                    }
+                    _ => connection
+                        .receive::<v5::ConnAck>()
+                        .await
+                        .map(|ack| ConnectResponse::new(&ack, time_sync)),
+                }
+            })
+            .await
+            .ok_or(Error::Timeout)??;
+
+        let response = self.complete_connect(pending, response)?;
+        if response.successful()
+            && let Some(presence) = presence
+        {
+            self.connection.send(&presence.online()).await?;
+        }
+
+        // TODO: according to the protocol, if the reason is not successful, the client must
+        // terminate the connection. Currently the connection trait just asks for Read/Write,
+        // there is no way to force close the connection.
+        //
+        // Maybe that is okay, maybe we should keep internal state on the client/connection and
+        // reject all further interactions, or just do nothing.
+
+        Ok(response)
+    }
+
+    /// Returns whether a connect was started, but not yet acknowledged by the server.
+    ///
+    /// A connect is pending, when the [`Self::connect`] future is cancelled or times out
+    /// before the server responded.
+    pub fn pending_connect(&self) -> bool {
+        self.pending_connect.is_some()
+    }
+
+    /// Waits for the acknowledgement of a [pending](Self::pending_connect) connect, without
+    /// sending the connect again.
+    ///
+    /// Returns `None` if no connect is pending. Otherwise returns the response of the server,
+    /// like the original [`Self::connect`]. A [presence](Connect::with_presence) is not
+    /// announced and the [broker time](Connect::with_time_sync) is not read when the connect is
+    /// resumed.
+    ///
+    /// Fails with [`Error::InterruptedWrite`] if the connect was cancelled while it was being
+    /// sent. The transport is in an unknown state and a new connection must be established.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method *is* cancel safe, the connect stays pending until it is acknowledged.
+    pub async fn resume_connect(&mut self) -> Result<Option<ConnectResponse>, C::Error> {
+        let Some(pending) = self.pending_connect else {
+            return Ok(None);
+        };
+        if !pending.sent {
+            return Err(Error::InterruptedWrite);
+        }
+
+        let response = self
+            .timeout
+            .timeout(async {
+                self.connection
+                    .receive::<v5::ConnAck>()
+                    .await
+                    .map(|ack| ConnectResponse::new(&ack, None))
+            })
+            .await
+            .ok_or(Error::Timeout)??;
+
+        self.complete_connect(pending, response).map(Some)
+    }
+
+    /// Applies the acknowledgement of a connect.
+    fn complete_connect(
+        &mut self,
+        pending: PendingConnect,
+        mut response: ConnectResponse,
+    ) -> Result<ConnectResponse, C::Error> {
+        self.pending_connect = None;
+        self.connection.stats.record_reason(response.reason as u8);
+
+        if response.reason == v5::ConnAckReason::UnsupportedProtocolVersion {
+            return Err(Error::UnsupportedProtocolVersion);
+        }
+
+        if response.reason == v5::ConnAckReason::Success {
+            let (min, max) = pending.keep_alive_bounds;
+            if let Some(keep_alive) = response.server_keep_alive
+                && !(min..=max).contains(&keep_alive)
+            {
+                log::debug!("server keep alive {keep_alive} is outside of {min}..={max}");
+                response.keep_alive_out_of_bounds = true;
+            }
+
+            self.keep_alive = response.server_keep_alive.unwrap_or(pending.keep_alive);
+            self.session_expiry = response
+                .session_expiry_interval
+                .unwrap_or(pending.session_expiry);
+            self.connected = true;
+            if !response.session_present() {
+                self.connection.received.clear();
+            }
+            self.connection.detached.reset();
+            if let Some(id) = response.assigned_client_id {
+                self.assigned_client_id = Some(id);
+            }
+            #[cfg(feature = "subscribe")]
+            {
+                self.connection.pending_subscribe = None;
+            }
+            self.connection.stats.record_connect();
+        }
+
+        Ok(response)
+    }
+
+    /// Subscribes to a topic.
+    ///
+    /// The returned future can be configured with the options of the subscription, e.g. the
+    /// [QoS](Subscribe::qos), before it is awaited.
+    ///
+    /// Returns the reason of the server's acknowledgement, which contains the
+    /// [granted QoS](v5::SubAckReason::granted_qos). Returns [`Error::SubscriptionRejected`] if
+    /// the server does not accept the subscription.
+    ///
+    /// If a previous subscribe was cancelled or timed out after the subscription was sent, its
+    /// acknowledgement is awaited first, see [`Self::resume_subscribe`].
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use miniqtt::protocol::{QoS, v5::RetainHandling};
+    /// # async fn example<C>(client: &mut miniqtt::Client<C, Vec<u8>>) -> miniqtt::client::Result<(), C::Error>
+    /// # where
+    /// #     C: embedded_io_async::Read + embedded_io_async::Write,
+    /// # {
+    /// let granted = client
+    ///     .subscribe("sensors/#")
+    ///     .qos(QoS::AtLeastOnce)
+    ///     .no_local(true)
+    ///     .retain_handling(RetainHandling::DoNotSendRetained)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "subscribe")]
+    pub fn subscribe<'a>(
+        &mut self,
+        topic: &'a str,
+    ) -> Subscribe<
+        'a,
+        impl MakeFuture<SubscribeOptions<'a>, Output = Result<v5::SubAckReason, C::Error>>,
+    > {
+        Subscribe::new(topic, |options| async move {
+            match self.resume_subscribe().await {
+                Ok(_) | Err(Error::SubscriptionRejected(_)) => {}
+                Err(err) => return Err(err),
+            }
+
+            let packet = v5::Subscribe {
+                identifier: self.next_identifier(),
+                properties: options.properties,
+                topics: &[options.filter],
+            };
+            self.connection.send(&packet).await?;
+            self.connection.pending_subscribe = Some(PendingSubscribe {
+                identifier: packet.identifier,
+                reason: None,
+            });
+
+            self.resume_subscribe().await?.ok_or(Error::Protocol)
+        })
+    }
+
+    /// Returns the identifier of a subscription, which was sent but not yet acknowledged.
+    ///
+    /// A subscription is pending, when a [`Self::subscribe`] future is cancelled or times out
+    /// while waiting for the acknowledgement of the server. It stays pending until its result
+    /// is returned by [`Self::resume_subscribe`], also when the acknowledgement is received
+    /// while receiving messages.
+    #[cfg(feature = "subscribe")]
+    pub fn pending_subscribe(&self) -> Option<PacketId> {
+        self.connection
+            .pending_subscribe
+            .map(|pending| pending.identifier)
+    }
+
+    /// Waits for the acknowledgement of a [pending](Self::pending_subscribe) subscription,
+    /// without sending the subscription again.
+    ///
+    /// Returns `None` immediately if no subscription is pending. Otherwise returns the result of
+    /// the original [`Self::subscribe`], without waiting if the acknowledgement was already
+    /// received, e.g. by [`Self::receive`].
+    ///
+    /// # Cancel safety
+    ///
+    /// This method *is* cancel safe, the subscription stays pending until it is acknowledged.
+    #[cfg(feature = "subscribe")]
+    pub async fn resume_subscribe(&mut self) -> Result<Option<v5::SubAckReason>, C::Error> {
+        let Some(PendingSubscribe { identifier, reason }) = self.connection.pending_subscribe
+        else {
+            return Ok(None);
+        };
+
+        let (ack_identifier, reason) = match reason {
+            Some(reason) => (identifier, Some(reason)),
+            None => {
+                let ack = self
+                    .timeout
+                    .timeout(self.connection.receive::<v5::SubAck>())
+                    .await
+                    .ok_or(Error::Timeout)??;
+
+                let ack_identifier = ack.identifier;
+                let reason = ack.reasons().next();
+                drop(ack);
+                if let Some(reason) = reason {
+                    self.connection.stats.record_reason(reason as u8);
+                }
+                (ack_identifier, reason)
+            }
+        };
+        self.connection.pending_subscribe = None;
+
+        if ack_identifier != identifier {
+            log::debug!("unexpected suback identifier: {ack_identifier}");
+            return Err(Error::Protocol);
+        }
+
+        match reason {
+            Some(reason) if reason.is_success() => Ok(Some(reason)),
+            Some(reason) => Err(Error::SubscriptionRejected(reason)),
+            None => Err(Error::Protocol),
+        }
+    }
+
+    /// Subscribes to multiple topic filters with a single SUBSCRIBE packet.
+    ///
+    /// Saves a round trip per additional topic filter over [`Self::subscribe`]. Returns the
+    /// granted QoS or the reason of the rejection for each topic filter, in the order of the
+    /// topic filters. Unlike [`Self::subscribe`], a rejected topic filter is not an error.
+    ///
+    /// Fails with [`Error::Invalid`] if no topic filter is passed.
+    ///
+    /// A cancelled multi-topic subscribe can be resumed with [`Self::resume_subscribe`], which
+    /// only reports the result of the first topic filter.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use miniqtt::protocol::{QoS, v5::{RetainHandling, TopicFilter}};
+    /// # async fn example<C>(client: &mut miniqtt::Client<C, Vec<u8>>) -> miniqtt::client::Result<(), C::Error>
+    /// # where
+    /// #     C: embedded_io_async::Read + embedded_io_async::Write,
+    /// # {
+    /// let filter = |name| TopicFilter {
+    ///     name,
+    ///     qos: QoS::AtLeastOnce,
+    ///     no_local: false,
+    ///     retain_as_published: false,
+    ///     retain: RetainHandling::SendRetained,
+    /// };
+    ///
+    /// let [config, commands] = client
+    ///     .subscribe_many(&[filter("device/config"), filter("device/commands")])
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is *not* cancel safe.
+    #[cfg(feature = "subscribe")]
+    pub async fn subscribe_many<const N: usize>(
+        &mut self,
+        filters: &[v5::TopicFilter<'_>; N],
+    ) -> Result<[core::result::Result<QoS, v5::SubAckReason>; N], C::Error> {
+        if N == 0 {
+            return Err(Error::Invalid(protocol::ValidationError::Empty));
+        }
+
+        match self.resume_subscribe().await {
+            Ok(_) | Err(Error::SubscriptionRejected(_)) => {}
+            Err(err) => return Err(err),
+        }
+
+        let identifier = self.next_identifier();
+        let packet = v5::Subscribe {
+            identifier,
+            properties: &[],
+            topics: filters,
+        };
+        self.connection.send(&packet).await?;
+        self.connection.pending_subscribe = Some(PendingSubscribe {
+            identifier,
+            reason: None,
+        });
+
+        let ack = self
+            .timeout
+            .timeout(self.connection.receive::<v5::SubAck>())
+            .await
+            .ok_or(Error::Timeout)??;
+
+        let ack_identifier = ack.identifier;
+        let ack_len = ack.reasons().len();
+        let mut reasons = [v5::SubAckReason::UnspecifiedError; N];
+        for (slot, reason) in reasons.iter_mut().zip(ack.reasons()) {
+            *slot = reason;
+        }
+        drop(ack);
+        self.connection.pending_subscribe = None;
+        for reason in &reasons[..ack_len.min(N)] {
+            self.connection.stats.record_reason(*reason as u8);
+        }
+
+        if ack_identifier != identifier {
+            log::debug!("unexpected suback identifier: {ack_identifier}");
+            return Err(Error::Protocol);
+        }
+
+        if ack_len != N {
+            log::debug!("suback contains {ack_len} reasons for {N} topic filters");
+            return Err(Error::Protocol);
+        }
+
+        Ok(reasons.map(|reason| reason.granted_qos().ok_or(reason)))
+    }
+
+    /// Unsubscribes from a topic filter.
+    ///
+    /// Succeeds if the client was not subscribed to the topic filter.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is *not* cancel safe.
+    #[cfg(feature = "unsubscribe")]
+    pub async fn unsubscribe(&mut self, topic: &str) -> Result<(), C::Error> {
+        #[cfg(feature = "subscribe")]
+        match self.resume_subscribe().await {
+            Ok(_) | Err(Error::SubscriptionRejected(_)) => {}
+            Err(err) => return Err(err),
+        }
+
+        let packet = v5::Unsubscribe {
+            identifier: self.next_identifier(),
+            topics: &[topic],
+        };
+        self.connection.send(&packet).await?;
+
+        let ack = self
+            .timeout
+            .timeout(self.connection.receive::<v5::UnsubAck>())
+            .await
+            .ok_or(Error::Timeout)??;
+
+        let ack_identifier = ack.identifier;
+        let reason = ack.reasons().next();
+        drop(ack);
+        if let Some(reason) = reason {
+            self.connection.stats.record_reason(reason as u8);
+        }
+
+        if ack_identifier != packet.identifier {
+            log::debug!("unexpected unsuback identifier: {ack_identifier}");
+            return Err(Error::Protocol);
+        }
+
+        match reason {
+            Some(reason) if reason.is_success() => Ok(()),
+            Some(reason) => Err(Error::UnsubscribeRejected(reason)),
+            None => Err(Error::Protocol),
+        }
+    }
+
+    /// Fetches the retained message of a topic, e.g. to bootstrap the configuration at boot.
+    ///
+    /// Subscribes to the topic, waits for its retained message and unsubscribes again. The
+    /// payload of the retained message is passed to `f`, which can also decompress or decode it.
+    ///
+    /// Returns `None` if the topic has no retained message. The server sends nothing in this
+    /// case, the client waits until its [`Timeout`] elapses, a client without a timeout waits
+    /// forever.
+    ///
+    /// Other messages received while waiting are dropped. This is best used before subscribing to
+    /// other topics.
+    ///
+    /// Fails with [`Error::Invalid`] if `topic` is not a valid topic name, e.g. a topic filter
+    /// containing wildcards.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # async fn example<C, T>(
+    /// #     client: &mut miniqtt::Client<C, [u8; 128], T>,
+    /// # ) -> miniqtt::client::Result<(), C::Error>
+    /// # where
+    /// #     C: embedded_io_async::Read + embedded_io_async::Write,
+    /// #     T: miniqtt::client::Timeout,
+    /// # {
+    /// # fn decompress(data: &[u8], out: &mut [u8]) -> usize { 0 }
+    /// let mut config = [0; 256];
+    /// let len = client
+    ///     .fetch_retained("device/42/config", |payload| decompress(payload, &mut config))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(all(feature = "subscribe", feature = "unsubscribe"))]
+    pub async fn fetch_retained<F, R>(&mut self, topic: &str, f: F) -> Result<Option<R>, C::Error>
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        // Messages are matched by their topic, a wildcard filter would never match.
+        protocol::validate_topic(topic).map_err(Error::Invalid)?;
+        self.subscribe(topic).await?;
+
+        let connection = &mut self.connection;
+        let result = self
+            .timeout
+            .timeout(async move {
+                loop {
+                    let message = connection.receive::<v5::Publish>().await?;
+                    if message.retain && message.topic == topic {
+                        return Ok::<_, Error<C::Error>>(f(message.payload));
+                    }
+                    log::debug!("dropping message while waiting for the retained message");
+                }
+            })
+            .await
+            .transpose()?;
+
+        self.unsubscribe(topic).await?;
+
+        Ok(result)
+    }
+
+    /// Re-authenticates the client during the active session, using extended authentication.
+    ///
+    /// Sends an AUTH packet with the reason [`ReAuthenticate`](v5::AuthReason::ReAuthenticate)
+    /// and returns the response of the server. The `method` must match the authentication method
+    /// used to connect. As long as the server responds with
+    /// [`ContinueAuthentication`](v5::AuthReason::ContinueAuthentication), the next step of the
+    /// exchange is sent with [`Self::continue_authentication`]. A server which rejects the
+    /// authentication closes the connection.
+    ///
+    /// Nothing else is sent until the exchange completes. Messages published by the server while
+    /// the exchange is in progress stay buffered and are returned by [`Self::receive`] afterwards.
+    ///
+    /// Spec: [4.12.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901257)
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use miniqtt::protocol::v5::AuthReason;
+    /// # async fn example<C>(
+    /// #     client: &mut miniqtt::Client<C, [u8; 128]>,
+    /// # ) -> miniqtt::client::Result<(), C::Error>
+    /// # where
+    /// #     C: embedded_io_async::Read + embedded_io_async::Write,
+    /// # {
+    /// # fn respond(challenge: Option<&[u8]>) -> [u8; 4] { [0; 4] }
+    /// let mut auth = client.reauthenticate("SCRAM-SHA-1", b"client-first").await?;
+    /// while auth.reason == AuthReason::ContinueAuthentication {
+    ///     let response = respond(auth.properties.authentication_data());
+    ///     drop(auth);
+    ///     auth = client.continue_authentication("SCRAM-SHA-1", &response).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "auth")]
+    pub async fn reauthenticate(
+        &mut self,
+        method: &str,
+        data: &[u8],
+    ) -> Result<MessageGuard<'_, v5::Auth<'_>>, C::Error> {
+        self.authenticate(v5::AuthReason::ReAuthenticate, method, data)
+            .await
+    }
+
+    /// Sends the next step of an extended authentication exchange and returns the response of
+    /// the server.
+    ///
+    /// See [`Self::reauthenticate`].
+    #[cfg(feature = "auth")]
+    pub async fn continue_authentication(
+        &mut self,
+        method: &str,
+        data: &[u8],
+    ) -> Result<MessageGuard<'_, v5::Auth<'_>>, C::Error> {
+        self.authenticate(v5::AuthReason::ContinueAuthentication, method, data)
+            .await
+    }
+
+    #[cfg(feature = "auth")]
+    async fn authenticate(
+        &mut self,
+        reason: v5::AuthReason,
+        method: &str,
+        data: &[u8],
+    ) -> Result<MessageGuard<'_, v5::Auth<'_>>, C::Error> {
+        let properties = [
+            v5::AuthProperty::AuthenticationMethod(method),
+            v5::AuthProperty::AuthenticationData(data),
+        ];
+        self.connection
+            .send(&v5::Auth::new(reason, &properties[..]))
+            .await?;
+
+        self.timeout
+            .timeout(self.connection.receive::<v5::Auth>())
+            .await
+            .ok_or(Error::Timeout)?
+    }
+
+    /// Publishes a message.
+    ///
+    /// The returned [`Publish`] configures the message, e.g. its quality of service or whether
+    /// it is retained, before it is sent.
+    ///
+    /// A QoS 1 publish waits for the acknowledgement of the server, a QoS 2 publish completes
+    /// the exactly once handshake with the server. Both return [`Error::PublishRejected`] if
+    /// the server does not accept the message.
+    ///
+    /// Messages which arrive while waiting for the server stay buffered and are returned by the
+    /// following calls to [`Self::receive`]. If they fill up the buffer before the server
+    /// responds, publishing fails with [`Error::InsufficientBufferSize`].
+    ///
+    /// Returns the packet identifier of a [detached](Publish::detach) publish, which identifies
+    /// it in the dead letter callback, otherwise `None`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # async fn example<C>(
+    /// #     client: &mut miniqtt::Client<C, [u8; 128]>,
+    /// # ) -> miniqtt::client::Result<(), C::Error>
+    /// # where
+    /// #     C: embedded_io_async::Read + embedded_io_async::Write,
+    /// # {
+    /// client
+    ///     .publish("sensors/temperature", b"21.5")
+    ///     .retain(true)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Cancel safety
+    ///
+    /// The returned future is *not* cancel safe.
+    // TODO: Topic Alias (publish(..).with_alias(&mut my_alias)), where the alias tracks its
+    // internal register state (including id). Not sure how you'd free an alias again, maybe there
+    // is just no API for that and you just re-use different topic ids?
+    pub fn publish<'a>(
+        &mut self,
+        topic: &'a str,
+        payload: &'a [u8],
+    ) -> Publish<'a, impl MakeFuture<PublishOptions<'a>, Output = Result<Option<PacketId>, C::Error>>>
+    {
+        Publish::new(topic, payload, |options| async move {
+            self.connection.check_publish_topic(options.topic)?;
+
+            let identifier = match options.qos {
+                QoS::AtMostOnce => None,
+                _ => Some(self.next_identifier()),
+            };
+            let mut buffer = [v5::PublishProperty::PayloadFormatIndicator(0); 5];
+            let builder_properties = options.builder_properties(&mut buffer);
+            let packet = v5::Publish {
+                dup: false,
+                qos: options.qos,
+                retain: options.retain,
+                identifier,
+                topic: options.topic,
+                properties: &[builder_properties, options.properties][..],
+                payload: options.payload,
+            };
+            self.connection.send(&packet).await?;
+
+            match (options.qos, identifier) {
+                (QoS::AtLeastOnce, Some(identifier))
+                    if options.detach && self.connection.detached.insert(identifier) =>
+                {
+                    return Ok(Some(identifier));
+                }
+                (QoS::AtLeastOnce, Some(identifier)) => self.receive_pub_ack(identifier).await?,
+                (QoS::ExactlyOnce, Some(identifier)) => {
+                    self.complete_exactly_once(identifier).await?
+                }
+                _ => {}
+            }
+            Ok(None)
+        })
+    }
+
+    /// Waits for the PUBACK of a QoS 1 publish.
+    async fn receive_pub_ack(&mut self, identifier: PacketId) -> Result<(), C::Error> {
+        let (ack_identifier, reason) = loop {
+            let ack = self
+                .timeout
+                .timeout(self.connection.receive::<v5::PubAck>())
+                .await
+                .ok_or(Error::Timeout)??;
+
+            let (ack_identifier, reason) = (ack.identifier, ack.reason);
+            drop(ack);
+            self.connection.stats.record_reason(reason as u8);
+
+            // Acknowledgements of detached publishes may arrive first.
+            if !self.connection.detached.settle(ack_identifier, reason) {
+                break (ack_identifier, reason);
+            }
+        };
+
+        if ack_identifier != identifier {
+            log::debug!("unexpected puback identifier: {ack_identifier}");
+            return Err(Error::Protocol);
+        }
+
+        match reason.is_success() {
+            true => Ok(()),
+            false => Err(Error::PublishRejected(reason)),
+        }
+    }
+
+    /// Drives the handshake of a QoS 2 publish to completion.
+    ///
+    /// Spec: [4.3.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901237)
+    async fn complete_exactly_once(&mut self, identifier: PacketId) -> Result<(), C::Error> {
+        let rec = self
+            .timeout
+            .timeout(self.connection.receive::<v5::PubRec>())
+            .await
+            .ok_or(Error::Timeout)??;
+
+        let (rec_identifier, reason) = (rec.identifier, rec.reason);
+        drop(rec);
+        self.connection.stats.record_reason(reason as u8);
+
+        if rec_identifier != identifier {
+            log::debug!("unexpected pubrec identifier: {rec_identifier}");
+            return Err(Error::Protocol);
+        }
+        // A rejected publish ends the handshake, there is nothing to release.
+        if !reason.is_success() {
+            return Err(Error::PublishRejected(reason));
+        }
+
+        let release = v5::PubRel {
+            identifier,
+            reason: v5::PubCompReason::Success,
+        };
+        self.connection.send(&release).await?;
+
+        let comp = self
+            .timeout
+            .timeout(self.connection.receive::<v5::PubComp>())
+            .await
+            .ok_or(Error::Timeout)??;
+
+        let (comp_identifier, reason) = (comp.identifier, comp.reason);
+        drop(comp);
+        self.connection.stats.record_reason(reason as u8);
+
+        if comp_identifier != identifier {
+            log::debug!("unexpected pubcomp identifier: {comp_identifier}");
+            return Err(Error::Protocol);
+        }
+
+        match reason.is_success() {
+            true => Ok(()),
+            false => Err(Error::PublishIncomplete(reason)),
+        }
+    }
+
+    /// Publishes a QoS 0 message, without retain and without properties.
+    ///
+    /// Shorthand for [`Self::publish`].
+    pub async fn send(&mut self, topic: &str, payload: &[u8]) -> Result<(), C::Error> {
+        self.publish(topic, payload).await?;
+        Ok(())
+    }
+
+    /// Executes a command received from a [`CommandQueue`].
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is *not* cancel safe.
+    #[cfg(feature = "std")]
+    pub async fn execute(&mut self, command: &Command) -> Result<(), C::Error> {
+        match command {
+            Command::Publish { topic, payload } => self.send(topic, payload).await,
+            #[cfg(feature = "subscribe")]
+            Command::Subscribe(topic) => self.subscribe(topic).await.map(drop),
+            #[cfg(feature = "unsubscribe")]
+            Command::Unsubscribe(topic) => self.unsubscribe(topic).await,
+        }
+    }
+
+    /// Publishes the [`Stats`] of the connection, see [`StatsReport`].
+    ///
+    /// The client has no clock, the `uptime` of the application is supplied by the caller.
+    pub async fn publish_stats(
+        &mut self,
+        report: &StatsReport<'_>,
+        uptime: Duration,
+    ) -> Result<(), C::Error> {
+        // Fits the largest possible values of all stats.
+        let mut buffer = [0; 160];
+        let payload = self
+            .connection
+            .stats
+            .write_json(uptime, &mut buffer)
+            .ok_or(Error::PacketTooLarge)?;
+
+        self.send(report.topic(), payload).await
+    }
+
+    /// Publishes a message with a payload scattered across multiple slices.
+    ///
+    /// The slices are written in order, without copying them into a temporary buffer.
+    pub async fn publish_vectored(
+        &mut self,
+        topic: &str,
+        payload: &[&[u8]],
+    ) -> Result<(), C::Error> {
+        self.connection.check_publish_topic(topic)?;
+
+        let packet: v5::Publish<'_, _, &[v5::PublishProperty<'_>]> = v5::Publish {
+            dup: false,
+            qos: QoS::AtMostOnce,
+            retain: false,
+            identifier: None,
+            topic,
+            properties: &[],
+            payload,
+        };
+
+        self.connection.send(&packet).await?;
+
+        Ok(())
+    }
+
+    /// Sends the publish staged in `staging`, if there is one.
+    ///
+    /// Returns `true` if a publish was sent. The slot is freed once the publish was sent, even if
+    /// sending failed or was cancelled.
+    pub async fn send_staged<const N: usize>(
+        &mut self,
+        staging: &Staging<N>,
+    ) -> Result<bool, C::Error> {
+        let Some(staged) = staging.take() else {
+            return Ok(false);
+        };
+
+        self.connection.send_raw(&staged).await?;
+
+        Ok(true)
+    }
+
+    /// Replies to a request, implementing the responder side of the MQTT 5 request/response
+    /// pattern.
+    ///
+    /// Publishes `payload` to the [Response Topic](v5::PublishProperty::ResponseTopic) of the
+    /// request, carrying back its [Correlation Data](v5::PublishProperty::CorrelationData). Both
+    /// are copied out of the received message into a [`ReplyTo`], which does not borrow the
+    /// client.
+    ///
+    /// See [`ReplyTo`] for an example.
+    pub async fn reply<const N: usize>(
+        &mut self,
+        reply_to: &ReplyTo<N>,
+        payload: &[u8],
+    ) -> Result<(), C::Error> {
+        let topic = reply_to.topic();
+        self.connection.check_publish_topic(topic)?;
+
+        let correlation_data = reply_to
+            .correlation_data()
+            .map(v5::PublishProperty::CorrelationData);
+        let packet: v5::Publish<'_, _, &[v5::PublishProperty<'_>]> = v5::Publish {
+            dup: false,
+            qos: QoS::AtMostOnce,
+            retain: false,
+            identifier: None,
+            topic,
+            properties: correlation_data.as_slice(),
+            payload,
+        };
+        self.connection.send(&packet).await
+    }
+
+    /// Receives a message from the MQTT server.
+    ///
+    /// The message borrows from the receive buffer, it must be dropped before the next message
+    /// can be received.
+    ///
+    /// QoS 1 and QoS 2 messages are acknowledged with a PUBACK or PUBREC once they are dropped,
+    /// the acknowledgement is sent before the next packet is received or sent. PUBRELs sent by
+    /// the server, e.g. after resuming a session, are answered with a PUBCOMP and not returned.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method *is* cancel safe. An acknowledgement or PUBCOMP may be partially written,
+    /// when the future is cancelled while answering the server.
+    pub async fn receive(&mut self) -> Result<Message<'_>, C::Error> {
+        self.connection.receive::<v5::Publish>().await
+    }
+
+    /// Returns a stream of every packet received from the server.
+    ///
+    /// Besides messages, the stream yields the packets which the server sends on its own, like a
+    /// DISCONNECT, AUTH or ping responses. [`Self::receive`] handles these internally, the stream
+    /// is meant for applications which need full visibility into the connection.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use miniqtt::client::Event;
+    /// # async fn example<C>(
+    /// #     client: &mut miniqtt::Client<C, [u8; 128]>,
+    /// # ) -> miniqtt::client::Result<(), C::Error>
+    /// # where
+    /// #     C: embedded_io_async::Read + embedded_io_async::Write,
+    /// # {
+    /// let mut events = client.events();
+    /// loop {
+    ///     match &*events.next().await? {
+    ///         Event::Publish(message) => println!("message on {}", message.topic),
+    ///         Event::Disconnect(disconnect) => break println!("closed: {:?}", disconnect.reason),
+    ///         _ => {}
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn events(&mut self) -> Events<'_, C, B> {
+        Events::new(&mut self.connection)
+    }
+
+    /// Receives a message from the MQTT server, keeping the connection alive while waiting.
+    ///
+    /// Sends a PINGREQ whenever the [keep alive](Self::keep_alive) interval elapses while
+    /// waiting, measured with `delay`. The responses of the server are consumed transparently.
+    /// This allows an application to wait for messages for longer than the keep alive, without
+    /// scheduling pings itself.
+    ///
+    /// With a [clock](ConnectionBuilder::with_clock), the first ping is due one keep alive after
+    /// the [last sent](Self::last_tx_at) packet. Otherwise it is due one keep alive after the
+    /// start of the call and the keep alive must have been honored before the call, see
+    /// [`Self::ping_due_in`]. Nothing is sent if the keep alive is disabled.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method *is* cancel safe, if `delay` is cancel safe. A PINGREQ may be partially
+    /// written, when the future is cancelled while sending it.
+    #[cfg(feature = "embedded-hal-async-1")]
+    pub async fn receive_with_pings<D>(&mut self, delay: &mut D) -> Result<Message<'_>, C::Error>
+    where
+        D: embedded_hal_async::delay::DelayNs,
+    {
+        if self.keep_alive == 0 {
+            return self.connection.receive::<v5::Publish>().await;
+        }
+
+        let interval_ms = u32::from(self.keep_alive) * 1000;
+        let next_ms = match (self.connection.clock, self.connection.last_tx) {
+            (Some(clock), Some(last_tx)) => self
+                .ping_due_in(clock.now().saturating_sub(last_tx))
+                .map_or(interval_ms, |due| due.as_millis() as u32),
+            _ => interval_ms,
+        };
+        let pings = DelayPings {
+            delay,
+            next_ms,
+            interval_ms,
+        };
+        self.connection
+            .receive_pinging::<v5::Publish, _>(pings)
+            .await
+    }
+
+    /// Receives a message from the MQTT server, or waits for `future` to complete, whichever
+    /// happens first.
+    ///
+    /// This can be used to wait for messages, while also waiting for a timer or another event,
+    /// without selecting over [`Self::receive`] manually. If both are ready, the message is
+    /// returned and `future` is dropped.
+    ///
+    /// Data which was already read from the connection, when `future` completes first, stays
+    /// buffered and is returned by the next receive.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method *is* cancel safe, if `future` is cancel safe.
+    pub async fn receive_or<F>(&mut self, future: F) -> Result<ReceiveOr<'_, F::Output>, C::Error>
+    where
+        F: Future,
     {
-        Connect::new(client_id, |packet| async move {
-            self.connection.send(&packet).await?;
-
-            let ack = self.connection.receive::<v5::ConnAck>().await?;
-
-            // TODO: according to the protocol, if the reason is not successful, the client must
-            // terminate the connection. Currently the connection trait just asks for Read/Write,
-            // there is no way to force close the connection.
-            //
-            // Maybe that is okay, maybe we should keep internal state on the client/connection and
-            // reject all further interactions, or just do nothing.
-
-            Ok(ConnectResponse { ack })
-        })
+        match select(self.connection.receive::<v5::Publish>(), future).await {
+            Either::Left(message) => message.map(ReceiveOr::Message),
+            Either::Right(output) => Ok(ReceiveOr::Other(output)),
+        }
     }
 
-    pub async fn subscribe(&mut self, topic: &str) -> Result<(), C::Error> {
-        let packet = v5::Subscribe {
-            identifier: self.next_identifier(),
-            topics: &[TopicFilter {
-                name: topic,
-                qos: QoS::AtMostOnce,
-                no_local: Default::default(),
-                retain_as_published: Default::default(),
-                retain: Default::default(),
-            }],
-        };
-        self.connection.send(&packet).await?;
-
-        let _ack = self.connection.receive::<v5::SubAck>().await?;
+    /// Receives a message which has already been read from the connection.
+    ///
+    /// Unlike [`Self::receive`], this never reads from the connection, it only parses data which
+    /// is already buffered. Returns `None` if no complete message is buffered, or the next
+    /// QoS 1 or QoS 2 message is buffered while the previous one is not yet acknowledged.
+    ///
+    /// This can be used to drain all buffered messages, before waiting for the connection again.
+    pub fn try_receive(&mut self) -> Result<Option<Message<'_>>, C::Error> {
+        self.connection.try_receive::<v5::Publish>()
+    }
 
-        Ok(())
+    /// Returns the amount of complete packets, which have already been read from the connection.
+    ///
+    /// Brokers may send multiple packets at once, which are read from the connection together.
+    /// Buffered packets are received without waiting for the connection, an event loop can
+    /// drain them with [`Self::try_receive`] before sleeping.
+    ///
+    /// This is a hint, buffered packets may also be packets which are not returned as messages.
+    pub fn buffered_packets(&self) -> usize {
+        self.connection.buffered_packets()
     }
 
-    // TODO: Make a builder like for `connect` which supports:
-    //  - QoS
-    //  - Topic Alias (send(..).with_alias(&mut my_alias)), where the alias tracks its internal
-    //  register state (including id). Not sure how you'd free an alias again, maybe there is just
-    //  no API for that and you just re-use different topic ids?
-    pub async fn send(&mut self, topic: &str, payload: &[u8]) -> Result<(), C::Error> {
-        let packet = v5::Publish {
-            dup: false,
-            qos: QoS::AtMostOnce,
-            retain: false,
-            identifier: None,
-            topic,
-            payload,
-        };
+    /// Sends a PINGREQ and waits for the PINGRESP of the server.
+    ///
+    /// Keeps the connection alive when nothing else is sent, see [`Self::ping_due_in`], and
+    /// verifies the server is still reachable. The wait is bounded by the timeout of the client.
+    ///
+    /// Spec: [3.12](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901195)
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is *not* cancel safe.
+    pub async fn ping(&mut self) -> Result<(), C::Error> {
+        self.connection.send(&v5::PingReq).await?;
 
-        self.connection.send(&packet).await?;
+        self.timeout
+            .timeout(self.connection.receive::<v5::PingResp>())
+            .await
+            .ok_or(Error::Timeout)??;
 
         Ok(())
     }
 
-    /// Receives a message from the MQTT server.
+    /// Sends a PINGREQ, if one is due according to [`Self::next_ping_in`].
+    ///
+    /// Returns whether a ping was sent. The response of the server is consumed transparently by
+    /// the next receive. Calling this whenever the application wakes up keeps the connection
+    /// alive, without any other timer than the one the application already waits on.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use core::time::Duration;
+    /// # use miniqtt::client::ReceiveOr;
+    /// # async fn example<C>(
+    /// #     client: &mut miniqtt::Client<C, [u8; 128]>,
+    /// #     sleep: impl AsyncFn(Duration),
+    /// # ) -> miniqtt::client::Result<(), C::Error>
+    /// # where
+    /// #     C: embedded_io_async::Read + embedded_io_async::Write,
+    /// # {
+    /// loop {
+    ///     let timeout = client.next_ping_in().unwrap_or(Duration::from_secs(60));
+    ///     if let ReceiveOr::Message(message) = client.receive_or(sleep(timeout)).await? {
+    ///         println!("{:?}", message.payload);
+    ///     }
+    ///
+    ///     client.keep_alive_tick().await?;
+    /// }
+    /// # }
+    /// ```
     ///
     /// # Cancel safety
     ///
-    /// This method *is* cancel safe.
-    pub async fn receive(&mut self) -> Result<(), C::Error> {
-        // TODO: while this is cancel safe, it may be interleaved with with different send
-        // calls which expect different packages from the server.
-        // These in-between publish messages may need to be dropped (so we can get to the ACK)
-        // or temporarily buffered and skipped (if the buffer size is big enough).
-        // This should follow the QoS of the package.
-        let _message = self.connection.receive::<v5::Publish>().await?;
+    /// This method is *not* cancel safe. A PINGREQ may be partially written, when the future is
+    /// cancelled.
+    pub async fn keep_alive_tick(&mut self) -> Result<bool, C::Error> {
+        if self.next_ping_in() != Some(Duration::ZERO) {
+            return Ok(false);
+        }
 
-        Ok(())
+        self.connection.send(&v5::PingReq).await?;
+        Ok(true)
     }
 
     /// Disconnects from the server by sending a disconnect message and dropping the connection.
     ///
+    /// The reason and properties of the disconnect message can be configured on the returned
+    /// [`Disconnect`] future. By default the server is asked to publish the will message.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use miniqtt::protocol::v5::{DisconnectProperty, DisconnectReason};
+    /// # async fn example<C>(client: &mut miniqtt::Client<C, Vec<u8>>) -> miniqtt::client::Result<(), C::Error>
+    /// # where
+    /// #     C: embedded_io_async::Read + embedded_io_async::Write,
+    /// # {
+    /// client
+    ///     .disconnect()
+    ///     .reason(DisconnectReason::NormalDisconnection)
+    ///     .with_properties(&[DisconnectProperty::ReasonString("shutting down")])
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
     /// # Cancel safety
     ///
     /// This method is *not* cancel safe.
-    pub async fn disconnect(&mut self) -> Result<(), C::Error> {
-        // TODO: should probably keep track of connection state
-        // and also drop the connection here.
-        self.connection.send(&v5::Disconnect {}).await?;
+    pub fn disconnect<'a>(
+        &mut self,
+    ) -> Disconnect<'a, impl MakeFuture<DisconnectOptions<'a>, Output = Result<(), C::Error>>> {
+        Disconnect::new(|options| async move {
+            // TODO: should probably drop the connection here.
+            self.connected = false;
+            // The server already closed the connection, there is nobody to notify.
+            if self.connection.closed {
+                return Ok(());
+            }
+            let packet = v5::Disconnect::new(options.reason, options.properties);
+            self.connection.send(&packet).await?;
 
-        Ok(())
+            Ok(())
+        })
     }
 
-    fn next_identifier(&self) -> u16 {
-        self.identifier.fetch_add(1, Ordering::Relaxed)
+    fn next_identifier(&self) -> PacketId {
+        loop {
+            let identifier = self
+                .identifier
+                .fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+            // Zero is not a valid packet identifier, skip it when wrapping around.
+            if let Some(identifier) = PacketId::new(identifier) {
+                return identifier;
+            }
+        }
     }
 }
 
@@ -144,23 +1520,147 @@ pub struct Connection<C, B> {
     inner: C,
     /// Temporary buffer for bytes read from the connection.
     rx_buffer: B,
-    /// Current amount of bytes read from the connection and stored in the buffer.
+    /// Offset of the first byte in the buffer, which was not yet consumed.
+    start: usize,
+    /// End of the bytes read from the connection and stored in the buffer.
     size: usize,
-    /// Current position in the buffer.
+    /// Updated by the [`MessageGuard`] of the last received packet once it is released.
     ///
-    /// Set after successfully parsing an packet, indicating the offset of the start of the next
-    /// packet.
-    position: Option<usize>,
+    /// Its position is relative to `start` and indicates the offset of the start of the next
+    /// packet. A released QoS 1 or QoS 2 message leaves an acknowledgement to be sent.
+    release: Release,
+    /// When consumed data is removed from the buffer.
+    compaction: Compaction,
+    /// Frames received packets.
+    decoder: Decoder,
+    /// Receive buffer fill level thresholds which are reported.
+    watermarks: Option<Watermarks>,
+    /// Whether publishing to topics reserved for the server is allowed.
+    allow_reserved_topics: bool,
+    /// Whether payloads are omitted when logging packets.
+    redact_payloads: bool,
+    /// Records sent and received frames.
+    recorder: Option<Recorder>,
+    /// Counters of the connection.
+    stats: Stats,
+    /// Timestamps read and write activity.
+    clock: Option<&'static dyn Clock>,
+    /// When data was last read from the connection.
+    last_rx: Option<Duration>,
+    /// When a packet was last written to the connection.
+    last_tx: Option<Duration>,
+    /// Whether the server sent a DISCONNECT or closed the connection, nothing is read from the
+    /// connection afterwards.
+    closed: bool,
+    /// Identifier of a received PUBREL, which still needs to be answered with a PUBCOMP.
+    pending_release: Option<PacketId>,
+    /// Identifiers of received QoS 2 messages, which are not yet released by the server.
+    received: PacketIdWindow<2>,
+    /// Detached QoS 1 publishes, which are not yet acknowledged.
+    detached: Detached,
+    /// A subscription which was sent, but whose result was not yet returned.
+    #[cfg(feature = "subscribe")]
+    pending_subscribe: Option<PendingSubscribe>,
 }
 
 impl<C, B> Connection<C, B> {
-    pub fn new(inner: C, rx_buffer: B) -> Self {
-        Self {
+    /// Creates a new connection with the default configuration.
+    ///
+    /// Use [`Self::builder`] to configure the connection.
+    pub fn new(inner: C, rx_buffer: B) -> Self
+    where
+        B: Buffer,
+    {
+        Self::builder(inner, rx_buffer).build()
+    }
+
+    /// Creates a builder, to configure a new connection.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use miniqtt::client::Compaction;
+    /// # fn example<C>(stream: C) {
+    /// let connection: miniqtt::Connection<C, Vec<u8>> =
+    ///     miniqtt::Connection::builder(stream, Vec::new())
+    ///         .max_packet_size(4096)
+    ///         .compaction(Compaction::Lazy)
+    ///         .redact_payloads(true)
+    ///         .build();
+    /// # }
+    /// ```
+    pub fn builder(inner: C, rx_buffer: B) -> ConnectionBuilder<C, B> {
+        ConnectionBuilder::new(Self {
             inner,
             rx_buffer,
+            start: 0,
             size: 0,
-            position: None,
+            release: Release::default(),
+            compaction: Compaction::default(),
+            decoder: Decoder::new(),
+            watermarks: None,
+            allow_reserved_topics: false,
+            redact_payloads: false,
+            recorder: None,
+            stats: Stats::default(),
+            clock: None,
+            last_rx: None,
+            last_tx: None,
+            closed: false,
+            pending_release: None,
+            received: PacketIdWindow::new(),
+            detached: Detached::default(),
+            #[cfg(feature = "subscribe")]
+            pending_subscribe: None,
+        })
+    }
+
+    /// Returns the recorder, if configured with [`ConnectionBuilder::with_recorder`].
+    pub fn recorder(&self) -> Option<&Recorder> {
+        self.recorder.as_ref()
+    }
+
+    /// Returns the recorder mutably, if configured with [`ConnectionBuilder::with_recorder`].
+    pub fn recorder_mut(&mut self) -> Option<&mut Recorder> {
+        self.recorder.as_mut()
+    }
+
+    /// Returns the stats of the connection.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Returns the time data was last read from the connection.
+    ///
+    /// Returns `None` until data is read, or if no clock is configured with
+    /// [`ConnectionBuilder::with_clock`].
+    pub fn last_rx_at(&self) -> Option<Duration> {
+        self.last_rx
+    }
+
+    /// Returns the time a packet was last written to the connection.
+    ///
+    /// Returns `None` until a packet is written, or if no clock is configured with
+    /// [`ConnectionBuilder::with_clock`].
+    pub fn last_tx_at(&self) -> Option<Duration> {
+        self.last_tx
+    }
+
+    fn record_tx(&mut self) {
+        if let Some(clock) = self.clock {
+            self.last_tx = Some(clock.now());
+        }
+    }
+
+    /// Fails if publishing to `topic` is not allowed, see
+    /// [`ConnectionBuilder::allow_reserved_topics`], or `topic` is not a valid topic name.
+    fn check_publish_topic<E>(&self, topic: &str) -> Result<(), E> {
+        protocol::validate_topic(topic).map_err(Error::Invalid)?;
+        if !self.allow_reserved_topics && crate::topic::is_reserved(topic) {
+            log::debug!("refusing to publish to reserved topic: {topic}");
+            return Err(Error::ReservedTopic);
         }
+        Ok(())
     }
 }
 
@@ -172,83 +1672,571 @@ where
     where
         T: Packet,
         T: Writable,
-        T: core::fmt::Debug,
-        T::Error<C::Error>: Into<C::Error>,
+        T: LogPacket,
+        T::Error<C::Error>: Into<WriteError<C::Error>>,
     {
-        log::debug!("-> {packet:?}");
+        self.check_open()?;
+        self.answer_pending().await?;
 
-        FixedHeader::new(T::TYPE, packet.flags(), packet.size())
-            .write_to(&mut self.inner)
-            .await?;
+        let sink = Recording::new(&mut self.inner, self.recorder.as_mut());
+        Self::send_to(sink, self.redact_payloads, packet).await?;
+        self.record_tx();
+
+        Ok(())
+    }
+
+    /// Fails with [`Error::Disconnected`] if the server closed the connection.
+    ///
+    /// The server closes the network connection after sending a DISCONNECT, nothing can be sent
+    /// until the client connects again.
+    fn check_open(&self) -> Result<(), C::Error> {
+        match self.closed {
+            true => Err(Error::Disconnected),
+            false => Ok(()),
+        }
+    }
+
+    /// Sends the acknowledgement of a released message and answers a skipped PUBREL.
+    async fn answer_pending(&mut self) -> Result<(), C::Error> {
+        self.acknowledge().await?;
+        self.complete_release().await
+    }
+
+    /// Acknowledges a released QoS 1 or QoS 2 message, see [`MessageGuard`].
+    ///
+    /// Spec: [4.3.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901236)
+    async fn acknowledge(&mut self) -> Result<(), C::Error> {
+        let Some(ack) = self.release.pending else {
+            return Ok(());
+        };
+
+        let sink = Recording::new(&mut self.inner, self.recorder.as_mut());
+        match ack {
+            Ack::PubAck(identifier) => {
+                let ack = v5::PubAck {
+                    identifier,
+                    reason: v5::PubAckReason::Success,
+                    properties: v5::AckProperties::default(),
+                };
+                Self::send_to(sink, self.redact_payloads, &ack).await?;
+            }
+            Ack::PubRec(identifier, reason) => {
+                let receipt = v5::PubRec { identifier, reason };
+                Self::send_to(sink, self.redact_payloads, &receipt).await?;
+            }
+        }
+        self.record_tx();
+        self.release.pending = None;
 
-        packet.write_to(&mut self.inner).await.map_err(Into::into)?;
+        Ok(())
+    }
+
+    /// Answers a PUBREL of the server, which was skipped while receiving.
+    ///
+    /// The identifier of a received QoS 2 message is kept until the server releases it, a
+    /// message which is sent again with the same identifier before is not delivered twice. A
+    /// message is delivered before its PUBREC is sent, the release of an unknown identifier,
+    /// e.g. of a session resumed by a new connection, is completed as well.
+    ///
+    /// Spec: [4.3.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901237)
+    async fn complete_release(&mut self) -> Result<(), C::Error> {
+        let Some(identifier) = self.pending_release else {
+            return Ok(());
+        };
+
+        let complete = v5::PubComp {
+            identifier,
+            reason: v5::PubCompReason::Success,
+        };
+        let sink = Recording::new(&mut self.inner, self.recorder.as_mut());
+        Self::send_to(sink, self.redact_payloads, &complete).await?;
+        self.record_tx();
+        self.pending_release = None;
 
         Ok(())
     }
+
+    /// Sends an already serialized packet.
+    async fn send_raw(&mut self, data: &[u8]) -> Result<(), C::Error> {
+        self.check_open()?;
+        self.answer_pending().await?;
+
+        log::debug!("-> {} bytes", data.len());
+
+        let mut sink = Recording::new(&mut self.inner, self.recorder.as_mut());
+        if let Err(error) = embedded_io_async::Write::write_all(&mut sink, data).await {
+            let packet_type = data.first().map_or(0, |b| b >> 4);
+            return Err(write_error(packet_type, sink.written(), error));
+        }
+        self.record_tx();
+
+        Ok(())
+    }
+
+    /// Sends a packet, without borrowing the entire connection.
+    async fn send_to<T>(
+        mut sink: Recording<'_, C>,
+        redact_payload: bool,
+        packet: &T,
+    ) -> Result<(), C::Error>
+    where
+        T: Packet,
+        T: Writable,
+        T: LogPacket,
+        T::Error<C::Error>: Into<WriteError<C::Error>>,
+    {
+        log::debug!(
+            "-> {:?}",
+            Logged {
+                packet,
+                redact_payload
+            }
+        );
+
+        match Encoder::new().write(packet, &mut sink).await {
+            Ok(()) => Ok(()),
+            Err(WriteError::Write(error)) => Err(write_error(T::TYPE, sink.written(), error)),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Returns `data[range]`, or an empty slice if the range is out of bounds.
+///
+/// The offsets into the receive buffer never leave its bounds. Receiving buffered packets uses
+/// this instead of indexing, which keeps it free of panics, see the `no-panic` example.
+fn bounded(data: &[u8], range: Range<usize>) -> &[u8] {
+    data.get(range).unwrap_or_default()
+}
+
+/// Creates the error for a packet which failed to be written after `written` bytes.
+fn write_error<E>(packet_type: u8, written: usize, error: E) -> Error<E> {
+    match written {
+        0 => log::debug!("failed to write packet of type {packet_type}, nothing was sent"),
+        _ => log::error!(
+            "failed to write packet of type {packet_type} after {written} bytes, the connection is corrupted"
+        ),
+    }
+    Error::Write {
+        packet_type,
+        written,
+        error,
+    }
 }
 
 impl<C, B> Connection<C, B>
 where
     C: embedded_io_async::Read,
+    C: embedded_io_async::Write,
     B: Buffer,
 {
-    async fn receive<'a, T>(&'a mut self) -> Result<T, C::Error>
+    /// Answers the challenges of the server, until the connect is acknowledged.
+    #[cfg(feature = "auth")]
+    async fn authenticate_connect(
+        &mut self,
+        authenticator: Authentication<'_>,
+        time_sync: Option<TimeSync<'_>>,
+    ) -> Result<ConnectResponse, C::Error> {
+        let method = authenticator.method();
+        loop {
+            let reply = self.receive::<ConnectReply>().await?;
+            let data = match &*reply {
+                ConnectReply::ConnAck(ack) if ack.reason == v5::ConnAckReason::Success => {
+                    if !authenticator.finish(ack.properties.authentication_data()) {
+                        log::debug!("authenticator rejected the server");
+                        return Err(Error::AuthenticationAborted);
+                    }
+                    return Ok(ConnectResponse::new(ack, time_sync));
+                }
+                ConnectReply::ConnAck(ack) => return Ok(ConnectResponse::new(ack, time_sync)),
+                ConnectReply::Auth(auth)
+                    if auth.reason == v5::AuthReason::ContinueAuthentication =>
+                {
+                    authenticator.respond(auth.properties.authentication_data())
+                }
+                ConnectReply::Auth(_) => return Err(Error::Protocol),
+            };
+            drop(reply);
+
+            let data = data.ok_or(Error::AuthenticationAborted)?;
+            let properties = [
+                v5::AuthProperty::AuthenticationMethod(method),
+                v5::AuthProperty::AuthenticationData(data),
+            ];
+            self.send(&v5::Auth::new(
+                v5::AuthReason::ContinueAuthentication,
+                &properties[..],
+            ))
+            .await?;
+        }
+    }
+
+    async fn receive<'a, T>(&'a mut self) -> Result<MessageGuard<'a, T::Packet<'a>>, C::Error>
     where
-        T: Parse<'a, Error = PacketError>,
-        T: core::fmt::Debug,
+        T: Receive,
     {
-        // Move all the remaining data which is left in the buffer to the beginning,
-        // to make sure the next package is properly aligned.
-        // We need to do this at the beginning of reading a new packet, instead of
-        // at the end, because the just read packet may point into the buffer.
-        //
-        // There are two possible optimization we can do:
-        //  1) Make the buffer wrap, which requires support in all packets to parse from
-        //     a non continuous slice.
-        //  2) Read in two iterations. The first read only reads enough for the fixed header, 2-5
-        //     bytes, from that we know how long the total length of the packet is and we can
-        //     target read just enough for the packet, minimizing the amount of data we have to
-        //     copy.
-        if let Some(position) = self.position.take() {
-            log::trace!(
-                "{:?} -{}",
-                &self.rx_buffer.as_slice()[..self.size],
-                position
-            );
-            self.rx_buffer
-                .as_slice_mut()
-                .copy_within(position..self.size, 0);
-            self.size -= position;
-            log::trace!(
-                "{:?} ={}",
-                &self.rx_buffer.as_slice()[..self.size],
-                self.size
-            );
+        self.receive_pinging::<T, _>(NoPings).await
+    }
+
+    /// Receives a packet, sending a PINGREQ whenever one is due while waiting.
+    async fn receive_pinging<'a, T, P>(
+        &'a mut self,
+        mut pings: P,
+    ) -> Result<MessageGuard<'a, T::Packet<'a>>, C::Error>
+    where
+        T: Receive,
+        P: Pings,
+    {
+        self.compact();
+
+        loop {
+            let mut due = pin!(pings.due());
+
+            // Reading does not restart the schedule, pings are due independent of received data.
+            loop {
+                #[cfg(not(feature = "forbid-unsafe"))]
+                // SAFETY: the returned packet is tied to the lifetime of `self`.
+                if let Some((len, packet)) = unsafe { self.parse_buffered::<T>()? } {
+                    return Ok(MessageGuard::new(packet, len, &mut self.release));
+                }
+                #[cfg(feature = "forbid-unsafe")]
+                if let Some(len) = self.parse_buffered::<T>()? {
+                    return self.reparse_buffered::<T>(len);
+                }
+
+                // Acknowledgements and releases are answered before parsing continues, the next
+                // message or release may follow.
+                if self.release.pending.is_some() || self.pending_release.is_some() {
+                    self.answer_pending().await?;
+                    continue;
+                }
+
+                // The server closes the connection after a DISCONNECT. Reading may race with
+                // the close and fail, instead of reporting a clean end of the connection.
+                if self.closed {
+                    return Err(Error::Disconnected);
+                }
+
+                let remaining = self
+                    .rx_buffer
+                    .as_slice_mut()
+                    .get_mut(self.size..)
+                    .unwrap_or_default();
+
+                if remaining.is_empty() {
+                    if self.start > 0 {
+                        self.shift();
+                        continue;
+                    }
+
+                    let _len = self.rx_buffer.as_slice().len();
+                    if let Ok(()) = self.rx_buffer.try_resize() {
+                        // Safety check, if buffer does not grow, this is an endless loop.
+                        debug_assert!(
+                            _len < self.rx_buffer.as_slice().len(),
+                            "buffer must grow when resized"
+                        );
+                        continue;
+                    }
+                    // TODO: maybe can recover here by just skipping the current packet,
+                    // assuming the buffer is big enough to parse the fixed header.
+                    //
+                    // This allows recovery from oversized `PUBLISH` packets while still
+                    // handling other packets gracefully.
+                    // Need to consider QoS levels here possibly.
+                    //
+                    // In any case, we should return an error here at least once to inform the user,
+                    // something was dropped.
+                    self.stats.record_dropped();
+                    return Err(Error::InsufficientBufferSize);
+                }
+
+                let r = match select(self.inner.read(remaining), due.as_mut()).await {
+                    Either::Left(r) => r?,
+                    Either::Right(()) => break,
+                };
+                if r == 0 {
+                    match self.start == self.size {
+                        true => log::debug!("Clean Exit"),
+                        false => log::debug!("Connection Reset by Peer"),
+                    };
+                    self.closed = true;
+                    return Err(Error::Disconnected);
+                } else {
+                    self.size += r;
+                    self.stats.record_buffered(self.size);
+                    if let Some(clock) = self.clock {
+                        self.last_rx = Some(clock.now());
+                    }
+                    log::trace!(
+                        "{:?} +{r}",
+                        bounded(self.rx_buffer.as_slice(), 0..self.size)
+                    );
+
+                    if let Some(watermarks) = &self.watermarks {
+                        let capacity = self.rx_buffer.as_slice().len();
+                        watermarks.check(self.size - r, self.size, capacity);
+                    }
+                }
+            }
+
+            self.send(&v5::PingReq).await?;
+        }
+    }
+
+    /// Attempts to parse a packet which is already completely contained in the buffer,
+    /// without reading from the connection.
+    fn try_receive<'a, T>(&'a mut self) -> Result<Option<MessageGuard<'a, T::Packet<'a>>>, C::Error>
+    where
+        T: Receive,
+    {
+        self.compact();
+
+        #[cfg(not(feature = "forbid-unsafe"))]
+        {
+            // SAFETY: the returned packet is tied to the lifetime of `self`.
+            let packet = unsafe { self.parse_buffered::<T>()? };
+            Ok(packet.map(|(len, packet)| MessageGuard::new(packet, len, &mut self.release)))
+        }
+        #[cfg(feature = "forbid-unsafe")]
+        match self.parse_buffered::<T>()? {
+            Some(len) => self.reparse_buffered::<T>(len).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses a packet from the currently buffered data.
+    ///
+    /// Returns the length of the packet and the packet, or `None` if the buffer does not yet
+    /// contain a complete packet.
+    ///
+    /// # Safety
+    ///
+    /// The returned packet borrows from the buffer, but its lifetime is not tied to `self`.
+    /// Callers must tie the lifetime `'a` to a borrow of `self`.
+    //
+    // This seems really like a borrow checker limitation. A packet returned from a loop
+    // iteration keeps `self` borrowed for all following iterations, even though nothing
+    // references the buffer on the paths which continue the loop. The packet is only ever
+    // returned from the function, in which case it is tied to `self` again.
+    #[cfg(not(feature = "forbid-unsafe"))]
+    unsafe fn parse_buffered<'a, T>(&mut self) -> Result<Option<(usize, T::Packet<'a>)>, C::Error>
+    where
+        T: Receive,
+        Self: 'a,
+    {
+        loop {
+            // SAFETY: the caller ties `'a` to a borrow of `self`, see above.
+            let this = unsafe { &mut *(self as *mut Self) };
+            match this.decode_buffered::<T::Packet<'a>>()? {
+                Some(packet) => return Ok(Some(packet)),
+                None if self.release.pending.is_none() && self.promote_past_publishes() => continue,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Parses a packet from the currently buffered data, without returning it.
+    ///
+    /// Returns the length of the packet, or `None` if the buffer does not yet contain a complete
+    /// packet. The packet must be parsed again with [`Self::reparse_buffered`], which ties it to
+    /// the borrow of `self`.
+    ///
+    /// Parsing every packet twice is the price of not working around the borrow checker with
+    /// unsafe code.
+    #[cfg(feature = "forbid-unsafe")]
+    fn parse_buffered<T>(&mut self) -> Result<Option<usize>, C::Error>
+    where
+        T: Receive,
+    {
+        loop {
+            let packet = self.decode_buffered::<T::Packet<'_>>()?;
+            match packet.map(|(len, _)| len) {
+                Some(len) => return Ok(Some(len)),
+                None if self.release.pending.is_none() && self.promote_past_publishes() => continue,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Parses the packet of length `len` at the start of the buffer again, see
+    /// [`Self::parse_buffered`].
+    #[cfg(feature = "forbid-unsafe")]
+    fn reparse_buffered<'a, T>(
+        &'a mut self,
+        len: usize,
+    ) -> Result<MessageGuard<'a, T::Packet<'a>>, C::Error>
+    where
+        T: Receive,
+    {
+        let data = bounded(self.rx_buffer.as_slice(), self.start..self.start + len);
+        match self.decoder.decode::<T::Packet<'a>>(data) {
+            Ok(Some((len, packet))) => Ok(MessageGuard::new(packet, len, &mut self.release)),
+            // The packet was already parsed successfully.
+            _ => Err(Error::Protocol),
         }
+    }
 
+    /// Parses a packet from the currently buffered data, skipping PINGRESPs which are not
+    /// expected.
+    fn decode_buffered<'a, T>(&'a mut self) -> Result<Option<(usize, T)>, C::Error>
+    where
+        T: PacketParse<'a>,
+        T: LogPacket,
+    {
         loop {
-            let (data, remaining) = self.rx_buffer.as_slice_mut().split_at_mut(self.size);
-
-            // TODO: confirm the details written down here.
-            //
-            // This seems really like a borrow checker limitation. On each iteration of the loop,
-            // we split the buffer into two separate mutable borrows.
-            //  - The first one is *only* used to parse the package.
-            //  - THe second one is *only* used to read more data.
-            // None of the two halves escapes the loop iteration, unless we exit the function,
-            // on the next iteration, there are no more open references to `rx_buffer` and we can
-            // make a fresh split.
-            //
-            // The transmute _should_ be safe, as we still tie the packet to 'self, combined with
-            // the usage of the buffers described before..
-            match T::parse(unsafe { core::mem::transmute::<&[u8], &[u8]>(&*data) }) {
-                Ok((position, packet)) => {
-                    self.position = Some(position);
-                    log::debug!("<- {packet:?}");
-                    return Ok(packet);
+            let data = bounded(self.rx_buffer.as_slice(), self.start..self.size);
+
+            let decoded = self.decoder.decode::<T>(data);
+            match decoded {
+                Ok(Some((len, packet))) => {
+                    // Only a single acknowledgement is kept, it must be sent first.
+                    let ack = Ack::of(bounded(data, 0..len));
+                    if ack.is_some() && self.release.pending.is_some() {
+                        return Ok(None);
+                    }
+
+                    // A QoS 2 message is delivered once, until the server releases it. A
+                    // message which is handed out again, after its guard was leaked, is not a
+                    // duplicate.
+                    if let Some(Ack::PubRec(identifier, _)) = ack
+                        && self.release.delivering != ack
+                    {
+                        let reason = match self.received.insert(identifier.get()) {
+                            Ok(true) => None,
+                            Ok(false) => {
+                                log::debug!("<- duplicate publish {identifier:?}");
+                                Some(v5::PubRecReason::Success)
+                            }
+                            Err(WindowFull) => {
+                                log::debug!("<- publish {identifier:?}, too many unreleased");
+                                self.stats.record_dropped();
+                                Some(v5::PubRecReason::QuotaExceeded)
+                            }
+                        };
+                        if let Some(reason) = reason {
+                            if let Some(recorder) = &mut self.recorder {
+                                recorder.record(Direction::Received, bounded(data, 0..len));
+                            }
+                            self.start += len;
+                            self.release.pending = Some(Ack::PubRec(identifier, reason));
+                            continue;
+                        }
+                    }
+
+                    if let Some(recorder) = &mut self.recorder {
+                        recorder.record(Direction::Received, bounded(data, 0..len));
+                    }
+                    if data.first().map(|b| b >> 4) == Some(<v5::Disconnect as Packet>::TYPE) {
+                        self.closed = true;
+                    }
+                    log::debug!(
+                        "<- {:?}",
+                        Logged {
+                            packet: &packet,
+                            redact_payload: self.redact_payloads
+                        }
+                    );
+                    self.release.delivering = ack;
+                    return Ok(Some((len, packet)));
+                }
+                Ok(None) => return Ok(None),
+                Err(DecodeError::PacketTooLarge(_len)) => {
+                    log::debug!("packet exceeds maximum packet size: {_len}");
+                    self.stats.record_dropped();
+                    return Err(Error::PacketTooLarge);
                 }
-                Err(ParseError::NotEnoughData) => {}
-                Err(ParseError::Error(_err)) => {
+                Err(DecodeError::Packet(_err)) => {
+                    // PINGRESPs carry no information and may arrive at any time, they are
+                    // skipped unless they are received explicitly.
+                    if let Ok((len, _ping)) = <v5::PingResp as Parse>::parse(data) {
+                        if let Some(recorder) = &mut self.recorder {
+                            recorder.record(Direction::Received, bounded(data, 0..len));
+                        }
+                        log::debug!("<- {_ping:?}");
+                        self.start += len;
+                        continue;
+                    }
+
+                    // The server may release QoS 2 messages of a resumed session at any time.
+                    if let Ok((len, release)) = <v5::PubRel as Parse>::parse(data) {
+                        // Only a single release is kept, it must be answered first.
+                        if self.pending_release.is_some() {
+                            return Ok(None);
+                        }
+                        if let Some(recorder) = &mut self.recorder {
+                            recorder.record(Direction::Received, bounded(data, 0..len));
+                        }
+                        log::debug!("<- {release:?}");
+                        self.start += len;
+                        self.received.remove(release.identifier.get());
+                        self.pending_release = Some(release.identifier);
+                        continue;
+                    }
+
+                    // A PUBCOMP for a publish which is no longer awaited, e.g. the release of
+                    // a publish from before a session was resumed. There is nothing to answer.
+                    if let Ok((len, _complete)) = <v5::PubComp as Parse>::parse(data) {
+                        if let Some(recorder) = &mut self.recorder {
+                            recorder.record(Direction::Received, bounded(data, 0..len));
+                        }
+                        log::debug!("<- unexpected {_complete:?}");
+                        self.start += len;
+                        continue;
+                    }
+
+                    // The acknowledgement of a detached publish, which nobody awaits.
+                    if let Ok((len, ack)) = <v5::PubAck as Parse>::parse(data)
+                        && self.detached.contains(ack.identifier)
+                    {
+                        if let Some(recorder) = &mut self.recorder {
+                            recorder.record(Direction::Received, bounded(data, 0..len));
+                        }
+                        log::debug!("<- {ack:?}");
+                        let (identifier, reason) = (ack.identifier, ack.reason);
+                        self.start += len;
+                        self.stats.record_reason(reason as u8);
+                        self.detached.settle(identifier, reason);
+                        continue;
+                    }
+
+                    // The acknowledgement of a cancelled subscribe, which is not awaited. Its
+                    // result is kept for `Client::resume_subscribe`.
+                    #[cfg(feature = "subscribe")]
+                    if let Ok((len, ack)) = <v5::SubAck as Parse>::parse(data)
+                        && let Some(pending) = &mut self.pending_subscribe
+                        && pending.identifier == ack.identifier
+                        && pending.reason.is_none()
+                        && let Some(reason) = ack.reasons().next()
+                    {
+                        if let Some(recorder) = &mut self.recorder {
+                            recorder.record(Direction::Received, bounded(data, 0..len));
+                        }
+                        log::debug!("<- {ack:?}");
+                        pending.reason = Some(reason);
+                        self.start += len;
+                        self.stats.record_reason(reason as u8);
+                        continue;
+                    }
+
+                    // The server may close the connection at any time, e.g. when it shuts down.
+                    if let Ok((len, disconnect)) = <v5::Disconnect as Parse>::parse(data) {
+                        if let Some(recorder) = &mut self.recorder {
+                            recorder.record(Direction::Received, bounded(data, 0..len));
+                        }
+                        log::debug!("<- {disconnect:?}");
+                        self.start += len;
+                        self.closed = true;
+                        return Err(Error::ServerDisconnect(disconnect.reason));
+                    }
+
+                    // Messages may arrive at any time, also while an acknowledgement is
+                    // awaited. They stay buffered for the next receive, the first other packet
+                    // is moved in front of them, see `Self::promote_past_publishes`.
+                    if data.first().map(|b| b >> 4) == Some(<v5::Publish as Packet>::TYPE) {
+                        return Ok(None);
+                    }
+
                     // TODO: once we end up here, we will never make progress
                     //  1) Maybe just close the connection/disconnect, check the spec!
                     //  2) Try to recover:
@@ -258,43 +2246,125 @@ where
                     // Not trying to recover and just disconnecting is probably the better idea.
                     // Also need to consider QoS levels without disconnect.
                     log::debug!("protocol error: {_err:?}");
+                    if let Some(recorder) = &mut self.recorder {
+                        recorder.record(Direction::Received, data);
+                    }
+                    self.stats.record_dropped();
                     return Err(Error::Protocol);
                 }
             }
+        }
+    }
+}
 
-            if remaining.is_empty() {
-                let _len = self.rx_buffer.as_slice().len();
-                if let Ok(()) = self.rx_buffer.try_resize() {
-                    // Safety check, if buffer does not grow, this is an endless loop.
-                    debug_assert!(
-                        _len < self.rx_buffer.as_slice().len(),
-                        "buffer must grow when resized"
-                    );
-                    continue;
+impl<C, B> Connection<C, B>
+where
+    B: Buffer,
+{
+    /// Returns the amount of complete packets, which have already been read from the connection
+    /// and are not yet received.
+    ///
+    /// See [`Client::buffered_packets`].
+    pub fn buffered_packets(&self) -> usize {
+        let start = self.start + self.release.position.unwrap_or(0);
+        let mut data = bounded(self.rx_buffer.as_slice(), start..self.size);
+
+        let mut count = 0;
+        while let Ok(Some(len)) = self.decoder.frame_len(data) {
+            data = bounded(data, len..data.len());
+            count += 1;
+        }
+        count
+    }
+
+    /// Moves the first complete packet, which is not a `PUBLISH`, in front of the buffered
+    /// `PUBLISH` packets preceding it.
+    ///
+    /// Returns `false` if the buffer does not start with a `PUBLISH` packet, or no such packet
+    /// is buffered yet.
+    fn promote_past_publishes(&mut self) -> bool {
+        let data = bounded(self.rx_buffer.as_slice(), self.start..self.size);
+
+        let mut offset = 0;
+        while let Ok(Some(len)) = self.decoder.frame_len(bounded(data, offset..data.len())) {
+            let is_publish =
+                data.get(offset).map(|b| b >> 4) == Some(<v5::Publish as Packet>::TYPE);
+            if !is_publish {
+                if offset == 0 {
+                    return false;
+                }
+                let end = self.start + offset + len;
+                if let Some(frames) = self.rx_buffer.as_slice_mut().get_mut(self.start..end) {
+                    frames.rotate_right(len);
+                    return true;
                 }
-                // TODO: maybe can recover here by just skipping the current packet,
-                // assuming the buffer is big enough to parse the fixed header.
-                //
-                // This allows recovery from oversized `PUBLISH` packets while still
-                // handling other packets gracefully.
-                // Need to consider QoS levels here possibly.
-                //
-                // In any case, we should return an error here at least once to inform the user,
-                // something was dropped.
-                return Err(Error::InsufficientBufferSize);
+                return false;
             }
+            offset += len;
+        }
+        false
+    }
 
-            let r = self.inner.read(remaining).await?;
-            if r == 0 {
-                match data.is_empty() {
-                    true => log::debug!("Clean Exit"),
-                    false => log::debug!("Connection Reset by Peer"),
-                };
-                return Err(Error::Disconnected);
-            } else {
-                self.size += r;
-                log::trace!("{:?} +{r}", &self.rx_buffer.as_slice()[..self.size]);
+    /// Discards all buffered data, which belongs to the previous connection when connecting
+    /// again.
+    fn discard_buffered(&mut self) {
+        self.start = 0;
+        self.size = 0;
+        self.release.position = None;
+    }
+
+    /// Discards data of the released packet and, depending on the [`Compaction`], moves all
+    /// the remaining data which is left in the buffer to the beginning.
+    fn compact(&mut self) {
+        // We need to do this at the beginning of reading a new packet, instead of
+        // at the end, because the just read packet may point into the buffer.
+        if let Some(position) = self.release.position.take() {
+            self.start += position;
+        }
+
+        let data = bounded(self.rx_buffer.as_slice(), self.start..self.size);
+        if self.start == self.size {
+            self.start = 0;
+            self.size = 0;
+        } else if self.start > 0 && !matches!(self.decoder.frame_len(data), Ok(Some(_))) {
+            // A complete packet is parsed in place, without moving it first. The buffer is
+            // compacted once a packet is incomplete and more data needs to be read.
+            match self.compaction {
+                Compaction::Eager => self.shift(),
+                Compaction::Threshold(threshold) if self.start > threshold => self.shift(),
+                Compaction::Threshold(_) | Compaction::Lazy => {
+                    self.stats.record_compaction_skipped()
+                }
             }
         }
     }
+
+    /// Move all the remaining data which is left in the buffer to the beginning,
+    /// to make sure the next package is properly aligned.
+    fn shift(&mut self) {
+        // There are two possible optimization we can do:
+        //  1) Make the buffer wrap, which requires support in all packets to parse from
+        //     a non continuous slice.
+        //  2) Read in two iterations. The first read only reads enough for the fixed header, 2-5
+        //     bytes, from that we know how long the total length of the packet is and we can
+        //     target read just enough for the packet, minimizing the amount of data we have to
+        //     copy.
+        log::trace!(
+            "{:?} -{}",
+            bounded(self.rx_buffer.as_slice(), 0..self.size),
+            self.start
+        );
+        let buffer = self.rx_buffer.as_slice_mut();
+        if self.start <= self.size && self.size <= buffer.len() {
+            buffer.copy_within(self.start..self.size, 0);
+        }
+        self.size -= self.start;
+        self.start = 0;
+        self.stats.record_compaction();
+        log::trace!(
+            "{:?} ={}",
+            bounded(self.rx_buffer.as_slice(), 0..self.size),
+            self.size
+        );
+    }
 }
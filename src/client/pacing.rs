@@ -0,0 +1,255 @@
+use core::fmt::Write as _;
+use core::time::Duration;
+
+#[cfg(doc)]
+use crate::client::{Client, Connect};
+use crate::utils::SliceWriter;
+
+/// Paces the transfer of large payloads, e.g. firmware images, against the keep alive.
+///
+/// A PINGREQ cannot be sent while a packet is only partially written. A publish which takes
+/// longer to transfer than the [keep alive](Client::keep_alive) may cause the server to close the
+/// connection, before the publish is complete.
+///
+/// Based on the expected throughput of the link, the payload can either be sent in a single
+/// publish with a keep alive negotiated up front ([`Self::keep_alive_for`]), or split into
+/// [`Chunked`] publishes which each complete within the keep alive.
+///
+/// A transfer is budgeted at most half the keep alive. The server closes the connection once no
+/// complete packet was received for one and a half times the keep alive, a transfer which starts
+/// right before a PINGREQ is due delays the PINGREQ by the duration of the transfer.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::client::{Chunked, Pacing, Plan};
+/// # async fn example<C>(client: &mut miniqtt::Client<C, Vec<u8>>, firmware: &[u8]) -> miniqtt::client::Result<(), C::Error>
+/// # where
+/// #     C: embedded_io_async::Read + embedded_io_async::Write,
+/// # {
+/// // A slow link, transferring about 2 KiB per second.
+/// let pacing = Pacing::new(2048);
+///
+/// match pacing.plan(firmware.len(), client.keep_alive()) {
+///     Plan::Single => {
+///         client.publish("ota/image", firmware).await?;
+///     }
+///     Plan::Chunked { chunk_size, .. } => {
+///         let chunks = Chunked::new("ota/image", firmware, chunk_size);
+///
+///         let mut topic = [0; 64];
+///         for index in 0..chunks.count() {
+///             let (topic, chunk) = chunks.get(index, &mut topic).expect("topic fits");
+///             client.publish(topic, chunk).await?;
+///         }
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pacing {
+    bytes_per_second: u32,
+}
+
+impl Pacing {
+    /// Creates a new pacing, for a link which transfers `bytes_per_second`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_second` is `0`.
+    pub const fn new(bytes_per_second: u32) -> Self {
+        assert!(bytes_per_second > 0, "bytes per second must not be zero");
+        Self { bytes_per_second }
+    }
+
+    /// Returns the expected time it takes to transfer `len` bytes.
+    pub fn transfer_time(&self, len: usize) -> Duration {
+        let millis = (len as u64).saturating_mul(1000) / u64::from(self.bytes_per_second);
+        Duration::from_millis(millis)
+    }
+
+    /// Returns the smallest keep alive in seconds, which allows transferring `len` bytes in a
+    /// single publish, within half the keep alive.
+    ///
+    /// The keep alive can be requested when connecting, see [`Connect::keep_alive`]. The server
+    /// may still assign a smaller keep alive.
+    ///
+    /// Returns `None` if the transfer takes longer than the largest possible keep alive.
+    pub fn keep_alive_for(&self, len: usize) -> Option<u16> {
+        let secs = (len as u64)
+            .saturating_mul(2)
+            .div_ceil(u64::from(self.bytes_per_second));
+        u16::try_from(secs.max(1)).ok()
+    }
+
+    /// Returns the largest amount of bytes, which can be transferred within half of
+    /// `keep_alive` seconds.
+    ///
+    /// At least a single byte is transferred per chunk, even on links which are too slow to
+    /// transfer it in time.
+    ///
+    /// Returns `None` if the keep alive is `0`, which disables the keep alive mechanism and
+    /// does not limit the duration of a transfer.
+    pub fn chunk_size(&self, keep_alive: u16) -> Option<usize> {
+        if keep_alive == 0 {
+            return None;
+        }
+
+        let len = (u64::from(self.bytes_per_second) * u64::from(keep_alive) / 2).max(1);
+        Some(usize::try_from(len).unwrap_or(usize::MAX))
+    }
+
+    /// Decides how a payload of `len` bytes is transferred with the `keep_alive` in seconds.
+    pub fn plan(&self, len: usize, keep_alive: u16) -> Plan {
+        match self.chunk_size(keep_alive) {
+            Some(chunk_size) if len > chunk_size => Plan::Chunked {
+                chunk_size,
+                chunks: len.div_ceil(chunk_size),
+            },
+            _ => Plan::Single,
+        }
+    }
+}
+
+/// How a payload is transferred, see [`Pacing::plan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Plan {
+    /// The payload is transferred within the keep alive and can be sent in a single publish.
+    Single,
+    /// The payload must be split into [`Chunked`] publishes.
+    Chunked {
+        /// The largest chunk which is transferred within half the keep alive.
+        chunk_size: usize,
+        /// The amount of chunks.
+        chunks: usize,
+    },
+}
+
+/// A payload split into multiple publishes.
+///
+/// Chunk `index` of `count` chunks is published to `<topic>/<index>/<count>`, the index is zero
+/// based. Chunks must be published in order, each chunk is a complete publish which keeps the
+/// connection alive. Receivers subscribe to `<topic>/+/+` and reassemble the payload once all
+/// chunks were received.
+///
+/// An empty payload is published as a single empty chunk.
+#[derive(Debug, Clone, Copy)]
+pub struct Chunked<'a> {
+    topic: &'a str,
+    payload: &'a [u8],
+    chunk_size: usize,
+}
+
+impl<'a> Chunked<'a> {
+    /// Splits `payload` into chunks of at most `chunk_size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is `0`.
+    pub fn new(topic: &'a str, payload: &'a [u8], chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must not be zero");
+        Self {
+            topic,
+            payload,
+            chunk_size,
+        }
+    }
+
+    /// Returns the amount of chunks.
+    pub fn count(&self) -> usize {
+        self.payload.len().div_ceil(self.chunk_size).max(1)
+    }
+
+    /// Returns the topic and payload of the chunk at `index`.
+    ///
+    /// The topic is written into `buffer`. Returns `None` if `index` is out of bounds or the
+    /// buffer is too small.
+    pub fn get<'b>(&self, index: usize, buffer: &'b mut [u8]) -> Option<(&'b str, &'a [u8])> {
+        let count = self.count();
+        if index >= count {
+            return None;
+        }
+
+        let start = index * self.chunk_size;
+        let end = self.payload.len().min(start + self.chunk_size);
+        let chunk = &self.payload[start..end];
+
+        let mut w = SliceWriter { buffer, len: 0 };
+        write!(w, "{}/{index}/{count}", self.topic).ok()?;
+
+        let SliceWriter { buffer, len } = w;
+        let topic = core::str::from_utf8(&buffer[..len]).ok()?;
+        Some((topic, chunk))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacing_plan() {
+        let pacing = Pacing::new(1000);
+
+        assert_eq!(pacing.transfer_time(2500), Duration::from_millis(2500));
+        assert_eq!(pacing.keep_alive_for(2500), Some(5));
+        assert_eq!(pacing.keep_alive_for(0), Some(1));
+        assert_eq!(pacing.keep_alive_for(usize::MAX), None);
+
+        assert_eq!(pacing.plan(2500, 5), Plan::Single);
+        assert_eq!(
+            pacing.plan(2500, 4),
+            Plan::Chunked {
+                chunk_size: 2000,
+                chunks: 2
+            }
+        );
+        // Without a keep alive, the transfer can take arbitrarily long.
+        assert_eq!(pacing.plan(usize::MAX, 0), Plan::Single);
+    }
+
+    #[test]
+    fn test_pacing_half_keep_alive() {
+        let pacing = Pacing::new(1000);
+
+        // Two seconds of transfer fit into half of a keep alive of four seconds.
+        assert_eq!(pacing.chunk_size(4), Some(2000));
+        assert_eq!(pacing.keep_alive_for(2000), Some(4));
+        assert_eq!(pacing.plan(2000, 4), Plan::Single);
+        assert_eq!(
+            pacing.plan(2001, 4),
+            Plan::Chunked {
+                chunk_size: 2000,
+                chunks: 2
+            }
+        );
+        assert_eq!(pacing.keep_alive_for(2001), Some(5));
+
+        // A link too slow for half the keep alive still makes progress.
+        assert_eq!(Pacing::new(1).chunk_size(1), Some(1));
+    }
+
+    #[test]
+    fn test_chunked() {
+        let chunks = Chunked::new("ota", b"abcde", 2);
+        assert_eq!(chunks.count(), 3);
+
+        let mut buffer = [0; 16];
+        assert_eq!(chunks.get(0, &mut buffer), Some(("ota/0/3", &b"ab"[..])));
+        assert_eq!(chunks.get(2, &mut buffer), Some(("ota/2/3", &b"e"[..])));
+        assert_eq!(chunks.get(3, &mut buffer), None);
+
+        let mut buffer = [0; 6];
+        assert_eq!(chunks.get(0, &mut buffer), None);
+    }
+
+    #[test]
+    fn test_chunked_empty() {
+        let chunks = Chunked::new("ota", b"", 2);
+        assert_eq!(chunks.count(), 1);
+
+        let mut buffer = [0; 16];
+        assert_eq!(chunks.get(0, &mut buffer), Some(("ota/0/1", &b""[..])));
+    }
+}
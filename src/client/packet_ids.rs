@@ -0,0 +1,234 @@
+use core::fmt;
+
+#[cfg(doc)]
+use crate::client::Connection;
+#[cfg(doc)]
+use crate::protocol::v5;
+
+/// A fixed-size set of packet identifiers in flight.
+///
+/// Tracks the identifiers of inbound QoS 2 publishes between receiving the `PUBLISH` and
+/// receiving the matching `PUBREL`. A `PUBLISH` whose identifier is already contained is a
+/// duplicate, it must be acknowledged but not delivered again. The [`Connection`] keeps a
+/// window of 64 identifiers for this, it also tracks detached publishes in one.
+///
+/// Identifiers are stored as bits in a window of `N * 32` consecutive identifiers. The window
+/// slides forward to make room for new identifiers, as long as no identifier which is still in
+/// flight would fall out of the window. Servers usually assign identifiers sequentially, a window
+/// at least as big as the negotiated Receive Maximum is sufficient in this case.
+///
+/// When the window is full [`PacketIdWindow::insert`] fails with [`WindowFull`]. Nothing is
+/// dropped from the set, the exactly-once guarantee still holds, but the publish must be rejected
+/// (with the reason `0x97`, [Quota exceeded](v5::PubRecReason::QuotaExceeded)) instead of
+/// delivered.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::client::PacketIdWindow;
+/// let mut in_flight = PacketIdWindow::<2>::new();
+///
+/// assert_eq!(in_flight.insert(7), Ok(true));
+/// // Duplicate PUBLISH, must not be delivered again.
+/// assert_eq!(in_flight.insert(7), Ok(false));
+/// // PUBREL received, the identifier can be re-used.
+/// assert!(in_flight.remove(7));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PacketIdWindow<const N: usize> {
+    /// The first identifier in the window.
+    base: u16,
+    bits: [u32; N],
+}
+
+impl<const N: usize> PacketIdWindow<N> {
+    const SIZE: usize = N * 32;
+
+    /// Creates a new, empty window.
+    pub const fn new() -> Self {
+        Self {
+            base: 0,
+            bits: [0; N],
+        }
+    }
+
+    /// Adds an identifier to the set.
+    ///
+    /// Returns `true` if the identifier was newly inserted and `false` if it was already
+    /// contained.
+    pub fn insert(&mut self, id: u16) -> Result<bool, WindowFull> {
+        // An empty window can start anywhere, start it at the new identifier.
+        if self.is_empty() {
+            self.base = id;
+        }
+
+        let offset = match self.offset(id) {
+            Some(offset) => offset,
+            None => self.slide_to(id)?,
+        };
+
+        let was_set = self.get(offset);
+        self.set(offset, true);
+        Ok(!was_set)
+    }
+
+    /// Removes an identifier from the set.
+    ///
+    /// Returns whether the identifier was contained.
+    pub fn remove(&mut self, id: u16) -> bool {
+        let Some(offset) = self.offset(id) else {
+            return false;
+        };
+
+        let was_set = self.get(offset);
+        self.set(offset, false);
+        was_set
+    }
+
+    /// Returns the contained identifiers, starting with the first identifier in the window.
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..Self::SIZE)
+            .filter(|&offset| self.get(offset))
+            .map(|offset| self.base.wrapping_add(offset as u16))
+    }
+
+    /// Removes all identifiers from the set.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Returns whether the identifier is contained.
+    pub fn contains(&self, id: u16) -> bool {
+        self.offset(id).is_some_and(|offset| self.get(offset))
+    }
+
+    /// Returns the amount of contained identifiers.
+    pub fn len(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns `true` if no identifiers are contained.
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&word| word == 0)
+    }
+
+    /// Returns the offset of `id` in the window, if it is within the window.
+    fn offset(&self, id: u16) -> Option<usize> {
+        let offset = usize::from(id.wrapping_sub(self.base));
+        (offset < Self::SIZE).then_some(offset)
+    }
+
+    /// Moves the window forward, until `id` is the last identifier in the window.
+    ///
+    /// Returns the new offset of `id`.
+    fn slide_to(&mut self, id: u16) -> Result<usize, WindowFull> {
+        if Self::SIZE == 0 {
+            return Err(WindowFull);
+        }
+
+        let shift = usize::from(id.wrapping_sub(self.base)) - (Self::SIZE - 1);
+        if shift >= Self::SIZE || (0..shift).any(|offset| self.get(offset)) {
+            return Err(WindowFull);
+        }
+
+        for offset in 0..Self::SIZE {
+            let value = offset + shift < Self::SIZE && self.get(offset + shift);
+            self.set(offset, value);
+        }
+        self.base = self.base.wrapping_add(shift as u16);
+
+        Ok(Self::SIZE - 1)
+    }
+
+    fn get(&self, offset: usize) -> bool {
+        self.bits
+            .get(offset / 32)
+            .is_some_and(|word| word & (1 << (offset % 32)) != 0)
+    }
+
+    fn set(&mut self, offset: usize, value: bool) {
+        let mask = 1 << (offset % 32);
+        let Some(word) = self.bits.get_mut(offset / 32) else {
+            return;
+        };
+        match value {
+            true => *word |= mask,
+            false => *word &= !mask,
+        }
+    }
+}
+
+impl<const N: usize> Default for PacketIdWindow<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returned when a [`PacketIdWindow`] cannot fit another identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowFull;
+
+impl fmt::Display for WindowFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("packet identifier window is full")
+    }
+}
+
+impl core::error::Error for WindowFull {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packet_id_window_slide() {
+        let mut window = PacketIdWindow::<1>::new();
+
+        assert_eq!(window.insert(100), Ok(true));
+        assert_eq!(window.insert(131), Ok(true));
+        // 100 is still in flight, 132 would push it out of the window.
+        assert_eq!(window.insert(132), Err(WindowFull));
+        assert_eq!(window.len(), 2);
+
+        assert!(window.iter().eq([100, 131]));
+        assert!(window.remove(100));
+        assert_eq!(window.insert(132), Ok(true));
+        assert_eq!(window.insert(131), Ok(false));
+        assert!(window.contains(132));
+        assert!(!window.contains(100));
+        assert_eq!(window.len(), 2);
+    }
+
+    #[test]
+    fn test_packet_id_window_wrap() {
+        let mut window = PacketIdWindow::<2>::new();
+
+        assert_eq!(window.insert(u16::MAX - 1), Ok(true));
+        assert_eq!(window.insert(u16::MAX), Ok(true));
+        assert_eq!(window.insert(1), Ok(true));
+        assert_eq!(window.insert(u16::MAX), Ok(false));
+
+        assert!(window.remove(u16::MAX - 1));
+        assert!(window.remove(u16::MAX));
+        assert!(window.remove(1));
+        assert!(window.is_empty());
+
+        // An empty window moves to any identifier.
+        assert_eq!(window.insert(500), Ok(true));
+        assert!(window.contains(500));
+    }
+
+    #[test]
+    fn test_packet_id_window_behind() {
+        let mut window = PacketIdWindow::<1>::new();
+
+        assert_eq!(window.insert(10), Ok(true));
+        // Identifiers behind the window cannot be tracked without losing 10.
+        assert_eq!(window.insert(9), Err(WindowFull));
+        assert!(!window.remove(9));
+        assert!(window.contains(10));
+    }
+}
@@ -0,0 +1,34 @@
+/// Schedules PINGREQs sent while waiting for a packet.
+pub(super) trait Pings {
+    /// Completes when the next PINGREQ is due.
+    fn due(&mut self) -> impl Future<Output = ()>;
+}
+
+/// Never sends a PINGREQ.
+pub(super) struct NoPings;
+
+impl Pings for NoPings {
+    fn due(&mut self) -> impl Future<Output = ()> {
+        core::future::pending()
+    }
+}
+
+/// Sends a PINGREQ, whenever the keep alive interval elapses.
+#[cfg(feature = "embedded-hal-async-1")]
+pub(super) struct DelayPings<'a, D> {
+    pub delay: &'a mut D,
+    /// Delay until the next PINGREQ.
+    pub next_ms: u32,
+    pub interval_ms: u32,
+}
+
+#[cfg(feature = "embedded-hal-async-1")]
+impl<D> Pings for DelayPings<'_, D>
+where
+    D: embedded_hal_async::delay::DelayNs,
+{
+    fn due(&mut self) -> impl Future<Output = ()> {
+        let next_ms = core::mem::replace(&mut self.next_ms, self.interval_ms);
+        self.delay.delay_ms(next_ms)
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(doc)]
+use crate::client::Connect;
+#[cfg(feature = "will")]
+use crate::protocol::QoS;
+use crate::protocol::v5;
+
+/// Announces whether a client is online on a topic.
+///
+/// When [connecting](Connect::with_presence), a retained online message is published and a
+/// retained offline message is configured as will. Subscribers of the topic always receive the
+/// latest state of the client, also when the client goes away without disconnecting.
+///
+/// By default the payloads are the raw strings `online` and `offline`.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::client::Presence;
+/// # async fn example<C>(client: &mut miniqtt::Client<C, Vec<u8>>) -> miniqtt::client::Result<(), C::Error>
+/// # where
+/// #     C: embedded_io_async::Read + embedded_io_async::Write,
+/// # {
+/// let presence = Presence::new("devices/sensor-1/status").json();
+/// client.connect("sensor-1").with_presence(presence).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "will")]
+#[derive(Debug, Clone, Copy)]
+pub struct Presence<'a> {
+    topic: &'a str,
+    online: &'a [u8],
+    offline: &'a [u8],
+}
+
+#[cfg(feature = "will")]
+impl<'a> Presence<'a> {
+    /// Creates a new presence announced on `topic`.
+    pub fn new(topic: &'a str) -> Self {
+        Self {
+            topic,
+            online: b"online",
+            offline: b"offline",
+        }
+    }
+
+    /// Uses the JSON payloads `{"online":true}` and `{"online":false}`.
+    pub fn json(self) -> Self {
+        self.with_payloads(br#"{"online":true}"#, br#"{"online":false}"#)
+    }
+
+    /// Uses custom payloads for the online and offline messages.
+    pub fn with_payloads(mut self, online: &'a [u8], offline: &'a [u8]) -> Self {
+        self.online = online;
+        self.offline = offline;
+        self
+    }
+
+    /// Returns the topic the presence is announced on.
+    pub fn topic(&self) -> &'a str {
+        self.topic
+    }
+
+    /// The retained message published after connecting.
+    pub(super) fn online(&self) -> v5::Publish<'a, &'a [u8], &'a [v5::PublishProperty<'a>]> {
+        v5::Publish {
+            dup: false,
+            qos: QoS::AtMostOnce,
+            retain: true,
+            identifier: None,
+            topic: self.topic,
+            properties: &[],
+            payload: self.online,
+        }
+    }
+
+    /// The retained will published by the server when the client goes away.
+    pub(super) fn will(&self) -> v5::Will<'a> {
+        v5::Will {
+            retain: true,
+            qos: QoS::AtMostOnce,
+            properties: &[],
+            topic: self.topic,
+            payload: self.offline,
+        }
+    }
+}
+
+/// Without wills there is no presence, a connect never announces one.
+#[cfg(not(feature = "will"))]
+#[derive(Debug, Clone, Copy)]
+pub struct Presence<'a>(core::convert::Infallible, core::marker::PhantomData<&'a ()>);
+
+#[cfg(not(feature = "will"))]
+impl<'a> Presence<'a> {
+    pub(super) fn online(&self) -> v5::Publish<'a, &'a [u8], &'a [v5::PublishProperty<'a>]> {
+        match self.0 {}
+    }
+}
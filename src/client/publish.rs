@@ -0,0 +1,287 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::client::MakeFuture;
+use crate::client::PacketIdWindow;
+use crate::client::utils::LazyMakeFuture;
+#[cfg(doc)]
+use crate::client::{Client, ConnectionBuilder, Error};
+use crate::log;
+use crate::protocol::types::PacketId;
+use crate::protocol::{QoS, v5};
+
+pin_project_lite::pin_project! {
+    /// Future returned by [`Client::publish`].
+    ///
+    /// # Cancel safety
+    ///
+    /// This future is *not* cancel safe.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Publish<'a, T> {
+        options: PublishOptions<'a>,
+        #[pin]
+        inner: T,
+    }
+}
+
+/// The options of a [`Publish`], passed to the future which sends it.
+#[derive(Debug, Clone, Copy)]
+pub struct PublishOptions<'a> {
+    pub(super) topic: &'a str,
+    pub(super) payload: &'a [u8],
+    pub(super) qos: QoS,
+    pub(super) retain: bool,
+    pub(super) message_expiry_interval: Option<u32>,
+    pub(super) content_type: Option<&'a str>,
+    pub(super) response_topic: Option<&'a str>,
+    pub(super) correlation_data: Option<&'a [u8]>,
+    pub(super) utf8_payload: bool,
+    pub(super) detach: bool,
+    pub(super) properties: &'a [v5::PublishProperty<'a>],
+}
+
+impl<'a> PublishOptions<'a> {
+    /// Writes the properties configured through the builder into `buffer`.
+    ///
+    /// Properties passed with [`Publish::with_properties`] are not included.
+    pub(super) fn builder_properties<'b>(
+        &self,
+        buffer: &'b mut [v5::PublishProperty<'a>; 5],
+    ) -> &'b [v5::PublishProperty<'a>] {
+        let properties = [
+            self.utf8_payload
+                .then_some(v5::PublishProperty::PayloadFormatIndicator(1)),
+            self.message_expiry_interval
+                .map(v5::PublishProperty::MessageExpiryInterval),
+            self.content_type.map(v5::PublishProperty::ContentType),
+            self.response_topic.map(v5::PublishProperty::ResponseTopic),
+            self.correlation_data
+                .map(v5::PublishProperty::CorrelationData),
+        ];
+
+        let mut len = 0;
+        for property in properties.into_iter().flatten() {
+            buffer[len] = property;
+            len += 1;
+        }
+        &buffer[..len]
+    }
+}
+
+impl<'a, T> Publish<'a, T> {
+    /// Configures the quality of service of the message.
+    ///
+    /// Defaults to [`QoS::AtMostOnce`]. With [`QoS::AtLeastOnce`] and [`QoS::ExactlyOnce`] the
+    /// publish waits until the server acknowledged the message.
+    pub fn qos(mut self, qos: QoS) -> Self {
+        self.options.qos = qos;
+        self
+    }
+
+    /// Whether the server should retain the message for future subscribers of the topic.
+    ///
+    /// Defaults to `false`.
+    pub fn retain(mut self, retain: bool) -> Self {
+        self.options.retain = retain;
+        self
+    }
+
+    /// Configures the lifetime of the message in seconds.
+    ///
+    /// The server discards the message, if it cannot be delivered to a subscriber within the
+    /// interval.
+    ///
+    /// Spec: [3.3.2.3.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901112)
+    pub fn with_message_expiry_interval(mut self, seconds: u32) -> Self {
+        self.options.message_expiry_interval = Some(seconds);
+        self
+    }
+
+    /// Configures the content type of the message, e.g. a MIME type.
+    ///
+    /// Spec: [3.3.2.3.9](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901118)
+    pub fn with_content_type(mut self, content_type: &'a str) -> Self {
+        self.options.content_type = Some(content_type);
+        self
+    }
+
+    /// Configures the topic a receiver should publish the response to this message to.
+    ///
+    /// Spec: [3.3.2.3.5](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901114)
+    pub fn with_response_topic(mut self, response_topic: &'a str) -> Self {
+        self.options.response_topic = Some(response_topic);
+        self
+    }
+
+    /// Configures data which identifies the request a response belongs to.
+    ///
+    /// Spec: [3.3.2.3.6](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901115)
+    pub fn with_correlation_data(mut self, correlation_data: &'a [u8]) -> Self {
+        self.options.correlation_data = Some(correlation_data);
+        self
+    }
+
+    /// Whether the payload is UTF-8 encoded character data, sent as the payload format
+    /// indicator.
+    ///
+    /// Defaults to `false`, which leaves the format of the payload unspecified.
+    ///
+    /// Spec: [3.3.2.3.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901111)
+    pub fn utf8_payload(mut self, utf8_payload: bool) -> Self {
+        self.options.utf8_payload = utf8_payload;
+        self
+    }
+
+    /// Returns as soon as a [`QoS::AtLeastOnce`] message is sent, without waiting for the
+    /// PUBACK. The publish returns the packet identifier of the message.
+    ///
+    /// The acknowledgement is tracked by the connection and consumed whenever a later operation
+    /// receives from the server. A rejected message is reported with its packet identifier to
+    /// the dead letter callback, configured with [`ConnectionBuilder::with_dead_letter`], see
+    /// [`Client::pending_acks`].
+    ///
+    /// In flight messages are tracked in a window of 64 consecutive packet identifiers. A publish
+    /// whose identifier does not fit into the window, because an older message is still
+    /// unacknowledged, waits for its PUBACK like a regular publish.
+    ///
+    /// Messages are never retransmitted, a detached publish alone does not guarantee delivery.
+    /// Messages which are still unacknowledged when the client connects again are reported to
+    /// the dead letter callback as [`DeadLetter::Lost`], they may or may not have been
+    /// delivered. It is up to the application to publish dead letters again.
+    ///
+    /// Has no effect on messages with any other quality of service.
+    pub fn detach(mut self) -> Self {
+        self.options.detach = true;
+        self
+    }
+
+    /// Configures additional properties for the message, e.g. user properties.
+    ///
+    /// The properties are sent after the properties configured through the other methods of
+    /// the builder.
+    pub fn with_properties(mut self, properties: &'a [v5::PublishProperty<'a>]) -> Self {
+        self.options.properties = properties;
+        self
+    }
+}
+
+impl Publish<'_, ()> {
+    pub(super) fn new<'a, M, F, O>(
+        topic: &'a str,
+        payload: &'a [u8],
+        m: M,
+    ) -> Publish<'a, impl MakeFuture<PublishOptions<'a>, Output = O>>
+    where
+        M: FnOnce(PublishOptions<'a>) -> F,
+        F: Future<Output = O>,
+    {
+        Publish {
+            options: PublishOptions {
+                topic,
+                payload,
+                qos: QoS::AtMostOnce,
+                retain: false,
+                message_expiry_interval: None,
+                content_type: None,
+                response_topic: None,
+                correlation_data: None,
+                utf8_payload: false,
+                detach: false,
+                properties: &[],
+            },
+            inner: LazyMakeFuture::new(m),
+        }
+    }
+}
+
+impl<'a, M> Future for Publish<'a, M>
+where
+    M: MakeFuture<PublishOptions<'a>>,
+{
+    type Output = M::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        this.inner.poll(this.options, cx)
+    }
+}
+
+/// Why a [detached](Publish::detach) publish was not delivered, reported to the dead letter
+/// callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadLetter {
+    /// The server rejected the message with the reason of the PUBACK.
+    Rejected(v5::PubAckReason),
+    /// The client connected again before the message was acknowledged.
+    ///
+    /// The message is not retransmitted, it may or may not have been delivered.
+    Lost,
+}
+
+/// Tracks the acknowledgements of detached QoS 1 publishes, see [`Publish::detach`].
+#[derive(Debug, Default)]
+pub(super) struct Detached {
+    /// Identifiers of publishes, which are not yet acknowledged.
+    in_flight: PacketIdWindow<2>,
+    /// Invoked for publishes, which are not delivered.
+    pub(super) dead_letter: Option<fn(PacketId, DeadLetter)>,
+}
+
+impl Detached {
+    /// Tracks the acknowledgement of a sent publish.
+    ///
+    /// Returns `false` if too many publishes are in flight, the acknowledgement must be awaited
+    /// instead.
+    pub(super) fn insert(&mut self, identifier: PacketId) -> bool {
+        match self.in_flight.insert(identifier.get()) {
+            Ok(_) => true,
+            Err(_) => {
+                log::debug!("too many detached publishes in flight, awaiting puback: {identifier}");
+                false
+            }
+        }
+    }
+
+    /// Returns whether the publish with `identifier` is not yet acknowledged.
+    pub(super) fn contains(&self, identifier: PacketId) -> bool {
+        self.in_flight.contains(identifier.get())
+    }
+
+    /// Returns the amount of publishes, which are not yet acknowledged.
+    pub(super) fn pending(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Consumes the acknowledgement of a publish.
+    ///
+    /// Rejections are reported to the dead letter callback. Returns `false` if the
+    /// acknowledgement does not belong to a detached publish.
+    pub(super) fn settle(&mut self, identifier: PacketId, reason: v5::PubAckReason) -> bool {
+        if !self.in_flight.remove(identifier.get()) {
+            return false;
+        }
+
+        if !reason.is_success() {
+            log::debug!("detached publish {identifier} rejected: {reason:?}");
+            if let Some(dead_letter) = self.dead_letter {
+                dead_letter(identifier, DeadLetter::Rejected(reason));
+            }
+        }
+        true
+    }
+
+    /// Forgets all publishes which are not yet acknowledged, e.g. when the client connects
+    /// again.
+    ///
+    /// Their acknowledgements were lost with the previous connection, they are reported to the
+    /// dead letter callback.
+    pub(super) fn reset(&mut self) {
+        for identifier in self.in_flight.iter().filter_map(PacketId::new) {
+            log::debug!("detached publish {identifier} lost");
+            if let Some(dead_letter) = self.dead_letter {
+                dead_letter(identifier, DeadLetter::Lost);
+            }
+        }
+        self.in_flight.clear();
+    }
+}
@@ -0,0 +1,369 @@
+use core::fmt;
+
+#[cfg(doc)]
+use crate::client::{Connection, ConnectionBuilder};
+
+/// Size of the record header preceding every frame:
+/// direction, timestamp, frame length and recorded length.
+const HEADER: usize = 1 + 8 + 4 + 4;
+
+/// Direction of a recorded [`Frame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The frame was sent to the server.
+    Sent,
+    /// The frame was received from the server.
+    Received,
+}
+
+/// Records the most recent frames sent and received by a [`Connection`].
+///
+/// Frames are stored with their direction and a timestamp in a caller provided buffer.
+/// When the buffer is full, the oldest frames are discarded. Frames which do not fit into the
+/// buffer on their own are truncated.
+///
+/// After an error, the recorded [frames](Self::frames) can be dumped to provide evidence of the
+/// exchanged packets, without a network capture.
+///
+/// See [`ConnectionBuilder::with_recorder`].
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::client::Recorder;
+/// # fn example<C>(stream: C, buffer: &'static mut [u8]) {
+/// fn now() -> u64 {
+///     // Milliseconds since boot, from the platform's clock.
+///     # 0
+/// }
+///
+/// let connection = miniqtt::Connection::builder(stream, [0; 128])
+///     .with_recorder(Recorder::new(buffer, now))
+///     .build();
+///
+/// // After an error:
+/// if let Some(recorder) = connection.recorder() {
+///     for frame in recorder.frames() {
+///         println!("{} {:?} {:02x?}", frame.timestamp, frame.direction, frame.data);
+///     }
+/// }
+/// # }
+/// ```
+pub struct Recorder {
+    buffer: &'static mut [u8],
+    /// Amount of bytes used by the recorded frames.
+    len: usize,
+    /// Offset of the frame which is currently recorded, more data may be appended to it.
+    current: Option<usize>,
+    /// Source of frame timestamps.
+    now: fn() -> u64,
+}
+
+impl Recorder {
+    /// Creates a new recorder, which stores frames in `buffer`.
+    ///
+    /// `now` is invoked for every recorded frame and returns its timestamp, the unit is up to
+    /// the caller.
+    pub fn new(buffer: &'static mut [u8], now: fn() -> u64) -> Self {
+        Self {
+            buffer,
+            len: 0,
+            current: None,
+            now,
+        }
+    }
+
+    /// Returns an iterator over all recorded frames, from the oldest to the most recent frame.
+    pub fn frames(&self) -> Frames<'_> {
+        Frames {
+            data: self.as_bytes(),
+        }
+    }
+
+    /// Returns the recorded frames in their serialized form.
+    ///
+    /// The data can be saved, e.g. to a file, and read again with [`Frames::new`]. A saved
+    /// session can be replayed with a [`Replay`](crate::transport::Replay) transport.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+
+    /// Discards all recorded frames.
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.current = None;
+    }
+
+    /// Records a complete frame.
+    pub(super) fn record(&mut self, direction: Direction, data: &[u8]) {
+        self.begin(direction);
+        self.append(data);
+        self.current = None;
+    }
+
+    /// Starts recording a new frame, data is added with [`Self::append`].
+    pub(super) fn begin(&mut self, direction: Direction) {
+        self.current = None;
+        self.evict(HEADER);
+
+        if self.buffer.len() - self.len < HEADER {
+            return;
+        }
+
+        let header = &mut self.buffer[self.len..self.len + HEADER];
+        header[0] = direction as u8;
+        header[1..9].copy_from_slice(&(self.now)().to_le_bytes());
+        header[9..].fill(0);
+
+        self.current = Some(self.len);
+        self.len += HEADER;
+    }
+
+    /// Appends data to the frame which is currently recorded.
+    pub(super) fn append(&mut self, data: &[u8]) {
+        // Data which is not recorded must not evict other frames.
+        if self.current.is_none() {
+            return;
+        }
+        self.evict(data.len());
+        let Some(current) = self.current else {
+            return;
+        };
+
+        let recorded = data.len().min(self.buffer.len() - self.len);
+        self.buffer[self.len..self.len + recorded].copy_from_slice(&data[..recorded]);
+        self.len += recorded;
+
+        let header = &mut self.buffer[current..current + HEADER];
+        let add = |field: &mut [u8], n: usize| {
+            let value = read_u32(field).saturating_add(n as u32);
+            field.copy_from_slice(&value.to_le_bytes());
+        };
+        add(&mut header[9..13], data.len());
+        add(&mut header[13..17], recorded);
+    }
+
+    /// Discards the oldest frames, until at least `needed` bytes are available or only the
+    /// current frame is left.
+    fn evict(&mut self, needed: usize) {
+        while self.buffer.len() - self.len < needed && self.len > 0 && self.current != Some(0) {
+            let size = HEADER + read_u32(&self.buffer[13..17]) as usize;
+            self.buffer.copy_within(size..self.len, 0);
+            self.len -= size;
+            self.current = self.current.map(|current| current - size);
+        }
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("frames", &self.frames().count())
+            .field("len", &self.len)
+            .field("capacity", &self.buffer.len())
+            .finish()
+    }
+}
+
+/// A frame recorded by a [`Recorder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame<'a> {
+    /// Whether the frame was sent or received.
+    pub direction: Direction,
+    /// Timestamp of the frame, as returned by the time source of the recorder.
+    pub timestamp: u64,
+    /// Length of the frame.
+    ///
+    /// For sent frames this is the amount of bytes which were written, it is shorter than the
+    /// packet if writing the packet failed.
+    pub len: usize,
+    /// Recorded data of the frame, may be shorter than [`Self::len`] if the frame was truncated.
+    pub data: &'a [u8],
+}
+
+impl Frame<'_> {
+    /// Returns `true` if the frame did not fit into the recorder and was truncated.
+    pub fn is_truncated(&self) -> bool {
+        self.data.len() < self.len
+    }
+}
+
+/// Iterator over recorded frames, see [`Recorder::frames`].
+#[derive(Debug, Clone)]
+pub struct Frames<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Frames<'a> {
+    /// Reads frames saved from [`Recorder::as_bytes`].
+    ///
+    /// Iteration stops at the first incomplete frame.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Frame<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (header, rest) = self.data.split_at_checked(HEADER)?;
+
+        let direction = match header[0] {
+            0 => Direction::Sent,
+            _ => Direction::Received,
+        };
+        let timestamp = u64::from_le_bytes(header[1..9].try_into().unwrap());
+        let len = read_u32(&header[9..13]) as usize;
+        let recorded = read_u32(&header[13..17]) as usize;
+
+        let (data, rest) = rest.split_at_checked(recorded)?;
+        self.data = rest;
+
+        Some(Frame {
+            direction,
+            timestamp,
+            len,
+            data,
+        })
+    }
+}
+
+/// A writer which records all data written to the inner writer.
+pub(super) struct Recording<'a, W> {
+    inner: &'a mut W,
+    recorder: Option<&'a mut Recorder>,
+    /// Amount of bytes written to the sink.
+    written: usize,
+}
+
+impl<'a, W> Recording<'a, W> {
+    /// Creates a new sink, which records the written data as a new sent frame.
+    pub fn new(inner: &'a mut W, mut recorder: Option<&'a mut Recorder>) -> Self {
+        if let Some(recorder) = &mut recorder {
+            recorder.begin(Direction::Sent);
+        }
+        Self {
+            inner,
+            recorder,
+            written: 0,
+        }
+    }
+
+    /// Returns the amount of bytes written to the sink.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+}
+
+impl<W> embedded_io_async::ErrorType for Recording<'_, W>
+where
+    W: embedded_io_async::ErrorType,
+{
+    type Error = W::Error;
+}
+
+impl<W> embedded_io_async::Write for Recording<'_, W>
+where
+    W: embedded_io_async::Write,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let written = self.inner.write(buf).await?;
+        if let Some(recorder) = &mut self.recorder {
+            recorder.append(&buf[..written]);
+        }
+        self.written += written;
+        Ok(written)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush().await
+    }
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_le_bytes(data[..4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder<const N: usize>() -> Recorder {
+        Recorder::new(Box::leak(Box::new([0; N])), || 7)
+    }
+
+    fn frames(recorder: &Recorder) -> Vec<(Direction, &[u8])> {
+        recorder.frames().map(|f| (f.direction, f.data)).collect()
+    }
+
+    #[test]
+    fn test_recorder_evicts_oldest() {
+        let mut recorder = recorder::<{ 3 * HEADER + 6 }>();
+
+        recorder.record(Direction::Sent, b"aa");
+        recorder.record(Direction::Received, b"bb");
+        recorder.record(Direction::Sent, b"cc");
+        assert_eq!(
+            frames(&recorder),
+            [
+                (Direction::Sent, &b"aa"[..]),
+                (Direction::Received, b"bb"),
+                (Direction::Sent, b"cc")
+            ]
+        );
+
+        recorder.record(Direction::Received, b"dd");
+        assert_eq!(
+            frames(&recorder),
+            [
+                (Direction::Received, &b"bb"[..]),
+                (Direction::Sent, b"cc"),
+                (Direction::Received, b"dd")
+            ]
+        );
+        assert!(recorder.frames().all(|f| f.timestamp == 7));
+
+        recorder.clear();
+        assert_eq!(recorder.frames().count(), 0);
+    }
+
+    #[test]
+    fn test_recorder_truncates() {
+        let mut recorder = recorder::<{ HEADER + 4 }>();
+
+        recorder.record(Direction::Sent, b"aa");
+        recorder.begin(Direction::Received);
+        recorder.append(b"bbb");
+        recorder.append(b"ccc");
+
+        let frame = recorder.frames().next().unwrap();
+        assert_eq!(recorder.frames().count(), 1);
+        assert_eq!(frame.direction, Direction::Received);
+        assert_eq!(frame.data, b"bbbc");
+        assert_eq!(frame.len, 6);
+        assert!(frame.is_truncated());
+    }
+
+    #[test]
+    fn test_frames_saved() {
+        let mut recorder = recorder::<64>();
+        recorder.record(Direction::Sent, b"aa");
+        recorder.record(Direction::Received, b"bb");
+
+        let saved = recorder.as_bytes().to_vec();
+        assert!(Frames::new(&saved).eq(recorder.frames()));
+
+        // An incomplete frame is not returned.
+        let frames = Frames::new(&saved[..saved.len() - 1]);
+        assert_eq!(frames.map(|f| f.data).collect::<Vec<_>>(), [b"aa"]);
+    }
+
+    #[test]
+    fn test_recorder_too_small() {
+        let mut recorder = recorder::<{ HEADER - 1 }>();
+
+        recorder.record(Direction::Sent, b"aa");
+        assert_eq!(recorder.frames().count(), 0);
+    }
+}
@@ -0,0 +1,105 @@
+use core::fmt;
+
+#[cfg(doc)]
+use crate::client::{Client, Message};
+use crate::protocol::v5;
+
+/// The destination of a reply to a request, implementing the responder side of the MQTT 5
+/// request/response pattern.
+///
+/// Copies the [Response Topic](v5::PublishProperty::ResponseTopic) and the
+/// [Correlation Data](v5::PublishProperty::CorrelationData) out of a received [`Message`], with a
+/// combined capacity of `N` bytes. The message can be dropped, before the reply is sent with
+/// [`Client::reply`].
+///
+/// Spec: [4.10](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901252)
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::client::ReplyTo;
+/// # async fn example<C>(
+/// #     client: &mut miniqtt::Client<C, [u8; 128]>,
+/// # ) -> miniqtt::client::Result<(), C::Error>
+/// # where
+/// #     C: embedded_io_async::Read + embedded_io_async::Write,
+/// # {
+/// let message = client.receive().await?;
+/// let status: &[u8] = match message.payload {
+///     b"ping" => b"pong",
+///     _ => b"unknown",
+/// };
+/// let reply_to = ReplyTo::<64>::new(&message);
+/// drop(message);
+///
+/// if let Some(reply_to) = reply_to {
+///     client.reply(&reply_to, status).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub struct ReplyTo<const N: usize = 128> {
+    buffer: [u8; N],
+    topic_len: usize,
+    /// Length of the Correlation Data, which follows the topic in the buffer.
+    correlation_data_len: Option<usize>,
+}
+
+impl<const N: usize> ReplyTo<N> {
+    /// Copies the Response Topic and Correlation Data of `message`.
+    ///
+    /// Returns `None` if the message has no Response Topic, or the Response Topic and the
+    /// Correlation Data together are longer than `N` bytes.
+    pub fn new(message: &v5::Publish<'_>) -> Option<Self> {
+        let topic = message.properties.response_topic()?;
+        let correlation_data = message.properties.correlation_data();
+
+        let mut buffer = [0; N];
+        buffer
+            .get_mut(..topic.len())?
+            .copy_from_slice(topic.as_bytes());
+        if let Some(data) = correlation_data {
+            buffer
+                .get_mut(topic.len()..topic.len() + data.len())?
+                .copy_from_slice(data);
+        }
+
+        Some(Self {
+            buffer,
+            topic_len: topic.len(),
+            correlation_data_len: correlation_data.map(<[u8]>::len),
+        })
+    }
+
+    /// Returns the topic the reply is published to.
+    pub fn topic(&self) -> &str {
+        let topic = &self.buffer[..self.topic_len];
+
+        #[cfg(not(feature = "forbid-unsafe"))]
+        {
+            // SAFETY: the topic is only ever initialized from a `str`, up to `topic_len`.
+            unsafe { core::str::from_utf8_unchecked(topic) }
+        }
+        #[cfg(feature = "forbid-unsafe")]
+        {
+            // The topic is only ever initialized from a `str`, up to `topic_len`.
+            core::str::from_utf8(topic).unwrap_or_default()
+        }
+    }
+
+    /// Returns the Correlation Data the reply carries back, if the request had any.
+    pub fn correlation_data(&self) -> Option<&[u8]> {
+        let len = self.correlation_data_len?;
+        self.buffer.get(self.topic_len..self.topic_len + len)
+    }
+}
+
+impl<const N: usize> fmt::Debug for ReplyTo<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplyTo")
+            .field("topic", &self.topic())
+            .field("correlation_data", &self.correlation_data())
+            .finish()
+    }
+}
@@ -0,0 +1,308 @@
+#[cfg(not(feature = "forbid-unsafe"))]
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+#[cfg(doc)]
+use crate::client::Client;
+#[cfg(doc)]
+use crate::protocol::codec::Encoder;
+use crate::protocol::types::VariableByteInteger;
+use crate::protocol::{Packet, v5};
+
+/// The slot is free.
+const EMPTY: u8 = 0;
+/// A publish is being serialized into the slot.
+const WRITING: u8 = 1;
+/// The slot contains a publish, which can be sent.
+const READY: u8 = 2;
+/// The publish is being sent by the client.
+const SENDING: u8 = 3;
+
+/// A slot for a single QoS 0 publish, staged outside of the task which drives the [`Client`].
+///
+/// Publishes are staged through a [`Handle`], without locking and without access to the client,
+/// which makes it possible to publish from interrupt handlers, e.g. for button presses or
+/// alarms. The publish is serialized into the slot immediately, the task driving the client
+/// sends it with [`Client::send_staged`].
+///
+/// Only one publish can be staged at a time, staging fails until the previous publish was sent.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::client::Staging;
+/// static ALARM: Staging<64> = Staging::new();
+///
+/// fn on_button_press() {
+///     // Dropped if the previous alarm was not sent yet.
+///     let _ = ALARM.handle().try_publish_qos0("alarm", b"pressed");
+/// }
+///
+/// # async fn example<C>(
+/// #     client: &mut miniqtt::Client<C, [u8; 128]>,
+/// # ) -> miniqtt::client::Result<(), C::Error>
+/// # where
+/// #     C: embedded_io_async::Read + embedded_io_async::Write,
+/// # {
+/// // In the event loop, e.g. after being signaled by the interrupt handler:
+/// client.send_staged(&ALARM).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Staging<const N: usize> {
+    state: AtomicU8,
+    len: AtomicUsize,
+    #[cfg(not(feature = "forbid-unsafe"))]
+    buffer: UnsafeCell<[u8; N]>,
+    /// Without unsafe code the buffer cannot be shared, bytes are copied in and out instead.
+    #[cfg(feature = "forbid-unsafe")]
+    buffer: [AtomicU8; N],
+}
+
+// SAFETY: The buffer is only accessed by the party which moved the state from `EMPTY` to
+// `WRITING`, or from `READY` to `SENDING`, there is never more than one.
+#[cfg(not(feature = "forbid-unsafe"))]
+unsafe impl<const N: usize> Sync for Staging<N> {}
+
+impl<const N: usize> Staging<N> {
+    /// Creates a new, empty slot.
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            len: AtomicUsize::new(0),
+            #[cfg(not(feature = "forbid-unsafe"))]
+            buffer: UnsafeCell::new([0; N]),
+            #[cfg(feature = "forbid-unsafe")]
+            buffer: [const { AtomicU8::new(0) }; N],
+        }
+    }
+
+    /// Returns a handle, to stage publishes in this slot.
+    pub fn handle(&self) -> Handle<'_, N> {
+        Handle { staging: self }
+    }
+
+    /// Returns `true` if a publish is staged and was not yet sent.
+    pub fn is_pending(&self) -> bool {
+        self.state.load(Ordering::Acquire) != EMPTY
+    }
+
+    /// Takes the staged publish, the slot is freed once the returned guard is dropped.
+    pub(super) fn take(&self) -> Option<Staged<'_, N>> {
+        self.state
+            .compare_exchange(READY, SENDING, Ordering::Acquire, Ordering::Relaxed)
+            .ok()?;
+
+        Some(Staged {
+            staging: self,
+            #[cfg(feature = "forbid-unsafe")]
+            buffer: core::array::from_fn(|i| self.buffer[i].load(Ordering::Relaxed)),
+        })
+    }
+}
+
+impl<const N: usize> Default for Staging<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Debug for Staging<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Staging")
+            .field("pending", &self.is_pending())
+            .field("capacity", &N)
+            .finish()
+    }
+}
+
+/// Stages publishes in a [`Staging`] slot.
+#[derive(Debug, Clone, Copy)]
+pub struct Handle<'a, const N: usize> {
+    staging: &'a Staging<N>,
+}
+
+impl<const N: usize> Handle<'_, N> {
+    /// Stages a QoS 0 publish, without retain and without properties.
+    ///
+    /// Never blocks, never waits and never panics, it is safe to call from interrupt context.
+    /// Fails if the previous publish was not yet sent or the publish does not fit into the slot.
+    pub fn try_publish_qos0(&self, topic: &str, payload: &[u8]) -> Result<(), TryPublishError> {
+        let staging = self.staging;
+        staging
+            .state
+            .compare_exchange(EMPTY, WRITING, Ordering::Acquire, Ordering::Relaxed)
+            .map_err(|_| TryPublishError::Busy)?;
+
+        // SAFETY: The state is `WRITING`, nobody else accesses the buffer.
+        #[cfg(not(feature = "forbid-unsafe"))]
+        let buffer = unsafe { &mut *staging.buffer.get() };
+        #[cfg(feature = "forbid-unsafe")]
+        let buffer = &mut [0; N];
+        match encode_qos0(topic, payload, buffer) {
+            Some(len) => {
+                #[cfg(feature = "forbid-unsafe")]
+                for (slot, &byte) in staging.buffer.iter().zip(&buffer[..len]) {
+                    slot.store(byte, Ordering::Relaxed);
+                }
+                staging.len.store(len, Ordering::Relaxed);
+                staging.state.store(READY, Ordering::Release);
+                Ok(())
+            }
+            None => {
+                staging.state.store(EMPTY, Ordering::Release);
+                Err(TryPublishError::TooLarge)
+            }
+        }
+    }
+}
+
+/// Writes a QoS 0 publish, without retain and without properties, into `buffer`.
+///
+/// Returns the length of the publish, or `None` if it does not fit into `buffer`. Unlike
+/// [`Encoder::encode`], which drives the asynchronous writers of the packet, this is plain
+/// synchronous code, which is checked to never panic by the `no-panic` example.
+fn encode_qos0(topic: &str, payload: &[u8], buffer: &mut [u8]) -> Option<usize> {
+    let topic_len = u16::try_from(topic.len()).ok()?;
+    // Topic, followed by an empty property length and the payload.
+    let remaining = payload.len().checked_add(topic.len() + 3)?;
+    let length = VariableByteInteger::try_from(remaining).ok()?;
+
+    let parts: [&[u8]; 6] = [
+        &[<v5::Publish as Packet>::TYPE << 4],
+        length.as_slice(),
+        &topic_len.to_be_bytes(),
+        topic.as_bytes(),
+        &[0],
+        payload,
+    ];
+
+    let mut len = 0;
+    for part in parts {
+        let end = len + part.len();
+        buffer.get_mut(len..end)?.copy_from_slice(part);
+        len = end;
+    }
+    Some(len)
+}
+
+/// A staged publish, which is being sent. Frees the slot when dropped.
+pub(super) struct Staged<'a, const N: usize> {
+    staging: &'a Staging<N>,
+    /// Copy of the staged publish.
+    #[cfg(feature = "forbid-unsafe")]
+    buffer: [u8; N],
+}
+
+impl<const N: usize> Deref for Staged<'_, N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        let len = self.staging.len.load(Ordering::Relaxed);
+        // SAFETY: The state is `SENDING`, the buffer is not modified until the guard is dropped.
+        #[cfg(not(feature = "forbid-unsafe"))]
+        let buffer = unsafe { &*self.staging.buffer.get() };
+        #[cfg(feature = "forbid-unsafe")]
+        let buffer = &self.buffer;
+        &buffer[..len]
+    }
+}
+
+impl<const N: usize> Drop for Staged<'_, N> {
+    fn drop(&mut self) {
+        // Even a partially sent publish is not sent again, it is QoS 0.
+        self.staging.state.store(EMPTY, Ordering::Release);
+    }
+}
+
+/// Returned when a publish cannot be staged, see [`Handle::try_publish_qos0`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryPublishError {
+    /// The previously staged publish was not yet sent.
+    Busy,
+    /// The publish does not fit into the slot.
+    TooLarge,
+}
+
+impl fmt::Display for TryPublishError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Busy => f.write_str("a publish is already staged"),
+            Self::TooLarge => f.write_str("publish does not fit into the staging slot"),
+        }
+    }
+}
+
+impl core::error::Error for TryPublishError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staging_slot() {
+        let staging = Staging::<16>::new();
+        let handle = staging.handle();
+
+        assert!(staging.take().is_none());
+        assert_eq!(handle.try_publish_qos0("a", b"b"), Ok(()));
+        assert_eq!(
+            handle.try_publish_qos0("a", b"c"),
+            Err(TryPublishError::Busy)
+        );
+        assert!(staging.is_pending());
+
+        let staged = staging.take().unwrap();
+        assert_eq!(&*staged, [0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'b']);
+        // Still sending.
+        assert_eq!(
+            handle.try_publish_qos0("a", b"c"),
+            Err(TryPublishError::Busy)
+        );
+        drop(staged);
+
+        assert!(!staging.is_pending());
+        assert_eq!(handle.try_publish_qos0("a", b"c"), Ok(()));
+    }
+
+    #[test]
+    fn test_encode_qos0() {
+        use crate::protocol::QoS;
+        use crate::protocol::codec::Encoder;
+
+        let payload = [0xab; 200];
+        let packet: v5::Publish<'_, _, &[v5::PublishProperty<'_>]> = v5::Publish {
+            dup: false,
+            qos: QoS::AtMostOnce,
+            retain: false,
+            identifier: None,
+            topic: "a/b",
+            properties: &[],
+            payload: &payload[..],
+        };
+
+        let mut expected = [0; 256];
+        let len = Encoder::new().encode(&packet, &mut expected).unwrap();
+
+        let mut buffer = [0; 256];
+        assert_eq!(encode_qos0("a/b", &payload, &mut buffer), Some(len));
+        assert_eq!(buffer[..len], expected[..len]);
+
+        assert_eq!(encode_qos0("a/b", &payload, &mut buffer[..len - 1]), None);
+    }
+
+    #[test]
+    fn test_staging_too_large() {
+        let staging = Staging::<8>::new();
+
+        assert_eq!(
+            staging.handle().try_publish_qos0("a", b"bcd"),
+            Err(TryPublishError::TooLarge)
+        );
+        // The slot is free again.
+        assert!(!staging.is_pending());
+        assert_eq!(staging.handle().try_publish_qos0("a", b"b"), Ok(()));
+    }
+}
@@ -0,0 +1,279 @@
+use core::fmt::{self, Write as _};
+use core::time::Duration;
+
+use crate::utils::SliceWriter;
+
+#[cfg(doc)]
+use crate::client::{Client, ConnectionBuilder};
+
+/// Monotonic counters of a [`Connection`](crate::Connection).
+///
+/// Counters only ever increase. A client which reconnects with a new connection can continue
+/// counting with [`ConnectionBuilder::with_stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// The amount of successful connects.
+    pub connects: u32,
+    /// The amount of received packets, which could not be parsed or did not fit into the
+    /// receive buffer.
+    pub dropped: u32,
+    /// The highest amount of bytes stored in the receive buffer at once.
+    pub rx_high_watermark: usize,
+    /// The amount of times data in the receive buffer was moved to its start.
+    pub compactions: u32,
+    /// The amount of times moving data in the receive buffer was deferred by the
+    /// [`Compaction`](crate::client::Compaction).
+    pub compactions_skipped: u32,
+    /// The reason codes of acknowledgements received from the server.
+    pub reasons: ReasonCounts,
+}
+
+impl Stats {
+    /// Returns the amount of connects after the first connect.
+    pub fn reconnects(&self) -> u32 {
+        self.connects.saturating_sub(1)
+    }
+
+    pub(super) fn record_connect(&mut self) {
+        self.connects = self.connects.saturating_add(1);
+    }
+
+    pub(super) fn record_dropped(&mut self) {
+        self.dropped = self.dropped.saturating_add(1);
+    }
+
+    pub(super) fn record_buffered(&mut self, size: usize) {
+        self.rx_high_watermark = self.rx_high_watermark.max(size);
+    }
+
+    pub(super) fn record_compaction(&mut self) {
+        self.compactions = self.compactions.saturating_add(1);
+    }
+
+    pub(super) fn record_compaction_skipped(&mut self) {
+        self.compactions_skipped = self.compactions_skipped.saturating_add(1);
+    }
+
+    pub(super) fn record_reason(&mut self, code: u8) {
+        self.reasons.record(code);
+    }
+
+    /// Serializes the stats as JSON into `buffer`, returns the written payload.
+    pub(super) fn write_json<'b>(
+        &self,
+        uptime: Duration,
+        buffer: &'b mut [u8],
+    ) -> Option<&'b [u8]> {
+        let mut w = SliceWriter { buffer, len: 0 };
+        write!(
+            w,
+            r#"{{"uptime":{},"connects":{},"reconnects":{},"dropped":{},"rx_high_watermark":{}}}"#,
+            uptime.as_secs(),
+            self.connects,
+            self.reconnects(),
+            self.dropped,
+            self.rx_high_watermark,
+        )
+        .ok()?;
+
+        let SliceWriter { buffer, len } = w;
+        Some(&buffer[..len])
+    }
+}
+
+/// Reason codes which can be sent in acknowledgements, in ascending order.
+///
+/// Spec: [2.4](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901031)
+const ACK_REASONS: [u8; 32] = [
+    0x00, 0x01, 0x02, 0x10, 0x11, 0x80, 0x81, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a,
+    0x8c, 0x8f, 0x90, 0x91, 0x92, 0x95, 0x97, 0x99, 0x9a, 0x9b, 0x9c, 0x9d, 0x9e, 0x9f, 0xa1, 0xa2,
+];
+
+/// Counters per reason code of the acknowledgements received from the server.
+///
+/// Counts the reasons of CONNACK, PUBACK, PUBREC, PUBCOMP, SUBACK and UNSUBACK packets. Reason codes have the same
+/// meaning in all acknowledgements, e.g. `0x97` is a quota exceeded and `0x87` is not
+/// authorized, which makes throttling and authorization problems of the server visible.
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReasonCounts {
+    counts: [u32; ACK_REASONS.len()],
+}
+
+impl ReasonCounts {
+    /// Returns how often the reason `code` was received.
+    pub fn get(&self, code: u8) -> u32 {
+        match ACK_REASONS.binary_search(&code) {
+            Ok(index) => self.counts[index],
+            Err(_) => 0,
+        }
+    }
+
+    /// Returns how often a failure, a reason code of `0x80` or greater, was received.
+    pub fn failures(&self) -> u32 {
+        self.iter()
+            .filter(|&(code, _)| code >= 0x80)
+            .fold(0, |sum, (_, count)| sum.saturating_add(count))
+    }
+
+    /// Returns the received reason codes and their counts, in ascending order of the codes.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, u32)> + '_ {
+        ACK_REASONS
+            .iter()
+            .zip(self.counts)
+            .filter(|&(_, count)| count > 0)
+            .map(|(&code, count)| (code, count))
+    }
+
+    fn record(&mut self, code: u8) {
+        let index = ACK_REASONS.binary_search(&code).ok();
+        if let Some(count) = index.and_then(|index| self.counts.get_mut(index)) {
+            *count = count.saturating_add(1);
+        }
+    }
+}
+
+impl fmt::Debug for ReasonCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+        for (code, count) in self.iter() {
+            map.key(&format_args!("{code:#04x}")).value(&count);
+        }
+        map.finish()
+    }
+}
+
+/// Periodically publishes the [`Stats`] of a client to a topic, see [`Client::publish_stats`].
+///
+/// The stats are published as a JSON object, together with the uptime supplied by the
+/// application:
+///
+/// ```json
+/// {"uptime":3600,"connects":2,"reconnects":1,"dropped":0,"rx_high_watermark":412}
+/// ```
+///
+/// The topic should be in the namespace of the device, topics reserved for the server, like
+/// `$SYS`, are refused unless [allowed](ConnectionBuilder::allow_reserved_topics).
+///
+/// # Examples:
+///
+/// ```
+/// # use core::time::Duration;
+/// # use miniqtt::client::{ReceiveOr, StatsReport};
+/// # async fn example<C>(
+/// #     client: &mut miniqtt::Client<C, [u8; 128]>,
+/// #     sleep: impl AsyncFn(Duration),
+/// #     uptime: impl Fn() -> Duration,
+/// # ) -> miniqtt::client::Result<(), C::Error>
+/// # where
+/// #     C: embedded_io_async::Read + embedded_io_async::Write,
+/// # {
+/// let report = StatsReport::new("devices/sensor-1/stats", Duration::from_secs(60));
+///
+/// loop {
+///     let due = match client.receive_or(sleep(report.interval())).await? {
+///         ReceiveOr::Message(message) => {
+///             println!("{:?}", message.payload);
+///             false
+///         }
+///         ReceiveOr::Other(()) => true,
+///     };
+///
+///     if due {
+///         client.publish_stats(&report, uptime()).await?;
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct StatsReport<'a> {
+    topic: &'a str,
+    interval: Duration,
+}
+
+impl<'a> StatsReport<'a> {
+    /// Creates a new report published to `topic` every `interval`.
+    pub fn new(topic: &'a str, interval: Duration) -> Self {
+        Self { topic, interval }
+    }
+
+    /// Returns the topic the stats are published to.
+    pub fn topic(&self) -> &'a str {
+        self.topic
+    }
+
+    /// Returns the interval in which the stats are published.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the time until the next report is due, given the time since the last report.
+    pub fn due_in(&self, since_last_report: Duration) -> Duration {
+        self.interval.saturating_sub(since_last_report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_json() {
+        let mut stats = Stats::default();
+        stats.record_connect();
+        stats.record_connect();
+        stats.record_dropped();
+        stats.record_buffered(412);
+        stats.record_buffered(12);
+
+        let mut buffer = [0; 128];
+        let json = stats
+            .write_json(Duration::from_secs(3600), &mut buffer)
+            .unwrap();
+        assert_eq!(
+            json,
+            br#"{"uptime":3600,"connects":2,"reconnects":1,"dropped":1,"rx_high_watermark":412}"#
+        );
+    }
+
+    #[test]
+    fn test_stats_json_buffer_too_small() {
+        let mut buffer = [0; 16];
+        assert_eq!(
+            Stats::default().write_json(Duration::ZERO, &mut buffer),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reason_counts() {
+        let mut stats = Stats::default();
+        stats.record_reason(0x00);
+        stats.record_reason(0x97);
+        stats.record_reason(0x00);
+        stats.record_reason(0x87);
+        // Not a reason of an acknowledgement.
+        stats.record_reason(0x8e);
+
+        let reasons = stats.reasons;
+        assert_eq!(reasons.get(0x00), 2);
+        assert_eq!(reasons.get(0x97), 1);
+        assert_eq!(reasons.get(0x80), 0);
+        assert_eq!(reasons.get(0x8e), 0);
+        assert_eq!(reasons.failures(), 2);
+        assert_eq!(
+            reasons.iter().collect::<Vec<_>>(),
+            [(0x00, 2), (0x87, 1), (0x97, 1)]
+        );
+        assert_eq!(format!("{reasons:?}"), "{0x00: 2, 0x87: 1, 0x97: 1}");
+    }
+
+    #[test]
+    fn test_report_due_in() {
+        let report = StatsReport::new("a", Duration::from_secs(60));
+        assert_eq!(
+            report.due_in(Duration::from_secs(20)),
+            Duration::from_secs(40)
+        );
+        assert_eq!(report.due_in(Duration::from_secs(90)), Duration::ZERO);
+    }
+}
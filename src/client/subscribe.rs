@@ -0,0 +1,110 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::client::MakeFuture;
+use crate::client::utils::LazyMakeFuture;
+#[cfg(doc)]
+use crate::client::{Client, Error};
+use crate::protocol::v5::{RetainHandling, TopicFilter};
+use crate::protocol::{QoS, v5};
+
+pin_project_lite::pin_project! {
+    /// Future returned by [`Client::subscribe`].
+    ///
+    /// # Cancel safety
+    ///
+    /// This future is *not* cancel safe. A subscription which was sent before the future was
+    /// cancelled can be resumed with [`Client::resume_subscribe`].
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Subscribe<'a, T> {
+        options: SubscribeOptions<'a>,
+        #[pin]
+        inner: T,
+    }
+}
+
+/// The options of a [`Subscribe`], passed to the future which sends it.
+#[derive(Debug, Clone, Copy)]
+pub struct SubscribeOptions<'a> {
+    pub(super) filter: TopicFilter<'a>,
+    pub(super) properties: &'a [v5::SubscribeProperty<'a>],
+}
+
+impl<'a, T> Subscribe<'a, T> {
+    /// Configures the maximum quality of service of messages sent by the server.
+    ///
+    /// Defaults to [`QoS::AtMostOnce`]. The server may grant a lower QoS, which is returned on
+    /// success.
+    pub fn qos(mut self, qos: QoS) -> Self {
+        self.options.filter.qos = qos;
+        self
+    }
+
+    /// Whether the server should not forward messages published by this client.
+    ///
+    /// Defaults to `false`.
+    pub fn no_local(mut self, no_local: bool) -> Self {
+        self.options.filter.no_local = no_local;
+        self
+    }
+
+    /// Whether messages keep the retain flag they were published with.
+    ///
+    /// Defaults to `false`, which clears the retain flag of all messages, except retained messages
+    /// sent when the subscription is established.
+    pub fn retain_as_published(mut self, retain_as_published: bool) -> Self {
+        self.options.filter.retain_as_published = retain_as_published;
+        self
+    }
+
+    /// Configures whether retained messages are sent when the subscription is established.
+    ///
+    /// Defaults to [`RetainHandling::SendRetained`].
+    pub fn retain_handling(mut self, retain: RetainHandling) -> Self {
+        self.options.filter.retain = retain;
+        self
+    }
+
+    /// Configures additional properties for the subscription.
+    pub fn with_properties(mut self, properties: &'a [v5::SubscribeProperty<'a>]) -> Self {
+        self.options.properties = properties;
+        self
+    }
+}
+
+impl Subscribe<'_, ()> {
+    pub(super) fn new<'a, M, F, O>(
+        topic: &'a str,
+        m: M,
+    ) -> Subscribe<'a, impl MakeFuture<SubscribeOptions<'a>, Output = O>>
+    where
+        M: FnOnce(SubscribeOptions<'a>) -> F,
+        F: Future<Output = O>,
+    {
+        Subscribe {
+            options: SubscribeOptions {
+                filter: TopicFilter {
+                    name: topic,
+                    qos: QoS::AtMostOnce,
+                    no_local: false,
+                    retain_as_published: false,
+                    retain: RetainHandling::default(),
+                },
+                properties: &[],
+            },
+            inner: LazyMakeFuture::new(m),
+        }
+    }
+}
+
+impl<'a, M> Future for Subscribe<'a, M>
+where
+    M: MakeFuture<SubscribeOptions<'a>>,
+{
+    type Output = M::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        this.inner.poll(this.options, cx)
+    }
+}
@@ -0,0 +1,200 @@
+use core::time::Duration;
+
+#[cfg(doc)]
+use crate::client::{Client, Connect, ConnectResponse};
+use crate::protocol::v5;
+
+/// Reads the time of the broker from a user property, for devices without a real time clock.
+///
+/// MQTT has no standard way to transmit the current time, but brokers and bridges can be
+/// configured to add it as user property to the CONNACK or to publishes on a designated topic.
+/// The value of the property must be the unix time in milliseconds, as decimal string.
+///
+/// The time is read from the CONNACK with [`Connect::with_time_sync`] and from publishes with
+/// [`Self::read_publish`], the [`BrokerTime`] estimates the current time from it.
+///
+/// # Examples:
+///
+/// ```
+/// # use core::time::Duration;
+/// # use miniqtt::client::{BrokerTime, TimeSync};
+/// # async fn example<C>(
+/// #     client: &mut miniqtt::Client<C, [u8; 128]>,
+/// #     monotonic: impl Fn() -> Duration,
+/// # ) -> miniqtt::client::Result<(), C::Error>
+/// # where
+/// #     C: embedded_io_async::Read + embedded_io_async::Write,
+/// # {
+/// let time_sync = TimeSync::new("time");
+///
+/// let response = client
+///     .connect("sensor-1")
+///     .with_properties(&[time_sync.request()])
+///     .with_time_sync(time_sync)
+///     .await?;
+///
+/// if let Some(broker) = response.broker_time() {
+///     let time = BrokerTime::new(broker, monotonic());
+///     // Later, when timestamping telemetry:
+///     let timestamp = time.now(monotonic());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSync<'a> {
+    key: &'a str,
+}
+
+impl<'a> TimeSync<'a> {
+    /// Creates a new time sync, which reads the time from the user property `key`.
+    pub const fn new(key: &'a str) -> Self {
+        Self { key }
+    }
+
+    /// Returns the key of the user property.
+    pub fn key(&self) -> &'a str {
+        self.key
+    }
+
+    /// Returns a user property with the configured key and an empty value, for brokers which only
+    /// include the time when it is requested in the CONNECT.
+    pub fn request(&self) -> v5::ConnectProperty<'a> {
+        v5::ConnectProperty::UserProperty {
+            key: self.key,
+            value: "",
+        }
+    }
+
+    /// Returns the time in the properties of a CONNACK, in milliseconds since the unix epoch.
+    pub fn read_connack(&self, properties: &v5::ConnAckProperties<'_>) -> Option<u64> {
+        parse_millis(properties.user_property(self.key)?)
+    }
+
+    /// Returns the time in the properties of a publish, in milliseconds since the unix epoch.
+    pub fn read_publish(&self, publish: &v5::Publish<'_>) -> Option<u64> {
+        publish
+            .properties
+            .into_iter()
+            .find_map(|property| match property {
+                v5::PublishProperty::UserProperty { key, value } if key == self.key => {
+                    parse_millis(value)
+                }
+                _ => None,
+            })
+    }
+}
+
+/// An estimate of the time of the broker, based on a monotonic clock of the device.
+///
+/// The estimate is the time reported by the broker, advanced by the time which passed locally.
+/// The transmission delay is not accounted for unless it is known, see
+/// [`Self::with_round_trip`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BrokerTime {
+    /// Difference between the broker time and the local clock in milliseconds.
+    offset: i64,
+}
+
+impl BrokerTime {
+    /// Creates a new estimate from the broker time in milliseconds since the unix epoch and the
+    /// time of the local monotonic clock when it was received.
+    pub fn new(broker: u64, local: Duration) -> Self {
+        Self {
+            offset: i64::try_from(broker)
+                .unwrap_or(i64::MAX)
+                .saturating_sub(millis(local)),
+        }
+    }
+
+    /// Accounts for the transmission delay of the broker time, assuming it took half of the
+    /// round trip time, e.g. measured from sending the CONNECT to receiving the CONNACK.
+    pub fn with_round_trip(mut self, round_trip: Duration) -> Self {
+        self.offset = self.offset.saturating_add(millis(round_trip) / 2);
+        self
+    }
+
+    /// Returns the difference between the broker time and the local clock in milliseconds.
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// Returns the estimated broker time in milliseconds since the unix epoch, given the current
+    /// time of the local monotonic clock.
+    pub fn now(&self, local: Duration) -> u64 {
+        millis(local).saturating_add(self.offset).max(0) as u64
+    }
+}
+
+fn millis(duration: Duration) -> i64 {
+    i64::try_from(duration.as_millis()).unwrap_or(i64::MAX)
+}
+
+fn parse_millis(value: &str) -> Option<u64> {
+    // `u64::from_str` accepts a leading `+`.
+    if !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "properties")]
+    #[test]
+    fn test_read_connack() {
+        // A CONNACK with the user properties `time=1700000000123` and `other=1`.
+        let mut data = vec![0x20, 0x00, 0x00, 0x00, 0x00];
+        for (key, value) in [("other", "1"), ("time", "1700000000123")] {
+            data.push(0x26);
+            for s in [key, value] {
+                data.extend_from_slice(&(s.len() as u16).to_be_bytes());
+                data.extend_from_slice(s.as_bytes());
+            }
+        }
+        data[4] = (data.len() - 5) as u8;
+        data[1] = (data.len() - 2) as u8;
+
+        let (_, ack) = crate::protocol::codec::Decoder::new()
+            .decode::<v5::ConnAck>(&data)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            TimeSync::new("time").read_connack(&ack.properties),
+            Some(1_700_000_000_123)
+        );
+        assert_eq!(
+            TimeSync::new("other").read_connack(&ack.properties),
+            Some(1)
+        );
+        assert_eq!(TimeSync::new("missing").read_connack(&ack.properties), None);
+    }
+
+    #[test]
+    fn test_parse_millis() {
+        assert_eq!(parse_millis("1700000000123"), Some(1_700_000_000_123));
+        assert_eq!(parse_millis(""), None);
+        assert_eq!(parse_millis("+1"), None);
+        assert_eq!(parse_millis("-1"), None);
+        assert_eq!(parse_millis("1.5"), None);
+        assert_eq!(parse_millis("99999999999999999999"), None);
+    }
+
+    #[test]
+    fn test_broker_time() {
+        let time = BrokerTime::new(1_700_000_000_000, Duration::from_secs(10));
+        assert_eq!(time.offset(), 1_699_999_990_000);
+        assert_eq!(time.now(Duration::from_secs(10)), 1_700_000_000_000);
+        assert_eq!(time.now(Duration::from_secs(70)), 1_700_000_060_000);
+
+        let time = time.with_round_trip(Duration::from_millis(300));
+        assert_eq!(time.now(Duration::from_secs(10)), 1_700_000_000_150);
+
+        // The broker clock is behind the local clock.
+        let time = BrokerTime::new(1_000, Duration::from_secs(10));
+        assert_eq!(time.offset(), -9_000);
+        assert_eq!(time.now(Duration::from_secs(5)), 0);
+    }
+}
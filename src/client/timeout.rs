@@ -0,0 +1,61 @@
+#[cfg(doc)]
+use crate::client::Client;
+#[cfg(feature = "embedded-hal-async-1")]
+use crate::client::utils::{Either, select};
+
+/// Limits how long the [`Client`] waits for responses from the server.
+pub trait Timeout {
+    /// Runs `future` to completion, returns `None` if it did not complete in time.
+    fn timeout<F>(&mut self, future: F) -> impl Future<Output = Option<F::Output>>
+    where
+        F: Future;
+}
+
+/// A [`Timeout`] which never elapses.
+///
+/// This is the default for a [`Client`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoTimeout;
+
+impl Timeout for NoTimeout {
+    async fn timeout<F>(&mut self, future: F) -> Option<F::Output>
+    where
+        F: Future,
+    {
+        Some(future.await)
+    }
+}
+
+/// A [`Timeout`] implemented with an [`embedded_hal_async::delay::DelayNs`].
+///
+/// This keeps timeouts independent of the used executor.
+#[cfg(feature = "embedded-hal-async-1")]
+#[derive(Debug)]
+pub struct DelayTimeout<D> {
+    delay: D,
+    timeout_ms: u32,
+}
+
+#[cfg(feature = "embedded-hal-async-1")]
+impl<D> DelayTimeout<D> {
+    /// Creates a new [`DelayTimeout`], elapsing after `timeout_ms` milliseconds.
+    pub fn new(delay: D, timeout_ms: u32) -> Self {
+        Self { delay, timeout_ms }
+    }
+}
+
+#[cfg(feature = "embedded-hal-async-1")]
+impl<D> Timeout for DelayTimeout<D>
+where
+    D: embedded_hal_async::delay::DelayNs,
+{
+    async fn timeout<F>(&mut self, future: F) -> Option<F::Output>
+    where
+        F: Future,
+    {
+        match select(future, self.delay.delay_ms(self.timeout_ms)).await {
+            Either::Left(output) => Some(output),
+            Either::Right(()) => None,
+        }
+    }
+}
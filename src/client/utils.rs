@@ -1,11 +1,27 @@
+use std::future::poll_fn;
 use std::marker::PhantomData;
-use std::pin::Pin;
+use std::pin::{Pin, pin};
 use std::task::{Context, Poll};
 
 pub trait MakeFuture<S> {
     type Output;
 
-    fn poll(self: Pin<&mut Self>, state: &S, cx: &mut Context<'_>) -> Poll<Self::Output>;
+    fn poll(self: Pin<&mut Self>, state: &mut S, cx: &mut Context<'_>) -> Poll<Self::Output>;
+}
+
+/// Options of a builder, which are moved into the future once it is created.
+pub trait TakeOptions {
+    /// Returns the options, leaving behind what cannot be used again.
+    fn take(&mut self) -> Self;
+}
+
+impl<T> TakeOptions for T
+where
+    T: Copy,
+{
+    fn take(&mut self) -> Self {
+        *self
+    }
 }
 
 pin_project_lite::pin_project! {
@@ -29,17 +45,17 @@ impl<M, S, F, O> MakeFuture<S> for LazyMakeFuture<M, F, S>
 where
     M: FnOnce(S) -> F,
     F: Future<Output = O>,
-    S: Copy,
+    S: TakeOptions,
 {
     type Output = O;
 
-    fn poll(mut self: Pin<&mut Self>, state: &S, cx: &mut Context<'_>) -> Poll<F::Output> {
+    fn poll(mut self: Pin<&mut Self>, state: &mut S, cx: &mut Context<'_>) -> Poll<F::Output> {
         loop {
             match self.as_mut().project() {
                 LazyMakeFutureProj::Make { make, .. } => {
                     // This will only panic if `make` panic'd before
                     let make = make.take().unwrap();
-                    let future = (make)(*state);
+                    let future = (make)(state.take());
                     self.set(LazyMakeFuture::Future { future });
                 }
                 LazyMakeFutureProj::Future { future } => break future.poll(cx),
@@ -47,3 +63,32 @@ where
         }
     }
 }
+
+/// Output of [`select`].
+pub(super) enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+/// Polls both futures concurrently, returns the output of the first future to complete.
+///
+/// If both futures are ready, `left` wins. The other future is dropped.
+pub(super) async fn select<A, B>(left: A, right: B) -> Either<A::Output, B::Output>
+where
+    A: Future,
+    B: Future,
+{
+    let mut left = pin!(left);
+    let mut right = pin!(right);
+
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = left.as_mut().poll(cx) {
+            return Poll::Ready(Either::Left(output));
+        }
+        if let Poll::Ready(output) = right.as_mut().poll(cx) {
+            return Poll::Ready(Either::Right(output));
+        }
+        Poll::Pending
+    })
+    .await
+}
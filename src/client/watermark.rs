@@ -0,0 +1,44 @@
+#[cfg(doc)]
+use crate::client::{Connection, ConnectionBuilder};
+
+/// Reported when the receive buffer of a [`Connection`] fills past a configured threshold.
+///
+/// See [`ConnectionBuilder::with_watermarks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watermark {
+    /// The threshold in percent of the buffer capacity which was passed.
+    pub threshold: u8,
+    /// The amount of bytes currently stored in the buffer.
+    pub used: usize,
+    /// The total capacity of the buffer.
+    pub capacity: usize,
+}
+
+/// Fill level thresholds of a receive buffer and the callback invoked when they are passed.
+pub(super) struct Watermarks {
+    pub thresholds: &'static [u8],
+    pub callback: fn(Watermark),
+}
+
+impl Watermarks {
+    /// Invokes the callback for every threshold passed while the buffer grew
+    /// from `before` to `after` bytes.
+    pub fn check(&self, before: usize, after: usize, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+
+        let percent = |used: usize| used.saturating_mul(100) / capacity;
+        let (from, to) = (percent(before), percent(after));
+
+        for &threshold in self.thresholds {
+            if (from + 1..=to).contains(&usize::from(threshold)) {
+                (self.callback)(Watermark {
+                    threshold,
+                    used: after,
+                    capacity,
+                });
+            }
+        }
+    }
+}
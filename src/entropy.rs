@@ -0,0 +1,39 @@
+//! Injectable sources of randomness.
+//!
+//! All randomness used by the client is provided through an [`EntropySource`], this allows
+//! embedded users to use a hardware RNG and keeps tests deterministic.
+
+/// A source of random numbers.
+///
+/// The random numbers are not used for cryptographic purposes.
+pub trait EntropySource {
+    /// Returns the next random number.
+    fn next_u32(&mut self) -> u32;
+}
+
+impl<T> EntropySource for &mut T
+where
+    T: EntropySource + ?Sized,
+{
+    fn next_u32(&mut self) -> u32 {
+        T::next_u32(self)
+    }
+}
+
+/// An [`EntropySource`] seeded from the randomly seeded hasher of the standard library.
+#[derive(Debug, Default)]
+pub struct StdEntropy {
+    counter: u64,
+}
+
+impl EntropySource for StdEntropy {
+    fn next_u32(&mut self) -> u32 {
+        use std::hash::{BuildHasher, Hasher, RandomState};
+
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(self.counter);
+        self.counter = self.counter.wrapping_add(1);
+
+        hasher.finish() as u32
+    }
+}
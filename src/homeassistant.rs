@@ -0,0 +1,386 @@
+//! Home Assistant MQTT discovery.
+//!
+//! Home Assistant discovers entities from retained config messages published to
+//! `<discovery_prefix>/<component>/<object_id>/config`. An [`Entity`] formats its config
+//! message and maps the state and command topics of the entity onto publishes and
+//! subscriptions of the client.
+//!
+//! Configs are written into a caller supplied buffer, without allocating.
+//!
+//! Requires the `homeassistant` feature.
+//!
+//! Spec: [MQTT Discovery](https://www.home-assistant.io/integrations/mqtt/#mqtt-discovery)
+//!
+//! # Examples:
+//!
+//! ```
+//! # use miniqtt::homeassistant::{Component, Entity};
+//! # async fn example<C>(client: &mut miniqtt::Client<C, Vec<u8>>) -> miniqtt::client::Result<(), C::Error>
+//! # where
+//! #     C: embedded_io_async::Read + embedded_io_async::Write,
+//! # {
+//! let relay = Entity::new(Component::Switch, "desk-relay", "Desk Relay")
+//!     .with_state_topic("desk/relay/state")
+//!     .with_command_topic("desk/relay/set");
+//!
+//! let mut buffer = [0; 256];
+//! relay.announce(client, &mut buffer).await?;
+//! relay.subscribe_commands(client).await?;
+//!
+//! let message = client.receive().await?;
+//! if let Some(on) = relay.command_on_off(&message) {
+//!     drop(message);
+//!     relay.publish_on_off(client, on).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use core::fmt::{self, Write as _};
+
+use crate::client::{Client, Error, Result, Timeout};
+use crate::protocol::{ValidationError, v5};
+use crate::traits::Buffer;
+use crate::utils::SliceWriter;
+
+/// The default prefix of discovery topics.
+pub const DISCOVERY_PREFIX: &str = "homeassistant";
+
+/// The type of an [`Entity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Component {
+    BinarySensor,
+    Button,
+    Light,
+    Number,
+    Sensor,
+    Switch,
+}
+
+impl Component {
+    /// Returns the component as it appears in the discovery topic.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BinarySensor => "binary_sensor",
+            Self::Button => "button",
+            Self::Light => "light",
+            Self::Number => "number",
+            Self::Sensor => "sensor",
+            Self::Switch => "switch",
+        }
+    }
+}
+
+/// An entity announced to Home Assistant, see the [module documentation](self).
+#[derive(Debug, Clone, Copy)]
+pub struct Entity<'a> {
+    discovery_prefix: &'a str,
+    component: Component,
+    object_id: &'a str,
+    name: &'a str,
+    state_topic: Option<&'a str>,
+    command_topic: Option<&'a str>,
+    availability_topic: Option<&'a str>,
+    device_class: Option<&'a str>,
+    unit_of_measurement: Option<&'a str>,
+}
+
+impl<'a> Entity<'a> {
+    /// Creates a new entity.
+    ///
+    /// The `object_id` is also used as the unique id of the entity, it must be unique across
+    /// all entities of the component.
+    pub fn new(component: Component, object_id: &'a str, name: &'a str) -> Self {
+        Self {
+            discovery_prefix: DISCOVERY_PREFIX,
+            component,
+            object_id,
+            name,
+            state_topic: None,
+            command_topic: None,
+            availability_topic: None,
+            device_class: None,
+            unit_of_measurement: None,
+        }
+    }
+
+    /// Sets the prefix of the discovery topic.
+    ///
+    /// Defaults to [`DISCOVERY_PREFIX`].
+    pub fn with_discovery_prefix(mut self, discovery_prefix: &'a str) -> Self {
+        self.discovery_prefix = discovery_prefix;
+        self
+    }
+
+    /// Sets the topic the entity publishes its state on.
+    pub fn with_state_topic(mut self, state_topic: &'a str) -> Self {
+        self.state_topic = Some(state_topic);
+        self
+    }
+
+    /// Sets the topic the entity receives commands on.
+    pub fn with_command_topic(mut self, command_topic: &'a str) -> Self {
+        self.command_topic = Some(command_topic);
+        self
+    }
+
+    /// Sets the topic the availability of the entity is announced on.
+    ///
+    /// Home Assistant expects the payloads `online` and `offline`, the defaults of a
+    /// [`Presence`](crate::client::Presence).
+    pub fn with_availability_topic(mut self, availability_topic: &'a str) -> Self {
+        self.availability_topic = Some(availability_topic);
+        self
+    }
+
+    /// Sets the device class, e.g. `temperature`.
+    pub fn with_device_class(mut self, device_class: &'a str) -> Self {
+        self.device_class = Some(device_class);
+        self
+    }
+
+    /// Sets the unit of measurement of the state, e.g. `°C`.
+    pub fn with_unit_of_measurement(mut self, unit_of_measurement: &'a str) -> Self {
+        self.unit_of_measurement = Some(unit_of_measurement);
+        self
+    }
+
+    /// Writes the discovery topic into `buffer`.
+    ///
+    /// Returns `None` if the buffer is too small.
+    pub fn config_topic<'b>(&self, buffer: &'b mut [u8]) -> Option<&'b str> {
+        let mut w = SliceWriter { buffer, len: 0 };
+        write!(
+            w,
+            "{}/{}/{}/config",
+            self.discovery_prefix,
+            self.component.as_str(),
+            self.object_id
+        )
+        .ok()?;
+
+        let SliceWriter { buffer, len } = w;
+        core::str::from_utf8(&buffer[..len]).ok()
+    }
+
+    /// Writes the JSON discovery config into `buffer`.
+    ///
+    /// Returns `None` if the buffer is too small.
+    pub fn config<'b>(&self, buffer: &'b mut [u8]) -> Option<&'b [u8]> {
+        let mut w = SliceWriter { buffer, len: 0 };
+        self.write_config(&mut w).ok()?;
+
+        let SliceWriter { buffer, len } = w;
+        Some(&buffer[..len])
+    }
+
+    fn write_config(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        let fields = [
+            ("name", Some(self.name)),
+            ("unique_id", Some(self.object_id)),
+            ("state_topic", self.state_topic),
+            ("command_topic", self.command_topic),
+            ("availability_topic", self.availability_topic),
+            ("device_class", self.device_class),
+            ("unit_of_measurement", self.unit_of_measurement),
+        ];
+
+        w.write_char('{')?;
+        for (i, (key, value)) in fields
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, value?)))
+            .enumerate()
+        {
+            if i > 0 {
+                w.write_char(',')?;
+            }
+            write!(w, "\"{key}\":")?;
+            write_json_str(w, value)?;
+        }
+        w.write_char('}')
+    }
+
+    /// Publishes the retained discovery config, which announces the entity.
+    ///
+    /// The topic and config are written into `buffer`, fails with
+    /// [`Error::PacketTooLarge`] if the buffer is too small.
+    pub async fn announce<C, B, T>(
+        &self,
+        client: &mut Client<C, B, T>,
+        buffer: &mut [u8],
+    ) -> Result<(), C::Error>
+    where
+        C: embedded_io_async::Read,
+        C: embedded_io_async::Write,
+        B: Buffer,
+        T: Timeout,
+    {
+        let len = self
+            .config_topic(buffer)
+            .ok_or(Error::PacketTooLarge)?
+            .len();
+        let (topic, buffer) = buffer.split_at_mut(len);
+        // The topic was just written from valid strings.
+        let topic = core::str::from_utf8(topic).unwrap_or_default();
+        let config = self.config(buffer).ok_or(Error::PacketTooLarge)?;
+
+        client.publish(topic, config).retain(true).await?;
+        Ok(())
+    }
+
+    /// Publishes an empty retained discovery config, which removes the entity.
+    pub async fn remove<C, B, T>(
+        &self,
+        client: &mut Client<C, B, T>,
+        buffer: &mut [u8],
+    ) -> Result<(), C::Error>
+    where
+        C: embedded_io_async::Read,
+        C: embedded_io_async::Write,
+        B: Buffer,
+        T: Timeout,
+    {
+        let topic = self.config_topic(buffer).ok_or(Error::PacketTooLarge)?;
+        client.publish(topic, &[]).retain(true).await?;
+        Ok(())
+    }
+
+    /// Publishes the retained state of the entity.
+    ///
+    /// Fails with [`Error::Invalid`] if no state topic is configured.
+    pub async fn publish_state<C, B, T>(
+        &self,
+        client: &mut Client<C, B, T>,
+        state: &[u8],
+    ) -> Result<(), C::Error>
+    where
+        C: embedded_io_async::Read,
+        C: embedded_io_async::Write,
+        B: Buffer,
+        T: Timeout,
+    {
+        let topic = self
+            .state_topic
+            .ok_or(Error::Invalid(ValidationError::Empty))?;
+        client.publish(topic, state).retain(true).await?;
+        Ok(())
+    }
+
+    /// Publishes the state `ON` or `OFF`, as used by switches, lights and binary sensors.
+    pub async fn publish_on_off<C, B, T>(
+        &self,
+        client: &mut Client<C, B, T>,
+        on: bool,
+    ) -> Result<(), C::Error>
+    where
+        C: embedded_io_async::Read,
+        C: embedded_io_async::Write,
+        B: Buffer,
+        T: Timeout,
+    {
+        let state: &[u8] = match on {
+            true => b"ON",
+            false => b"OFF",
+        };
+        self.publish_state(client, state).await
+    }
+
+    /// Subscribes to the command topic of the entity.
+    ///
+    /// Fails with [`Error::Invalid`] if no command topic is configured.
+    pub async fn subscribe_commands<C, B, T>(
+        &self,
+        client: &mut Client<C, B, T>,
+    ) -> Result<(), C::Error>
+    where
+        C: embedded_io_async::Read,
+        C: embedded_io_async::Write,
+        B: Buffer,
+        T: Timeout,
+    {
+        let topic = self
+            .command_topic
+            .ok_or(Error::Invalid(ValidationError::Empty))?;
+        client.subscribe(topic).await?;
+        Ok(())
+    }
+
+    /// Returns the command, if `message` was received on the command topic.
+    pub fn command<'m>(&self, message: &v5::Publish<'m>) -> Option<&'m [u8]> {
+        (Some(message.topic) == self.command_topic).then_some(message.payload)
+    }
+
+    /// Returns the command `ON` or `OFF`, if `message` was received on the command topic.
+    pub fn command_on_off(&self, message: &v5::Publish<'_>) -> Option<bool> {
+        match self.command(message)? {
+            b"ON" => Some(true),
+            b"OFF" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Writes `s` as a JSON string, escaping quotes, backslashes and control characters.
+fn write_json_str(w: &mut impl fmt::Write, s: &str) -> fmt::Result {
+    w.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => w.write_str("\\\"")?,
+            '\\' => w.write_str("\\\\")?,
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => w.write_char(c)?,
+        }
+    }
+    w.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        let entity = Entity::new(Component::Sensor, "t1", "Temperature")
+            .with_state_topic("t1/state")
+            .with_device_class("temperature")
+            .with_unit_of_measurement("°C");
+
+        let mut buffer = [0; 64];
+        assert_eq!(
+            entity.config_topic(&mut buffer),
+            Some("homeassistant/sensor/t1/config")
+        );
+
+        let mut buffer = [0; 256];
+        let config = core::str::from_utf8(entity.config(&mut buffer).unwrap()).unwrap();
+        assert_eq!(
+            config,
+            r#"{"name":"Temperature","unique_id":"t1","state_topic":"t1/state","device_class":"temperature","unit_of_measurement":"°C"}"#
+        );
+    }
+
+    #[test]
+    fn test_config_escaped() {
+        let entity =
+            Entity::new(Component::Button, "b", "Say \"hi\"\\\n").with_discovery_prefix("ha");
+
+        let mut buffer = [0; 32];
+        assert_eq!(entity.config_topic(&mut buffer), Some("ha/button/b/config"));
+
+        let mut buffer = [0; 64];
+        assert_eq!(
+            entity.config(&mut buffer),
+            Some(&br#"{"name":"Say \"hi\"\\\u000a","unique_id":"b"}"#[..])
+        );
+    }
+
+    #[test]
+    fn test_config_too_small() {
+        let entity = Entity::new(Component::Switch, "s", "S");
+
+        let mut buffer = [0; 27];
+        assert!(entity.config(&mut buffer).is_none());
+        let mut buffer = [0; 28];
+        assert!(entity.config(&mut buffer).is_some());
+    }
+}
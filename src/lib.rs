@@ -1,7 +1,20 @@
+#![cfg_attr(feature = "forbid-unsafe", forbid(unsafe_code))]
+
 pub mod client;
+pub mod entropy;
+#[cfg(feature = "homeassistant")]
+pub mod homeassistant;
 mod log;
+#[cfg(feature = "payload-codecs")]
+pub mod payload;
 pub mod protocol;
+#[cfg(feature = "shadow")]
+pub mod shadow;
+#[cfg(feature = "sparkplug")]
+pub mod sparkplug;
+pub mod topic;
 mod traits;
+pub mod transport;
 mod utils;
 
 pub use self::client::{Client, Connection};
@@ -1,5 +1,7 @@
 #![allow(unused)]
 
+use core::fmt;
+
 macro_rules! trace {
     ($s:literal $(, $x:expr)* $(,)?) => {
         {
@@ -59,3 +61,47 @@ macro_rules! error {
     };
 }
 pub(super) use error;
+
+/// Placeholder for secrets, like passwords, in debug output.
+pub(crate) struct Redacted;
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+/// Placeholder for payloads omitted from debug output, only showing their size.
+pub(crate) struct RedactedBytes(pub usize);
+
+impl fmt::Debug for RedactedBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} bytes>", self.0)
+    }
+}
+
+/// A packet which is logged when sent or received.
+pub(crate) trait LogPacket: fmt::Debug {
+    /// Formats the packet like [`fmt::Debug`], but omits payloads.
+    fn fmt_without_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Formats a [`LogPacket`], optionally omitting payloads.
+pub(crate) struct Logged<'a, T> {
+    pub packet: &'a T,
+    pub redact_payload: bool,
+}
+
+impl<T> fmt::Debug for Logged<'_, T>
+where
+    T: LogPacket,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.redact_payload {
+            true => self.packet.fmt_without_payload(f),
+            false => fmt::Debug::fmt(self.packet, f),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Comma separated values.
+
+/// Returns an iterator over the comma separated fields of a single line.
+///
+/// Whitespace around fields is trimmed. A field enclosed in double quotes may contain commas,
+/// the quotes are removed, escaped quotes (`""`) are returned as is.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::payload::csv;
+/// let fields: Vec<_> = csv::fields(r#"set, 21.5, "living room, north""#).collect();
+/// assert_eq!(fields, ["set", "21.5", "living room, north"]);
+/// ```
+pub fn fields(line: &str) -> Fields<'_> {
+    Fields {
+        remaining: Some(line),
+    }
+}
+
+/// Iterator over comma separated fields, see [`fields`].
+#[derive(Debug, Clone)]
+pub struct Fields<'a> {
+    remaining: Option<&'a str>,
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = self.remaining?.trim_start();
+
+        let end = match remaining.strip_prefix('"') {
+            Some(quoted) => quoted_end(quoted).map(|end| end + 1),
+            None => remaining.find(','),
+        };
+
+        let (field, rest) = match end {
+            Some(end) => (
+                &remaining[..end],
+                remaining[end..].split_once(',').map(|r| r.1),
+            ),
+            None => (remaining, None),
+        };
+        self.remaining = rest;
+
+        let field = field.trim();
+        Some(
+            field
+                .strip_prefix('"')
+                .and_then(|f| f.strip_suffix('"'))
+                .unwrap_or(field),
+        )
+    }
+}
+
+/// Returns the position of the closing quote, skipping escaped quotes.
+fn quoted_end(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '"' && chars.next_if(|&(_, c)| c == '"').is_none() {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_fields() {
+        assert_eq!(fields("").collect::<Vec<_>>(), [""]);
+        assert_eq!(fields("a,,b").collect::<Vec<_>>(), ["a", "", "b"]);
+        assert_eq!(fields(" a , b ,").collect::<Vec<_>>(), ["a", "b", ""]);
+        assert_eq!(
+            fields(r#""a, b" , "c ""d""", e"#).collect::<Vec<_>>(),
+            ["a, b", r#"c ""d"""#, "e"]
+        );
+        // Unterminated quotes extend to the end.
+        assert_eq!(fields(r#"a, "b, c"#).collect::<Vec<_>>(), ["a", r#""b, c"#]);
+    }
+}
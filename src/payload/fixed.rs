@@ -0,0 +1,90 @@
+//! Fixed-point decimal numbers, like `21.5` or `-0.125`.
+
+use core::fmt;
+
+/// Parses a decimal number into a fixed-point integer with `DECIMALS` decimal places.
+///
+/// The number is scaled by `10^DECIMALS`, additional decimal places are truncated.
+/// This avoids floating point parsing, which is expensive on targets without an FPU.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::payload::fixed;
+/// assert_eq!(fixed::parse::<2>("21.5"), Ok(2150));
+/// assert_eq!(fixed::parse::<2>("-0.125"), Ok(-12));
+/// assert_eq!(fixed::parse::<0>("+7"), Ok(7));
+/// assert!(fixed::parse::<2>("21,5").is_err());
+/// ```
+pub fn parse<const DECIMALS: u32>(s: &str) -> Result<i64, InvalidNumber> {
+    let s = s.trim();
+    let (negative, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+
+    let (integer, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(InvalidNumber);
+    }
+
+    let digits = integer.bytes().chain(
+        fraction
+            .bytes()
+            .chain(core::iter::repeat(b'0'))
+            .take(DECIMALS as usize),
+    );
+
+    let mut result: i64 = 0;
+    for digit in digits {
+        if !digit.is_ascii_digit() {
+            return Err(InvalidNumber);
+        }
+        result = result
+            .checked_mul(10)
+            .and_then(|r| r.checked_add(i64::from(digit - b'0')))
+            .ok_or(InvalidNumber)?;
+    }
+
+    // Truncated decimal places must still be digits.
+    if !fraction.bytes().all(|d| d.is_ascii_digit()) {
+        return Err(InvalidNumber);
+    }
+
+    Ok(if negative { -result } else { result })
+}
+
+/// Returned by [`parse`] if the input is not a valid decimal number, or it does not fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidNumber;
+
+impl fmt::Display for InvalidNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid decimal number")
+    }
+}
+
+impl core::error::Error for InvalidNumber {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_parse() {
+        assert_eq!(parse::<3>("1"), Ok(1000));
+        assert_eq!(parse::<3>(" 1.5 "), Ok(1500));
+        assert_eq!(parse::<3>(".25"), Ok(250));
+        assert_eq!(parse::<3>("2."), Ok(2000));
+        assert_eq!(parse::<3>("-1.23456"), Ok(-1234));
+        assert_eq!(parse::<0>("9223372036854775807"), Ok(i64::MAX));
+
+        assert_eq!(parse::<3>(""), Err(InvalidNumber));
+        assert_eq!(parse::<3>("-"), Err(InvalidNumber));
+        assert_eq!(parse::<3>("."), Err(InvalidNumber));
+        assert_eq!(parse::<3>("1.2.3"), Err(InvalidNumber));
+        assert_eq!(parse::<3>("1.23x5"), Err(InvalidNumber));
+        assert_eq!(parse::<1>("9223372036854775807"), Err(InvalidNumber));
+    }
+}
@@ -0,0 +1,73 @@
+//! Key value pairs, like `mode=auto;target=21.5`.
+
+/// Returns an iterator over the key value pairs of a payload.
+///
+/// Pairs are separated by `;`, `&`, `,` or line breaks, keys and values are separated by `=` or
+/// `:`. Whitespace around keys and values is trimmed, empty pairs are skipped. A pair without
+/// a separator yields an empty value.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::payload::key_value;
+/// let pairs: Vec<_> = key_value::pairs("mode=auto; target: 21.5\nboost").collect();
+/// assert_eq!(pairs, [("mode", "auto"), ("target", "21.5"), ("boost", "")]);
+/// ```
+pub fn pairs(payload: &str) -> Pairs<'_> {
+    Pairs {
+        inner: payload.split([';', '&', ',', '\n']),
+    }
+}
+
+/// Returns the value of the first pair with `key`.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::payload::key_value;
+/// assert_eq!(key_value::get("mode=auto;target=21.5", "target"), Some("21.5"));
+/// assert_eq!(key_value::get("mode=auto;target=21.5", "boost"), None);
+/// ```
+pub fn get<'a>(payload: &'a str, key: &str) -> Option<&'a str> {
+    pairs(payload).find(|&(k, _)| k == key).map(|(_, v)| v)
+}
+
+/// Iterator over key value pairs, see [`pairs`].
+#[derive(Debug, Clone)]
+pub struct Pairs<'a> {
+    inner: core::str::Split<'a, [char; 4]>,
+}
+
+impl<'a> Iterator for Pairs<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let pair = self.inner.next()?.trim();
+            if pair.is_empty() {
+                continue;
+            }
+
+            let (key, value) = pair.split_once(['=', ':']).unwrap_or((pair, ""));
+            return Some((key.trim_end(), value.trim_start()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_value_pairs() {
+        assert_eq!(pairs("").count(), 0);
+        assert_eq!(
+            pairs("a=1&b = 2;;c:3,\r\nd").collect::<Vec<_>>(),
+            [("a", "1"), ("b", "2"), ("c", "3"), ("d", "")]
+        );
+        assert_eq!(
+            pairs("url=http://x").collect::<Vec<_>>(),
+            [("url", "http://x")]
+        );
+    }
+}
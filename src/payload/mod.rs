@@ -0,0 +1,10 @@
+//! Tiny decoders for common payload formats.
+//!
+//! These cover simple command and sensor formats, without pulling in a heavier parsing crate.
+//! All decoders work on borrowed data and do not allocate.
+//!
+//! Requires the `payload-codecs` feature.
+
+pub mod csv;
+pub mod fixed;
+pub mod key_value;
@@ -0,0 +1,330 @@
+//! Framing of packets, independent of a connection.
+//!
+//! The [`Encoder`] and [`Decoder`] operate on plain buffers and sinks, they can be used to embed
+//! MQTT packets in other carriers, e.g. a serial link or a message queue. The
+//! [`Connection`](crate::Connection) of the client uses them to frame packets sent and received
+//! over the network.
+
+use core::fmt;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+
+use crate::protocol::types::{FixedHeader, VariableByteInteger};
+use crate::protocol::{Packet, PacketError, PacketParse, Parse, ParseError, WriteError};
+use crate::traits::Writable;
+
+/// Writes complete packets, including their fixed header.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::protocol::codec::Encoder;
+/// # use miniqtt::protocol::v5;
+/// let ping = Encoder::new().encode(&v5::PingReq, &mut [0; 8]).unwrap();
+/// assert_eq!(ping, 2);
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Encoder {
+    _private: (),
+}
+
+impl Encoder {
+    /// Creates a new encoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the size of the complete packet, including the fixed header.
+    pub fn frame_size<T>(&self, packet: &T) -> usize
+    where
+        T: Packet + Writable,
+    {
+        let size = packet.size();
+        VariableByteInteger::try_from(size).ok().size() + 1 + size
+    }
+
+    /// Writes the complete packet to `sink`.
+    pub async fn write<T, S>(&self, packet: &T, mut sink: S) -> Result<(), WriteError<S::Error>>
+    where
+        T: Packet + Writable,
+        T::Error<S::Error>: Into<WriteError<S::Error>>,
+        S: embedded_io_async::Write,
+    {
+        let length = VariableByteInteger::try_from(packet.size()).map_err(WriteError::Overflow)?;
+
+        FixedHeader::new(T::TYPE, packet.flags(), length)
+            .write_to(&mut sink)
+            .await?;
+        packet.write_to(&mut sink).await.map_err(Into::into)?;
+
+        Ok(())
+    }
+
+    /// Serializes the complete packet into `buffer`, returns the length of the packet.
+    pub fn encode<T>(&self, packet: &T, buffer: &mut [u8]) -> Result<usize, EncodeError>
+    where
+        T: Packet + Writable,
+    {
+        let size = packet.size();
+        let Ok(length) = VariableByteInteger::try_from(size) else {
+            return Err(EncodeError::PacketTooLarge);
+        };
+        let len = length.size() + 1 + size;
+        if len > buffer.len() {
+            return Err(EncodeError::BufferTooSmall);
+        }
+
+        let header = FixedHeader::new(T::TYPE, packet.flags(), length);
+        let mut sink = &mut buffer[..];
+        let write = pin!(async {
+            header.write_to(&mut sink).await.ok()?;
+            packet.write_to(&mut sink).await.ok()
+        });
+
+        // Writing into a slice never waits.
+        match write.poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(Some(())) => Ok(len),
+            _ => Err(EncodeError::BufferTooSmall),
+        }
+    }
+}
+
+/// Returned when a packet cannot be encoded, see [`Encoder::encode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeError {
+    /// The packet does not fit into the buffer.
+    BufferTooSmall,
+    /// The packet exceeds the maximum size of a packet.
+    PacketTooLarge,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BufferTooSmall => f.write_str("packet does not fit into the buffer"),
+            Self::PacketTooLarge => f.write_str("packet exceeds the maximum packet size"),
+        }
+    }
+}
+
+impl core::error::Error for EncodeError {}
+
+/// Reads complete packets from buffered data.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::protocol::codec::Decoder;
+/// # use miniqtt::protocol::v5;
+/// let decoder = Decoder::new().max_packet_size(1024);
+///
+/// // The PINGRESP is followed by an incomplete packet.
+/// let data = [0xd0, 0x00, 0x30];
+/// assert_eq!(decoder.frame_len(&data), Ok(Some(2)));
+/// let (len, _) = decoder.decode::<v5::PingResp>(&data).unwrap().unwrap();
+/// assert_eq!(decoder.frame_len(&data[len..]), Ok(None));
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Decoder {
+    max_packet_size: Option<usize>,
+    trailing_data: TrailingData,
+}
+
+impl Decoder {
+    /// Creates a new decoder, without a maximum packet size.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rejects packets which are larger than `max_packet_size`, including their fixed header.
+    pub fn max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = Some(max_packet_size);
+        self
+    }
+
+    /// Configures how data following the parsed fields of a packet is handled.
+    ///
+    /// Defaults to [`TrailingData::Reject`].
+    pub fn trailing_data(mut self, trailing_data: TrailingData) -> Self {
+        self.trailing_data = trailing_data;
+        self
+    }
+
+    /// Returns the length of the packet at the start of `data`.
+    ///
+    /// Returns `None` if the packet is not yet complete.
+    pub fn frame_len(&self, data: &[u8]) -> Result<Option<usize>, DecodeError> {
+        let (header_len, header) = match FixedHeader::parse(data) {
+            Ok(header) => header,
+            Err(ParseError::NotEnoughData) => return Ok(None),
+            Err(ParseError::Error(err)) => return Err(DecodeError::Packet(err)),
+        };
+
+        let len = header_len + header.length().as_u32() as usize;
+        self.check_size(len)?;
+
+        Ok((len <= data.len()).then_some(len))
+    }
+
+    /// Parses the packet at the start of `data`.
+    ///
+    /// Returns the length of the packet and the packet, or `None` if the packet is not yet
+    /// complete.
+    pub fn decode<'a, T>(&self, data: &'a [u8]) -> Result<Option<(usize, T)>, DecodeError>
+    where
+        T: PacketParse<'a>,
+    {
+        if let Ok((header_len, header)) = FixedHeader::parse(data) {
+            self.check_size(header_len + header.length().as_u32() as usize)?;
+        }
+
+        match super::parse_frame(data, self.trailing_data) {
+            Ok(packet) => Ok(Some(packet)),
+            Err(ParseError::NotEnoughData) => Ok(None),
+            Err(ParseError::Error(err)) => Err(DecodeError::Packet(err)),
+        }
+    }
+
+    fn check_size(&self, len: usize) -> Result<(), DecodeError> {
+        match self.max_packet_size {
+            Some(max_packet_size) if len > max_packet_size => Err(DecodeError::PacketTooLarge(len)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How a [`Decoder`] handles data within a packet, which follows all of its parsed fields.
+///
+/// Packets like a CONNACK or SUBACK end with a properties block of a declared length, any data
+/// after it is not part of the packet. Such data indicates a misbehaving server or a packet field
+/// this client does not know about.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingData {
+    /// Fails to decode the packet with [`PacketError::LengthMismatch`].
+    #[default]
+    Reject,
+    /// Skips the trailing data and decodes the packet.
+    Ignore,
+}
+
+/// Returned when a packet cannot be decoded, see [`Decoder`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The packet exceeds the configured maximum packet size, contains the size of the packet.
+    PacketTooLarge(usize),
+    /// The packet is malformed or not of the expected type.
+    Packet(PacketError),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PacketTooLarge(len) => {
+                write!(f, "packet of {len} bytes exceeds the maximum packet size")
+            }
+            Self::Packet(err) => err.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{QoS, v5};
+
+    fn publish(payload: &[u8]) -> v5::Publish<'_, &[u8], &[v5::PublishProperty<'_>]> {
+        v5::Publish {
+            dup: false,
+            qos: QoS::AtMostOnce,
+            retain: false,
+            identifier: None,
+            topic: "a",
+            properties: &[],
+            payload,
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let encoder = Encoder::new();
+        let packet = publish(b"hello");
+
+        let mut buffer = [0; 32];
+        let len = encoder.encode(&packet, &mut buffer).unwrap();
+        assert_eq!(len, encoder.frame_size(&packet));
+
+        let decoder = Decoder::new();
+        assert_eq!(decoder.frame_len(&buffer[..len]), Ok(Some(len)));
+        assert_eq!(decoder.frame_len(&buffer[..len - 1]), Ok(None));
+
+        let (decoded_len, decoded) = decoder
+            .decode::<v5::Publish>(&buffer[..len])
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded_len, len);
+        assert_eq!(decoded.topic, "a");
+        assert_eq!(decoded.payload, b"hello");
+    }
+
+    #[test]
+    fn test_encode_buffer_too_small() {
+        let packet = publish(b"hello");
+        let len = Encoder::new().frame_size(&packet);
+
+        let mut buffer = [0; 32];
+        assert_eq!(
+            Encoder::new().encode(&packet, &mut buffer[..len - 1]),
+            Err(EncodeError::BufferTooSmall)
+        );
+        assert_eq!(Encoder::new().encode(&packet, &mut buffer[..len]), Ok(len));
+    }
+
+    #[test]
+    fn test_decode_max_packet_size() {
+        let decoder = Decoder::new().max_packet_size(4);
+
+        // Rejected as soon as the fixed header is complete.
+        let data = [0x30, 0x05, 0x00];
+        assert_eq!(
+            decoder.frame_len(&data),
+            Err(DecodeError::PacketTooLarge(7))
+        );
+        assert!(matches!(
+            decoder.decode::<v5::Publish>(&data),
+            Err(DecodeError::PacketTooLarge(7))
+        ));
+    }
+
+    #[test]
+    fn test_decode_trailing_data() {
+        // A CONNACK with an empty properties block, followed by a stray byte.
+        let data = [0x20, 0x04, 0x00, 0x00, 0x00, 0xff];
+
+        assert!(matches!(
+            Decoder::new().decode::<v5::ConnAck>(&data),
+            Err(DecodeError::Packet(PacketError::LengthMismatch))
+        ));
+
+        let decoder = Decoder::new().trailing_data(TrailingData::Ignore);
+        let (len, connack) = decoder.decode::<v5::ConnAck>(&data).unwrap().unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(connack.reason, v5::ConnAckReason::Success);
+
+        // Missing data is never ignored.
+        assert!(matches!(
+            decoder.decode::<v5::ConnAck>(&[0x20, 0x02, 0x00, 0x00]),
+            Err(DecodeError::Packet(PacketError::LengthMismatch))
+        ));
+    }
+
+    #[test]
+    fn test_decode_wrong_type() {
+        let result = Decoder::new().decode::<v5::Publish>(&[0xd0, 0x00]);
+        assert!(matches!(
+            result,
+            Err(DecodeError::Packet(PacketError::InvalidPacketType { .. }))
+        ));
+    }
+}
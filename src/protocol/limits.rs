@@ -0,0 +1,199 @@
+//! Limits of the MQTT protocol and helpers to validate values against them.
+//!
+//! The client validates values before sending them, applications can use the same helpers to
+//! reject invalid topics or payloads early, e.g. when they are received from a user.
+
+use core::fmt;
+
+/// Maximum length in bytes of a topic name or topic filter.
+///
+/// Topics are UTF-8 encoded strings, which are prefixed with a two byte length.
+///
+/// Spec: [1.5.4](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901010)
+pub const MAX_TOPIC_LEN: usize = u16::MAX as usize;
+
+/// Maximum remaining length of a packet, the largest value of a variable byte integer.
+///
+/// Spec: [1.5.5](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901011)
+pub const MAX_REMAINING_LENGTH: u32 = 268_435_455;
+
+/// Maximum length in bytes of a client identifier, which every server must accept.
+///
+/// Servers must accept identifiers of up to 23 bytes, containing only `0-9`, `a-z` and `A-Z`.
+/// Longer identifiers are accepted by most servers, but are not guaranteed to be.
+///
+/// Spec: [3.1.3.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901059)
+pub const MAX_CLIENT_ID_COMPAT: usize = 23;
+
+/// Largest packet identifier, identifiers range from `1` to `MAX_PACKET_ID`.
+///
+/// Spec: [2.2.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901026)
+pub const MAX_PACKET_ID: u16 = u16::MAX;
+
+/// Validates a topic name, used to publish a message.
+///
+/// Topic names must not be empty, must not exceed [`MAX_TOPIC_LEN`] and must not contain
+/// wildcards or the null character.
+///
+/// Spec: [4.7.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901247)
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::protocol::{self, ValidationError};
+/// assert_eq!(protocol::validate_topic("sensor/kitchen"), Ok(()));
+/// assert_eq!(protocol::validate_topic(""), Err(ValidationError::Empty));
+/// assert_eq!(protocol::validate_topic("sensor/+"), Err(ValidationError::Wildcard));
+/// ```
+pub fn validate_topic(topic: &str) -> Result<(), ValidationError> {
+    validate_str(topic)?;
+    if topic.is_empty() {
+        return Err(ValidationError::Empty);
+    }
+    if topic.contains(['+', '#']) {
+        return Err(ValidationError::Wildcard);
+    }
+    Ok(())
+}
+
+/// Validates a topic filter, used to subscribe to topics.
+///
+/// Topic filters must not be empty and must not exceed [`MAX_TOPIC_LEN`]. Wildcards must
+/// occupy an entire level and the multi level wildcard (`#`) must be the last level.
+///
+/// Spec: [4.7.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901242)
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::protocol::{self, ValidationError};
+/// assert_eq!(protocol::validate_topic_filter("sensor/+/temperature"), Ok(()));
+/// assert_eq!(protocol::validate_topic_filter("sensor/#"), Ok(()));
+/// assert_eq!(protocol::validate_topic_filter("sensor/#/temperature"), Err(ValidationError::Wildcard));
+/// ```
+pub fn validate_topic_filter(filter: &str) -> Result<(), ValidationError> {
+    validate_str(filter)?;
+    if filter.is_empty() {
+        return Err(ValidationError::Empty);
+    }
+
+    let mut levels = filter.split('/').peekable();
+    while let Some(level) = levels.next() {
+        let is_last = levels.peek().is_none();
+        match level {
+            "+" => {}
+            "#" if is_last => {}
+            level if level.contains(['+', '#']) => return Err(ValidationError::Wildcard),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates a client identifier.
+///
+/// The identifier may be empty, the server then assigns an identifier. Identifiers longer than
+/// [`MAX_CLIENT_ID_COMPAT`] are valid, but may be rejected by the server.
+///
+/// Spec: [3.1.3.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901059)
+pub fn validate_client_id(client_id: &str) -> Result<(), ValidationError> {
+    validate_str(client_id)
+}
+
+/// Validates the size of a payload.
+///
+/// The payload, together with the rest of the packet, must not exceed
+/// [`MAX_REMAINING_LENGTH`]. The server may announce a smaller maximum packet size.
+pub fn validate_payload(payload: &[u8]) -> Result<(), ValidationError> {
+    if payload.len() > MAX_REMAINING_LENGTH as usize {
+        return Err(ValidationError::TooLong);
+    }
+    Ok(())
+}
+
+/// Validates a string can be encoded as a UTF-8 encoded string of the protocol.
+///
+/// Spec: [1.5.4](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901010)
+fn validate_str(s: &str) -> Result<(), ValidationError> {
+    if s.len() > MAX_TOPIC_LEN {
+        return Err(ValidationError::TooLong);
+    }
+    if s.contains('\0') {
+        return Err(ValidationError::NullCharacter);
+    }
+    Ok(())
+}
+
+/// A value violates a limit of the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The value must not be empty.
+    Empty,
+    /// The value exceeds its maximum length.
+    TooLong,
+    /// The value contains a wildcard where it is not allowed.
+    Wildcard,
+    /// The value contains the null character `U+0000`.
+    NullCharacter,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("value must not be empty"),
+            Self::TooLong => f.write_str("value exceeds the maximum length"),
+            Self::Wildcard => f.write_str("value contains an invalid wildcard"),
+            Self::NullCharacter => f.write_str("value contains a null character"),
+        }
+    }
+}
+
+impl core::error::Error for ValidationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_topic() {
+        assert_eq!(validate_topic("a/b/c"), Ok(()));
+        assert_eq!(validate_topic("/"), Ok(()));
+        assert_eq!(validate_topic(""), Err(ValidationError::Empty));
+        assert_eq!(validate_topic("a/#"), Err(ValidationError::Wildcard));
+        assert_eq!(validate_topic("a+"), Err(ValidationError::Wildcard));
+        assert_eq!(validate_topic("a\0"), Err(ValidationError::NullCharacter));
+
+        let topic = "a".repeat(MAX_TOPIC_LEN);
+        assert_eq!(validate_topic(&topic), Ok(()));
+        let topic = "a".repeat(MAX_TOPIC_LEN + 1);
+        assert_eq!(validate_topic(&topic), Err(ValidationError::TooLong));
+    }
+
+    #[test]
+    fn test_validate_topic_filter() {
+        assert_eq!(validate_topic_filter("#"), Ok(()));
+        assert_eq!(validate_topic_filter("+/+"), Ok(()));
+        assert_eq!(validate_topic_filter("a/+/c/#"), Ok(()));
+        assert_eq!(validate_topic_filter(""), Err(ValidationError::Empty));
+        assert_eq!(
+            validate_topic_filter("a/#/c"),
+            Err(ValidationError::Wildcard)
+        );
+        assert_eq!(
+            validate_topic_filter("a/b+"),
+            Err(ValidationError::Wildcard)
+        );
+        assert_eq!(validate_topic_filter("a#"), Err(ValidationError::Wildcard));
+    }
+
+    #[test]
+    fn test_validate_client_id() {
+        assert_eq!(validate_client_id(""), Ok(()));
+        assert_eq!(validate_client_id("miniqtt"), Ok(()));
+        assert_eq!(
+            validate_client_id("mini\0qtt"),
+            Err(ValidationError::NullCharacter)
+        );
+    }
+}
@@ -1,10 +1,19 @@
+use core::fmt;
+
+use crate::log;
+
+mod limits;
 mod qos;
+mod sizing;
 
+pub mod codec;
 pub mod types;
 pub mod utils;
 pub mod v5;
 
+pub use limits::*;
 pub use qos::*;
+pub use sizing::*;
 
 pub trait Packet {
     const TYPE: u8;
@@ -14,6 +23,30 @@ pub trait Packet {
     }
 }
 
+/// Decodes the next packet received from a server.
+///
+/// Returns the amount of bytes consumed and the decoded packet. This can be used to reassemble
+/// packets from custom transports without going through a [`Connection`](crate::Connection).
+///
+/// Returns [`ParseError::NotEnoughData`] when `data` does not contain a complete packet yet,
+/// decoding can be re-attempted once more data is available.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::protocol::{self, ParseError, v5::AnyPacket};
+/// let data = [0x20, 0x03, 0x00, 0x00, 0x00, 0x90];
+///
+/// assert!(matches!(protocol::decode(&data[..3]), Err(ParseError::NotEnoughData)));
+///
+/// let (len, packet) = protocol::decode(&data).unwrap();
+/// assert_eq!(len, 5);
+/// assert!(matches!(packet, AnyPacket::ConnAck(_)));
+/// ```
+pub fn decode(data: &[u8]) -> ParseResult<(usize, v5::AnyPacket<'_>)> {
+    <v5::AnyPacket as Parse>::parse(data)
+}
+
 pub trait Parse<'a>: Sized {
     type Error;
 
@@ -26,6 +59,8 @@ pub trait PacketParse<'a>: Sized {
     fn parse(data: &'a [u8]) -> ParseResult<(usize, Self), PacketError>;
 }
 
+/// Packets are only parsed once they are completely available and must consume exactly the
+/// remaining length announced in their fixed header.
 impl<'a, T> Parse<'a> for T
 where
     T: PacketParse<'a>,
@@ -33,15 +68,45 @@ where
     type Error = PacketError;
 
     fn parse(data: &'a [u8]) -> ParseResult<(usize, Self), Self::Error> {
-        <T as PacketParse>::parse(data)
+        parse_frame(data, codec::TrailingData::Reject)
+    }
+}
+
+/// Parses a complete packet, which must not be followed by unparsed data within its frame,
+/// unless `trailing` allows it.
+pub(crate) fn parse_frame<'a, T>(
+    data: &'a [u8],
+    trailing: codec::TrailingData,
+) -> ParseResult<(usize, T)>
+where
+    T: PacketParse<'a>,
+{
+    let (header_len, header) = types::FixedHeader::parse(data)?;
+    let packet_len = header_len + header.length().as_u32() as usize;
+    let data = data.get(..packet_len).ok_or(ParseError::NotEnoughData)?;
+
+    match <T as PacketParse>::parse(data) {
+        Ok((len, packet)) if len == packet_len => Ok((len, packet)),
+        Ok((len, packet)) if len < packet_len && trailing == codec::TrailingData::Ignore => {
+            let _trailing = packet_len - len;
+            log::debug!("ignoring {_trailing} trailing bytes of packet");
+            Ok((packet_len, packet))
+        }
+        // The packet is complete, a parser which needs more data or does not consume all
+        // data disagrees with the remaining length.
+        Ok(_) | Err(ParseError::NotEnoughData) => Err(PacketError::LengthMismatch.into()),
+        Err(err) => Err(err),
     }
 }
 
 pub type ParseResult<T, E = PacketError> = Result<T, ParseError<E>>;
 
-#[derive(Debug, PartialEq, Eq)] // TODO: impl error
+/// An error parsing data.
+#[derive(Debug, PartialEq, Eq)]
 pub enum ParseError<T> {
+    /// The data is incomplete, parsing can be re-attempted once more data is available.
     NotEnoughData,
+    /// The data is invalid.
     Error(T),
 }
 
@@ -63,13 +128,142 @@ impl<T> From<T> for ParseError<T> {
     }
 }
 
-#[derive(Debug)]
+impl<T> fmt::Display for ParseError<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotEnoughData => write!(f, "Not enough data to parse"),
+            Self::Error(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<T> core::error::Error for ParseError<T> where T: core::error::Error {}
+
+/// An error parsing a packet.
+#[derive(Debug, PartialEq, Eq)]
 pub enum PacketError {
     /// The parsed packet type in the fixed header does not match the expected type.
     InvalidPacketType {
         expected: u8,
         actual: u8,
     },
+    /// The packet type is not known or not supported.
+    UnsupportedPacketType(u8),
+    /// The contents of the packet do not match the remaining length of its fixed header.
+    LengthMismatch,
     // TODO: this should be more descriptive
     ProtocolError,
 }
+
+impl fmt::Display for PacketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPacketType { expected, actual } => {
+                write!(
+                    f,
+                    "Invalid packet type, expected '{expected}' got '{actual}'"
+                )
+            }
+            Self::UnsupportedPacketType(ty) => write!(f, "Unsupported packet type '{ty}'"),
+            Self::LengthMismatch => write!(f, "Packet does not match its remaining length"),
+            Self::ProtocolError => write!(f, "Malformed packet"),
+        }
+    }
+}
+
+impl core::error::Error for PacketError {}
+
+impl From<types::VariableByteIntegerInvalid> for PacketError {
+    fn from(_: types::VariableByteIntegerInvalid) -> Self {
+        Self::ProtocolError
+    }
+}
+
+impl From<types::PacketIdZero> for PacketError {
+    fn from(_: types::PacketIdZero) -> Self {
+        Self::ProtocolError
+    }
+}
+
+/// An error writing a packet.
+#[derive(Debug)]
+pub enum WriteError<E> {
+    /// Writing to the sink failed.
+    Write(E),
+    /// A length does not fit into a [`VariableByteInteger`](types::VariableByteInteger).
+    Overflow(types::VariableByteIntegerOverflow),
+}
+
+impl<E> From<E> for WriteError<E> {
+    fn from(value: E) -> Self {
+        Self::Write(value)
+    }
+}
+
+impl<E> fmt::Display for WriteError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Write(err) => write!(f, "Failed to write packet: {err}"),
+            Self::Overflow(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E> core::error::Error for WriteError<E> where E: core::error::Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packet_length_mismatch() {
+        // CONNACK with a trailing byte, which is not consumed.
+        let data = [0x20, 0x04, 0x00, 0x00, 0x00, 0xff];
+        assert!(matches!(
+            decode(&data),
+            Err(ParseError::Error(PacketError::LengthMismatch))
+        ));
+
+        // CONNACK with properties exceeding the remaining length.
+        let data = [0x20, 0x03, 0x00, 0x00, 0x03, 0x13, 0x00, 0x3c];
+        assert!(matches!(
+            decode(&data),
+            Err(ParseError::Error(PacketError::LengthMismatch))
+        ));
+
+        // PUBLISH with properties exceeding the remaining length.
+        let data = [0x30, 0x04, 0x00, 0x01, b'a', 0x02, 0x00, 0x00];
+        assert!(matches!(
+            decode(&data),
+            Err(ParseError::Error(PacketError::LengthMismatch))
+        ));
+    }
+
+    #[test]
+    fn test_parse_error_display() {
+        let err = decode(&[0x00, 0x00]).unwrap_err();
+        assert_eq!(err.to_string(), "Unsupported packet type '0'");
+
+        let err = decode(&[0x20, 0x80]).unwrap_err();
+        assert_eq!(err.to_string(), "Not enough data to parse");
+    }
+
+    #[test]
+    fn test_packet_incomplete() {
+        let data = [0x20, 0x03, 0x00, 0x00, 0x00];
+
+        for len in 0..data.len() {
+            assert!(matches!(
+                decode(&data[..len]),
+                Err(ParseError::NotEnoughData)
+            ));
+        }
+        assert!(matches!(decode(&data), Ok((5, v5::AnyPacket::ConnAck(_)))));
+    }
+}
@@ -0,0 +1,211 @@
+//! Sizes of received packets, to dimension the receive buffer of a connection.
+//!
+//! A [`Connection`](crate::Connection) must be able to hold a complete packet in its receive
+//! buffer. Fixed size buffers can be checked at compile time against the packets an
+//! application expects to receive.
+
+use crate::protocol::{MAX_CLIENT_ID_COMPAT, Packet, v5};
+
+/// Size of the smallest valid encoding of a received packet, including the fixed header.
+pub trait MinSize: Packet {
+    /// The smallest size of the packet in bytes.
+    const MIN_SIZE: usize;
+}
+
+impl MinSize for v5::ConnAck<'_> {
+    // Acknowledge flags, reason code and an empty property length.
+    const MIN_SIZE: usize = frame_size(3);
+}
+
+impl MinSize for v5::PubAck<'_> {
+    // The reason code and properties may be omitted.
+    const MIN_SIZE: usize = frame_size(2);
+}
+
+impl MinSize for v5::PubRec {
+    const MIN_SIZE: usize = frame_size(2);
+}
+
+impl MinSize for v5::PubRel {
+    const MIN_SIZE: usize = frame_size(2);
+}
+
+impl MinSize for v5::PubComp {
+    const MIN_SIZE: usize = frame_size(2);
+}
+
+impl MinSize for v5::Publish<'_> {
+    // An empty topic, which is only valid with a topic alias, and an empty payload.
+    const MIN_SIZE: usize = frame_size(3);
+}
+
+impl MinSize for v5::PingResp {
+    const MIN_SIZE: usize = frame_size(0);
+}
+
+impl MinSize for v5::Disconnect<'_> {
+    // Without a reason code, which is a normal disconnection.
+    const MIN_SIZE: usize = frame_size(0);
+}
+
+#[cfg(feature = "auth")]
+impl MinSize for v5::Auth<'_> {
+    // Without a reason code, which is a success.
+    const MIN_SIZE: usize = frame_size(0);
+}
+
+#[cfg(feature = "subscribe")]
+impl MinSize for v5::SubAck<'_> {
+    const MIN_SIZE: usize = suback_size(1);
+}
+
+#[cfg(feature = "unsubscribe")]
+impl MinSize for v5::UnsubAck<'_> {
+    const MIN_SIZE: usize = suback_size(1);
+}
+
+/// Returns the smallest receive buffer, which can hold the packet `T`.
+///
+/// Packets may be larger, e.g. when the server includes properties. This is a lower bound to
+/// check fixed size buffers against at compile time.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::protocol::{self, v5};
+/// const RX_BUFFER: usize = 128;
+///
+/// const _: () = assert!(RX_BUFFER >= protocol::min_rx_buffer_for::<v5::PubAck>());
+/// const _: () = assert!(RX_BUFFER >= protocol::RECOMMENDED_CONNACK_SIZE);
+/// const _: () = assert!(RX_BUFFER >= protocol::publish_size("sensors/kitchen".len(), 64));
+/// ```
+pub const fn min_rx_buffer_for<T>() -> usize
+where
+    T: MinSize,
+{
+    T::MIN_SIZE
+}
+
+/// Smallest receive buffer a connection works with.
+///
+/// Every packet the client receives fits into a buffer of this size, as long as the server
+/// does not include properties or a payload.
+pub const MIN_RX_BUFFER_SIZE: usize = suback_size(1);
+
+/// Recommended receive buffer size, to receive a CONNACK with properties.
+///
+/// Fits a CONNACK which contains every fixed size property and a server assigned client
+/// identifier of up to [`MAX_CLIENT_ID_COMPAT`] bytes. Reason strings, user properties and
+/// authentication data are not accounted for.
+///
+/// Spec: [3.2.2.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901080)
+pub const RECOMMENDED_CONNACK_SIZE: usize = {
+    let properties = 5 // Session Expiry Interval
+        + 3 // Receive Maximum
+        + 2 // Maximum QoS
+        + 2 // Retain Available
+        + 5 // Maximum Packet Size
+        + 3 // Topic Alias Maximum
+        + 2 // Wildcard Subscription Available
+        + 2 // Subscription Identifiers Available
+        + 2 // Shared Subscription Available
+        + 3 // Server Keep Alive
+        + 3 + MAX_CLIENT_ID_COMPAT; // Assigned Client Identifier
+
+    frame_size(2 + vbi_size(properties) + properties)
+};
+
+/// Returns the size of a SUBACK for `topics` topic filters, without properties.
+///
+/// The same size applies to an UNSUBACK.
+///
+/// Spec: [3.9](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901171)
+pub const fn suback_size(topics: usize) -> usize {
+    // Packet identifier, an empty property length and a reason code per topic filter.
+    frame_size(2 + 1 + topics)
+}
+
+/// Returns the size of a PUBLISH with a topic of `topic_len` and a payload of `payload_len`
+/// bytes, without properties.
+///
+/// Includes the packet identifier of QoS 1 and QoS 2 messages.
+///
+/// Spec: [3.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901100)
+pub const fn publish_size(topic_len: usize, payload_len: usize) -> usize {
+    // Topic with its length, packet identifier and an empty property length.
+    frame_size(2 + topic_len + 2 + 1 + payload_len)
+}
+
+/// Returns the size of a packet with `remaining` bytes following the fixed header.
+const fn frame_size(remaining: usize) -> usize {
+    1 + vbi_size(remaining) + remaining
+}
+
+/// Returns the encoded size of `value` as a variable byte integer.
+const fn vbi_size(value: usize) -> usize {
+    match value {
+        0..128 => 1,
+        128..16_384 => 2,
+        16_384..2_097_152 => 3,
+        _ => 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::decode;
+    use crate::protocol::types::VariableByteInteger;
+
+    /// Asserts `data` is a complete packet of the minimum size of `T`.
+    fn assert_min_size<T: MinSize>(data: &[u8]) {
+        assert_eq!(data.len(), min_rx_buffer_for::<T>());
+        let (len, _) = decode(data).unwrap();
+        assert_eq!(len, data.len());
+    }
+
+    #[test]
+    fn test_min_size() {
+        assert_min_size::<v5::ConnAck>(&[0x20, 0x03, 0x00, 0x00, 0x00]);
+        assert_min_size::<v5::PubAck>(&[0x40, 0x02, 0x00, 0x01]);
+        assert_min_size::<v5::PubRec>(&[0x50, 0x02, 0x00, 0x01]);
+        assert_min_size::<v5::PubRel>(&[0x62, 0x02, 0x00, 0x01]);
+        assert_min_size::<v5::PubComp>(&[0x70, 0x02, 0x00, 0x01]);
+        assert_min_size::<v5::Publish>(&[0x30, 0x03, 0x00, 0x00, 0x00]);
+        assert_min_size::<v5::PingResp>(&[0xd0, 0x00]);
+        assert_min_size::<v5::Disconnect>(&[0xe0, 0x00]);
+        #[cfg(feature = "auth")]
+        assert_min_size::<v5::Auth>(&[0xf0, 0x00]);
+        #[cfg(feature = "subscribe")]
+        assert_min_size::<v5::SubAck>(&[0x90, 0x04, 0x00, 0x01, 0x00, 0x00]);
+        #[cfg(feature = "unsubscribe")]
+        assert_min_size::<v5::UnsubAck>(&[0xb0, 0x04, 0x00, 0x01, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_vbi_size() {
+        for value in [
+            0,
+            127,
+            128,
+            16_383,
+            16_384,
+            2_097_151,
+            2_097_152,
+            268_435_455,
+        ] {
+            let vbi = VariableByteInteger::try_from(value).unwrap();
+            assert_eq!(vbi_size(value), vbi.size(), "{value}");
+        }
+    }
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(MIN_RX_BUFFER_SIZE, 6);
+        assert_eq!(RECOMMENDED_CONNACK_SIZE, 60);
+        assert_eq!(suback_size(200), 206);
+        assert_eq!(publish_size(1, 1), 9);
+        // The remaining length takes two bytes.
+        assert_eq!(publish_size(1, 200), 209);
+    }
+}
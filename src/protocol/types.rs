@@ -1,4 +1,7 @@
-use crate::protocol::{PacketError, Parse, ParseError};
+use core::fmt;
+use core::num::NonZeroU16;
+
+use crate::protocol::{MAX_REMAINING_LENGTH, PacketError, Parse, ParseError};
 use crate::traits::Writable;
 use crate::utils::Cursor;
 
@@ -11,12 +14,11 @@ pub struct FixedHeader {
 }
 
 impl FixedHeader {
-    pub fn new(packet: u8, flags: u8, size: usize) -> Self {
+    pub fn new(packet: u8, flags: u8, length: VariableByteInteger) -> Self {
         // TODO: flags only lower bits are set -> err
-        // TODO: error when size too large
         Self {
             start: (packet << 4) | (flags & 0b1111),
-            length: size.try_into().unwrap(),
+            length,
         }
     }
 
@@ -138,6 +140,107 @@ impl<'a> Parse<'a> for BinaryData<'a> {
     }
 }
 
+/// A packet identifier, which is never zero.
+///
+/// Spec: [2.2.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901026)
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::protocol::types::PacketId;
+/// let id = PacketId::new(7).unwrap();
+/// assert_eq!(id.get(), 7);
+///
+/// assert!(PacketId::new(0).is_none());
+/// assert!(PacketId::try_from(0).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PacketId(NonZeroU16);
+
+impl PacketId {
+    /// The first packet identifier.
+    pub const MIN: Self = Self(NonZeroU16::MIN);
+
+    /// Creates a packet identifier, returns `None` if `id` is zero.
+    pub const fn new(id: u16) -> Option<Self> {
+        match NonZeroU16::new(id) {
+            Some(id) => Some(Self(id)),
+            None => None,
+        }
+    }
+
+    /// Returns the identifier as a primitive.
+    pub const fn get(self) -> u16 {
+        self.0.get()
+    }
+}
+
+impl fmt::Display for PacketId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<NonZeroU16> for PacketId {
+    fn from(value: NonZeroU16) -> Self {
+        Self(value)
+    }
+}
+
+impl From<PacketId> for u16 {
+    fn from(value: PacketId) -> Self {
+        value.get()
+    }
+}
+
+impl TryFrom<u16> for PacketId {
+    type Error = PacketIdZero;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Self::new(value).ok_or(PacketIdZero)
+    }
+}
+
+impl Writable for PacketId {
+    type Error<E> = E;
+
+    fn size(&self) -> usize {
+        2
+    }
+
+    async fn write_to<S>(&self, sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        self.get().write_to(sink).await
+    }
+}
+
+impl<'a> Parse<'a> for PacketId {
+    type Error = PacketError;
+
+    fn parse(data: &'a [u8]) -> Result<(usize, Self), ParseError<Self::Error>> {
+        let mut cursor = Cursor::new(data);
+
+        let id = cursor.read_u16_be()?;
+        let id = Self::try_from(id).map_err(PacketError::from)?;
+
+        Ok((cursor.position(), id))
+    }
+}
+
+/// Error when a packet identifier is zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketIdZero;
+
+impl fmt::Display for PacketIdZero {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("packet identifier must not be zero")
+    }
+}
+
+impl core::error::Error for PacketIdZero {}
+
 /// A variable byte integer.
 ///
 /// Specification: <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901011>.
@@ -160,7 +263,7 @@ pub struct VariableByteInteger(
 
 impl VariableByteInteger {
     fn encode(num: u32) -> Result<Self, VariableByteIntegerOverflow> {
-        if num > 0xfffffff {
+        if num > MAX_REMAINING_LENGTH {
             return Err(VariableByteIntegerOverflow { _private: () });
         }
 
@@ -214,7 +317,8 @@ impl VariableByteInteger {
             }
         }
 
-        if result[3] > 0x7f {
+        let [.., last] = result;
+        if last > 0x7f {
             return Err(VariableByteIntegerInvalid { _private: () }.into());
         }
 
@@ -352,16 +456,37 @@ impl Writable for VariableByteInteger {
     }
 }
 
-#[derive(Debug)] // TODO: implement `Error`
+/// Error when a value is too large to be encoded as a [`VariableByteInteger`].
+#[derive(Debug)]
 pub struct VariableByteIntegerOverflow {
     _private: (),
 }
 
-#[derive(Debug, PartialEq, Eq)] // TODO: implement `Error`
+impl fmt::Display for VariableByteIntegerOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Value exceeds the maximum variable byte integer '268435455'"
+        )
+    }
+}
+
+impl core::error::Error for VariableByteIntegerOverflow {}
+
+/// Error when parsing a malformed [`VariableByteInteger`].
+#[derive(Debug, PartialEq, Eq)]
 pub struct VariableByteIntegerInvalid {
     _private: (),
 }
 
+impl fmt::Display for VariableByteIntegerInvalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid variable byte integer")
+    }
+}
+
+impl core::error::Error for VariableByteIntegerInvalid {}
+
 impl From<u8> for VariableByteInteger {
     fn from(value: u8) -> Self {
         Self::encode(value.into()).unwrap()
@@ -0,0 +1,308 @@
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::log::{LogPacket, Redacted};
+use crate::protocol::types::{BinaryData, EncodedStr};
+use crate::protocol::utils::CursorExt;
+use crate::protocol::v5::Property;
+use crate::protocol::v5::property::{ParseProperty, ParsedProperties, Properties, PropertyValue};
+use crate::protocol::{Packet, PacketError, PacketParse, Parse, ParseResult, WriteError};
+use crate::traits::Writable;
+use crate::utils::{Cursor, write_many};
+
+/// An AUTH packet, exchanged during extended authentication.
+///
+/// Received packets carry their [`AuthProperties`], packets which are sent carry a slice of
+/// [`AuthProperty`].
+///
+/// Spec: [3.15](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901217)
+pub struct Auth<'a, Props = AuthProperties<'a>> {
+    pub reason: AuthReason,
+    pub properties: Props,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a, Props> Auth<'a, Props> {
+    /// Creates a new AUTH packet.
+    pub fn new(reason: AuthReason, properties: Props) -> Self {
+        Self {
+            reason,
+            properties,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Props> fmt::Debug for Auth<'_, Props>
+where
+    Props: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Auth")
+            .field("reason", &self.reason)
+            .field("properties", &self.properties)
+            .finish()
+    }
+}
+
+impl<Props> Packet for Auth<'_, Props> {
+    const TYPE: u8 = 0b1111;
+}
+
+impl<Props> LogPacket for Auth<'_, Props> where Props: fmt::Debug {}
+
+impl Writable for Auth<'_, &[AuthProperty<'_>]> {
+    type Error<E> = WriteError<E>;
+
+    fn size(&self) -> usize {
+        1 + Properties(self.properties).size()
+    }
+
+    async fn write_to<S>(&self, mut sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        (self.reason as u8).write_to(&mut sink).await?;
+        Properties(self.properties).write_to(&mut sink).await?;
+
+        Ok(())
+    }
+}
+
+impl<'a> PacketParse<'a> for Auth<'a> {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let mut cursor = Cursor::new(data);
+
+        let fixed_header = cursor.read_fixed_header::<Self>()?;
+
+        // The reason and properties may be omitted, if the reason is `Success`.
+        if fixed_header.length().as_u32() == 0 {
+            return Ok((
+                cursor.position(),
+                Self::new(AuthReason::Success, AuthProperties::default()),
+            ));
+        }
+
+        let reason = cursor.read()?;
+        let properties = match fixed_header.length().as_u32() {
+            1 => AuthProperties::default(),
+            _ => AuthProperties::read(&mut cursor)?,
+        };
+
+        Ok((cursor.position(), Self::new(reason, properties)))
+    }
+}
+
+/// The reason specified in the [`Auth`] packet.
+///
+/// Spec: [3.15.2.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901220)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AuthReason {
+    /// Authentication is successful.
+    Success = 0x00,
+    /// Continue the authentication with another step.
+    ContinueAuthentication = 0x18,
+    /// Initiate a re-authentication, only sent by the client.
+    ReAuthenticate = 0x19,
+}
+
+impl<'a> Parse<'a> for AuthReason {
+    type Error = PacketError;
+
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let mut cursor = Cursor::new(data);
+
+        let result = match cursor.read_u8()? {
+            0x00 => Self::Success,
+            0x18 => Self::ContinueAuthentication,
+            0x19 => Self::ReAuthenticate,
+            _ => return Err(PacketError::ProtocolError.into()),
+        };
+
+        Ok((cursor.position(), result))
+    }
+}
+
+/// Properties of an [`Auth`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AuthProperty<'a> {
+    /// The name of the authentication method, must match the method used to connect.
+    ///
+    /// Spec: [3.15.2.2.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901223)
+    AuthenticationMethod(&'a str),
+    /// Binary Data containing authentication data.
+    ///
+    /// Spec: [3.15.2.2.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901224)
+    AuthenticationData(&'a [u8]),
+    /// A human readable string designed for diagnostics.
+    ///
+    /// Spec: [3.15.2.2.4](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901225)
+    ReasonString(&'a str),
+    /// A custom authentication related property.
+    ///
+    /// Spec: [3.15.2.2.5](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901226)
+    UserProperty { key: &'a str, value: &'a str },
+}
+
+impl Writable for AuthProperty<'_> {
+    type Error<E> = E;
+
+    fn size(&self) -> usize {
+        let payload = match self {
+            Self::AuthenticationMethod(v) => EncodedStr(v).size(),
+            Self::AuthenticationData(v) => BinaryData(v).size(),
+            Self::ReasonString(v) => EncodedStr(v).size(),
+            Self::UserProperty { key, value } => EncodedStr(key).size() + EncodedStr(value).size(),
+        };
+        1 + payload
+    }
+
+    async fn write_to<S>(&self, mut sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        match self {
+            Self::AuthenticationMethod(v) => write_many!(sink, 0x15u8, EncodedStr(v)),
+            Self::AuthenticationData(v) => write_many!(sink, 0x16u8, BinaryData(v)),
+            Self::ReasonString(v) => write_many!(sink, 0x1fu8, EncodedStr(v)),
+            Self::UserProperty { key, value } => {
+                write_many!(sink, 0x26u8, EncodedStr(key), EncodedStr(value))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The authentication data is always redacted.
+impl fmt::Debug for AuthProperty<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AuthenticationMethod(v) => {
+                f.debug_tuple("AuthenticationMethod").field(v).finish()
+            }
+            Self::AuthenticationData(_) => f
+                .debug_tuple("AuthenticationData")
+                .field(&Redacted)
+                .finish(),
+            Self::ReasonString(v) => f.debug_tuple("ReasonString").field(v).finish(),
+            Self::UserProperty { key, value } => f
+                .debug_struct("UserProperty")
+                .field("key", key)
+                .field("value", value)
+                .finish(),
+        }
+    }
+}
+
+impl<'a> ParseProperty<'a> for AuthProperty<'a> {
+    fn from_raw(id: u8, value: PropertyValue<'a>) -> Option<Self> {
+        let property = match (id, value) {
+            (0x15, PropertyValue::Str(v)) => Self::AuthenticationMethod(v),
+            (0x16, PropertyValue::BinaryData(v)) => Self::AuthenticationData(v),
+            (0x1f, PropertyValue::Str(v)) => Self::ReasonString(v),
+            (0x26, PropertyValue::StrPair(key, value)) => Self::UserProperty { key, value },
+            _ => return None,
+        };
+
+        Some(property)
+    }
+}
+
+impl Property for AuthProperty<'_> {
+    fn into_write_error<E>(err: Self::Error<E>) -> WriteError<E> {
+        WriteError::Write(err)
+    }
+}
+
+/// Properties of a received [`Auth`].
+///
+/// Properties are validated when the packet is parsed.
+#[derive(Clone, Copy, Default)]
+pub struct AuthProperties<'a>(ParsedProperties<'a, AuthProperty<'a>>);
+
+impl fmt::Debug for AuthProperties<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(*self).finish()
+    }
+}
+
+impl<'a> AuthProperties<'a> {
+    fn read(cursor: &mut Cursor<'a>) -> ParseResult<Self> {
+        Properties::parse(cursor).map(Self)
+    }
+
+    /// Returns the [Authentication Method](AuthProperty::AuthenticationMethod).
+    pub fn authentication_method(&self) -> Option<&'a str> {
+        self.into_iter().find_map(|property| match property {
+            AuthProperty::AuthenticationMethod(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// Returns the [Authentication Data](AuthProperty::AuthenticationData).
+    pub fn authentication_data(&self) -> Option<&'a [u8]> {
+        self.into_iter().find_map(|property| match property {
+            AuthProperty::AuthenticationData(v) => Some(v),
+            _ => None,
+        })
+    }
+}
+
+impl<'a> Iterator for AuthProperties<'a> {
+    type Item = AuthProperty<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::CountingSink;
+
+    #[test]
+    fn test_auth_parse_success_shorthand() {
+        let (len, auth) = <Auth as Parse>::parse(&[0xf0, 0x00]).unwrap();
+        assert_eq!(len, 2);
+        assert_eq!(auth.reason, AuthReason::Success);
+        assert_eq!(auth.properties.count(), 0);
+    }
+
+    #[cfg(feature = "properties")]
+    #[test]
+    fn test_auth_parse_continue() {
+        let data = [
+            0xf0, 0x0d, 0x18, 0x0b, 0x15, 0x00, 0x03, b'f', b'o', b'o', 0x16, 0x00, 0x02, 0x01,
+            0x02,
+        ];
+        let (len, auth) = <Auth as Parse>::parse(&data).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(auth.reason, AuthReason::ContinueAuthentication);
+        assert_eq!(auth.properties.authentication_method(), Some("foo"));
+        assert_eq!(
+            auth.properties.authentication_data(),
+            Some(&[0x01, 0x02][..])
+        );
+    }
+
+    #[test]
+    fn test_auth_parse_invalid_reason() {
+        assert!(<Auth as Parse>::parse(&[0xf0, 0x01, 0x87]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_auth_size_matches_written() {
+        let properties = [
+            AuthProperty::AuthenticationMethod("foo"),
+            AuthProperty::AuthenticationData(b"data"),
+        ];
+        let auth = Auth::new(AuthReason::ReAuthenticate, &properties[..]);
+
+        let mut sink = CountingSink::default();
+        auth.write_to(&mut sink).await.unwrap();
+        assert_eq!(sink.count(), auth.size());
+    }
+}
@@ -1,91 +1,294 @@
-use crate::protocol::types::{BinaryData, EncodedStr, VariableByteInteger};
+use core::fmt;
+
+use crate::log::{LogPacket, Redacted, RedactedBytes};
+use crate::protocol::types::{BinaryData, EncodedStr};
 use crate::protocol::utils::CursorExt;
-use crate::protocol::v5::{Property, property::Properties};
-use crate::protocol::{Packet, PacketError, PacketParse, Parse, ParseResult, QoS};
+use crate::protocol::v5::Property;
+use crate::protocol::v5::flags::ConnectFlags;
+use crate::protocol::v5::property::{
+    ParseProperty, ParsedProperties, Properties, PropertyChunks, PropertyValue, RawProperties,
+};
+use crate::protocol::{
+    Packet, PacketError, PacketParse, Parse, ParseError, ParseResult, QoS, WriteError,
+};
 use crate::traits::Writable;
 use crate::utils::{Cursor, write_many};
 
-#[derive(Debug, Clone, Copy)]
-pub struct Connect<'a> {
+/// A CONNECT packet.
+///
+/// Properties are a slice of [`ConnectProperty`] and [`WillProperty`] when sending the packet.
+/// A parsed packet contains [`ConnectProperties`] and [`WillProperties`] instead, which iterate
+/// the properties directly from the received data.
+#[derive(Clone, Copy)]
+pub struct Connect<'a, P = &'a [ConnectProperty<'a>], W = &'a [WillProperty<'a>]> {
     pub client_id: &'a str,
     pub keep_alive: u16,
     pub clean_start: bool,
-    pub will: Option<Will<'a>>,
+    pub will: Option<Will<'a, W>>,
     pub username: Option<&'a str>,
-    pub password: Option<&'a str>,
-    pub properties: &'a [ConnectProperty<'a>],
+    pub password: Option<&'a [u8]>,
+    pub properties: P,
 }
 
-impl Packet for Connect<'_> {
+impl<P, W> Packet for Connect<'_, P, W> {
     const TYPE: u8 = 0b0001;
 }
 
+impl<P, W> Connect<'_, P, W>
+where
+    P: fmt::Debug,
+    W: fmt::Debug,
+{
+    fn fmt_debug(&self, f: &mut fmt::Formatter<'_>, payload: bool) -> fmt::Result {
+        f.debug_struct("Connect")
+            .field("client_id", &self.client_id)
+            .field("keep_alive", &self.keep_alive)
+            .field("clean_start", &self.clean_start)
+            .field(
+                "will",
+                &self.will.as_ref().map(|will| WillDebug { will, payload }),
+            )
+            .field("username", &self.username)
+            .field("password", &self.password.map(|_| Redacted))
+            .field("properties", &self.properties)
+            .finish()
+    }
+}
+
+/// The password is always redacted.
+impl<P, W> fmt::Debug for Connect<'_, P, W>
+where
+    P: fmt::Debug,
+    W: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_debug(f, true)
+    }
+}
+
+impl<P, W> LogPacket for Connect<'_, P, W>
+where
+    P: fmt::Debug,
+    W: fmt::Debug,
+{
+    fn fmt_without_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_debug(f, false)
+    }
+}
+
+/// The protocol name, the beginning of the variable header of a [`Connect`].
+///
+/// Spec: [3.1.2.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901036)
+const PROTOCOL_NAME: EncodedStr<'static> = EncodedStr("MQTT");
+
+/// The protocol version, following the [`PROTOCOL_NAME`].
+///
+/// Spec: [3.1.2.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901037)
+const PROTOCOL_VERSION: u8 = 5;
+
+impl<P, W> Connect<'_, P, W> {
+    /// The connect flags of the variable header.
+    ///
+    /// Spec: [3.1.2.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901038)
+    fn connect_flags(&self) -> u8 {
+        let flags = ConnectFlags::new(self.clean_start)
+            .with_username(self.username.is_some())
+            .with_password(self.password.is_some());
+
+        match &self.will {
+            Some(will) => flags.with_will(will.qos, will.retain).bits(),
+            None => flags.bits(),
+        }
+    }
+}
+
 impl Writable for Connect<'_> {
-    type Error<E> = E;
+    type Error<E> = WriteError<E>;
+
+    fn size(&self) -> usize {
+        self.size_with(Properties(self.properties))
+    }
+
+    async fn write_to<S>(&self, sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        self.write_with(sink, Properties(self.properties)).await
+    }
+}
+
+impl Writable for Connect<'_, &[&[ConnectProperty<'_>]]> {
+    type Error<E> = WriteError<E>;
 
     fn size(&self) -> usize {
-        10 + Properties(self.properties).size()
-            + EncodedStr(self.client_id).size()
+        self.size_with(PropertyChunks(self.properties))
+    }
+
+    async fn write_to<S>(&self, sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        self.write_with(sink, PropertyChunks(self.properties)).await
+    }
+}
+
+impl<P> Connect<'_, P> {
+    fn size_with<W>(&self, properties: W) -> usize
+    where
+        W: Writable,
+    {
+        let variable_header = PROTOCOL_NAME.size()
+            + PROTOCOL_VERSION.size()
+            + self.connect_flags().size()
+            + self.keep_alive.size()
+            + properties.size();
+
+        let payload = EncodedStr(self.client_id).size()
             + self.will.size()
             + self.username.map(EncodedStr).size()
-            + self.password.map(EncodedStr).size()
+            + self.password.map(BinaryData).size();
+
+        variable_header + payload
     }
 
-    async fn write_to<T>(&self, mut sink: T) -> Result<(), T::Error>
+    async fn write_with<S, W>(&self, mut sink: S, properties: W) -> Result<(), WriteError<S::Error>>
     where
-        T: embedded_io_async::Write,
+        S: embedded_io_async::Write,
+        W: Writable<Error<S::Error> = WriteError<S::Error>>,
     {
         // Protocol Name:
-        EncodedStr("MQTT").write_to(&mut sink).await?;
+        PROTOCOL_NAME.write_to(&mut sink).await?;
 
         // Protocol Version:
-        5u8.write_to(&mut sink).await?;
+        PROTOCOL_VERSION.write_to(&mut sink).await?;
 
         // Connect Flags:
-        let connect_flags = {
-            let username = u8::from(self.username.is_some());
-            let password = u8::from(self.password.is_some());
-            let will_retain = u8::from(self.will.is_some_and(|w| w.retain));
-            let will_qos = u8::from(self.will.map(|w| w.qos).unwrap_or(QoS::AtMostOnce));
-            let will = u8::from(self.will.is_some());
-            let clean_start = u8::from(self.clean_start);
-
-            username << 7
-                | password << 6
-                | will_retain << 5
-                | will_qos << 3
-                | will << 2
-                | clean_start << 1
-        };
-        connect_flags.write_to(&mut sink).await?;
+        self.connect_flags().write_to(&mut sink).await?;
 
         // Keep Alive:
         self.keep_alive.write_to(&mut sink).await?;
 
         // Properties:
-        Properties(self.properties).write_to(&mut sink).await?;
+        properties.write_to(&mut sink).await?;
 
         // Payload:
         EncodedStr(self.client_id).write_to(&mut sink).await?;
         self.will.write_to(&mut sink).await?;
         self.username.map(EncodedStr).write_to(&mut sink).await?;
-        self.password.map(EncodedStr).write_to(&mut sink).await?;
+        self.password.map(BinaryData).write_to(&mut sink).await?;
 
         Ok(())
     }
 }
 
+impl<'a> PacketParse<'a> for Connect<'a, ConnectProperties<'a>, WillProperties<'a>> {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let mut cursor = Cursor::new(data);
+
+        let fixed_header = cursor.read_fixed_header::<Self>()?;
+        if fixed_header.flags() != 0 {
+            return Err(PacketError::ProtocolError.into());
+        }
+
+        // Parse from the complete packet, running out of data means the packet is malformed.
+        let packet = cursor.read_slice(fixed_header.length().as_u32() as usize)?;
+        let connect = Self::parse_body(packet).map_err(|err| match err {
+            ParseError::NotEnoughData => PacketError::ProtocolError,
+            ParseError::Error(err) => err,
+        })?;
+
+        Ok((cursor.position(), connect))
+    }
+}
+
+impl<'a> Connect<'a, ConnectProperties<'a>, WillProperties<'a>> {
+    /// Parses the variable header and payload of a CONNECT.
+    fn parse_body(data: &'a [u8]) -> ParseResult<Self> {
+        let mut cursor = Cursor::new(data);
+
+        let EncodedStr(protocol_name) = cursor.read()?;
+        let protocol_version = cursor.read_u8()?;
+        if protocol_name != PROTOCOL_NAME.0 || protocol_version != PROTOCOL_VERSION {
+            return Err(PacketError::ProtocolError.into());
+        }
+
+        let flags = ConnectFlags::from_bits(cursor.read_u8()?)?;
+
+        let keep_alive = cursor.read_u16_be()?;
+        let properties = ConnectProperties::read(&mut cursor)?;
+
+        let EncodedStr(client_id) = cursor.read()?;
+        let will = match flags.will() {
+            true => Some(Will {
+                retain: flags.will_retain(),
+                qos: flags.will_qos(),
+                properties: WillProperties::read(&mut cursor)?,
+                topic: cursor.read::<EncodedStr>()?.0,
+                payload: cursor.read::<BinaryData>()?.0,
+            }),
+            false => None,
+        };
+        let username = match flags.username() {
+            true => Some(cursor.read::<EncodedStr>()?.0),
+            false => None,
+        };
+        let password = match flags.password() {
+            true => Some(cursor.read::<BinaryData>()?.0),
+            false => None,
+        };
+
+        if cursor.position() != data.len() {
+            return Err(PacketError::LengthMismatch.into());
+        }
+
+        Ok(Self {
+            client_id,
+            keep_alive,
+            clean_start: flags.clean_start(),
+            will,
+            username,
+            password,
+            properties,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
-pub struct Will<'a> {
+pub struct Will<'a, P = &'a [WillProperty<'a>]> {
     pub retain: bool,
     pub qos: QoS,
-    pub properties: &'a [WillProperty<'a>],
+    pub properties: P,
     pub topic: &'a str,
     pub payload: &'a [u8],
 }
 
+/// Formats a [`Will`], optionally omitting the payload.
+struct WillDebug<'b, 'a, P> {
+    will: &'b Will<'a, P>,
+    payload: bool,
+}
+
+impl<P> fmt::Debug for WillDebug<'_, '_, P>
+where
+    P: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.payload {
+            return fmt::Debug::fmt(self.will, f);
+        }
+
+        f.debug_struct("Will")
+            .field("retain", &self.will.retain)
+            .field("qos", &self.will.qos)
+            .field("properties", &self.will.properties)
+            .field("topic", &self.will.topic)
+            .field("payload", &RedactedBytes(self.will.payload.len()))
+            .finish()
+    }
+}
+
 impl Writable for Will<'_> {
-    type Error<E> = E;
+    type Error<E> = WriteError<E>;
 
     fn size(&self) -> usize {
         Properties(self.properties).size()
@@ -106,7 +309,7 @@ impl Writable for Will<'_> {
 }
 
 /// Properties accepted in a [`Connect`] request.
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ConnectProperty<'a> {
     /// The Session Expiry Interval in seconds.
     ///
@@ -188,10 +391,92 @@ impl Writable for ConnectProperty<'_> {
     }
 }
 
-impl Property for ConnectProperty<'_> {}
+/// The authentication data is always redacted.
+impl fmt::Debug for ConnectProperty<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SessionExpiryInterval(v) => {
+                f.debug_tuple("SessionExpiryInterval").field(v).finish()
+            }
+            Self::ReceiveMaximum(v) => f.debug_tuple("ReceiveMaximum").field(v).finish(),
+            Self::MaximumPacketSize(v) => f.debug_tuple("MaximumPacketSize").field(v).finish(),
+            Self::TopicAliasMaximum(v) => f.debug_tuple("TopicAliasMaximum").field(v).finish(),
+            Self::RequestResponseInformation(v) => f
+                .debug_tuple("RequestResponseInformation")
+                .field(v)
+                .finish(),
+            Self::RequestProblemInformation(v) => {
+                f.debug_tuple("RequestProblemInformation").field(v).finish()
+            }
+            Self::UserProperty { key, value } => f
+                .debug_struct("UserProperty")
+                .field("key", key)
+                .field("value", value)
+                .finish(),
+            Self::AuthenticationMethod(v) => {
+                f.debug_tuple("AuthenticationMethod").field(v).finish()
+            }
+            Self::AuthenticationData(_) => f
+                .debug_tuple("AuthenticationData")
+                .field(&Redacted)
+                .finish(),
+        }
+    }
+}
+
+impl<'a> ParseProperty<'a> for ConnectProperty<'a> {
+    fn from_raw(id: u8, value: PropertyValue<'a>) -> Option<Self> {
+        let property = match (id, value) {
+            (0x11, PropertyValue::FourByteInteger(v)) => Self::SessionExpiryInterval(v),
+            (0x21, PropertyValue::TwoByteInteger(v)) => Self::ReceiveMaximum(v),
+            (0x27, PropertyValue::FourByteInteger(v)) => Self::MaximumPacketSize(v),
+            (0x22, PropertyValue::TwoByteInteger(v)) => Self::TopicAliasMaximum(v),
+            (0x19, PropertyValue::Byte(v)) => Self::RequestResponseInformation(v),
+            (0x17, PropertyValue::Byte(v @ (0 | 1))) => Self::RequestProblemInformation(v == 1),
+            (0x26, PropertyValue::StrPair(key, value)) => Self::UserProperty { key, value },
+            (0x15, PropertyValue::Str(v)) => Self::AuthenticationMethod(v),
+            (0x16, PropertyValue::BinaryData(v)) => Self::AuthenticationData(v),
+            _ => return None,
+        };
+
+        Some(property)
+    }
+}
+
+impl Property for ConnectProperty<'_> {
+    fn into_write_error<E>(err: Self::Error<E>) -> WriteError<E> {
+        WriteError::Write(err)
+    }
+}
+
+/// Properties of a parsed [`Connect`].
+///
+/// Properties are validated when the packet is parsed.
+#[derive(Clone, Copy)]
+pub struct ConnectProperties<'a>(ParsedProperties<'a, ConnectProperty<'a>>);
+
+impl fmt::Debug for ConnectProperties<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(*self).finish()
+    }
+}
+
+impl<'a> ConnectProperties<'a> {
+    fn read(cursor: &mut Cursor<'a>) -> ParseResult<Self> {
+        Properties::parse(cursor).map(Self)
+    }
+}
+
+impl<'a> Iterator for ConnectProperties<'a> {
+    type Item = ConnectProperty<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
 
 /// [`Will`] specific properties accepted in a [`Connect`] request.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WillProperty<'a> {
     /// The Will Delay Interval in seconds.
     ///
@@ -234,7 +519,7 @@ impl Writable for WillProperty<'_> {
             Self::MessageExpiryInterval(v) => v.size(),
             Self::ContentType(v) => EncodedStr(v).size(),
             Self::ResponseTopic(v) => EncodedStr(v).size(),
-            Self::CorrelationData(v) => v.size(),
+            Self::CorrelationData(v) => BinaryData(v).size(),
             Self::UserProperty { key, value } => EncodedStr(key).size() + EncodedStr(value).size(),
         };
         1 + payload
@@ -260,35 +545,318 @@ impl Writable for WillProperty<'_> {
     }
 }
 
-impl Property for WillProperty<'_> {}
+impl<'a> ParseProperty<'a> for WillProperty<'a> {
+    fn from_raw(id: u8, value: PropertyValue<'a>) -> Option<Self> {
+        let property = match (id, value) {
+            (0x18, PropertyValue::FourByteInteger(v)) => Self::WillDelay(v),
+            (0x01, PropertyValue::Byte(v)) => Self::PayloadFormatIndicator(v),
+            (0x02, PropertyValue::FourByteInteger(v)) => Self::MessageExpiryInterval(v),
+            (0x03, PropertyValue::Str(v)) => Self::ContentType(v),
+            (0x08, PropertyValue::Str(v)) => Self::ResponseTopic(v),
+            (0x09, PropertyValue::BinaryData(v)) => Self::CorrelationData(v),
+            (0x26, PropertyValue::StrPair(key, value)) => Self::UserProperty { key, value },
+            _ => return None,
+        };
+
+        Some(property)
+    }
+}
+
+impl Property for WillProperty<'_> {
+    fn into_write_error<E>(err: Self::Error<E>) -> WriteError<E> {
+        WriteError::Write(err)
+    }
+}
+
+/// Properties of the [`Will`] of a parsed [`Connect`].
+///
+/// Properties are validated when the packet is parsed.
+#[derive(Clone, Copy)]
+pub struct WillProperties<'a>(ParsedProperties<'a, WillProperty<'a>>);
+
+impl fmt::Debug for WillProperties<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(*self).finish()
+    }
+}
+
+impl<'a> WillProperties<'a> {
+    fn read(cursor: &mut Cursor<'a>) -> ParseResult<Self> {
+        Properties::parse(cursor).map(Self)
+    }
+}
+
+impl<'a> Iterator for WillProperties<'a> {
+    type Item = WillProperty<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
 
-#[derive(Debug)]
-pub struct ConnAck {
+#[derive(Debug, Clone, Copy)]
+pub struct ConnAck<'a> {
     // TODO: should probably look into a bitflags crate for flags like that
     pub ack_flags: u8,
     pub reason: ConnAckReason,
+    /// The keep alive interval assigned by the server, which must be used instead of the keep
+    /// alive sent by the client.
+    ///
+    /// Spec: [3.2.2.3.14](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901094)
+    pub server_keep_alive: Option<u16>,
+    /// The session expiry interval in seconds assigned by the server, which must be used instead
+    /// of the session expiry interval sent by the client.
+    ///
+    /// Spec: [3.2.2.3.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901082)
+    pub session_expiry_interval: Option<u32>,
+    /// The client identifier assigned by the server, if the client connected with an empty
+    /// client identifier.
+    ///
+    /// Spec: [3.2.2.3.7](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901087)
+    pub assigned_client_identifier: Option<&'a str>,
+    /// The remaining properties of the packet.
+    pub properties: ConnAckProperties<'a>,
+}
+
+impl<'a> ConnAck<'a> {
+    /// Whether the server resumed an existing session.
+    ///
+    /// Spec: [3.2.2.1.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901078)
+    pub fn session_present(&self) -> bool {
+        self.ack_flags & 1 == 1
+    }
+
+    /// Returns the encoded properties of the packet, without the property length.
+    ///
+    /// Only some properties are exposed as typed fields, the raw bytes allow decoding the
+    /// remaining properties without support from this crate. Properties are encoded as an
+    /// identifier followed by the value.
+    ///
+    /// Spec: [3.2.2.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901080)
+    pub fn raw_properties(&self) -> &'a [u8] {
+        self.properties.0.as_bytes()
+    }
 }
 
-impl Packet for ConnAck {
+impl Packet for ConnAck<'_> {
     const TYPE: u8 = 0b0010;
 }
 
-impl<'a> PacketParse<'a> for ConnAck {
-    fn parse(data: &[u8]) -> ParseResult<(usize, Self), PacketError> {
+impl LogPacket for ConnAck<'_> {}
+
+impl<'a> PacketParse<'a> for ConnAck<'a> {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self), PacketError> {
         let mut cursor = Cursor::new(data);
 
-        let _fixed_header = cursor.read_fixed_header::<Self>()?;
+        let fixed_header = cursor.read_fixed_header::<Self>()?;
 
         let ack_flags = cursor.read_u8()?;
+
+        // Servers which only support MQTT 3.1.1 reply with a 3.1.1 CONNACK, which has no
+        // properties and return code `0x01` for an unacceptable protocol version.
+        //
+        // Spec: [3.2.2.3](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718035)
+        if fixed_header.length().as_u32() == 2 && cursor.peek_u8() == Some(0x01) {
+            cursor.read_u8()?;
+            return Ok((
+                cursor.position(),
+                Self {
+                    ack_flags,
+                    reason: ConnAckReason::UnsupportedProtocolVersion,
+                    server_keep_alive: None,
+                    session_expiry_interval: None,
+                    assigned_client_identifier: None,
+                    properties: ConnAckProperties::default(),
+                },
+            ));
+        }
+
         let reason = cursor.read()?;
 
-        // TODO: actually parse the properties
-        let properties = cursor
-            .read::<VariableByteInteger>()
-            .map_err(|err| err.map(|_| PacketError::ProtocolError))?;
-        let _ = cursor.read_slice(properties.as_u32() as usize)?;
+        let mut server_keep_alive = None;
+        let mut session_expiry_interval = None;
+        let mut assigned_client_identifier = None;
+        let properties = RawProperties::read(&mut cursor)?;
+        for property in properties {
+            match property? {
+                (0x11, PropertyValue::FourByteInteger(v)) => session_expiry_interval = Some(v),
+                (0x13, PropertyValue::TwoByteInteger(v)) => server_keep_alive = Some(v),
+                (0x12, PropertyValue::Str(v)) => assigned_client_identifier = Some(v),
+                _ => {}
+            }
+        }
+
+        Ok((
+            cursor.position(),
+            Self {
+                ack_flags,
+                reason,
+                server_keep_alive,
+                session_expiry_interval,
+                assigned_client_identifier,
+                properties: ConnAckProperties(properties),
+            },
+        ))
+    }
+}
+
+/// Properties of a received [`ConnAck`].
+///
+/// Properties are validated when the packet is parsed.
+#[derive(Clone, Copy, Default)]
+pub struct ConnAckProperties<'a>(RawProperties<'a>);
+
+impl fmt::Debug for ConnAckProperties<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.user_properties()).finish()
+    }
+}
+
+impl<'a> ConnAckProperties<'a> {
+    /// Returns the user properties as key value pairs.
+    ///
+    /// Spec: [3.2.2.3.10](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901090)
+    pub fn user_properties(&self) -> impl Iterator<Item = (&'a str, &'a str)> + use<'a> {
+        self.0.filter_map(|property| match property.ok()? {
+            (0x26, PropertyValue::StrPair(key, value)) => Some((key, value)),
+            _ => None,
+        })
+    }
+
+    /// Returns the value of the first user property with the key `key`.
+    pub fn user_property(&self, key: &str) -> Option<&'a str> {
+        self.user_properties()
+            .find_map(|(k, value)| (k == key).then_some(value))
+    }
+
+    /// Returns the amount of QoS 1 and QoS 2 publishes the server processes concurrently.
+    ///
+    /// Defaults to `65535`, if the server did not send the property.
+    ///
+    /// Spec: [3.2.2.3.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901083)
+    pub fn receive_maximum(&self) -> u16 {
+        match self.find(0x21) {
+            Some(PropertyValue::TwoByteInteger(v)) => v,
+            _ => u16::MAX,
+        }
+    }
+
+    /// Returns the highest QoS the server supports.
+    ///
+    /// Defaults to [`QoS::ExactlyOnce`], if the server did not send the property.
+    ///
+    /// Spec: [3.2.2.3.4](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901084)
+    pub fn maximum_qos(&self) -> QoS {
+        match self.find(0x24) {
+            Some(PropertyValue::Byte(v)) => QoS::try_from(v).unwrap_or(QoS::ExactlyOnce),
+            _ => QoS::ExactlyOnce,
+        }
+    }
+
+    /// Returns whether the server supports retained messages.
+    ///
+    /// Spec: [3.2.2.3.5](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901085)
+    pub fn retain_available(&self) -> bool {
+        self.flag(0x25)
+    }
+
+    /// Returns the maximum size of a packet the server accepts, `None` if there is no limit.
+    ///
+    /// Spec: [3.2.2.3.6](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901086)
+    pub fn maximum_packet_size(&self) -> Option<u32> {
+        match self.find(0x27)? {
+            PropertyValue::FourByteInteger(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the highest topic alias the server accepts, `0` if topic aliases are not
+    /// accepted.
+    ///
+    /// Spec: [3.2.2.3.8](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901088)
+    pub fn topic_alias_maximum(&self) -> u16 {
+        match self.find(0x22) {
+            Some(PropertyValue::TwoByteInteger(v)) => v,
+            _ => 0,
+        }
+    }
+
+    /// Returns a human readable reason, intended for diagnostics.
+    ///
+    /// Spec: [3.2.2.3.9](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901089)
+    pub fn reason_string(&self) -> Option<&'a str> {
+        self.str(0x1f)
+    }
+
+    /// Returns whether the server supports wildcard subscriptions.
+    ///
+    /// Spec: [3.2.2.3.11](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901091)
+    pub fn wildcard_subscription_available(&self) -> bool {
+        self.flag(0x28)
+    }
+
+    /// Returns whether the server supports subscription identifiers.
+    ///
+    /// Spec: [3.2.2.3.12](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901092)
+    pub fn subscription_identifiers_available(&self) -> bool {
+        self.flag(0x29)
+    }
+
+    /// Returns whether the server supports shared subscriptions.
+    ///
+    /// Spec: [3.2.2.3.13](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901093)
+    pub fn shared_subscription_available(&self) -> bool {
+        self.flag(0x2a)
+    }
+
+    /// Returns the basis for creating response topics, if requested in the connect.
+    ///
+    /// Spec: [3.2.2.3.15](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901095)
+    pub fn response_information(&self) -> Option<&'a str> {
+        self.str(0x1a)
+    }
+
+    /// Returns another server the client should use.
+    ///
+    /// Spec: [3.2.2.3.16](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901096)
+    pub fn server_reference(&self) -> Option<&'a str> {
+        self.str(0x1c)
+    }
+
+    /// Returns the method of an extended authentication.
+    ///
+    /// Spec: [3.2.2.3.17](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901097)
+    pub fn authentication_method(&self) -> Option<&'a str> {
+        self.str(0x15)
+    }
+
+    /// Returns the data of an extended authentication.
+    ///
+    /// Spec: [3.2.2.3.18](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901098)
+    pub fn authentication_data(&self) -> Option<&'a [u8]> {
+        match self.find(0x16)? {
+            PropertyValue::BinaryData(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the first property with the identifier `id`.
+    fn find(&self, id: u8) -> Option<PropertyValue<'a>> {
+        self.0
+            .filter_map(|property| property.ok())
+            .find_map(|(i, value)| (i == id).then_some(value))
+    }
+
+    /// Returns the value of a boolean property, which defaults to `true`.
+    fn flag(&self, id: u8) -> bool {
+        !matches!(self.find(id), Some(PropertyValue::Byte(0)))
+    }
 
-        Ok((cursor.position(), Self { ack_flags, reason }))
+    fn str(&self, id: u8) -> Option<&'a str> {
+        match self.find(id)? {
+            PropertyValue::Str(v) => Some(v),
+            _ => None,
+        }
     }
 }
 
@@ -300,7 +868,7 @@ impl<'a> PacketParse<'a> for ConnAck {
 /// of the listed reasons:
 ///
 /// > The Server sending the CONNACK packet MUST use one of the Connect Reason Code values T-3.2.2-8].
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum ConnAckReason {
     /// The Connection is accepted.
@@ -390,6 +958,307 @@ impl<'a> Parse<'a> for ConnAckReason {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::CountingSink;
+
+    #[tokio::test]
+    async fn test_connect_size_matches_written() {
+        let will_properties = [
+            WillProperty::WillDelay(10),
+            WillProperty::ContentType("text/plain"),
+            WillProperty::CorrelationData(b"\x00\x01"),
+        ];
+        let wills: [Option<Will>; 3] = [
+            None,
+            Some(Will {
+                retain: true,
+                qos: QoS::AtLeastOnce,
+                properties: &[],
+                topic: "will",
+                payload: b"",
+            }),
+            Some(Will {
+                retain: false,
+                qos: QoS::ExactlyOnce,
+                properties: &will_properties,
+                topic: "will/topic",
+                payload: b"offline",
+            }),
+        ];
+        let properties = [
+            ConnectProperty::SessionExpiryInterval(99),
+            ConnectProperty::UserProperty {
+                key: "key",
+                value: "value",
+            },
+            ConnectProperty::AuthenticationData(b"data"),
+        ];
+
+        for client_id in ["", "miniqtt"] {
+            for keep_alive in [0, 60] {
+                for clean_start in [false, true] {
+                    for will in wills {
+                        for username in [None, Some("foo")] {
+                            for password in [None, Some(&b"\xffbar"[..])] {
+                                for properties in [&[][..], &properties[..]] {
+                                    let connect = Connect {
+                                        client_id,
+                                        keep_alive,
+                                        clean_start,
+                                        will,
+                                        username,
+                                        password,
+                                        properties,
+                                    };
+
+                                    let mut sink = CountingSink::default();
+                                    connect.write_to(&mut sink).await.unwrap();
+                                    assert_eq!(connect.size(), sink.count(), "{connect:?}");
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "properties")]
+    #[tokio::test]
+    async fn test_connect_parse_written() {
+        use crate::protocol::types::FixedHeader;
+
+        let will_properties = [
+            WillProperty::WillDelay(10),
+            WillProperty::CorrelationData(b"\x00\x01"),
+        ];
+        let properties = [
+            ConnectProperty::SessionExpiryInterval(99),
+            ConnectProperty::RequestProblemInformation(true),
+            ConnectProperty::UserProperty {
+                key: "key",
+                value: "value",
+            },
+        ];
+        let connect: Connect<'_> = Connect {
+            client_id: "miniqtt",
+            keep_alive: 60,
+            clean_start: true,
+            will: Some(Will {
+                retain: true,
+                qos: QoS::AtLeastOnce,
+                properties: &will_properties,
+                topic: "will/topic",
+                payload: b"offline",
+            }),
+            username: Some("foo"),
+            password: Some(b"\xffbar"),
+            properties: &properties,
+        };
+
+        let mut data = Vec::new();
+        FixedHeader::new(<Connect>::TYPE, 0, connect.size().try_into().unwrap())
+            .write_to(&mut data)
+            .await
+            .unwrap();
+        connect.write_to(&mut data).await.unwrap();
+
+        let mut cursor = Cursor::new(&data);
+        let parsed = cursor
+            .read::<Connect<'_, ConnectProperties<'_>, WillProperties<'_>>>()
+            .unwrap();
+        assert_eq!(cursor.position(), data.len());
+
+        assert_eq!(parsed.client_id, "miniqtt");
+        assert_eq!(parsed.keep_alive, 60);
+        assert!(parsed.clean_start);
+        assert_eq!(parsed.username, Some("foo"));
+        assert_eq!(parsed.password, Some(&b"\xffbar"[..]));
+        assert!(parsed.properties.eq(properties));
+
+        let will = parsed.will.unwrap();
+        assert!(will.retain);
+        assert_eq!(will.qos, QoS::AtLeastOnce);
+        assert_eq!(will.topic, "will/topic");
+        assert_eq!(will.payload, b"offline");
+        assert!(will.properties.eq(will_properties));
+
+        // Incomplete packets need more data.
+        assert!(matches!(
+            Cursor::new(&data[..data.len() - 1]).read::<Connect<
+                '_,
+                ConnectProperties<'_>,
+                WillProperties<'_>,
+            >>(),
+            Err(ParseError::NotEnoughData)
+        ));
+    }
+
+    #[cfg(feature = "properties")]
+    #[test]
+    fn test_connect_parse_invalid() {
+        type Parsed<'a> = Connect<'a, ConnectProperties<'a>, WillProperties<'a>>;
+
+        // Protocol version 4.
+        let data = [
+            0x10, 0x0d, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04, 0x02, 0x00, 0x00, 0x00, 0x00,
+            0x00,
+        ];
+        assert!(matches!(
+            Cursor::new(&data).read::<Parsed>(),
+            Err(ParseError::Error(PacketError::ProtocolError))
+        ));
+
+        // Will retain without a will.
+        let data = [
+            0x10, 0x0d, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x05, 0x22, 0x00, 0x00, 0x00, 0x00,
+            0x00,
+        ];
+        assert!(matches!(
+            Cursor::new(&data).read::<Parsed>(),
+            Err(ParseError::Error(PacketError::ProtocolError))
+        ));
+
+        // Server keep alive is not a valid CONNECT property.
+        let data = [
+            0x10, 0x10, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x05, 0x02, 0x00, 0x00, 0x03, 0x13,
+            0x00, 0x3c, 0x00, 0x00,
+        ];
+        assert!(matches!(
+            Cursor::new(&data).read::<Parsed>(),
+            Err(ParseError::Error(PacketError::ProtocolError))
+        ));
+
+        // Valid, without properties and an empty client id.
+        let data = [
+            0x10, 0x0d, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x05, 0x02, 0x00, 0x00, 0x00, 0x00,
+            0x00,
+        ];
+        let parsed = Cursor::new(&data).read::<Parsed>().unwrap();
+        assert_eq!(parsed.client_id, "");
+        assert!(parsed.will.is_none());
+        assert_eq!(parsed.properties.count(), 0);
+    }
+
+    #[test]
+    fn test_connect_debug_redacted() {
+        use crate::log::Logged;
+
+        let properties = [ConnectProperty::AuthenticationData(b"token")];
+        let connect: Connect<'_> = Connect {
+            client_id: "miniqtt",
+            keep_alive: 0,
+            clean_start: true,
+            will: Some(Will {
+                retain: false,
+                qos: QoS::AtMostOnce,
+                properties: &[],
+                topic: "will",
+                payload: b"offline",
+            }),
+            username: Some("foo"),
+            password: Some(b"secret"),
+            properties: &properties,
+        };
+
+        let debug = format!("{connect:?}");
+        assert!(!debug.contains("secret"), "{debug}");
+        assert!(!debug.contains("116, 111, 107"), "{debug}");
+        assert!(
+            debug.contains("[111, 102, 102, 108, 105, 110, 101]"),
+            "{debug}"
+        );
+
+        let logged = Logged {
+            packet: &connect,
+            redact_payload: true,
+        };
+        let debug = format!("{logged:?}");
+        assert!(!debug.contains("secret"), "{debug}");
+        assert!(!debug.contains("111, 102, 102"), "{debug}");
+        assert!(debug.contains("<7 bytes>"), "{debug}");
+    }
+
+    #[cfg(feature = "properties")]
+    #[test]
+    fn test_conn_ack_raw_properties() {
+        // Server keep alive `30` and assigned client identifier `c`.
+        let data = [
+            0x20, 0x0a, 0x01, 0x00, 0x07, 0x13, 0x00, 0x1e, 0x12, 0x00, 0x01, b'c',
+        ];
+
+        let (_, ack) = <ConnAck as Parse>::parse(&data).unwrap();
+        assert_eq!(ack.server_keep_alive, Some(30));
+        assert_eq!(ack.assigned_client_identifier, Some("c"));
+        assert_eq!(
+            ack.raw_properties(),
+            [0x13, 0x00, 0x1e, 0x12, 0x00, 0x01, b'c']
+        );
+
+        // MQTT 3.1.1 CONNACK, without properties.
+        let (_, ack) = <ConnAck as Parse>::parse(&[0x20, 0x02, 0x00, 0x01]).unwrap();
+        assert_eq!(ack.raw_properties(), []);
+    }
+
+    #[cfg(feature = "properties")]
+    #[test]
+    fn test_conn_ack_properties() {
+        #[rustfmt::skip]
+        let data = [
+            0x20, 0x1c, 0x01, 0x00, 0x19,
+            // Receive maximum 10.
+            0x21, 0x00, 0x0a,
+            // Maximum QoS 1.
+            0x24, 0x01,
+            // Retain not available.
+            0x25, 0x00,
+            // Maximum packet size 1024.
+            0x27, 0x00, 0x00, 0x04, 0x00,
+            // Topic alias maximum 5.
+            0x22, 0x00, 0x05,
+            // Shared subscriptions not available.
+            0x2a, 0x00,
+            // Reason string `r`.
+            0x1f, 0x00, 0x01, b'r',
+            // Authentication data.
+            0x16, 0x00, 0x01, 0xff,
+        ];
+
+        let (len, ack) = <ConnAck as Parse>::parse(&data).unwrap();
+        assert_eq!(len, data.len());
+        assert!(ack.session_present());
+
+        let properties = ack.properties;
+        assert_eq!(properties.receive_maximum(), 10);
+        assert_eq!(properties.maximum_qos(), QoS::AtLeastOnce);
+        assert!(!properties.retain_available());
+        assert_eq!(properties.maximum_packet_size(), Some(1024));
+        assert_eq!(properties.topic_alias_maximum(), 5);
+        assert!(properties.wildcard_subscription_available());
+        assert!(properties.subscription_identifiers_available());
+        assert!(!properties.shared_subscription_available());
+        assert_eq!(properties.reason_string(), Some("r"));
+        assert_eq!(properties.response_information(), None);
+        assert_eq!(properties.server_reference(), None);
+        assert_eq!(properties.authentication_method(), None);
+        assert_eq!(properties.authentication_data(), Some(&[0xff][..]));
+    }
+
+    #[test]
+    fn test_conn_ack_properties_defaults() {
+        let (_, ack) = <ConnAck as Parse>::parse(&[0x20, 0x03, 0x00, 0x00, 0x00]).unwrap();
+        assert!(!ack.session_present());
+
+        let properties = ack.properties;
+        assert_eq!(properties.receive_maximum(), u16::MAX);
+        assert_eq!(properties.maximum_qos(), QoS::ExactlyOnce);
+        assert!(properties.retain_available());
+        assert_eq!(properties.maximum_packet_size(), None);
+        assert_eq!(properties.topic_alias_maximum(), 0);
+        assert!(properties.wildcard_subscription_available());
+        assert!(properties.subscription_identifiers_available());
+        assert!(properties.shared_subscription_available());
+        assert_eq!(properties.reason_string(), None);
+    }
 
     #[test]
     fn test_parsed_conn_ack_reason_matches_value() {
@@ -0,0 +1,412 @@
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::log::LogPacket;
+use crate::protocol::types::EncodedStr;
+use crate::protocol::utils::CursorExt;
+use crate::protocol::v5::Property;
+use crate::protocol::v5::property::{ParseProperty, ParsedProperties, Properties, PropertyValue};
+use crate::protocol::{Packet, PacketError, PacketParse, Parse, ParseResult, WriteError};
+use crate::traits::Writable;
+use crate::utils::{Cursor, write_many};
+
+/// A DISCONNECT packet, the final packet sent by the client or the server.
+///
+/// Received packets carry their [`DisconnectProperties`], packets which are sent carry a slice
+/// of [`DisconnectProperty`].
+///
+/// Spec: [3.14](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901205)
+pub struct Disconnect<'a, Props = DisconnectProperties<'a>> {
+    pub reason: DisconnectReason,
+    pub properties: Props,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a, Props> Disconnect<'a, Props> {
+    /// Creates a new DISCONNECT packet.
+    pub fn new(reason: DisconnectReason, properties: Props) -> Self {
+        Self {
+            reason,
+            properties,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Props> fmt::Debug for Disconnect<'_, Props>
+where
+    Props: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Disconnect")
+            .field("reason", &self.reason)
+            .field("properties", &self.properties)
+            .finish()
+    }
+}
+
+impl<Props> Packet for Disconnect<'_, Props> {
+    const TYPE: u8 = 0b1110;
+}
+
+impl<Props> LogPacket for Disconnect<'_, Props> where Props: fmt::Debug {}
+
+impl Writable for Disconnect<'_, &[DisconnectProperty<'_>]> {
+    type Error<E> = WriteError<E>;
+
+    fn size(&self) -> usize {
+        // The property length may be omitted, if there are no properties.
+        match self.properties.is_empty() {
+            true => 1,
+            false => 1 + Properties(self.properties).size(),
+        }
+    }
+
+    async fn write_to<S>(&self, mut sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        // Reason Code:
+        (self.reason as u8).write_to(&mut sink).await?;
+
+        // Properties:
+        if !self.properties.is_empty() {
+            Properties(self.properties).write_to(&mut sink).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> PacketParse<'a> for Disconnect<'a> {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let mut cursor = Cursor::new(data);
+
+        let fixed_header = cursor.read_fixed_header::<Self>()?;
+
+        // The reason and properties may be omitted, if the reason is `NormalDisconnection`.
+        let (reason, properties) = match fixed_header.length().as_u32() {
+            0 => (
+                DisconnectReason::NormalDisconnection,
+                DisconnectProperties::default(),
+            ),
+            1 => (cursor.read()?, DisconnectProperties::default()),
+            _ => (cursor.read()?, DisconnectProperties::read(&mut cursor)?),
+        };
+
+        Ok((cursor.position(), Self::new(reason, properties)))
+    }
+}
+
+/// The reason specified in the [`Disconnect`] packet.
+///
+/// Spec: [3.14.2.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901208)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DisconnectReason {
+    /// Close the connection normally, do not send the Will Message.
+    NormalDisconnection = 0x00,
+    /// The client wishes to disconnect but requires that the server also publishes its Will
+    /// Message.
+    DisconnectWithWillMessage = 0x04,
+    /// The connection is closed but the sender either does not wish to reveal the reason, or
+    /// none of the other Reason Codes apply.
+    UnspecifiedError = 0x80,
+    /// The received packet does not conform to this specification.
+    MalformedPacket = 0x81,
+    /// An unexpected or out of order packet was received.
+    ProtocolError = 0x82,
+    /// The packet received is valid but cannot be processed by this implementation.
+    ImplementationSpecificError = 0x83,
+    /// The request is not authorized.
+    NotAuthorized = 0x87,
+    /// The server is busy and cannot continue processing requests from this client.
+    ServerBusy = 0x89,
+    /// The server is shutting down.
+    ServerShuttingDown = 0x8b,
+    /// The connection is closed because no packet has been received for 1.5 times the keep
+    /// alive time.
+    KeepAliveTimeout = 0x8d,
+    /// Another connection using the same client id has connected.
+    SessionTakenOver = 0x8e,
+    /// The topic filter is correctly formed, but is not accepted by this server.
+    TopicFilterInvalid = 0x8f,
+    /// The topic name is correctly formed, but is not accepted by this server.
+    TopicNameInvalid = 0x90,
+    /// The client has received more than Receive Maximum publications without sending an
+    /// acknowledgement.
+    ReceiveMaximumExceeded = 0x93,
+    /// The client has received a topic alias which is greater than the maximum it sent.
+    TopicAliasInvalid = 0x94,
+    /// The packet size is greater than the maximum packet size for this client.
+    PacketTooLarge = 0x95,
+    /// The received data rate is too high.
+    MessageRateTooHigh = 0x96,
+    /// An implementation or administrative imposed limit has been exceeded.
+    QuotaExceeded = 0x97,
+    /// The connection is closed due to an administrative action.
+    AdministrativeAction = 0x98,
+    /// The payload format does not match the one specified by the Payload Format Indicator.
+    PayloadFormatInvalid = 0x99,
+    /// The server does not support retained messages.
+    RetainNotSupported = 0x9a,
+    /// The client specified a QoS greater than the maximum QoS of the server.
+    QoSNotSupported = 0x9b,
+    /// The client should temporarily change its server.
+    UseAnotherServer = 0x9c,
+    /// The server is moved and the client should permanently change its server location.
+    ServerMoved = 0x9d,
+    /// The server does not support shared subscriptions.
+    SharedSubscriptionsNotSupported = 0x9e,
+    /// This connection is closed because the connection rate is too high.
+    ConnectionRateExceeded = 0x9f,
+    /// The maximum connection time authorized for this connection has been exceeded.
+    MaximumConnectTime = 0xa0,
+    /// The server does not support subscription identifiers.
+    SubscriptionIdentifiersNotSupported = 0xa1,
+    /// The server does not support wildcard subscriptions.
+    WildcardSubscriptionsNotSupported = 0xa2,
+}
+
+impl TryFrom<u8> for DisconnectReason {
+    type Error = PacketError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let result = match value {
+            0x00 => Self::NormalDisconnection,
+            0x04 => Self::DisconnectWithWillMessage,
+            0x80 => Self::UnspecifiedError,
+            0x81 => Self::MalformedPacket,
+            0x82 => Self::ProtocolError,
+            0x83 => Self::ImplementationSpecificError,
+            0x87 => Self::NotAuthorized,
+            0x89 => Self::ServerBusy,
+            0x8b => Self::ServerShuttingDown,
+            0x8d => Self::KeepAliveTimeout,
+            0x8e => Self::SessionTakenOver,
+            0x8f => Self::TopicFilterInvalid,
+            0x90 => Self::TopicNameInvalid,
+            0x93 => Self::ReceiveMaximumExceeded,
+            0x94 => Self::TopicAliasInvalid,
+            0x95 => Self::PacketTooLarge,
+            0x96 => Self::MessageRateTooHigh,
+            0x97 => Self::QuotaExceeded,
+            0x98 => Self::AdministrativeAction,
+            0x99 => Self::PayloadFormatInvalid,
+            0x9a => Self::RetainNotSupported,
+            0x9b => Self::QoSNotSupported,
+            0x9c => Self::UseAnotherServer,
+            0x9d => Self::ServerMoved,
+            0x9e => Self::SharedSubscriptionsNotSupported,
+            0x9f => Self::ConnectionRateExceeded,
+            0xa0 => Self::MaximumConnectTime,
+            0xa1 => Self::SubscriptionIdentifiersNotSupported,
+            0xa2 => Self::WildcardSubscriptionsNotSupported,
+            _ => return Err(PacketError::ProtocolError),
+        };
+
+        Ok(result)
+    }
+}
+
+impl<'a> Parse<'a> for DisconnectReason {
+    type Error = PacketError;
+
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let mut cursor = Cursor::new(data);
+        let reason = Self::try_from(cursor.read_u8()?)?;
+
+        Ok((cursor.position(), reason))
+    }
+}
+
+/// Properties of a [`Disconnect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectProperty<'a> {
+    /// The session expiry interval in seconds, only sent by the client.
+    ///
+    /// Spec: [3.14.2.2.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901211)
+    SessionExpiryInterval(u32),
+    /// A human readable string designed for diagnostics.
+    ///
+    /// Spec: [3.14.2.2.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901212)
+    ReasonString(&'a str),
+    /// A custom property, e.g. for diagnostics.
+    ///
+    /// Spec: [3.14.2.2.4](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901213)
+    UserProperty { key: &'a str, value: &'a str },
+    /// Another server to use, only sent by the server.
+    ///
+    /// Spec: [3.14.2.2.5](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901214)
+    ServerReference(&'a str),
+}
+
+impl Writable for DisconnectProperty<'_> {
+    type Error<E> = E;
+
+    fn size(&self) -> usize {
+        let payload = match self {
+            Self::SessionExpiryInterval(v) => v.size(),
+            Self::ReasonString(v) => EncodedStr(v).size(),
+            Self::UserProperty { key, value } => EncodedStr(key).size() + EncodedStr(value).size(),
+            Self::ServerReference(v) => EncodedStr(v).size(),
+        };
+        1 + payload
+    }
+
+    async fn write_to<S>(&self, mut sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        match self {
+            Self::SessionExpiryInterval(v) => write_many!(sink, 0x11u8, v),
+            Self::ReasonString(v) => write_many!(sink, 0x1fu8, EncodedStr(v)),
+            Self::UserProperty { key, value } => {
+                write_many!(sink, 0x26u8, EncodedStr(key), EncodedStr(value))
+            }
+            Self::ServerReference(v) => write_many!(sink, 0x1cu8, EncodedStr(v)),
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> ParseProperty<'a> for DisconnectProperty<'a> {
+    fn from_raw(id: u8, value: PropertyValue<'a>) -> Option<Self> {
+        let property = match (id, value) {
+            (0x11, PropertyValue::FourByteInteger(v)) => Self::SessionExpiryInterval(v),
+            (0x1f, PropertyValue::Str(v)) => Self::ReasonString(v),
+            (0x26, PropertyValue::StrPair(key, value)) => Self::UserProperty { key, value },
+            (0x1c, PropertyValue::Str(v)) => Self::ServerReference(v),
+            _ => return None,
+        };
+
+        Some(property)
+    }
+}
+
+impl Property for DisconnectProperty<'_> {
+    fn into_write_error<E>(err: Self::Error<E>) -> WriteError<E> {
+        WriteError::Write(err)
+    }
+}
+
+/// Properties of a received [`Disconnect`].
+///
+/// Properties are validated when the packet is parsed.
+#[derive(Clone, Copy, Default)]
+pub struct DisconnectProperties<'a>(ParsedProperties<'a, DisconnectProperty<'a>>);
+
+impl fmt::Debug for DisconnectProperties<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(*self).finish()
+    }
+}
+
+impl<'a> DisconnectProperties<'a> {
+    fn read(cursor: &mut Cursor<'a>) -> ParseResult<Self> {
+        Properties::parse(cursor).map(Self)
+    }
+
+    /// Returns the [Reason String](DisconnectProperty::ReasonString).
+    pub fn reason_string(&self) -> Option<&'a str> {
+        self.into_iter().find_map(|property| match property {
+            DisconnectProperty::ReasonString(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// Returns the [Server Reference](DisconnectProperty::ServerReference).
+    pub fn server_reference(&self) -> Option<&'a str> {
+        self.into_iter().find_map(|property| match property {
+            DisconnectProperty::ServerReference(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// Returns the [User Properties](DisconnectProperty::UserProperty) as key value pairs, in
+    /// the order they were sent.
+    pub fn user_properties(&self) -> impl Iterator<Item = (&'a str, &'a str)> + use<'a> {
+        self.into_iter().filter_map(|property| match property {
+            DisconnectProperty::UserProperty { key, value } => Some((key, value)),
+            _ => None,
+        })
+    }
+
+    /// Returns the value of the first user property with the key `key`.
+    pub fn user_property(&self, key: &str) -> Option<&'a str> {
+        self.user_properties()
+            .find_map(|(k, value)| (k == key).then_some(value))
+    }
+}
+
+impl<'a> Iterator for DisconnectProperties<'a> {
+    type Item = DisconnectProperty<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::CountingSink;
+
+    #[test]
+    fn test_disconnect_reason_matches_value() {
+        for i in 0..u8::MAX {
+            let Ok(reason) = DisconnectReason::try_from(i) else {
+                continue;
+            };
+
+            assert_eq!(reason as u8, i);
+        }
+    }
+
+    #[test]
+    fn test_disconnect_parse_shorthand() {
+        let (len, disconnect) = <Disconnect as Parse>::parse(&[0xe0, 0x00]).unwrap();
+        assert_eq!(len, 2);
+        assert_eq!(disconnect.reason, DisconnectReason::NormalDisconnection);
+
+        let (_, disconnect) = <Disconnect as Parse>::parse(&[0xe0, 0x01, 0x8b]).unwrap();
+        assert_eq!(disconnect.reason, DisconnectReason::ServerShuttingDown);
+        assert_eq!(disconnect.properties.count(), 0);
+    }
+
+    #[cfg(feature = "properties")]
+    #[test]
+    fn test_disconnect_parse_properties() {
+        #[rustfmt::skip]
+        let data = [
+            0xe0, 0x10, 0x9c, 0x0e,
+            0x1c, 0x00, 0x04, b'h', b'o', b's', b't',
+            0x26, 0x00, 0x01, b'k', 0x00, 0x01, b'v',
+        ];
+        let (len, disconnect) = <Disconnect as Parse>::parse(&data).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(disconnect.reason, DisconnectReason::UseAnotherServer);
+        assert_eq!(disconnect.properties.server_reference(), Some("host"));
+        assert_eq!(disconnect.properties.reason_string(), None);
+        assert!(disconnect.properties.user_properties().eq([("k", "v")]));
+        assert_eq!(disconnect.properties.user_property("k"), Some("v"));
+    }
+
+    #[test]
+    fn test_disconnect_parse_invalid_reason() {
+        assert!(<Disconnect as Parse>::parse(&[0xe0, 0x01, 0x01]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_size_matches_written() {
+        let properties = [DisconnectProperty::ReasonString("bye")];
+        for properties in [&properties[..], &[]] {
+            let disconnect = Disconnect::new(DisconnectReason::NormalDisconnection, properties);
+
+            let mut sink = CountingSink::default();
+            disconnect.write_to(&mut sink).await.unwrap();
+            assert_eq!(sink.count(), disconnect.size());
+        }
+    }
+}
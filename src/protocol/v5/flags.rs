@@ -0,0 +1,368 @@
+//! Typed bitfields of packet flags and options.
+
+use core::fmt;
+
+#[cfg(feature = "subscribe")]
+use crate::protocol::v5::RetainHandling;
+use crate::protocol::{PacketError, QoS};
+
+/// Returns the QoS encoded in the two lowest bits, `None` for the invalid value `3`.
+const fn qos_from_bits(bits: u8) -> Option<QoS> {
+    match bits & 0b11 {
+        0 => Some(QoS::AtMostOnce),
+        1 => Some(QoS::AtLeastOnce),
+        2 => Some(QoS::ExactlyOnce),
+        _ => None,
+    }
+}
+
+/// Returns the QoS encoded in the two lowest bits of already validated flags.
+const fn qos_from_valid_bits(bits: u8) -> QoS {
+    match qos_from_bits(bits) {
+        Some(qos) => qos,
+        None => QoS::AtMostOnce,
+    }
+}
+
+/// The flags of the fixed header of a PUBLISH packet.
+///
+/// Spec: [3.3.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901101)
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct PublishFlags(u8);
+
+impl PublishFlags {
+    const DUP: u8 = 0b1000;
+    const QOS_SHIFT: u8 = 1;
+    const RETAIN: u8 = 0b0001;
+
+    /// Creates the flags of a PUBLISH packet.
+    pub const fn new(dup: bool, qos: QoS, retain: bool) -> Self {
+        Self((dup as u8) << 3 | (qos as u8) << Self::QOS_SHIFT | retain as u8)
+    }
+
+    /// Validates the flags of a received PUBLISH packet.
+    ///
+    /// Both QoS bits set is a malformed packet.
+    pub fn from_bits(bits: u8) -> Result<Self, PacketError> {
+        if bits & 0xf0 != 0 || qos_from_bits(bits >> Self::QOS_SHIFT).is_none() {
+            return Err(PacketError::ProtocolError);
+        }
+        Ok(Self(bits))
+    }
+
+    /// Returns the raw bits of the flags.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Returns the DUP flag, set if the packet is re-delivered.
+    pub const fn dup(self) -> bool {
+        self.0 & Self::DUP != 0
+    }
+
+    /// Returns the QoS level of the publish.
+    pub const fn qos(self) -> QoS {
+        qos_from_valid_bits(self.0 >> Self::QOS_SHIFT)
+    }
+
+    /// Returns the RETAIN flag.
+    pub const fn retain(self) -> bool {
+        self.0 & Self::RETAIN != 0
+    }
+}
+
+impl fmt::Debug for PublishFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PublishFlags")
+            .field("dup", &self.dup())
+            .field("qos", &self.qos())
+            .field("retain", &self.retain())
+            .finish()
+    }
+}
+
+/// The connect flags of the variable header of a CONNECT packet.
+///
+/// Spec: [3.1.2.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901038)
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConnectFlags(u8);
+
+impl ConnectFlags {
+    const USERNAME: u8 = 0b1000_0000;
+    const PASSWORD: u8 = 0b0100_0000;
+    const WILL_RETAIN: u8 = 0b0010_0000;
+    const WILL_QOS_SHIFT: u8 = 3;
+    const WILL: u8 = 0b0000_0100;
+    const CLEAN_START: u8 = 0b0000_0010;
+    const RESERVED: u8 = 0b0000_0001;
+
+    /// Creates connect flags without a will, username and password.
+    pub const fn new(clean_start: bool) -> Self {
+        Self((clean_start as u8) << 1)
+    }
+
+    /// Sets the will flag together with the QoS and retain flag of the will.
+    pub const fn with_will(self, qos: QoS, retain: bool) -> Self {
+        Self(self.0 | Self::WILL | (qos as u8) << Self::WILL_QOS_SHIFT | (retain as u8) << 5)
+    }
+
+    /// Sets whether a username is present in the payload.
+    pub const fn with_username(self, username: bool) -> Self {
+        Self(self.0 & !Self::USERNAME | (username as u8) << 7)
+    }
+
+    /// Sets whether a password is present in the payload.
+    pub const fn with_password(self, password: bool) -> Self {
+        Self(self.0 & !Self::PASSWORD | (password as u8) << 6)
+    }
+
+    /// Validates the flags of a received CONNECT packet.
+    ///
+    /// The reserved flag must be zero, will QoS and retain must be zero without a will.
+    pub fn from_bits(bits: u8) -> Result<Self, PacketError> {
+        let Some(will_qos) = qos_from_bits(bits >> Self::WILL_QOS_SHIFT) else {
+            return Err(PacketError::ProtocolError);
+        };
+        let flags = Self(bits);
+        let will_options = will_qos != QoS::AtMostOnce || flags.will_retain();
+        if bits & Self::RESERVED != 0 || (!flags.will() && will_options) {
+            return Err(PacketError::ProtocolError);
+        }
+        Ok(flags)
+    }
+
+    /// Returns the raw bits of the flags.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Returns `true` if a username is present in the payload.
+    pub const fn username(self) -> bool {
+        self.0 & Self::USERNAME != 0
+    }
+
+    /// Returns `true` if a password is present in the payload.
+    pub const fn password(self) -> bool {
+        self.0 & Self::PASSWORD != 0
+    }
+
+    /// Returns `true` if the will message is retained when it is published.
+    pub const fn will_retain(self) -> bool {
+        self.0 & Self::WILL_RETAIN != 0
+    }
+
+    /// Returns the QoS level of the will message.
+    pub const fn will_qos(self) -> QoS {
+        qos_from_valid_bits(self.0 >> Self::WILL_QOS_SHIFT)
+    }
+
+    /// Returns `true` if a will is present in the payload.
+    pub const fn will(self) -> bool {
+        self.0 & Self::WILL != 0
+    }
+
+    /// Returns `true` if the connection starts a new session.
+    pub const fn clean_start(self) -> bool {
+        self.0 & Self::CLEAN_START != 0
+    }
+}
+
+impl fmt::Debug for ConnectFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectFlags")
+            .field("username", &self.username())
+            .field("password", &self.password())
+            .field("will_retain", &self.will_retain())
+            .field("will_qos", &self.will_qos())
+            .field("will", &self.will())
+            .field("clean_start", &self.clean_start())
+            .finish()
+    }
+}
+
+/// The subscription options of a topic filter in a SUBSCRIBE packet.
+///
+/// Spec: [3.8.3.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901169)
+#[cfg(feature = "subscribe")]
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct SubscriptionOptions(u8);
+
+#[cfg(feature = "subscribe")]
+impl SubscriptionOptions {
+    const NO_LOCAL: u8 = 0b0000_0100;
+    const RETAIN_AS_PUBLISHED: u8 = 0b0000_1000;
+    const RETAIN_HANDLING_SHIFT: u8 = 4;
+    const RESERVED: u8 = 0b1100_0000;
+
+    /// Creates subscription options with the maximum QoS of the subscription.
+    pub const fn new(qos: QoS) -> Self {
+        Self(qos as u8)
+    }
+
+    /// Sets the No Local option.
+    pub const fn with_no_local(self, no_local: bool) -> Self {
+        Self(self.0 & !Self::NO_LOCAL | (no_local as u8) << 2)
+    }
+
+    /// Sets the Retain As Published option.
+    pub const fn with_retain_as_published(self, retain_as_published: bool) -> Self {
+        Self(self.0 & !Self::RETAIN_AS_PUBLISHED | (retain_as_published as u8) << 3)
+    }
+
+    /// Sets the Retain Handling option.
+    pub const fn with_retain_handling(self, retain_handling: RetainHandling) -> Self {
+        Self(self.0 & 0b1111 | (retain_handling as u8) << Self::RETAIN_HANDLING_SHIFT)
+    }
+
+    /// Validates received subscription options.
+    ///
+    /// The reserved bits must be zero, QoS and Retain Handling must not be `3`.
+    pub fn from_bits(bits: u8) -> Result<Self, PacketError> {
+        let invalid_qos = qos_from_bits(bits).is_none();
+        let invalid_retain_handling = (bits >> Self::RETAIN_HANDLING_SHIFT) & 0b11 == 3;
+        if bits & Self::RESERVED != 0 || invalid_qos || invalid_retain_handling {
+            return Err(PacketError::ProtocolError);
+        }
+        Ok(Self(bits))
+    }
+
+    /// Returns the raw bits of the options.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Returns the maximum QoS of the subscription.
+    pub const fn qos(self) -> QoS {
+        qos_from_valid_bits(self.0)
+    }
+
+    /// Returns the No Local option.
+    pub const fn no_local(self) -> bool {
+        self.0 & Self::NO_LOCAL != 0
+    }
+
+    /// Returns the Retain As Published option.
+    pub const fn retain_as_published(self) -> bool {
+        self.0 & Self::RETAIN_AS_PUBLISHED != 0
+    }
+
+    /// Returns the Retain Handling option.
+    pub const fn retain_handling(self) -> RetainHandling {
+        match (self.0 >> Self::RETAIN_HANDLING_SHIFT) & 0b11 {
+            1 => RetainHandling::SendRetainedOnNewSubscription,
+            2 => RetainHandling::DoNotSendRetained,
+            _ => RetainHandling::SendRetained,
+        }
+    }
+}
+
+#[cfg(feature = "subscribe")]
+impl fmt::Debug for SubscriptionOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubscriptionOptions")
+            .field("qos", &self.qos())
+            .field("no_local", &self.no_local())
+            .field("retain_as_published", &self.retain_as_published())
+            .field("retain_handling", &self.retain_handling())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QOS: [QoS; 3] = [QoS::AtMostOnce, QoS::AtLeastOnce, QoS::ExactlyOnce];
+
+    #[test]
+    fn test_publish_flags_round_trip() {
+        for qos in QOS {
+            for dup in [false, true] {
+                for retain in [false, true] {
+                    let flags = PublishFlags::new(dup, qos, retain);
+                    assert_eq!(PublishFlags::from_bits(flags.bits()).unwrap(), flags);
+                    assert_eq!(flags.dup(), dup);
+                    assert_eq!(flags.qos(), qos);
+                    assert_eq!(flags.retain(), retain);
+                }
+            }
+        }
+
+        assert_eq!(
+            PublishFlags::new(true, QoS::ExactlyOnce, true).bits(),
+            0b1101
+        );
+        assert!(PublishFlags::from_bits(0b0110).is_err());
+    }
+
+    #[test]
+    fn test_connect_flags_round_trip() {
+        let flags = ConnectFlags::new(true)
+            .with_will(QoS::ExactlyOnce, true)
+            .with_username(true)
+            .with_password(false);
+        assert_eq!(flags.bits(), 0b1011_0110);
+        assert_eq!(ConnectFlags::from_bits(flags.bits()).unwrap(), flags);
+        assert!(flags.username());
+        assert!(!flags.password());
+        assert!(flags.will_retain());
+        assert_eq!(flags.will_qos(), QoS::ExactlyOnce);
+        assert!(flags.will());
+        assert!(flags.clean_start());
+
+        let flags = ConnectFlags::new(false).with_password(true);
+        assert_eq!(flags.bits(), 0b0100_0000);
+        assert_eq!(ConnectFlags::from_bits(flags.bits()).unwrap(), flags);
+
+        // Reserved flag.
+        assert!(ConnectFlags::from_bits(0b0000_0001).is_err());
+        // Invalid will QoS.
+        assert!(ConnectFlags::from_bits(0b0001_1100).is_err());
+        // Will QoS and retain without a will.
+        assert!(ConnectFlags::from_bits(0b0000_1000).is_err());
+        assert!(ConnectFlags::from_bits(0b0010_0000).is_err());
+    }
+
+    #[cfg(feature = "subscribe")]
+    #[test]
+    fn test_subscription_options_round_trip() {
+        let retain_handlings = [
+            RetainHandling::SendRetained,
+            RetainHandling::SendRetainedOnNewSubscription,
+            RetainHandling::DoNotSendRetained,
+        ];
+        for qos in QOS {
+            for retain_handling in retain_handlings {
+                let options = SubscriptionOptions::new(qos)
+                    .with_no_local(true)
+                    .with_retain_as_published(false)
+                    .with_retain_handling(retain_handling);
+                assert_eq!(
+                    SubscriptionOptions::from_bits(options.bits()).unwrap(),
+                    options
+                );
+                assert_eq!(options.qos(), qos);
+                assert!(options.no_local());
+                assert!(!options.retain_as_published());
+                assert_eq!(options.retain_handling(), retain_handling);
+            }
+        }
+
+        let options = SubscriptionOptions::new(QoS::AtLeastOnce)
+            .with_retain_as_published(true)
+            .with_retain_handling(RetainHandling::DoNotSendRetained);
+        assert_eq!(options.bits(), 0b0010_1001);
+
+        assert!(SubscriptionOptions::from_bits(0b0100_0000).is_err());
+        assert!(SubscriptionOptions::from_bits(0b0000_0011).is_err());
+        assert!(SubscriptionOptions::from_bits(0b0011_0000).is_err());
+    }
+
+    #[test]
+    fn test_flags_debug() {
+        assert_eq!(
+            format!("{:?}", PublishFlags::new(false, QoS::AtLeastOnce, true)),
+            "PublishFlags { dup: false, qos: AtLeastOnce, retain: true }"
+        );
+    }
+}
@@ -1,169 +1,96 @@
-use crate::protocol::types::{EncodedStr, FixedHeader, VariableByteInteger};
-use crate::protocol::{Packet, PacketError, Parse, ParseError, QoS};
-use crate::traits::Writable;
-use crate::utils::Cursor;
+use crate::protocol::types::FixedHeader;
+use crate::protocol::{Packet, PacketError, PacketParse, Parse, ParseResult};
 
+#[cfg(feature = "auth")]
+pub mod auth;
 pub mod connect;
+pub mod disconnect;
+pub mod flags;
+pub mod ping;
 pub mod property;
+pub mod puback;
 pub mod publish;
-
-pub use self::connect::{ConnAck, ConnAckReason, Connect, ConnectProperty};
+pub mod pubrec;
+#[cfg(feature = "subscribe")]
+pub mod subscribe;
+#[cfg(feature = "unsubscribe")]
+pub mod unsubscribe;
+
+#[cfg(feature = "auth")]
+pub use self::auth::{Auth, AuthProperties, AuthProperty, AuthReason};
+pub use self::connect::{
+    ConnAck, ConnAckProperties, ConnAckReason, Connect, ConnectProperties, ConnectProperty, Will,
+    WillProperties, WillProperty,
+};
+pub use self::disconnect::{
+    Disconnect, DisconnectProperties, DisconnectProperty, DisconnectReason,
+};
+#[cfg(feature = "subscribe")]
+pub use self::flags::SubscriptionOptions;
+pub use self::flags::{ConnectFlags, PublishFlags};
+pub use self::ping::{PingReq, PingResp};
 pub use self::property::Property;
-pub use self::publish::Publish;
-
-#[derive(Debug)]
-pub struct Disconnect {}
-
-impl Packet for Disconnect {
-    const TYPE: u8 = 0b1110;
-}
-
-impl Writable for Disconnect {
-    type Error<E> = E;
-
-    fn size(&self) -> usize {
-        1
-    }
-
-    async fn write_to<T>(&self, mut sink: T) -> Result<(), T::Error>
-    where
-        T: embedded_io_async::Write,
-    {
-        // Reason Code:
-        sink.write_all(&[0x04]).await?;
-
-        Ok(())
-    }
-}
-
-#[derive(Debug)]
-pub struct Subscribe<'a> {
-    pub identifier: u16,
-    // TODO: properties
-    pub topics: &'a [TopicFilter<'a>],
-}
-
-impl Packet for Subscribe<'_> {
-    const TYPE: u8 = 0b1000;
-
-    fn flags(&self) -> u8 {
-        0b0010
-    }
-}
-
-impl Writable for Subscribe<'_> {
-    type Error<E> = E;
-
-    fn size(&self) -> usize {
-        self.identifier.size() + 1 + self.topics.size()
-    }
-
-    async fn write_to<T>(&self, mut sink: T) -> Result<(), T::Error>
-    where
-        T: embedded_io_async::Write,
-    {
-        // Identifier:
-        self.identifier.write_to(&mut sink).await?;
-
-        // Properties:
-        VariableByteInteger::from(0u8).write_to(&mut sink).await?;
-
-        // Payload:
-        self.topics.write_to(&mut sink).await?;
-
-        Ok(())
-    }
-}
-
-/// A topic filter, used to [subscribe](Subscribe) to topics.
+pub use self::puback::{AckProperties, AckProperty, PubAck, PubAckReason};
+pub use self::publish::{Payload, Publish, PublishProperties, PublishProperty};
+pub use self::pubrec::{PubComp, PubCompReason, PubRec, PubRecReason, PubRel};
+#[cfg(feature = "subscribe")]
+pub use self::subscribe::{
+    GrantedQoS, RetainHandling, SubAck, SubAckReason, Subscribe, SubscribeProperty, TopicFilter,
+};
+#[cfg(feature = "unsubscribe")]
+pub use self::unsubscribe::{UnsubAck, UnsubAckReason, Unsubscribe};
+
+/// Any packet which can be received by the client.
 ///
-/// Spec: [3.8.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901168).
+/// Returned from [`decode`](crate::protocol::decode).
 #[derive(Debug)]
-pub struct TopicFilter<'a> {
-    /// The name of the topic.
-    pub name: &'a str,
-    /// The Quality of Service level.
-    pub qos: QoS,
-    /// No Local option.
-    ///
-    /// If the value is true, Application Messages MUST NOT be forwarded to a connection with a
-    /// client id equal to the client id of the publishing connection.
-    pub no_local: bool,
-    /// Retain as Published option.
-    ///
-    /// If `true`, Application Messages forwarded using this subscription keep the RETAIN flag they
-    /// were published with. If `false`, Application Messages forwarded using this subscription have
-    /// the RETAIN flag set to `false`. Retained messages sent when the subscription is established have
-    /// the RETAIN flag set to `true.
-    pub retain_as_published: bool,
-    /// Retain handling.
-    ///
-    /// This option specifies whether retained messages are sent when the subscription is established.
-    /// This does not affect the sending of retained messages at any point after the subscribe.
-    /// If there are no retained messages matching the Topic Filter, all of these values act the same.
-    pub retain: RetainHandling,
-}
-
-/// [Topic filter](TopicFilter::retain) retain handling.
-#[derive(Debug, Default)]
-#[repr(u8)]
-pub enum RetainHandling {
-    /// Send retained messages at the time of the subscribe.
-    #[default]
-    SendRetained = 0,
-    /// Send retained messages at subscribe only if the subscription does not currently exist.
-    SendRetainedOnNewSubscription = 1,
-    /// Do not send retained messages at the time of the subscribe.
-    DoNotSendRetained = 3,
+pub enum AnyPacket<'a> {
+    #[cfg(feature = "auth")]
+    Auth(Auth<'a>),
+    ConnAck(ConnAck<'a>),
+    Disconnect(Disconnect<'a>),
+    PingResp(PingResp),
+    PubAck(PubAck<'a>),
+    PubComp(PubComp),
+    PubRec(PubRec),
+    PubRel(PubRel),
+    Publish(Publish<'a>),
+    #[cfg(feature = "subscribe")]
+    SubAck(SubAck<'a>),
+    #[cfg(feature = "unsubscribe")]
+    UnsubAck(UnsubAck<'a>),
 }
 
-impl Writable for TopicFilter<'_> {
-    type Error<E> = E;
-
-    fn size(&self) -> usize {
-        EncodedStr(self.name).size() + 1
-    }
-
-    async fn write_to<S>(&self, mut sink: S) -> Result<(), Self::Error<S::Error>>
-    where
-        S: embedded_io_async::Write,
-    {
-        EncodedStr(self.name).write_to(&mut sink).await?;
-
-        let options = (self.retain_as_published as u8) << 4
-            | u8::from(self.retain_as_published) << 3
-            | u8::from(self.no_local) << 2
-            | u8::from(self.qos);
-        options.write_to(&mut sink).await?;
-
-        Ok(())
+impl<'a> PacketParse<'a> for AnyPacket<'a> {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let (_, fixed_header) = FixedHeader::parse(data)?;
+
+        match fixed_header.ty() {
+            #[cfg(feature = "auth")]
+            <Auth>::TYPE => parse_as(data, Self::Auth),
+            ConnAck::TYPE => parse_as(data, Self::ConnAck),
+            <Disconnect>::TYPE => parse_as(data, Self::Disconnect),
+            PingResp::TYPE => parse_as(data, Self::PingResp),
+            <PubAck>::TYPE => parse_as(data, Self::PubAck),
+            PubComp::TYPE => parse_as(data, Self::PubComp),
+            PubRec::TYPE => parse_as(data, Self::PubRec),
+            PubRel::TYPE => parse_as(data, Self::PubRel),
+            <Publish>::TYPE => parse_as(data, Self::Publish),
+            #[cfg(feature = "subscribe")]
+            SubAck::TYPE => parse_as(data, Self::SubAck),
+            #[cfg(feature = "unsubscribe")]
+            UnsubAck::TYPE => parse_as(data, Self::UnsubAck),
+            ty => Err(PacketError::UnsupportedPacketType(ty).into()),
+        }
     }
 }
 
-#[derive(Debug)]
-pub struct SubAck {}
-
-impl Packet for SubAck {
-    const TYPE: u8 = 0b1001;
-}
-
-impl<'a> Parse<'a> for SubAck {
-    type Error = PacketError;
-
-    fn parse(data: &[u8]) -> Result<(usize, Self), ParseError<Self::Error>> {
-        let mut cursor = Cursor::new(data);
-
-        let fixed_header = cursor.read::<FixedHeader>()?;
-        if fixed_header.ty() != Self::TYPE {
-            return Err(PacketError::InvalidPacketType {
-                expected: Self::TYPE,
-                actual: fixed_header.ty(),
-            }
-            .into());
-        }
-
-        let _ = cursor.read_slice(fixed_header.length().as_u32() as usize)?;
-
-        Ok((cursor.position(), Self {}))
-    }
+// The frame is validated by the caller, see [`Parse`].
+fn parse_as<'a, T, F>(data: &'a [u8], f: F) -> ParseResult<(usize, AnyPacket<'a>)>
+where
+    T: PacketParse<'a>,
+    F: FnOnce(T) -> AnyPacket<'a>,
+{
+    let (len, packet) = <T as PacketParse>::parse(data)?;
+    Ok((len, f(packet)))
 }
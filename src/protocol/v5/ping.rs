@@ -0,0 +1,71 @@
+use crate::log::LogPacket;
+use crate::protocol::utils::CursorExt;
+use crate::protocol::{Packet, PacketParse, ParseResult};
+use crate::traits::Writable;
+use crate::utils::Cursor;
+
+/// Keeps the connection alive, while no other packets are sent.
+///
+/// Spec: [3.12](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901195)
+#[derive(Debug)]
+pub struct PingReq;
+
+impl Packet for PingReq {
+    const TYPE: u8 = 0b1100;
+}
+
+impl LogPacket for PingReq {}
+
+impl Writable for PingReq {
+    type Error<E> = E;
+
+    fn size(&self) -> usize {
+        0
+    }
+
+    async fn write_to<T>(&self, _sink: T) -> Result<(), T::Error>
+    where
+        T: embedded_io_async::Write,
+    {
+        Ok(())
+    }
+}
+
+/// Response of the server to a [`PingReq`].
+///
+/// Spec: [3.13](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901200)
+#[derive(Debug)]
+pub struct PingResp;
+
+impl Packet for PingResp {
+    const TYPE: u8 = 0b1101;
+}
+
+impl LogPacket for PingResp {}
+
+impl<'a> PacketParse<'a> for PingResp {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let mut cursor = Cursor::new(data);
+        cursor.read_fixed_header::<Self>()?;
+
+        Ok((cursor.position(), Self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{PacketError, Parse, ParseError};
+
+    #[test]
+    fn test_ping_resp_parse() {
+        assert!(matches!(
+            <PingResp as Parse>::parse(&[0xd0, 0x00]),
+            Ok((2, PingResp))
+        ));
+        assert!(matches!(
+            <PingResp as Parse>::parse(&[0xd0, 0x01, 0x00]),
+            Err(ParseError::Error(PacketError::LengthMismatch))
+        ));
+    }
+}
@@ -1,17 +1,71 @@
-use crate::{protocol::types::VariableByteInteger, traits::Writable};
+use core::marker::PhantomData;
 
-pub trait Property {}
+use crate::protocol::types::{BinaryData, EncodedStr, VariableByteInteger};
+use crate::protocol::{PacketError, ParseError, ParseResult, WriteError};
+use crate::traits::Writable;
+use crate::utils::Cursor;
+
+pub trait Property: Writable {
+    /// Converts an error from writing the property into a [`WriteError`].
+    fn into_write_error<E>(err: Self::Error<E>) -> WriteError<E>;
+}
 
 pub struct Properties<'a, T>(pub &'a [T]);
 
+impl<'a, T> Properties<'a, T> {
+    /// Parses a property block from the cursor, the counterpart to writing [`Properties`].
+    ///
+    /// All properties are validated, the returned iterator yields the parsed properties.
+    pub(crate) fn parse(cursor: &mut Cursor<'a>) -> ParseResult<ParsedProperties<'a, T>>
+    where
+        T: ParseProperty<'a>,
+    {
+        let raw = RawProperties::read(cursor)?;
+        for property in raw {
+            let (id, value) = property?;
+            T::from_raw(id, value).ok_or(PacketError::ProtocolError)?;
+        }
+
+        Ok(ParsedProperties {
+            raw,
+            _property: PhantomData,
+        })
+    }
+}
+
 impl<T> Writable for Properties<'_, T>
 where
-    T: Writable,
+    T: Property,
+{
+    type Error<E> = WriteError<E>;
+
+    fn size(&self) -> usize {
+        PropertyChunks(&[self.0]).size()
+    }
+
+    async fn write_to<S>(&self, sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        PropertyChunks(&[self.0]).write_to(sink).await
+    }
+}
+
+/// Properties from multiple slices, which are written as a single property block.
+pub struct PropertyChunks<'a, T>(pub &'a [&'a [T]]);
+
+impl<T> Writable for PropertyChunks<'_, T>
+where
+    T: Property,
 {
-    type Error<E> = T::Error<E>;
+    type Error<E> = WriteError<E>;
 
     fn size(&self) -> usize {
-        let s = self.0.size();
+        if !cfg!(feature = "properties") {
+            return 1;
+        }
+
+        let s = self.0.iter().map(|chunk| chunk.size()).sum::<usize>();
         VariableByteInteger::try_from(s).ok().size() + s
     }
 
@@ -19,15 +73,387 @@ where
     where
         S: embedded_io_async::Write,
     {
-        // TODO: error handling
-        VariableByteInteger::try_from(self.0.size())
-            .unwrap()
-            .write_to(&mut sink)
-            .await
-            .unwrap();
+        // Without property support, always write an empty property block.
+        if !cfg!(feature = "properties") {
+            return Ok(0u8.write_to(&mut sink).await?);
+        }
+
+        let s = self.0.iter().map(|chunk| chunk.size()).sum::<usize>();
+        let length = VariableByteInteger::try_from(s).map_err(WriteError::Overflow)?;
+        length.write_to(&mut sink).await?;
 
-        self.0.write_to(&mut sink).await?;
+        for property in self.0.iter().flat_map(|chunk| chunk.iter()) {
+            property
+                .write_to(&mut sink)
+                .await
+                .map_err(T::into_write_error)?;
+        }
 
         Ok(())
     }
 }
+
+/// A property value, as encoded in the protocol.
+///
+/// Spec: [2.2.2.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901029)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PropertyValue<'a> {
+    Byte(u8),
+    TwoByteInteger(u16),
+    FourByteInteger(u32),
+    VariableByteInteger(u32),
+    Str(&'a str),
+    BinaryData(&'a [u8]),
+    StrPair(&'a str, &'a str),
+}
+
+/// A property which can be parsed from a received packet.
+pub(crate) trait ParseProperty<'a>: Sized {
+    /// Converts a received property, returns `None` if the property is not valid in the packet.
+    fn from_raw(id: u8, value: PropertyValue<'a>) -> Option<Self>;
+}
+
+/// Iterator over validated properties, returned by [`Properties::parse`].
+pub(crate) struct ParsedProperties<'a, T> {
+    raw: RawProperties<'a>,
+    _property: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> ParsedProperties<'a, T> {
+    /// Returns the properties which were not yet iterated, without the property length.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.raw.as_bytes()
+    }
+}
+
+impl<T> Clone for ParsedProperties<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ParsedProperties<'_, T> {}
+
+impl<T> Default for ParsedProperties<'_, T> {
+    fn default() -> Self {
+        Self {
+            raw: RawProperties::default(),
+            _property: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iterator for ParsedProperties<'a, T>
+where
+    T: ParseProperty<'a>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, value) = self.raw.next()?.ok()?;
+        T::from_raw(id, value)
+    }
+}
+
+/// Iterator over the properties of a received packet.
+///
+/// Yields the property identifier and its value. Iteration stops after the first error.
+///
+/// Without the `properties` feature, properties are skipped and nothing is yielded.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct RawProperties<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> RawProperties<'a> {
+    /// Reads the property length followed by the properties from the cursor.
+    pub fn read(cursor: &mut Cursor<'a>) -> ParseResult<Self> {
+        let length = cursor
+            .read::<VariableByteInteger>()
+            .map_err(|err| err.map(|_| PacketError::ProtocolError))?;
+        let data = cursor.read_slice(length.as_u32() as usize)?;
+
+        Ok(Self { data })
+    }
+
+    /// Returns the properties which were not yet iterated, without the property length.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    fn read_next(&mut self) -> ParseResult<(u8, PropertyValue<'a>)> {
+        let mut cursor = Cursor::new(self.data);
+
+        let id = cursor.read_u8()?;
+        let value = match id {
+            0x01 | 0x17 | 0x19 | 0x24 | 0x25 | 0x28 | 0x29 | 0x2a => {
+                PropertyValue::Byte(cursor.read_u8()?)
+            }
+            0x13 | 0x21 | 0x22 | 0x23 => PropertyValue::TwoByteInteger(cursor.read_u16_be()?),
+            0x02 | 0x11 | 0x18 | 0x27 => PropertyValue::FourByteInteger(cursor.read_u32_be()?),
+            0x0b => {
+                let v = cursor
+                    .read::<VariableByteInteger>()
+                    .map_err(|err| err.map(|_| PacketError::ProtocolError))?;
+                PropertyValue::VariableByteInteger(v.as_u32())
+            }
+            0x03 | 0x08 | 0x12 | 0x15 | 0x1a | 0x1c | 0x1f => {
+                let EncodedStr(v) = cursor.read()?;
+                PropertyValue::Str(v)
+            }
+            0x09 | 0x16 => {
+                let BinaryData(v) = cursor.read()?;
+                PropertyValue::BinaryData(v)
+            }
+            0x26 => {
+                let EncodedStr(key) = cursor.read()?;
+                let EncodedStr(value) = cursor.read()?;
+                PropertyValue::StrPair(key, value)
+            }
+            _ => return Err(PacketError::ProtocolError.into()),
+        };
+
+        self.data = cursor.remaining();
+
+        Ok((id, value))
+    }
+}
+
+impl<'a> Iterator for RawProperties<'a> {
+    type Item = Result<(u8, PropertyValue<'a>), PacketError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() || !cfg!(feature = "properties") {
+            return None;
+        }
+
+        match self.read_next() {
+            Ok(property) => Some(Ok(property)),
+            Err(err) => {
+                self.data = &[];
+                // The property block is complete, missing data means the packet is malformed.
+                Some(Err(match err {
+                    ParseError::NotEnoughData => PacketError::ProtocolError,
+                    ParseError::Error(err) => err,
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "properties"))]
+mod tests {
+    use super::*;
+    use crate::protocol::v5::{ConnectProperty, DisconnectProperty, PublishProperty, WillProperty};
+    use crate::utils::CountingSink;
+
+    /// A property which claims to be of a certain size, without writing anything.
+    struct Synthetic(usize);
+
+    impl Writable for Synthetic {
+        type Error<E> = E;
+
+        fn size(&self) -> usize {
+            self.0
+        }
+
+        async fn write_to<S>(&self, _sink: S) -> Result<(), Self::Error<S::Error>>
+        where
+            S: embedded_io_async::Write,
+        {
+            Ok(())
+        }
+    }
+
+    impl Property for Synthetic {
+        fn into_write_error<E>(err: E) -> WriteError<E> {
+            WriteError::Write(err)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_properties_length_overflow() {
+        // The largest length a variable byte integer can encode.
+        let properties = [Synthetic(0x0fff_fff0), Synthetic(0x0f)];
+        let result = Properties(&properties)
+            .write_to(CountingSink::default())
+            .await;
+        assert!(result.is_ok());
+
+        let properties = [Synthetic(0x0fff_fff0), Synthetic(0x10)];
+        let result = Properties(&properties)
+            .write_to(CountingSink::default())
+            .await;
+        assert!(matches!(result, Err(WriteError::Overflow(_))));
+    }
+
+    /// Writes the properties and parses them again with [`Properties::parse`].
+    macro_rules! assert_round_trip {
+        ($ty:ident, $properties:expr) => {{
+            let properties = $properties;
+
+            let mut data = Vec::new();
+            Properties(&properties).write_to(&mut data).await.unwrap();
+            assert_eq!(data.len(), Properties(&properties).size());
+
+            let mut cursor = Cursor::new(&data);
+            let parsed = Properties::<$ty<'_>>::parse(&mut cursor).unwrap();
+            assert_eq!(cursor.position(), data.len());
+            assert!(parsed.eq(properties));
+        }};
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_connect_properties() {
+        assert_round_trip!(
+            ConnectProperty,
+            [
+                ConnectProperty::SessionExpiryInterval(300),
+                ConnectProperty::ReceiveMaximum(10),
+                ConnectProperty::MaximumPacketSize(1024),
+                ConnectProperty::TopicAliasMaximum(5),
+                ConnectProperty::RequestResponseInformation(1),
+                ConnectProperty::RequestProblemInformation(false),
+                ConnectProperty::UserProperty {
+                    key: "k",
+                    value: "v",
+                },
+                ConnectProperty::AuthenticationMethod("SCRAM-SHA-1"),
+                ConnectProperty::AuthenticationData(b"\x00\xff"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_will_properties() {
+        assert_round_trip!(
+            WillProperty,
+            [
+                WillProperty::WillDelay(30),
+                WillProperty::PayloadFormatIndicator(1),
+                WillProperty::MessageExpiryInterval(60),
+                WillProperty::ContentType("text/plain"),
+                WillProperty::ResponseTopic("r"),
+                WillProperty::CorrelationData(b"c"),
+                WillProperty::UserProperty {
+                    key: "k",
+                    value: "v",
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_publish_properties() {
+        assert_round_trip!(
+            PublishProperty,
+            [
+                PublishProperty::PayloadFormatIndicator(0),
+                PublishProperty::MessageExpiryInterval(60),
+                PublishProperty::TopicAlias(3),
+                PublishProperty::ResponseTopic("r"),
+                PublishProperty::CorrelationData(b"c"),
+                PublishProperty::UserProperty {
+                    key: "k",
+                    value: "v",
+                },
+                PublishProperty::SubscriptionIdentifier(0x0fff_ffff),
+                PublishProperty::ContentType("application/json"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_disconnect_properties() {
+        assert_round_trip!(
+            DisconnectProperty,
+            [
+                DisconnectProperty::SessionExpiryInterval(0),
+                DisconnectProperty::ReasonString("bye"),
+                DisconnectProperty::UserProperty {
+                    key: "k",
+                    value: "v",
+                },
+                DisconnectProperty::ServerReference("other.example.com"),
+            ]
+        );
+    }
+
+    #[cfg(feature = "auth")]
+    #[tokio::test]
+    async fn test_round_trip_auth_properties() {
+        use crate::protocol::v5::AuthProperty;
+
+        assert_round_trip!(
+            AuthProperty,
+            [
+                AuthProperty::AuthenticationMethod("SCRAM-SHA-1"),
+                AuthProperty::AuthenticationData(b"\x00\xff"),
+                AuthProperty::ReasonString("continue"),
+                AuthProperty::UserProperty {
+                    key: "k",
+                    value: "v",
+                },
+            ]
+        );
+    }
+
+    #[cfg(feature = "subscribe")]
+    #[tokio::test]
+    async fn test_round_trip_subscribe_properties() {
+        use crate::protocol::v5::SubscribeProperty;
+
+        assert_round_trip!(
+            SubscribeProperty,
+            [
+                SubscribeProperty::SubscriptionIdentifier(128),
+                SubscribeProperty::UserProperty {
+                    key: "k",
+                    value: "v",
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_property() {
+        // A topic alias, which is not valid in a DISCONNECT.
+        let data = [0x03, 0x23, 0x00, 0x01];
+        let mut cursor = Cursor::new(&data);
+        assert!(Properties::<DisconnectProperty<'_>>::parse(&mut cursor).is_err());
+    }
+
+    #[test]
+    fn test_raw_properties() {
+        let data = [
+            0x0d, 0x13, 0x00, 0x3c, 0x26, 0x00, 0x01, b'k', 0x00, 0x01, b'v', 0x0b, 0x80, 0x01,
+        ];
+        let mut cursor = Cursor::new(&data);
+
+        let properties = RawProperties::read(&mut cursor).unwrap();
+        assert_eq!(cursor.position(), data.len());
+
+        let properties = properties.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(
+            properties,
+            [
+                (0x13, PropertyValue::TwoByteInteger(60)),
+                (0x26, PropertyValue::StrPair("k", "v")),
+                (0x0b, PropertyValue::VariableByteInteger(128)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_raw_properties_malformed() {
+        let data = [0x02, 0x13, 0x00];
+        let mut cursor = Cursor::new(&data);
+
+        let mut properties = RawProperties::read(&mut cursor).unwrap();
+        assert!(matches!(
+            properties.next(),
+            Some(Err(PacketError::ProtocolError))
+        ));
+        assert!(properties.next().is_none());
+    }
+}
@@ -0,0 +1,346 @@
+use core::fmt;
+
+use crate::log::{self, LogPacket};
+use crate::protocol::types::{PacketId, VariableByteInteger};
+use crate::protocol::utils::CursorExt;
+use crate::protocol::v5::property::{ParseProperty, ParsedProperties, Properties, PropertyValue};
+use crate::protocol::{Packet, PacketError, PacketParse, ParseResult, WriteError};
+use crate::traits::Writable;
+use crate::utils::Cursor;
+
+#[cfg(doc)]
+use crate::protocol::v5::Publish;
+
+/// Acknowledgement of a QoS 1 [`Publish`].
+///
+/// Spec: [3.4](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901121)
+#[derive(Debug)]
+pub struct PubAck<'a> {
+    /// The identifier of the acknowledged [`Publish`].
+    pub identifier: PacketId,
+    pub reason: PubAckReason,
+    pub properties: AckProperties<'a>,
+}
+
+impl Packet for PubAck<'_> {
+    const TYPE: u8 = 0b0100;
+}
+
+impl LogPacket for PubAck<'_> {}
+
+impl<'a> PacketParse<'a> for PubAck<'a> {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let (len, identifier, reason, properties) =
+            parse_ack::<Self, _>(data, PubAckReason::Success)?;
+        Ok((
+            len,
+            Self {
+                identifier,
+                reason,
+                properties,
+            },
+        ))
+    }
+}
+
+impl Writable for PubAck<'_> {
+    type Error<E> = WriteError<E>;
+
+    fn size(&self) -> usize {
+        let properties = self.properties.as_bytes().len();
+        // The reason may be omitted for a success without properties, the property length
+        // if there are no properties.
+        match (self.reason, properties) {
+            (PubAckReason::Success, 0) => 2,
+            (_, 0) => 3,
+            (_, len) => 3 + VariableByteInteger::try_from(len).ok().size() + len,
+        }
+    }
+
+    async fn write_to<S>(&self, mut sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        let properties = self.properties.as_bytes();
+
+        // Identifier:
+        self.identifier.write_to(&mut sink).await?;
+
+        // Reason Code:
+        if self.reason != PubAckReason::Success || !properties.is_empty() {
+            (self.reason as u8).write_to(&mut sink).await?;
+        }
+
+        // Properties:
+        if !properties.is_empty() {
+            let len =
+                VariableByteInteger::try_from(properties.len()).map_err(WriteError::Overflow)?;
+            len.write_to(&mut sink).await?;
+            sink.write_all(properties).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses an acknowledgement of a publish, made up of the packet identifier, a reason and
+/// properties.
+///
+/// The reason and properties may be omitted, for a success without properties.
+pub(super) fn parse_ack<'a, T, R>(
+    data: &'a [u8],
+    success: R,
+) -> ParseResult<(usize, PacketId, R, AckProperties<'a>)>
+where
+    T: Packet,
+    R: TryFrom<u8, Error = PacketError>,
+{
+    let mut cursor = Cursor::new(data);
+
+    let fixed_header = cursor.read_fixed_header::<T>()?;
+    let packet_length = fixed_header.length().as_u32() as usize;
+
+    let identifier: PacketId = cursor.read()?;
+
+    let reason = match packet_length {
+        2 => success,
+        _ => {
+            let reason = cursor.read_u8()?;
+            R::try_from(reason).inspect_err(|_| {
+                log::debug!("invalid publish acknowledgement reason: {reason:#x}");
+            })?
+        }
+    };
+
+    let properties = match packet_length > 3 {
+        true => AckProperties::read(&mut cursor)?,
+        false => AckProperties::default(),
+    };
+
+    Ok((cursor.position(), identifier, reason, properties))
+}
+
+/// Properties of an acknowledgement, like a [`PubAck`] or a [`SubAck`](super::SubAck).
+///
+/// Spec: [3.4.2.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901125)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckProperty<'a> {
+    /// A human readable reason, intended for diagnostics.
+    ///
+    /// Spec: [3.4.2.2.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901127)
+    ReasonString(&'a str),
+    /// A custom property, e.g. diagnostics or routing metadata of the server.
+    ///
+    /// Spec: [3.4.2.2.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901128)
+    UserProperty { key: &'a str, value: &'a str },
+}
+
+impl<'a> ParseProperty<'a> for AckProperty<'a> {
+    fn from_raw(id: u8, value: PropertyValue<'a>) -> Option<Self> {
+        let property = match (id, value) {
+            (0x1f, PropertyValue::Str(v)) => Self::ReasonString(v),
+            (0x26, PropertyValue::StrPair(key, value)) => Self::UserProperty { key, value },
+            _ => return None,
+        };
+
+        Some(property)
+    }
+}
+
+/// Properties of a received acknowledgement.
+///
+/// Properties are validated when the packet is parsed.
+#[derive(Clone, Copy, Default)]
+pub struct AckProperties<'a>(ParsedProperties<'a, AckProperty<'a>>);
+
+impl fmt::Debug for AckProperties<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(*self).finish()
+    }
+}
+
+impl<'a> AckProperties<'a> {
+    pub(super) fn read(cursor: &mut Cursor<'a>) -> ParseResult<Self> {
+        Properties::parse(cursor).map(Self)
+    }
+
+    /// Returns the encoded properties, without the property length.
+    pub(super) fn as_bytes(&self) -> &'a [u8] {
+        self.0.as_bytes()
+    }
+
+    /// Returns the [Reason String](AckProperty::ReasonString).
+    pub fn reason_string(&self) -> Option<&'a str> {
+        self.into_iter().find_map(|property| match property {
+            AckProperty::ReasonString(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// Returns the [User Properties](AckProperty::UserProperty) as key value pairs, in the
+    /// order they were sent.
+    pub fn user_properties(&self) -> impl Iterator<Item = (&'a str, &'a str)> + use<'a> {
+        self.into_iter().filter_map(|property| match property {
+            AckProperty::UserProperty { key, value } => Some((key, value)),
+            _ => None,
+        })
+    }
+
+    /// Returns the value of the first user property with the key `key`.
+    pub fn user_property(&self, key: &str) -> Option<&'a str> {
+        self.user_properties()
+            .find_map(|(k, value)| (k == key).then_some(value))
+    }
+}
+
+impl<'a> Iterator for AckProperties<'a> {
+    type Item = AckProperty<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+/// The reason of a [`PubAck`] or a [`PubRec`](super::PubRec).
+///
+/// Spec: [3.4.2.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901124)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PubAckReason {
+    /// The message is accepted. Publication of the QoS 1 message proceeds.
+    Success = 0x00,
+    /// The message is accepted but there are no subscribers.
+    NoMatchingSubscribers = 0x10,
+    /// The receiver does not accept the publish but either does not want to reveal the reason,
+    /// or it does not match one of the other values.
+    UnspecifiedError = 0x80,
+    /// The PUBLISH is valid but the receiver is not willing to accept it.
+    ImplementationSpecificError = 0x83,
+    /// The PUBLISH is not authorized.
+    NotAuthorized = 0x87,
+    /// The Topic Name is not malformed, but is not accepted by this Client or Server.
+    TopicNameInvalid = 0x90,
+    /// The Packet Identifier is already in use.
+    PacketIdentifierInUse = 0x91,
+    /// An implementation or administrative imposed limit has been exceeded.
+    QuotaExceeded = 0x97,
+    /// The payload format does not match the specified Payload Format Indicator.
+    PayloadFormatInvalid = 0x99,
+}
+
+impl PubAckReason {
+    /// Returns whether the message was accepted.
+    pub fn is_success(&self) -> bool {
+        (*self as u8) < 0x80
+    }
+}
+
+impl TryFrom<u8> for PubAckReason {
+    type Error = PacketError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let result = match value {
+            0x00 => Self::Success,
+            0x10 => Self::NoMatchingSubscribers,
+            0x80 => Self::UnspecifiedError,
+            0x83 => Self::ImplementationSpecificError,
+            0x87 => Self::NotAuthorized,
+            0x90 => Self::TopicNameInvalid,
+            0x91 => Self::PacketIdentifierInUse,
+            0x97 => Self::QuotaExceeded,
+            0x99 => Self::PayloadFormatInvalid,
+            _ => return Err(PacketError::ProtocolError),
+        };
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Parse;
+
+    #[test]
+    fn test_pub_ack_reason_matches_value() {
+        for i in 0..u8::MAX {
+            let Ok(reason) = PubAckReason::try_from(i) else {
+                continue;
+            };
+
+            assert_eq!(reason as u8, i);
+        }
+    }
+
+    #[test]
+    fn test_pub_ack_parse() {
+        // Short form, without reason and properties.
+        let data = [0x40, 0x02, 0x00, 0x07];
+        let (len, ack) = <PubAck as Parse>::parse(&data).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(ack.identifier.get(), 7);
+        assert_eq!(ack.reason, PubAckReason::Success);
+
+        // Without properties.
+        let data = [0x40, 0x03, 0x00, 0x07, 0x10];
+        let (_, ack) = <PubAck as Parse>::parse(&data).unwrap();
+        assert_eq!(ack.reason, PubAckReason::NoMatchingSubscribers);
+
+        // With a reason string.
+        let data = [0x40, 0x08, 0x00, 0x07, 0x97, 0x04, 0x1f, 0x00, 0x01, b'q'];
+        let (len, ack) = <PubAck as Parse>::parse(&data).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(ack.reason, PubAckReason::QuotaExceeded);
+        #[cfg(feature = "properties")]
+        assert_eq!(ack.properties.reason_string(), Some("q"));
+
+        let data = [0x40, 0x03, 0x00, 0x07, 0x12];
+        assert!(<PubAck as Parse>::parse(&data).is_err());
+    }
+
+    #[cfg(feature = "properties")]
+    #[test]
+    fn test_pub_ack_user_properties() {
+        #[rustfmt::skip]
+        let data = [
+            0x40, 0x12, 0x00, 0x07, 0x00, 0x0e,
+            0x26, 0x00, 0x01, b'a', 0x00, 0x01, b'1',
+            0x26, 0x00, 0x01, b'b', 0x00, 0x01, b'2',
+        ];
+        let (len, ack) = <PubAck as Parse>::parse(&data).unwrap();
+        assert_eq!(len, data.len());
+        assert!(
+            ack.properties
+                .user_properties()
+                .eq([("a", "1"), ("b", "2")])
+        );
+        assert_eq!(ack.properties.user_property("b"), Some("2"));
+        assert_eq!(ack.properties.reason_string(), None);
+
+        // A topic alias is not valid in an acknowledgement.
+        let data = [0x40, 0x07, 0x00, 0x07, 0x00, 0x03, 0x23, 0x00, 0x01];
+        assert!(<PubAck as Parse>::parse(&data).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pub_ack_write() {
+        for data in [
+            &[0x40, 0x02, 0x00, 0x07][..],
+            &[0x40, 0x03, 0x00, 0x07, 0x10],
+            &[0x40, 0x08, 0x00, 0x07, 0x97, 0x04, 0x1f, 0x00, 0x01, b'q'],
+            &[0x40, 0x04, 0x00, 0x07, 0x00, 0x00],
+        ] {
+            let (_, ack) = <PubAck as Parse>::parse(data).unwrap();
+
+            let mut sink = Vec::new();
+            ack.write_to(&mut sink).await.unwrap();
+            assert_eq!(sink.len(), ack.size());
+            // The property length of empty properties is omitted.
+            let body = match data.len() {
+                6 => &data[2..4],
+                _ => &data[2..],
+            };
+            assert_eq!(sink, body);
+        }
+    }
+}
@@ -1,22 +1,62 @@
 use core::fmt;
 
-use crate::protocol::types::{EncodedStr, VariableByteInteger};
+use crate::log::{LogPacket, RedactedBytes};
+use crate::protocol::types::{BinaryData, EncodedStr, PacketId, VariableByteInteger};
 use crate::protocol::utils::CursorExt;
-use crate::protocol::{Packet, PacketError, Parse, ParseError, QoS};
+use crate::protocol::v5::Property;
+use crate::protocol::v5::flags::PublishFlags;
+use crate::protocol::v5::property::{
+    ParseProperty, ParsedProperties, Properties, PropertyChunks, PropertyValue,
+};
+use crate::protocol::{Packet, PacketError, PacketParse, ParseResult, QoS, WriteError};
 use crate::traits::Writable;
-use crate::utils::Cursor;
+use crate::utils::{Cursor, write_many};
 
-pub struct Publish<'a> {
+/// A PUBLISH packet.
+///
+/// Received packets carry their [`PublishProperties`], packets which are sent carry a slice of
+/// [`PublishProperty`].
+pub struct Publish<'a, P = &'a [u8], Props = PublishProperties<'a>> {
     pub dup: bool,
     pub qos: QoS,
     pub retain: bool,
-    pub identifier: Option<u16>,
+    pub identifier: Option<PacketId>,
     pub topic: &'a str,
-    pub payload: &'a [u8],
+    pub properties: Props,
+    pub payload: P,
 }
 
-impl fmt::Debug for Publish<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// The payload of a [`Publish`] packet.
+///
+/// A payload may be made up of multiple chunks, which are written in sequence. This allows
+/// sending a payload from multiple scattered slices without copying them into a single buffer.
+pub trait Payload {
+    /// Returns all chunks of the payload.
+    fn chunks(&self) -> impl Iterator<Item = &[u8]>;
+
+    /// Returns the total size of the payload in bytes.
+    fn size(&self) -> usize {
+        self.chunks().map(<[u8]>::len).sum()
+    }
+}
+
+impl Payload for &[u8] {
+    fn chunks(&self) -> impl Iterator<Item = &[u8]> {
+        core::iter::once(*self)
+    }
+}
+
+impl Payload for &[&[u8]] {
+    fn chunks(&self) -> impl Iterator<Item = &[u8]> {
+        self.iter().copied()
+    }
+}
+
+impl<P, Props> Publish<'_, P, Props>
+where
+    P: Payload,
+{
+    fn fmt_debug(&self, f: &mut fmt::Formatter<'_>, payload: bool) -> fmt::Result {
         write!(f, "Publish {{ ")?;
         write!(f, "Q{} ", self.qos as u8)?;
         write!(f, "D{} ", self.dup as u8)?;
@@ -26,9 +66,15 @@ impl fmt::Debug for Publish<'_> {
             None => write!(f, "Id:- ")?,
         };
         write!(f, "| {:?}: ", self.topic)?;
-        match str::from_utf8(self.payload) {
-            Ok(payload) => write!(f, "{payload:?} ")?,
-            Err(_) => write!(f, "{:?} ", self.payload)?,
+        if payload {
+            for chunk in self.payload.chunks() {
+                match str::from_utf8(chunk) {
+                    Ok(s) => write!(f, "{s:?} ")?,
+                    Err(_) => write!(f, "{chunk:?} ")?,
+                }
+            }
+        } else {
+            write!(f, "{:?} ", RedactedBytes(self.payload.size()))?;
         }
         write!(f, "}}")?;
 
@@ -36,26 +82,40 @@ impl fmt::Debug for Publish<'_> {
     }
 }
 
-impl Packet for Publish<'_> {
+impl<P, Props> fmt::Debug for Publish<'_, P, Props>
+where
+    P: Payload,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_debug(f, true)
+    }
+}
+
+impl<P, Props> LogPacket for Publish<'_, P, Props>
+where
+    P: Payload,
+{
+    fn fmt_without_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_debug(f, false)
+    }
+}
+
+impl<P, Props> Packet for Publish<'_, P, Props> {
     const TYPE: u8 = 0b0011;
 
     fn flags(&self) -> u8 {
-        (self.dup as u8) << 3 | (self.qos as u8) << 1 | self.retain as u8
+        PublishFlags::new(self.dup, self.qos, self.retain).bits()
     }
 }
 
-impl<'a> Parse<'a> for Publish<'a> {
-    type Error = PacketError;
-
-    fn parse(data: &'a [u8]) -> Result<(usize, Self), ParseError<Self::Error>> {
+impl<'a> PacketParse<'a> for Publish<'a> {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
         let mut cursor = Cursor::new(data);
 
         let fixed_header = cursor.read_fixed_header::<Self>()?;
 
-        let dup = fixed_header.flags() & 0b1000 > 0;
-        let qos = QoS::try_from((fixed_header.flags() >> 1) & 0b11)
-            .map_err(|_| PacketError::ProtocolError)?;
-        let retain = fixed_header.flags() & 0b0001 > 0;
+        let flags = PublishFlags::from_bits(fixed_header.flags())?;
+        let (dup, qos, retain) = (flags.dup(), flags.qos(), flags.retain());
 
         let packet_length = fixed_header.length().as_u32() as usize;
         let start_length = cursor.position();
@@ -64,16 +124,14 @@ impl<'a> Parse<'a> for Publish<'a> {
 
         let identifier = match qos {
             QoS::AtMostOnce => None,
-            _ => Some(cursor.read_u16_be()?),
+            _ => Some(cursor.read()?),
         };
 
-        let properties = cursor
-            .read::<VariableByteInteger>()
-            .map_err(|err| err.map(|_| PacketError::ProtocolError))?;
-        let _ = cursor.read_slice(properties.as_u32() as usize)?;
+        let properties = PublishProperties::read(&mut cursor)?;
 
-        // TODO: we might want some length validations here.
-        let body_len = packet_length - (cursor.position() - start_length);
+        let body_len = packet_length
+            .checked_sub(cursor.position() - start_length)
+            .ok_or(PacketError::LengthMismatch)?;
         let body = cursor.read_slice(body_len)?;
 
         Ok((
@@ -84,34 +142,271 @@ impl<'a> Parse<'a> for Publish<'a> {
                 identifier,
                 retain,
                 topic,
+                properties,
                 payload: body,
             },
         ))
     }
 }
 
-impl Writable for Publish<'_> {
-    type Error<E> = E;
+impl<P> Writable for Publish<'_, P, &[PublishProperty<'_>]>
+where
+    P: Payload,
+{
+    type Error<E> = WriteError<E>;
 
     fn size(&self) -> usize {
+        self.size_with(Properties(self.properties))
+    }
+
+    async fn write_to<S>(&self, sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        self.write_with(sink, Properties(self.properties)).await
+    }
+}
+
+/// Properties from multiple slices, e.g. properties configured by the client followed by
+/// properties passed by the user.
+impl<P> Writable for Publish<'_, P, &[&[PublishProperty<'_>]]>
+where
+    P: Payload,
+{
+    type Error<E> = WriteError<E>;
+
+    fn size(&self) -> usize {
+        self.size_with(PropertyChunks(self.properties))
+    }
+
+    async fn write_to<S>(&self, sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        self.write_with(sink, PropertyChunks(self.properties)).await
+    }
+}
+
+impl<P, Props> Publish<'_, P, Props>
+where
+    P: Payload,
+{
+    fn size_with<W>(&self, properties: W) -> usize
+    where
+        W: Writable,
+    {
         EncodedStr(self.topic).size()
             + self.identifier.size()
-            + VariableByteInteger::from(0u8).size()
-            + self.payload.len()
+            + properties.size()
+            + self.payload.size()
     }
 
-    async fn write_to<S>(&self, mut sink: S) -> Result<(), Self::Error<S::Error>>
+    async fn write_with<S, W>(&self, mut sink: S, properties: W) -> Result<(), WriteError<S::Error>>
     where
         S: embedded_io_async::Write,
+        W: Writable<Error<S::Error> = WriteError<S::Error>>,
     {
         EncodedStr(self.topic).write_to(&mut sink).await?;
         self.identifier.write_to(&mut sink).await?;
+        properties.write_to(&mut sink).await?;
 
-        // TODO: properties
-        VariableByteInteger::from(0u8).write_to(&mut sink).await?;
+        for chunk in self.payload.chunks() {
+            sink.write_all(chunk).await?;
+        }
 
-        sink.write_all(self.payload).await?;
+        Ok(())
+    }
+}
+
+/// Properties of a [`Publish`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublishProperty<'a> {
+    /// Whether the payload is UTF-8 encoded character data.
+    ///
+    /// Spec: [3.3.2.3.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901111)
+    PayloadFormatIndicator(u8),
+    /// The Message Expiry Interval in seconds.
+    ///
+    /// Spec: [3.3.2.3.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901112)
+    MessageExpiryInterval(u32),
+    /// A Topic Alias used to identify the topic instead of the Topic Name.
+    ///
+    /// Spec: [3.3.2.3.4](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901113)
+    TopicAlias(u16),
+    /// Used as the Topic Name for a response message.
+    ///
+    /// Spec: [3.3.2.3.5](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901114)
+    ResponseTopic(&'a str),
+    /// The Correlation Data is used by the sender of the Request Message to identify
+    /// which request the Response Message is for when it is received.
+    ///
+    /// Spec: [3.3.2.3.6](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901115)
+    CorrelationData(&'a [u8]),
+    /// A custom message related property.
+    ///
+    /// Spec: [3.3.2.3.7](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901116)
+    UserProperty { key: &'a str, value: &'a str },
+    /// The identifier of a subscription matching the message, only sent by the server.
+    ///
+    /// Spec: [3.3.2.3.8](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901117)
+    SubscriptionIdentifier(u32),
+    /// String describing the content of the message.
+    ///
+    /// Spec: [3.3.2.3.9](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901118)
+    ContentType(&'a str),
+}
+
+impl Writable for PublishProperty<'_> {
+    type Error<E> = WriteError<E>;
+
+    fn size(&self) -> usize {
+        let payload = match self {
+            Self::PayloadFormatIndicator(v) => v.size(),
+            Self::MessageExpiryInterval(v) => v.size(),
+            Self::TopicAlias(v) => v.size(),
+            Self::ResponseTopic(v) => EncodedStr(v).size(),
+            Self::CorrelationData(v) => BinaryData(v).size(),
+            Self::UserProperty { key, value } => EncodedStr(key).size() + EncodedStr(value).size(),
+            Self::SubscriptionIdentifier(v) => VariableByteInteger::try_from(*v).ok().size(),
+            Self::ContentType(v) => EncodedStr(v).size(),
+        };
+        1 + payload
+    }
+
+    async fn write_to<S>(&self, mut sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        match self {
+            Self::PayloadFormatIndicator(v) => write_many!(sink, 0x01u8, v),
+            Self::MessageExpiryInterval(v) => write_many!(sink, 0x02u8, *v),
+            Self::TopicAlias(v) => write_many!(sink, 0x23u8, *v),
+            Self::ResponseTopic(v) => write_many!(sink, 0x08u8, EncodedStr(v)),
+            Self::CorrelationData(v) => write_many!(sink, 0x09u8, BinaryData(v)),
+            Self::UserProperty { key, value } => {
+                write_many!(sink, 0x26u8, EncodedStr(key), EncodedStr(value))
+            }
+            Self::SubscriptionIdentifier(v) => {
+                let v = VariableByteInteger::try_from(*v).map_err(WriteError::Overflow)?;
+                write_many!(sink, 0x0bu8, v)
+            }
+            Self::ContentType(v) => write_many!(sink, 0x03u8, EncodedStr(v)),
+        }
 
         Ok(())
     }
 }
+
+impl<'a> ParseProperty<'a> for PublishProperty<'a> {
+    fn from_raw(id: u8, value: PropertyValue<'a>) -> Option<Self> {
+        let property = match (id, value) {
+            (0x01, PropertyValue::Byte(v)) => Self::PayloadFormatIndicator(v),
+            (0x02, PropertyValue::FourByteInteger(v)) => Self::MessageExpiryInterval(v),
+            (0x23, PropertyValue::TwoByteInteger(v)) => Self::TopicAlias(v),
+            (0x08, PropertyValue::Str(v)) => Self::ResponseTopic(v),
+            (0x09, PropertyValue::BinaryData(v)) => Self::CorrelationData(v),
+            (0x26, PropertyValue::StrPair(key, value)) => Self::UserProperty { key, value },
+            (0x0b, PropertyValue::VariableByteInteger(v)) => Self::SubscriptionIdentifier(v),
+            (0x03, PropertyValue::Str(v)) => Self::ContentType(v),
+            _ => return None,
+        };
+
+        Some(property)
+    }
+}
+
+impl Property for PublishProperty<'_> {
+    fn into_write_error<E>(err: Self::Error<E>) -> WriteError<E> {
+        err
+    }
+}
+
+/// Properties of a received [`Publish`].
+///
+/// Properties are validated when the packet is parsed.
+#[derive(Clone, Copy)]
+pub struct PublishProperties<'a>(ParsedProperties<'a, PublishProperty<'a>>);
+
+impl fmt::Debug for PublishProperties<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(*self).finish()
+    }
+}
+
+impl<'a> PublishProperties<'a> {
+    fn read(cursor: &mut Cursor<'a>) -> ParseResult<Self> {
+        Properties::parse(cursor).map(Self)
+    }
+
+    /// Returns the [Response Topic](PublishProperty::ResponseTopic) of the message.
+    pub fn response_topic(&self) -> Option<&'a str> {
+        self.into_iter().find_map(|property| match property {
+            PublishProperty::ResponseTopic(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// Returns the [Correlation Data](PublishProperty::CorrelationData) of the message.
+    pub fn correlation_data(&self) -> Option<&'a [u8]> {
+        self.into_iter().find_map(|property| match property {
+            PublishProperty::CorrelationData(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// Returns the [Content Type](PublishProperty::ContentType) of the message.
+    pub fn content_type(&self) -> Option<&'a str> {
+        self.into_iter().find_map(|property| match property {
+            PublishProperty::ContentType(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// Returns the remaining [Message Expiry Interval](PublishProperty::MessageExpiryInterval)
+    /// of the message in seconds.
+    pub fn message_expiry_interval(&self) -> Option<u32> {
+        self.into_iter().find_map(|property| match property {
+            PublishProperty::MessageExpiryInterval(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// Returns whether the payload is UTF-8 encoded character data, as indicated by the
+    /// [Payload Format Indicator](PublishProperty::PayloadFormatIndicator).
+    pub fn utf8_payload(&self) -> bool {
+        self.into_iter()
+            .any(|property| property == PublishProperty::PayloadFormatIndicator(1))
+    }
+
+    /// Returns the [Subscription Identifiers](PublishProperty::SubscriptionIdentifier) of all
+    /// subscriptions matching the message.
+    pub fn subscription_identifiers(&self) -> impl Iterator<Item = u32> + use<'a> {
+        self.into_iter().filter_map(|property| match property {
+            PublishProperty::SubscriptionIdentifier(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// Returns the [User Properties](PublishProperty::UserProperty) of the message as key value
+    /// pairs, in the order they were sent.
+    pub fn user_properties(&self) -> impl Iterator<Item = (&'a str, &'a str)> + use<'a> {
+        self.into_iter().filter_map(|property| match property {
+            PublishProperty::UserProperty { key, value } => Some((key, value)),
+            _ => None,
+        })
+    }
+
+    /// Returns the value of the first user property with the key `key`.
+    pub fn user_property(&self, key: &str) -> Option<&'a str> {
+        self.user_properties()
+            .find_map(|(k, value)| (k == key).then_some(value))
+    }
+}
+
+impl<'a> Iterator for PublishProperties<'a> {
+    type Item = PublishProperty<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
@@ -0,0 +1,298 @@
+use crate::log::LogPacket;
+use crate::protocol::types::PacketId;
+use crate::protocol::v5::PubAckReason;
+use crate::protocol::v5::puback::parse_ack;
+use crate::protocol::{Packet, PacketError, PacketParse, ParseResult};
+use crate::traits::Writable;
+
+#[cfg(doc)]
+use crate::protocol::v5::Publish;
+
+/// The reason of a [`PubRec`], it uses the same reason codes as a [`PubAck`](super::PubAck).
+///
+/// Spec: [3.5.2.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901134)
+pub type PubRecReason = PubAckReason;
+
+/// Receipt of a QoS 2 [`Publish`], the first step of the exactly once handshake.
+///
+/// The sender of the publish answers with a [`PubRel`].
+///
+/// Spec: [3.5](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901131)
+#[derive(Debug)]
+pub struct PubRec {
+    /// The identifier of the received [`Publish`].
+    pub identifier: PacketId,
+    pub reason: PubRecReason,
+}
+
+impl Packet for PubRec {
+    const TYPE: u8 = 0b0101;
+}
+
+impl LogPacket for PubRec {}
+
+impl<'a> PacketParse<'a> for PubRec {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let (len, identifier, reason, _) = parse_ack::<Self, _>(data, PubRecReason::Success)?;
+        Ok((len, Self { identifier, reason }))
+    }
+}
+
+impl Writable for PubRec {
+    type Error<E> = E;
+
+    fn size(&self) -> usize {
+        // The reason may be omitted for a success without properties.
+        match self.reason {
+            PubRecReason::Success => 2,
+            _ => 3,
+        }
+    }
+
+    async fn write_to<T>(&self, mut sink: T) -> Result<(), T::Error>
+    where
+        T: embedded_io_async::Write,
+    {
+        // Identifier:
+        self.identifier.write_to(&mut sink).await?;
+
+        // Reason Code:
+        if self.reason != PubRecReason::Success {
+            (self.reason as u8).write_to(&mut sink).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Release of a QoS 2 [`Publish`], the response to a [`PubRec`].
+///
+/// Spec: [3.6](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901141)
+#[derive(Debug)]
+pub struct PubRel {
+    /// The identifier of the released [`Publish`].
+    pub identifier: PacketId,
+    pub reason: PubCompReason,
+}
+
+impl Packet for PubRel {
+    const TYPE: u8 = 0b0110;
+
+    fn flags(&self) -> u8 {
+        0b0010
+    }
+}
+
+impl LogPacket for PubRel {}
+
+impl<'a> PacketParse<'a> for PubRel {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let (len, identifier, reason, _) = parse_ack::<Self, _>(data, PubCompReason::Success)?;
+        Ok((len, Self { identifier, reason }))
+    }
+}
+
+impl Writable for PubRel {
+    type Error<E> = E;
+
+    fn size(&self) -> usize {
+        // The reason may be omitted for a success without properties.
+        match self.reason {
+            PubCompReason::Success => 2,
+            _ => 3,
+        }
+    }
+
+    async fn write_to<T>(&self, mut sink: T) -> Result<(), T::Error>
+    where
+        T: embedded_io_async::Write,
+    {
+        // Identifier:
+        self.identifier.write_to(&mut sink).await?;
+
+        // Reason Code:
+        if self.reason != PubCompReason::Success {
+            (self.reason as u8).write_to(&mut sink).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Completion of a QoS 2 [`Publish`], the response to a [`PubRel`] and the last step of the
+/// exactly once handshake.
+///
+/// Spec: [3.7](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901151)
+#[derive(Debug)]
+pub struct PubComp {
+    /// The identifier of the completed [`Publish`].
+    pub identifier: PacketId,
+    pub reason: PubCompReason,
+}
+
+impl Packet for PubComp {
+    const TYPE: u8 = 0b0111;
+}
+
+impl LogPacket for PubComp {}
+
+impl<'a> PacketParse<'a> for PubComp {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let (len, identifier, reason, _) = parse_ack::<Self, _>(data, PubCompReason::Success)?;
+        Ok((len, Self { identifier, reason }))
+    }
+}
+
+impl Writable for PubComp {
+    type Error<E> = E;
+
+    fn size(&self) -> usize {
+        // The reason may be omitted for a success without properties.
+        match self.reason {
+            PubCompReason::Success => 2,
+            _ => 3,
+        }
+    }
+
+    async fn write_to<T>(&self, mut sink: T) -> Result<(), T::Error>
+    where
+        T: embedded_io_async::Write,
+    {
+        // Identifier:
+        self.identifier.write_to(&mut sink).await?;
+
+        // Reason Code:
+        if self.reason != PubCompReason::Success {
+            (self.reason as u8).write_to(&mut sink).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The reason of a [`PubRel`] or a [`PubComp`].
+///
+/// Spec: [3.7.2.1](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901154)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PubCompReason {
+    /// Packet Identifier released. Publication of the message is complete.
+    Success = 0x00,
+    /// The Packet Identifier is not known. This is not an error during recovery, but at other
+    /// times indicates a mismatch between the Session State on the Client and Server.
+    PacketIdentifierNotFound = 0x92,
+}
+
+impl PubCompReason {
+    /// Returns whether the handshake completed.
+    pub fn is_success(&self) -> bool {
+        (*self as u8) < 0x80
+    }
+}
+
+impl TryFrom<u8> for PubCompReason {
+    type Error = PacketError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let result = match value {
+            0x00 => Self::Success,
+            0x92 => Self::PacketIdentifierNotFound,
+            _ => return Err(PacketError::ProtocolError),
+        };
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Parse;
+    use crate::utils::CountingSink;
+
+    #[test]
+    fn test_pub_rec_parse() {
+        let data = [0x50, 0x02, 0x00, 0x07];
+        let (len, rec) = <PubRec as Parse>::parse(&data).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(rec.identifier.get(), 7);
+        assert_eq!(rec.reason, PubRecReason::Success);
+
+        let data = [0x50, 0x03, 0x00, 0x07, 0x87];
+        let (_, rec) = <PubRec as Parse>::parse(&data).unwrap();
+        assert_eq!(rec.reason, PubRecReason::NotAuthorized);
+
+        // A PUBACK is not a PUBREC.
+        let data = [0x40, 0x02, 0x00, 0x07];
+        assert!(<PubRec as Parse>::parse(&data).is_err());
+    }
+
+    #[test]
+    fn test_pub_comp_parse() {
+        let data = [0x70, 0x02, 0x00, 0x07];
+        let (_, comp) = <PubComp as Parse>::parse(&data).unwrap();
+        assert_eq!(comp.identifier.get(), 7);
+        assert_eq!(comp.reason, PubCompReason::Success);
+
+        let data = [0x70, 0x04, 0x00, 0x07, 0x92, 0x00];
+        let (len, comp) = <PubComp as Parse>::parse(&data).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(comp.reason, PubCompReason::PacketIdentifierNotFound);
+
+        let data = [0x70, 0x03, 0x00, 0x07, 0x97];
+        assert!(<PubComp as Parse>::parse(&data).is_err());
+    }
+
+    #[test]
+    fn test_pub_rel_parse() {
+        let data = [0x62, 0x02, 0x00, 0x07];
+        let (len, rel) = <PubRel as Parse>::parse(&data).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(rel.identifier.get(), 7);
+        assert_eq!(rel.reason, PubCompReason::Success);
+
+        let data = [0x62, 0x03, 0x00, 0x07, 0x92];
+        let (_, rel) = <PubRel as Parse>::parse(&data).unwrap();
+        assert_eq!(rel.reason, PubCompReason::PacketIdentifierNotFound);
+    }
+
+    #[tokio::test]
+    async fn test_pub_rel_pub_comp_size_matches_written() {
+        for reason in [
+            PubCompReason::Success,
+            PubCompReason::PacketIdentifierNotFound,
+        ] {
+            let packet = PubRel {
+                identifier: PacketId::MIN,
+                reason,
+            };
+
+            let mut sink = CountingSink::default();
+            packet.write_to(&mut sink).await.unwrap();
+            assert_eq!(sink.count(), packet.size());
+
+            let packet = PubComp {
+                identifier: PacketId::MIN,
+                reason,
+            };
+
+            let mut sink = CountingSink::default();
+            packet.write_to(&mut sink).await.unwrap();
+            assert_eq!(sink.count(), packet.size());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pub_rec_size_matches_written() {
+        for reason in [PubRecReason::Success, PubRecReason::QuotaExceeded] {
+            let packet = PubRec {
+                identifier: PacketId::MIN,
+                reason,
+            };
+
+            let mut sink = CountingSink::default();
+            packet.write_to(&mut sink).await.unwrap();
+            assert_eq!(sink.count(), packet.size());
+        }
+    }
+}
@@ -0,0 +1,492 @@
+use crate::log::{self, LogPacket};
+use crate::protocol::types::{EncodedStr, PacketId, VariableByteInteger};
+use crate::protocol::utils::CursorExt;
+use crate::protocol::v5::Property;
+use crate::protocol::v5::flags::SubscriptionOptions;
+use crate::protocol::v5::property::{ParseProperty, Properties, PropertyValue};
+use crate::protocol::v5::puback::AckProperties;
+use crate::protocol::{Packet, PacketError, PacketParse, ParseResult, QoS, WriteError};
+use crate::traits::Writable;
+use crate::utils::{Cursor, write_many};
+
+#[derive(Debug)]
+pub struct Subscribe<'a> {
+    pub identifier: PacketId,
+    pub properties: &'a [SubscribeProperty<'a>],
+    pub topics: &'a [TopicFilter<'a>],
+}
+
+impl LogPacket for Subscribe<'_> {}
+
+impl Packet for Subscribe<'_> {
+    const TYPE: u8 = 0b1000;
+
+    fn flags(&self) -> u8 {
+        0b0010
+    }
+}
+
+impl Writable for Subscribe<'_> {
+    type Error<E> = WriteError<E>;
+
+    fn size(&self) -> usize {
+        self.identifier.size() + Properties(self.properties).size() + self.topics.size()
+    }
+
+    async fn write_to<T>(&self, mut sink: T) -> Result<(), Self::Error<T::Error>>
+    where
+        T: embedded_io_async::Write,
+    {
+        // Identifier:
+        self.identifier.write_to(&mut sink).await?;
+
+        // Properties:
+        Properties(self.properties).write_to(&mut sink).await?;
+
+        // Payload:
+        self.topics.write_to(&mut sink).await?;
+
+        Ok(())
+    }
+}
+
+/// Properties of a [`Subscribe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscribeProperty<'a> {
+    /// The identifier of the subscription, which the server includes in every message matching
+    /// the subscription.
+    ///
+    /// Must be between `1` and `268_435_455`.
+    ///
+    /// Spec: [3.8.2.1.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901166)
+    SubscriptionIdentifier(u32),
+    /// A custom subscription related property.
+    ///
+    /// Spec: [3.8.2.1.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901167)
+    UserProperty { key: &'a str, value: &'a str },
+}
+
+impl Writable for SubscribeProperty<'_> {
+    type Error<E> = WriteError<E>;
+
+    fn size(&self) -> usize {
+        let payload = match self {
+            Self::SubscriptionIdentifier(v) => VariableByteInteger::try_from(*v).ok().size(),
+            Self::UserProperty { key, value } => EncodedStr(key).size() + EncodedStr(value).size(),
+        };
+        1 + payload
+    }
+
+    async fn write_to<S>(&self, mut sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        match self {
+            Self::SubscriptionIdentifier(v) => {
+                let v = VariableByteInteger::try_from(*v).map_err(WriteError::Overflow)?;
+                write_many!(sink, 0x0bu8, v)
+            }
+            Self::UserProperty { key, value } => {
+                write_many!(sink, 0x26u8, EncodedStr(key), EncodedStr(value))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> ParseProperty<'a> for SubscribeProperty<'a> {
+    fn from_raw(id: u8, value: PropertyValue<'a>) -> Option<Self> {
+        let property = match (id, value) {
+            (0x0b, PropertyValue::VariableByteInteger(v)) => Self::SubscriptionIdentifier(v),
+            (0x26, PropertyValue::StrPair(key, value)) => Self::UserProperty { key, value },
+            _ => return None,
+        };
+
+        Some(property)
+    }
+}
+
+impl Property for SubscribeProperty<'_> {
+    fn into_write_error<E>(err: Self::Error<E>) -> WriteError<E> {
+        err
+    }
+}
+
+/// A topic filter, used to [subscribe](Subscribe) to topics.
+///
+/// Spec: [3.8.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901168).
+#[derive(Debug, Clone, Copy)]
+pub struct TopicFilter<'a> {
+    /// The name of the topic.
+    pub name: &'a str,
+    /// The Quality of Service level.
+    pub qos: QoS,
+    /// No Local option.
+    ///
+    /// If the value is true, Application Messages MUST NOT be forwarded to a connection with a
+    /// client id equal to the client id of the publishing connection.
+    pub no_local: bool,
+    /// Retain as Published option.
+    ///
+    /// If `true`, Application Messages forwarded using this subscription keep the RETAIN flag they
+    /// were published with. If `false`, Application Messages forwarded using this subscription have
+    /// the RETAIN flag set to `false`. Retained messages sent when the subscription is established have
+    /// the RETAIN flag set to `true.
+    pub retain_as_published: bool,
+    /// Retain handling.
+    ///
+    /// This option specifies whether retained messages are sent when the subscription is established.
+    /// This does not affect the sending of retained messages at any point after the subscribe.
+    /// If there are no retained messages matching the Topic Filter, all of these values act the same.
+    pub retain: RetainHandling,
+}
+
+/// [Topic filter](TopicFilter::retain) retain handling.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RetainHandling {
+    /// Send retained messages at the time of the subscribe.
+    #[default]
+    SendRetained = 0,
+    /// Send retained messages at subscribe only if the subscription does not currently exist.
+    SendRetainedOnNewSubscription = 1,
+    /// Do not send retained messages at the time of the subscribe.
+    DoNotSendRetained = 2,
+}
+
+impl Writable for TopicFilter<'_> {
+    type Error<E> = E;
+
+    fn size(&self) -> usize {
+        EncodedStr(self.name).size() + 1
+    }
+
+    async fn write_to<S>(&self, mut sink: S) -> Result<(), Self::Error<S::Error>>
+    where
+        S: embedded_io_async::Write,
+    {
+        EncodedStr(self.name).write_to(&mut sink).await?;
+
+        let options = SubscriptionOptions::new(self.qos)
+            .with_no_local(self.no_local)
+            .with_retain_as_published(self.retain_as_published)
+            .with_retain_handling(self.retain);
+        options.bits().write_to(&mut sink).await?;
+
+        Ok(())
+    }
+}
+
+/// Acknowledgement of a [`Subscribe`] request.
+///
+/// Spec: [3.9](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901171)
+#[derive(Debug)]
+pub struct SubAck<'a> {
+    /// The identifier of the acknowledged [`Subscribe`] request.
+    pub identifier: PacketId,
+    /// Reason codes, validated to be valid [`SubAckReason`]s.
+    reasons: &'a [u8],
+    /// The properties, validated when the packet is parsed.
+    properties: AckProperties<'a>,
+}
+
+impl<'a> SubAck<'a> {
+    /// Returns the properties of the packet, like a reason string or user properties.
+    pub fn properties(&self) -> AckProperties<'a> {
+        self.properties
+    }
+
+    /// Returns the encoded properties of the packet, without the property length.
+    ///
+    /// Properties are encoded as an identifier followed by the value, the typed properties are
+    /// returned by [`Self::properties`].
+    ///
+    /// Spec: [2.2.2.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901029)
+    pub fn raw_properties(&self) -> &'a [u8] {
+        self.properties.as_bytes()
+    }
+
+    /// Returns the reason codes for each [`TopicFilter`] of the [`Subscribe`] request.
+    ///
+    /// Reasons are in the same order as the topic filters of the request.
+    pub fn reasons(&self) -> impl ExactSizeIterator<Item = SubAckReason> + 'a {
+        self.reasons.iter().map(|&reason| {
+            // Reasons are validated when parsing.
+            SubAckReason::try_from(reason).unwrap_or(SubAckReason::UnspecifiedError)
+        })
+    }
+
+    /// Returns the QoS granted for each [`TopicFilter`] of the [`Subscribe`] request.
+    ///
+    /// Rejected subscriptions yield the reason of the rejection. The iterator is aligned with
+    /// the topic filters of the request and can be zipped with them.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use miniqtt::protocol::v5::{SubAck, TopicFilter};
+    /// # fn example(filters: &[TopicFilter<'_>], ack: SubAck<'_>) {
+    /// for (filter, granted) in filters.iter().zip(ack.granted()) {
+    ///     match granted {
+    ///         Ok(qos) => println!("subscribed to {} with {qos:?}", filter.name),
+    ///         Err(reason) => println!("subscription to {} rejected: {reason:?}", filter.name),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn granted(&self) -> GrantedQoS<'a> {
+        GrantedQoS {
+            reasons: self.reasons.iter(),
+        }
+    }
+
+    /// Returns `true` if all subscriptions were accepted.
+    pub fn all_granted(&self) -> bool {
+        self.granted().all(|granted| granted.is_ok())
+    }
+
+    /// Returns the highest QoS granted for any of the subscriptions.
+    ///
+    /// Returns `None` if no subscription was accepted.
+    pub fn max_granted(&self) -> Option<QoS> {
+        self.granted().filter_map(Result::ok).max()
+    }
+}
+
+/// Iterator over the QoS granted for each [`TopicFilter`] of a [`Subscribe`] request.
+///
+/// See [`SubAck::granted`].
+#[derive(Debug, Clone)]
+pub struct GrantedQoS<'a> {
+    reasons: core::slice::Iter<'a, u8>,
+}
+
+impl Iterator for GrantedQoS<'_> {
+    type Item = Result<QoS, SubAckReason>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Reasons are validated when parsing.
+        let reason =
+            SubAckReason::try_from(*self.reasons.next()?).unwrap_or(SubAckReason::UnspecifiedError);
+
+        Some(reason.granted_qos().ok_or(reason))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.reasons.size_hint()
+    }
+}
+
+impl ExactSizeIterator for GrantedQoS<'_> {}
+
+impl Packet for SubAck<'_> {
+    const TYPE: u8 = 0b1001;
+}
+
+impl LogPacket for SubAck<'_> {}
+
+impl<'a> PacketParse<'a> for SubAck<'a> {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let mut cursor = Cursor::new(data);
+
+        let fixed_header = cursor.read_fixed_header::<Self>()?;
+        let packet_length = fixed_header.length().as_u32() as usize;
+        let start_length = cursor.position();
+
+        let identifier: PacketId = cursor.read()?;
+
+        let properties = AckProperties::read(&mut cursor)?;
+
+        let reasons_len = packet_length
+            .checked_sub(cursor.position() - start_length)
+            .ok_or(PacketError::ProtocolError)?;
+        let reasons = cursor.read_slice(reasons_len)?;
+        if let Some(&_invalid) = reasons
+            .iter()
+            .find(|&&r| SubAckReason::try_from(r).is_err())
+        {
+            log::debug!("invalid suback reason: {_invalid:#x}");
+            return Err(PacketError::ProtocolError.into());
+        }
+
+        Ok((
+            cursor.position(),
+            Self {
+                identifier,
+                reasons,
+                properties,
+            },
+        ))
+    }
+}
+
+/// The reason for each [`TopicFilter`] in the [`SubAck`] packet.
+///
+/// Spec: [3.9.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901178)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SubAckReason {
+    /// The subscription is accepted and the maximum QoS sent will be QoS 0.
+    GrantedQoS0 = 0x00,
+    /// The subscription is accepted and the maximum QoS sent will be QoS 1.
+    GrantedQoS1 = 0x01,
+    /// The subscription is accepted and any received QoS will be sent to this subscription.
+    GrantedQoS2 = 0x02,
+    /// The subscription is not accepted and the Server either does not wish to reveal the reason
+    /// or none of the other Reason Codes apply.
+    UnspecifiedError = 0x80,
+    /// The SUBSCRIBE is valid but the Server does not accept it.
+    ImplementationSpecificError = 0x83,
+    /// The Client is not authorized to make this subscription.
+    NotAuthorized = 0x87,
+    /// The Topic Filter is correctly formed but is not allowed for this Client.
+    TopicFilterInvalid = 0x8f,
+    /// The specified Packet Identifier is already in use.
+    PacketIdentifierInUse = 0x91,
+    /// An implementation or administrative imposed limit has been exceeded.
+    QuotaExceeded = 0x97,
+    /// The Server does not support Shared Subscriptions for this Client.
+    SharedSubscriptionsNotSupported = 0x9e,
+    /// The Server does not support Subscription Identifiers; the subscription is not accepted.
+    SubscriptionIdentifiersNotSupported = 0xa1,
+    /// The Server does not support Wildcard Subscriptions; the subscription is not accepted.
+    WildcardSubscriptionsNotSupported = 0xa2,
+}
+
+impl SubAckReason {
+    /// Returns whether the subscription was accepted.
+    pub fn is_success(&self) -> bool {
+        (*self as u8) < 0x80
+    }
+
+    /// Returns the maximum QoS granted by the server, if the subscription was accepted.
+    pub fn granted_qos(&self) -> Option<QoS> {
+        match self {
+            Self::GrantedQoS0 => Some(QoS::AtMostOnce),
+            Self::GrantedQoS1 => Some(QoS::AtLeastOnce),
+            Self::GrantedQoS2 => Some(QoS::ExactlyOnce),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the failure is transient and the subscription may succeed when retried.
+    ///
+    /// A [`Self::PacketIdentifierInUse`] can be retried immediately, with a new identifier,
+    /// a [`Self::QuotaExceeded`] should be retried after a backoff.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::PacketIdentifierInUse | Self::QuotaExceeded)
+    }
+}
+
+impl TryFrom<u8> for SubAckReason {
+    type Error = PacketError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let result = match value {
+            0x00 => Self::GrantedQoS0,
+            0x01 => Self::GrantedQoS1,
+            0x02 => Self::GrantedQoS2,
+            0x80 => Self::UnspecifiedError,
+            0x83 => Self::ImplementationSpecificError,
+            0x87 => Self::NotAuthorized,
+            0x8f => Self::TopicFilterInvalid,
+            0x91 => Self::PacketIdentifierInUse,
+            0x97 => Self::QuotaExceeded,
+            0x9e => Self::SharedSubscriptionsNotSupported,
+            0xa1 => Self::SubscriptionIdentifiersNotSupported,
+            0xa2 => Self::WildcardSubscriptionsNotSupported,
+            _ => return Err(PacketError::ProtocolError),
+        };
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{Parse, ParseError};
+
+    #[test]
+    fn test_sub_ack_reason_matches_value() {
+        for i in 0..u8::MAX {
+            let Ok(reason) = SubAckReason::try_from(i) else {
+                continue;
+            };
+
+            assert_eq!(reason as u8, i);
+        }
+    }
+
+    #[test]
+    fn test_sub_ack_parse() {
+        let data = [0x90, 0x06, 0x4e, 0x20, 0x00, 0x01, 0x8f, 0x97];
+
+        let (len, ack) = <SubAck as Parse>::parse(&data).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(ack.identifier.get(), 20_000);
+        assert_eq!(
+            ack.reasons().collect::<Vec<_>>(),
+            [
+                SubAckReason::GrantedQoS1,
+                SubAckReason::TopicFilterInvalid,
+                SubAckReason::QuotaExceeded
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sub_ack_raw_properties() {
+        // Reason string `ok`, followed by a single reason.
+        let data = [
+            0x90, 0x09, 0x00, 0x01, 0x05, 0x1f, 0x00, 0x02, b'o', b'k', 0x00,
+        ];
+
+        let (_, ack) = <SubAck as Parse>::parse(&data).unwrap();
+        assert_eq!(ack.raw_properties(), [0x1f, 0x00, 0x02, b'o', b'k']);
+        #[cfg(feature = "properties")]
+        assert_eq!(ack.properties().reason_string(), Some("ok"));
+        assert_eq!(
+            ack.reasons().collect::<Vec<_>>(),
+            [SubAckReason::GrantedQoS0]
+        );
+
+        let data = [0x90, 0x04, 0x4e, 0x20, 0x00, 0x00];
+        let (_, ack) = <SubAck as Parse>::parse(&data).unwrap();
+        assert_eq!(ack.raw_properties(), []);
+    }
+
+    #[test]
+    fn test_sub_ack_granted() {
+        let data = [0x90, 0x06, 0x4e, 0x20, 0x00, 0x01, 0x8f, 0x00];
+
+        let (_, ack) = <SubAck as Parse>::parse(&data).unwrap();
+        assert_eq!(
+            ack.granted().collect::<Vec<_>>(),
+            [
+                Ok(QoS::AtLeastOnce),
+                Err(SubAckReason::TopicFilterInvalid),
+                Ok(QoS::AtMostOnce)
+            ]
+        );
+        assert!(!ack.all_granted());
+        assert_eq!(ack.max_granted(), Some(QoS::AtLeastOnce));
+
+        let data = [0x90, 0x04, 0x4e, 0x20, 0x00, 0x87];
+
+        let (_, ack) = <SubAck as Parse>::parse(&data).unwrap();
+        assert!(!ack.all_granted());
+        assert_eq!(ack.max_granted(), None);
+    }
+
+    #[test]
+    fn test_sub_ack_parse_invalid_reason() {
+        let data = [0x90, 0x04, 0x4e, 0x20, 0x00, 0x03];
+
+        assert!(matches!(
+            <SubAck as Parse>::parse(&data),
+            Err(ParseError::Error(PacketError::ProtocolError))
+        ));
+    }
+}
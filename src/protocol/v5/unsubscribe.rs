@@ -0,0 +1,221 @@
+use crate::log::{self, LogPacket};
+use crate::protocol::types::{EncodedStr, PacketId, VariableByteInteger};
+use crate::protocol::utils::CursorExt;
+use crate::protocol::v5::property::RawProperties;
+use crate::protocol::{Packet, PacketError, PacketParse, ParseResult};
+use crate::traits::Writable;
+use crate::utils::Cursor;
+
+/// Removes subscriptions of the client.
+///
+/// Spec: [3.10](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901179)
+#[derive(Debug)]
+pub struct Unsubscribe<'a> {
+    pub identifier: PacketId,
+    /// The topic filters to unsubscribe from, must match the filters of the subscriptions.
+    pub topics: &'a [&'a str],
+}
+
+impl LogPacket for Unsubscribe<'_> {}
+
+impl Packet for Unsubscribe<'_> {
+    const TYPE: u8 = 0b1010;
+
+    fn flags(&self) -> u8 {
+        0b0010
+    }
+}
+
+impl Writable for Unsubscribe<'_> {
+    type Error<E> = E;
+
+    fn size(&self) -> usize {
+        self.identifier.size()
+            + 1
+            + self
+                .topics
+                .iter()
+                .map(|topic| EncodedStr(topic).size())
+                .sum::<usize>()
+    }
+
+    async fn write_to<T>(&self, mut sink: T) -> Result<(), T::Error>
+    where
+        T: embedded_io_async::Write,
+    {
+        // Identifier:
+        self.identifier.write_to(&mut sink).await?;
+
+        // Properties:
+        VariableByteInteger::from(0u8).write_to(&mut sink).await?;
+
+        // Payload:
+        for topic in self.topics {
+            EncodedStr(topic).write_to(&mut sink).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Acknowledgement of an [`Unsubscribe`] request.
+///
+/// Spec: [3.11](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901187)
+#[derive(Debug)]
+pub struct UnsubAck<'a> {
+    /// The identifier of the acknowledged [`Unsubscribe`] request.
+    pub identifier: PacketId,
+    /// Reason codes, validated to be valid [`UnsubAckReason`]s.
+    reasons: &'a [u8],
+}
+
+impl<'a> UnsubAck<'a> {
+    /// Returns the reason codes for each topic filter of the [`Unsubscribe`] request.
+    ///
+    /// Reasons are in the same order as the topic filters of the request.
+    pub fn reasons(&self) -> impl ExactSizeIterator<Item = UnsubAckReason> + 'a {
+        self.reasons.iter().map(|&reason| {
+            // Reasons are validated when parsing.
+            UnsubAckReason::try_from(reason).unwrap_or(UnsubAckReason::UnspecifiedError)
+        })
+    }
+}
+
+impl Packet for UnsubAck<'_> {
+    const TYPE: u8 = 0b1011;
+}
+
+impl LogPacket for UnsubAck<'_> {}
+
+impl<'a> PacketParse<'a> for UnsubAck<'a> {
+    fn parse(data: &'a [u8]) -> ParseResult<(usize, Self)> {
+        let mut cursor = Cursor::new(data);
+
+        let fixed_header = cursor.read_fixed_header::<Self>()?;
+        let packet_length = fixed_header.length().as_u32() as usize;
+        let start_length = cursor.position();
+
+        let identifier: PacketId = cursor.read()?;
+
+        for property in RawProperties::read(&mut cursor)? {
+            property?;
+        }
+
+        let reasons_len = packet_length
+            .checked_sub(cursor.position() - start_length)
+            .ok_or(PacketError::ProtocolError)?;
+        let reasons = cursor.read_slice(reasons_len)?;
+        if let Some(&_invalid) = reasons
+            .iter()
+            .find(|&&r| UnsubAckReason::try_from(r).is_err())
+        {
+            log::debug!("invalid unsuback reason: {_invalid:#x}");
+            return Err(PacketError::ProtocolError.into());
+        }
+
+        Ok((
+            cursor.position(),
+            Self {
+                identifier,
+                reasons,
+            },
+        ))
+    }
+}
+
+/// The reason for each topic filter in the [`UnsubAck`] packet.
+///
+/// Spec: [3.11.3](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901194)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum UnsubAckReason {
+    /// The subscription is deleted.
+    Success = 0x00,
+    /// No matching Topic Filter is being used by the Client.
+    NoSubscriptionExisted = 0x11,
+    /// The unsubscribe could not be completed and the Server either does not wish to reveal the
+    /// reason or none of the other Reason Codes apply.
+    UnspecifiedError = 0x80,
+    /// The UNSUBSCRIBE is valid but the Server does not accept it.
+    ImplementationSpecificError = 0x83,
+    /// The Client is not authorized to unsubscribe.
+    NotAuthorized = 0x87,
+    /// The Topic Filter is correctly formed but is not allowed for this Client.
+    TopicFilterInvalid = 0x8f,
+    /// The specified Packet Identifier is already in use.
+    PacketIdentifierInUse = 0x91,
+}
+
+impl UnsubAckReason {
+    /// Returns whether the client is no longer subscribed to the topic filter.
+    pub fn is_success(&self) -> bool {
+        (*self as u8) < 0x80
+    }
+}
+
+impl TryFrom<u8> for UnsubAckReason {
+    type Error = PacketError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let result = match value {
+            0x00 => Self::Success,
+            0x11 => Self::NoSubscriptionExisted,
+            0x80 => Self::UnspecifiedError,
+            0x83 => Self::ImplementationSpecificError,
+            0x87 => Self::NotAuthorized,
+            0x8f => Self::TopicFilterInvalid,
+            0x91 => Self::PacketIdentifierInUse,
+            _ => return Err(PacketError::ProtocolError),
+        };
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Parse;
+    use crate::utils::CountingSink;
+
+    #[test]
+    fn test_unsub_ack_reason_matches_value() {
+        for i in 0..u8::MAX {
+            let Ok(reason) = UnsubAckReason::try_from(i) else {
+                continue;
+            };
+
+            assert_eq!(reason as u8, i);
+        }
+    }
+
+    #[test]
+    fn test_unsub_ack_parse() {
+        let data = [0xb0, 0x05, 0x00, 0x07, 0x00, 0x00, 0x11];
+        let (len, ack) = <UnsubAck as Parse>::parse(&data).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(ack.identifier.get(), 7);
+        assert_eq!(
+            ack.reasons().collect::<Vec<_>>(),
+            [
+                UnsubAckReason::Success,
+                UnsubAckReason::NoSubscriptionExisted
+            ]
+        );
+
+        let data = [0xb0, 0x04, 0x00, 0x07, 0x00, 0x12];
+        assert!(<UnsubAck as Parse>::parse(&data).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_size_matches_written() {
+        let packet = Unsubscribe {
+            identifier: PacketId::MIN,
+            topics: &["a", "b/c"],
+        };
+
+        let mut sink = CountingSink::default();
+        packet.write_to(&mut sink).await.unwrap();
+        assert_eq!(sink.count(), packet.size());
+    }
+}
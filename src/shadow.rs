@@ -0,0 +1,254 @@
+//! A lightweight device shadow, built on top of publish and subscribe.
+//!
+//! A shadow bundles the topics a device uses to share its state:
+//!
+//! - a status topic, announcing whether the device is online, see [`Presence`],
+//! - a reported topic, the device periodically publishes its current state to,
+//! - a desired topic, applications publish the state they want the device to have to.
+//!
+//! All topics are derived from a base topic and a [`TopicScheme`]. Reported states are
+//! retained, applications always see the last state the device reported.
+//!
+//! Requires the `shadow` feature.
+//!
+//! # Examples:
+//!
+//! ```
+//! # use core::time::Duration;
+//! # use miniqtt::shadow::{Shadow, TopicScheme};
+//! # async fn example<C>(client: &mut miniqtt::Client<C, Vec<u8>>) -> miniqtt::client::Result<(), C::Error>
+//! # where
+//! #     C: embedded_io_async::Read + embedded_io_async::Write,
+//! # {
+//! let shadow = Shadow::<64>::new("devices/sensor-1", TopicScheme::new(), Duration::from_secs(60))
+//!     .expect("topics fit into the buffer");
+//!
+//! client.connect("sensor-1").with_presence(shadow.presence()).await?;
+//! shadow.subscribe(client).await?;
+//! shadow.report(client, br#"{"led":"off"}"#).await?;
+//!
+//! let message = client.receive().await?;
+//! if let Some(desired) = shadow.desired(&message) {
+//!     // Apply the desired state and report the new state.
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use core::time::Duration;
+
+use crate::client::{Client, Presence, Result, Timeout};
+use crate::protocol::{self, v5};
+use crate::traits::Buffer;
+
+/// The topics of a [`Shadow`], relative to the base topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TopicScheme<'a> {
+    status: &'a str,
+    reported: &'a str,
+    desired: &'a str,
+}
+
+impl<'a> TopicScheme<'a> {
+    /// Creates the default scheme, using the topics `status`, `reported` and `desired`.
+    pub const fn new() -> Self {
+        Self {
+            status: "status",
+            reported: "reported",
+            desired: "desired",
+        }
+    }
+
+    /// Sets the topic presence is announced on.
+    pub const fn status(mut self, status: &'a str) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Sets the topic the device reports its state on.
+    pub const fn reported(mut self, reported: &'a str) -> Self {
+        self.reported = reported;
+        self
+    }
+
+    /// Sets the topic the device receives the desired state on.
+    pub const fn desired(mut self, desired: &'a str) -> Self {
+        self.desired = desired;
+        self
+    }
+}
+
+impl Default for TopicScheme<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A device shadow, see the [module documentation](self).
+///
+/// The topics are stored inline, they must fit into `N` bytes in total.
+#[derive(Debug, Clone)]
+pub struct Shadow<const N: usize = 128> {
+    buffer: [u8; N],
+    /// End of the status, reported and desired topic in the buffer.
+    ends: [usize; 3],
+    interval: Duration,
+}
+
+impl<const N: usize> Shadow<N> {
+    /// Creates a new shadow below `base`, which reports its state every `interval`.
+    ///
+    /// Returns `None` if the topics do not fit into the buffer or are not valid topic names.
+    pub fn new(base: &str, scheme: TopicScheme<'_>, interval: Duration) -> Option<Self> {
+        let mut buffer = [0; N];
+        let mut ends = [0; 3];
+
+        let mut len = 0;
+        for (end, suffix) in ends
+            .iter_mut()
+            .zip([scheme.status, scheme.reported, scheme.desired])
+        {
+            let start = len;
+            for part in [base, "/", suffix] {
+                buffer
+                    .get_mut(len..len + part.len())?
+                    .copy_from_slice(part.as_bytes());
+                len += part.len();
+            }
+
+            let topic = core::str::from_utf8(&buffer[start..len]).ok()?;
+            protocol::validate_topic(topic).ok()?;
+            *end = len;
+        }
+
+        Some(Self {
+            buffer,
+            ends,
+            interval,
+        })
+    }
+
+    /// Returns the topic presence is announced on.
+    pub fn status_topic(&self) -> &str {
+        self.topic(0)
+    }
+
+    /// Returns the topic the device reports its state on.
+    pub fn reported_topic(&self) -> &str {
+        self.topic(1)
+    }
+
+    /// Returns the topic the device receives the desired state on.
+    pub fn desired_topic(&self) -> &str {
+        self.topic(2)
+    }
+
+    /// Returns the interval in which the state is reported.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the time until the next report is due, given the time since the last report.
+    pub fn due_in(&self, since_last_report: Duration) -> Duration {
+        self.interval.saturating_sub(since_last_report)
+    }
+
+    /// Returns the JSON presence on the status topic, used when [connecting].
+    ///
+    /// [connecting]: crate::client::Connect::with_presence
+    pub fn presence(&self) -> Presence<'_> {
+        Presence::new(self.status_topic()).json()
+    }
+
+    /// Returns the desired state, if `message` was received on the desired topic.
+    pub fn desired<'m>(&self, message: &v5::Publish<'m>) -> Option<&'m [u8]> {
+        (message.topic == self.desired_topic()).then_some(message.payload)
+    }
+
+    /// Subscribes to the desired topic.
+    pub async fn subscribe<C, B, T>(&self, client: &mut Client<C, B, T>) -> Result<(), C::Error>
+    where
+        C: embedded_io_async::Read,
+        C: embedded_io_async::Write,
+        B: Buffer,
+        T: Timeout,
+    {
+        client.subscribe(self.desired_topic()).await?;
+        Ok(())
+    }
+
+    /// Publishes the current `state` as a retained message on the reported topic.
+    ///
+    /// Reporting the state periodically, see [`Self::due_in`], doubles as a heartbeat.
+    pub async fn report<C, B, T>(
+        &self,
+        client: &mut Client<C, B, T>,
+        state: &[u8],
+    ) -> Result<(), C::Error>
+    where
+        C: embedded_io_async::Read,
+        C: embedded_io_async::Write,
+        B: Buffer,
+        T: Timeout,
+    {
+        client
+            .publish(self.reported_topic(), state)
+            .retain(true)
+            .await?;
+        Ok(())
+    }
+
+    fn topic(&self, index: usize) -> &str {
+        let start = match index {
+            0 => 0,
+            index => self.ends[index - 1],
+        };
+        let topic = &self.buffer[start..self.ends[index]];
+        // The topics are copied from valid strings in `Self::new`.
+        core::str::from_utf8(topic).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shadow_topics() {
+        let shadow = Shadow::<64>::new("d/1", TopicScheme::new(), Duration::from_secs(60)).unwrap();
+        assert_eq!(shadow.status_topic(), "d/1/status");
+        assert_eq!(shadow.reported_topic(), "d/1/reported");
+        assert_eq!(shadow.desired_topic(), "d/1/desired");
+
+        let scheme = TopicScheme::new()
+            .status("online")
+            .reported("state")
+            .desired("state/set");
+        let shadow = Shadow::<64>::new("d/1", scheme, Duration::from_secs(60)).unwrap();
+        assert_eq!(shadow.status_topic(), "d/1/online");
+        assert_eq!(shadow.reported_topic(), "d/1/state");
+        assert_eq!(shadow.desired_topic(), "d/1/state/set");
+    }
+
+    #[test]
+    fn test_shadow_too_small() {
+        // The status and reported topic fit, the desired topic does not.
+        assert!(Shadow::<26>::new("d", TopicScheme::new(), Duration::ZERO).is_none());
+        assert!(Shadow::<27>::new("d", TopicScheme::new(), Duration::ZERO).is_some());
+    }
+
+    #[test]
+    fn test_shadow_invalid_topic() {
+        assert!(Shadow::<64>::new("d/+", TopicScheme::new(), Duration::ZERO).is_none());
+    }
+
+    #[test]
+    fn test_shadow_due_in() {
+        let shadow = Shadow::<64>::new("d", TopicScheme::new(), Duration::from_secs(60)).unwrap();
+        assert_eq!(
+            shadow.due_in(Duration::from_secs(20)),
+            Duration::from_secs(40)
+        );
+        assert_eq!(shadow.due_in(Duration::from_secs(90)), Duration::ZERO);
+    }
+}
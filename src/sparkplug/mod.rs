@@ -0,0 +1,451 @@
+//! Sparkplug B topic namespace and session sequencing for edge nodes.
+//!
+//! Sparkplug B defines how industrial devices publish their state over MQTT. Edge nodes publish
+//! to topics of the form `spBv1.0/<group_id>/<message_type>/<edge_node_id>[/<device_id>]`, see
+//! [`Topic`].
+//!
+//! An [`EdgeNode`] tracks the sequence numbers of a node:
+//!
+//! - the birth/death sequence number (`bdSeq`), which ties the NBIRTH of a session to the
+//!   NDEATH, registered as will when connecting,
+//! - the message sequence number (`seq`), which starts at `0` with the NBIRTH and increments
+//!   with every following message of the node.
+//!
+//! Payloads are protobuf encoded, an encoder is available with the `sparkplug-protobuf` feature
+//! in the `protobuf` module.
+//!
+//! Requires the `sparkplug` feature.
+//!
+//! Spec: [Sparkplug 3.0](https://sparkplug.eclipse.org/specification/version/3.0/documents/sparkplug-specification-3.0.0.pdf)
+//!
+//! # Examples:
+//!
+//! ```
+//! # use miniqtt::sparkplug::{EdgeNode, MessageType};
+//! # async fn example<C>(client: &mut miniqtt::Client<C, Vec<u8>>) -> miniqtt::client::Result<(), C::Error>
+//! # where
+//! #     C: embedded_io_async::Read + embedded_io_async::Write,
+//! # {
+//! # let (death, birth, data): (&[u8], &[u8], &[u8]) = (&[], &[], &[]);
+//! let mut node = EdgeNode::<64>::new("plant-1", "line-4").expect("valid identifiers");
+//!
+//! // The NDEATH payload contains `node.bd_seq()`.
+//! client.connect("line-4").with_will(node.death(death)).await?;
+//! node.subscribe_commands(client).await?;
+//!
+//! // The NBIRTH payload contains `node.bd_seq()` and the sequence number `node.birth()`.
+//! node.publish(client, MessageType::NBirth, None, birth).await?;
+//! // Following payloads contain the sequence number `node.next_seq()`.
+//! node.publish(client, MessageType::NData, None, data).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use core::fmt;
+
+use crate::client::{Client, Error, Result, Timeout};
+use crate::protocol::{QoS, ValidationError, v5};
+use crate::traits::Buffer;
+
+#[cfg(feature = "sparkplug-protobuf")]
+pub mod protobuf;
+
+/// The namespace of all Sparkplug B topics.
+pub const NAMESPACE: &str = "spBv1.0";
+
+/// The type of a Sparkplug message, part of its [`Topic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    /// Birth certificate of an edge node.
+    NBirth,
+    /// Death certificate of an edge node.
+    NDeath,
+    /// Birth certificate of a device.
+    DBirth,
+    /// Death certificate of a device.
+    DDeath,
+    /// Data of an edge node.
+    NData,
+    /// Data of a device.
+    DData,
+    /// Command to an edge node.
+    NCmd,
+    /// Command to a device.
+    DCmd,
+}
+
+impl MessageType {
+    /// Returns the type as it appears in a topic.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NBirth => "NBIRTH",
+            Self::NDeath => "NDEATH",
+            Self::DBirth => "DBIRTH",
+            Self::DDeath => "DDEATH",
+            Self::NData => "NDATA",
+            Self::DData => "DDATA",
+            Self::NCmd => "NCMD",
+            Self::DCmd => "DCMD",
+        }
+    }
+
+    /// Returns whether the message is addressed to a device, rather than an edge node.
+    pub fn is_device(&self) -> bool {
+        matches!(self, Self::DBirth | Self::DDeath | Self::DData | Self::DCmd)
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        let ty = match s {
+            "NBIRTH" => Self::NBirth,
+            "NDEATH" => Self::NDeath,
+            "DBIRTH" => Self::DBirth,
+            "DDEATH" => Self::DDeath,
+            "NDATA" => Self::NData,
+            "DDATA" => Self::DData,
+            "NCMD" => Self::NCmd,
+            "DCMD" => Self::DCmd,
+            _ => return None,
+        };
+        Some(ty)
+    }
+}
+
+/// A topic of the Sparkplug B namespace.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::sparkplug::{MessageType, Topic};
+/// let topic = Topic::parse("spBv1.0/plant-1/DCMD/line-4/press").unwrap();
+/// assert_eq!(topic.message_type, MessageType::DCmd);
+/// assert_eq!(topic.device_id, Some("press"));
+///
+/// let mut buffer = [0; 64];
+/// assert_eq!(topic.write(&mut buffer), Some("spBv1.0/plant-1/DCMD/line-4/press"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Topic<'a> {
+    pub group_id: &'a str,
+    pub message_type: MessageType,
+    pub edge_node_id: &'a str,
+    /// The device, only present for device messages.
+    pub device_id: Option<&'a str>,
+}
+
+impl<'a> Topic<'a> {
+    /// Parses a Sparkplug B topic.
+    ///
+    /// Returns `None` if the topic is not in the Sparkplug B namespace, or the presence of the
+    /// device does not match the message type.
+    pub fn parse(topic: &'a str) -> Option<Self> {
+        let mut levels = topic.split('/');
+        if levels.next()? != NAMESPACE {
+            return None;
+        }
+
+        let group_id = levels.next()?;
+        let message_type = MessageType::from_str(levels.next()?)?;
+        let edge_node_id = levels.next()?;
+        let device_id = levels.next();
+        if levels.next().is_some() || message_type.is_device() != device_id.is_some() {
+            return None;
+        }
+
+        let topic = Self {
+            group_id,
+            message_type,
+            edge_node_id,
+            device_id,
+        };
+        topic.is_valid().then_some(topic)
+    }
+
+    /// Writes the topic into `buffer`.
+    ///
+    /// Returns `None` if the buffer is too small.
+    pub fn write<'b>(&self, buffer: &'b mut [u8]) -> Option<&'b str> {
+        let mut len = 0;
+        for part in self.parts().flatten() {
+            buffer
+                .get_mut(len..len + part.len())?
+                .copy_from_slice(part.as_bytes());
+            len += part.len();
+        }
+
+        core::str::from_utf8(&buffer[..len]).ok()
+    }
+
+    fn parts(&self) -> impl Iterator<Item = Option<&'a str>> {
+        [
+            Some(NAMESPACE),
+            Some("/"),
+            Some(self.group_id),
+            Some("/"),
+            Some(self.message_type.as_str()),
+            Some("/"),
+            Some(self.edge_node_id),
+            self.device_id.map(|_| "/"),
+            self.device_id,
+        ]
+        .into_iter()
+    }
+
+    fn is_valid(&self) -> bool {
+        [self.group_id, self.edge_node_id]
+            .into_iter()
+            .chain(self.device_id)
+            .all(is_valid_id)
+    }
+}
+
+impl fmt::Display for Topic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.parts()
+            .flatten()
+            .try_for_each(|part| f.write_str(part))
+    }
+}
+
+/// Returns whether `id` can be used as a group, edge node or device identifier.
+///
+/// Identifiers must not be empty and must not contain `/`, `+` or `#`.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && !id.contains(['/', '+', '#'])
+}
+
+/// A Sparkplug B edge node, see the [module documentation](self).
+///
+/// The NDEATH topic is stored inline, it must fit into `N` bytes. Topics of other messages are
+/// formatted into a buffer of `N` bytes on the stack when publishing.
+#[derive(Debug, Clone)]
+pub struct EdgeNode<'a, const N: usize = 128> {
+    group_id: &'a str,
+    edge_node_id: &'a str,
+    death_topic: [u8; N],
+    death_topic_len: usize,
+    bd_seq: u8,
+    seq: u8,
+}
+
+impl<'a, const N: usize> EdgeNode<'a, N> {
+    /// Creates a new edge node with the birth/death sequence number `0`.
+    ///
+    /// Returns `None` if an identifier is invalid or the NDEATH topic does not fit into the
+    /// buffer.
+    pub fn new(group_id: &'a str, edge_node_id: &'a str) -> Option<Self> {
+        let topic = Topic {
+            group_id,
+            message_type: MessageType::NDeath,
+            edge_node_id,
+            device_id: None,
+        };
+        if !topic.is_valid() {
+            return None;
+        }
+
+        let mut death_topic = [0; N];
+        let death_topic_len = topic.write(&mut death_topic)?.len();
+
+        Some(Self {
+            group_id,
+            edge_node_id,
+            death_topic,
+            death_topic_len,
+            bd_seq: 0,
+            seq: 0,
+        })
+    }
+
+    /// Returns the birth/death sequence number of the current session.
+    pub fn bd_seq(&self) -> u8 {
+        self.bd_seq
+    }
+
+    /// Starts a new session, must be called before reconnecting.
+    ///
+    /// Increments the birth/death sequence number, the NDEATH and NBIRTH of the new session
+    /// must contain the new number.
+    pub fn next_session(&mut self) -> u8 {
+        self.bd_seq = self.bd_seq.wrapping_add(1);
+        self.bd_seq
+    }
+
+    /// Returns the sequence number of an NBIRTH, which restarts the message sequence.
+    pub fn birth(&mut self) -> u8 {
+        self.seq = 0;
+        self.seq
+    }
+
+    /// Returns the sequence number of the next message after the NBIRTH.
+    pub fn next_seq(&mut self) -> u8 {
+        self.seq = self.seq.wrapping_add(1);
+        self.seq
+    }
+
+    /// Returns the NDEATH topic of the node.
+    pub fn death_topic(&self) -> &str {
+        // The topic is written from valid strings in `Self::new`.
+        core::str::from_utf8(&self.death_topic[..self.death_topic_len]).unwrap_or_default()
+    }
+
+    /// Returns the NDEATH will, used when [connecting].
+    ///
+    /// The `payload` must contain the current [birth/death sequence number](Self::bd_seq).
+    ///
+    /// [connecting]: crate::client::Connect::with_will
+    pub fn death<'s>(&'s self, payload: &'s [u8]) -> v5::Will<'s> {
+        v5::Will {
+            retain: false,
+            qos: QoS::AtLeastOnce,
+            properties: &[],
+            topic: self.death_topic(),
+            payload,
+        }
+    }
+
+    /// Returns the topic of a message of the node, or of one of its devices.
+    pub fn topic<'t>(&self, message_type: MessageType, device_id: Option<&'t str>) -> Topic<'t>
+    where
+        'a: 't,
+    {
+        Topic {
+            group_id: self.group_id,
+            message_type,
+            edge_node_id: self.edge_node_id,
+            device_id,
+        }
+    }
+
+    /// Publishes a message of the node, or of one of its devices.
+    ///
+    /// The `payload` must contain the sequence number of the message, see [`Self::birth`] and
+    /// [`Self::next_seq`].
+    ///
+    /// Returns [`Error::Invalid`] if the device is missing for a device message, is given for a
+    /// node message or is not a valid identifier, or the topic does not fit into `N` bytes.
+    pub async fn publish<C, B, T>(
+        &self,
+        client: &mut Client<C, B, T>,
+        message_type: MessageType,
+        device_id: Option<&str>,
+        payload: &[u8],
+    ) -> Result<(), C::Error>
+    where
+        C: embedded_io_async::Read,
+        C: embedded_io_async::Write,
+        B: Buffer,
+        T: Timeout,
+    {
+        let topic = self.topic(message_type, device_id);
+        match (message_type.is_device(), device_id) {
+            (true, None) | (_, Some("")) => return Err(Error::Invalid(ValidationError::Empty)),
+            (false, Some(_)) => return Err(Error::Invalid(ValidationError::TooLong)),
+            _ if !topic.is_valid() => return Err(Error::Invalid(ValidationError::Wildcard)),
+            _ => {}
+        }
+
+        let mut buffer = [0; N];
+        let topic = write_topic(&topic, &mut buffer)?;
+        client.publish(topic, payload).await?;
+        Ok(())
+    }
+
+    /// Subscribes to the commands for the node and all of its devices.
+    pub async fn subscribe_commands<C, B, T>(
+        &self,
+        client: &mut Client<C, B, T>,
+    ) -> Result<(), C::Error>
+    where
+        C: embedded_io_async::Read,
+        C: embedded_io_async::Write,
+        B: Buffer,
+        T: Timeout,
+    {
+        let mut buffer = [0; N];
+        let node = self.topic(MessageType::NCmd, None);
+        client.subscribe(write_topic(&node, &mut buffer)?).await?;
+
+        let devices = self.topic(MessageType::DCmd, Some("+"));
+        client
+            .subscribe(write_topic(&devices, &mut buffer)?)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Writes `topic` into `buffer`, failing if the buffer is too small.
+fn write_topic<'b, E>(topic: &Topic<'_>, buffer: &'b mut [u8]) -> Result<&'b str, E> {
+    topic
+        .write(buffer)
+        .ok_or(Error::Invalid(ValidationError::TooLong))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_parse() {
+        let topic = Topic::parse("spBv1.0/g/NDATA/n").unwrap();
+        assert_eq!(
+            topic,
+            Topic {
+                group_id: "g",
+                message_type: MessageType::NData,
+                edge_node_id: "n",
+                device_id: None,
+            }
+        );
+        assert_eq!(topic.to_string(), "spBv1.0/g/NDATA/n");
+
+        let topic = Topic::parse("spBv1.0/g/DDATA/n/d").unwrap();
+        assert_eq!(topic.device_id, Some("d"));
+        assert_eq!(topic.to_string(), "spBv1.0/g/DDATA/n/d");
+
+        assert!(Topic::parse("spAv1.0/g/NDATA/n").is_none());
+        assert!(Topic::parse("spBv1.0/g/NDATA/n/d").is_none());
+        assert!(Topic::parse("spBv1.0/g/DDATA/n").is_none());
+        assert!(Topic::parse("spBv1.0/g/DDATA/n/d/e").is_none());
+        assert!(Topic::parse("spBv1.0/g/STATE/n").is_none());
+        assert!(Topic::parse("spBv1.0//NDATA/n").is_none());
+    }
+
+    #[test]
+    fn test_topic_write() {
+        let topic = Topic::parse("spBv1.0/g/DCMD/n/d").unwrap();
+        let mut buffer = [0; 18];
+        assert_eq!(topic.write(&mut buffer), Some("spBv1.0/g/DCMD/n/d"));
+        let mut buffer = [0; 17];
+        assert_eq!(topic.write(&mut buffer), None);
+    }
+
+    #[test]
+    fn test_edge_node_sequence() {
+        let mut node = EdgeNode::<32>::new("g", "n").unwrap();
+        assert_eq!(node.death_topic(), "spBv1.0/g/NDEATH/n");
+        assert_eq!(node.bd_seq(), 0);
+
+        assert_eq!(node.birth(), 0);
+        assert_eq!(node.next_seq(), 1);
+        assert_eq!(node.next_seq(), 2);
+
+        // The sequence wraps around after 255.
+        for _ in 3..=255 {
+            node.next_seq();
+        }
+        assert_eq!(node.next_seq(), 0);
+
+        // A new session restarts the sequence with the birth.
+        assert_eq!(node.next_session(), 1);
+        assert_eq!(node.birth(), 0);
+    }
+
+    #[test]
+    fn test_edge_node_invalid() {
+        assert!(EdgeNode::<32>::new("g/h", "n").is_none());
+        assert!(EdgeNode::<32>::new("g", "").is_none());
+        assert!(EdgeNode::<32>::new("g", "#").is_none());
+        // The NDEATH topic does not fit.
+        assert!(EdgeNode::<17>::new("g", "n").is_none());
+    }
+}
@@ -0,0 +1,309 @@
+//! Protobuf encoding of Sparkplug B payloads.
+//!
+//! Encodes the subset of the Sparkplug B payload schema, which is needed to publish births,
+//! deaths and data of scalar metrics: timestamps, sequence numbers and metrics with a name, an
+//! alias, a timestamp and a value. Payloads are written into a caller supplied buffer, without
+//! allocating.
+//!
+//! Requires the `sparkplug-protobuf` feature.
+//!
+//! # Examples:
+//!
+//! ```
+//! # use miniqtt::sparkplug::EdgeNode;
+//! # use miniqtt::sparkplug::protobuf::{Metric, PayloadWriter, Value};
+//! let mut node = EdgeNode::<64>::new("plant-1", "line-4").unwrap();
+//!
+//! let mut buffer = [0; 128];
+//! let birth = PayloadWriter::new(&mut buffer)
+//!     .timestamp(1_700_000_000_000)
+//!     .seq(node.birth())
+//!     .metric(&Metric::bd_seq(node.bd_seq()))
+//!     .metric(&Metric::new("temperature", Value::Float(21.5)).with_alias(1))
+//!     .finish()
+//!     .expect("payload fits into the buffer");
+//! # assert!(!birth.is_empty());
+//! ```
+
+/// A value of a [`Metric`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value<'a> {
+    Int32(i32),
+    Int64(i64),
+    UInt32(u32),
+    UInt64(u64),
+    Float(f32),
+    Double(f64),
+    Boolean(bool),
+    String(&'a str),
+}
+
+impl Value<'_> {
+    /// Returns the Sparkplug data type of the value.
+    pub fn data_type(&self) -> u32 {
+        match self {
+            Self::Int32(_) => 3,
+            Self::Int64(_) => 4,
+            Self::UInt32(_) => 7,
+            Self::UInt64(_) => 8,
+            Self::Float(_) => 9,
+            Self::Double(_) => 10,
+            Self::Boolean(_) => 11,
+            Self::String(_) => 12,
+        }
+    }
+}
+
+/// A metric of a Sparkplug B payload.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metric<'a> {
+    /// The name of the metric, may be omitted after the birth when an alias is used.
+    pub name: Option<&'a str>,
+    /// The alias of the metric, announced in the birth.
+    pub alias: Option<u64>,
+    /// The time the value was recorded, in milliseconds since the Unix epoch.
+    pub timestamp: Option<u64>,
+    pub value: Value<'a>,
+}
+
+impl<'a> Metric<'a> {
+    /// Creates a new metric with a name.
+    pub fn new(name: &'a str, value: Value<'a>) -> Self {
+        Self {
+            name: Some(name),
+            alias: None,
+            timestamp: None,
+            value,
+        }
+    }
+
+    /// Creates a new metric, which is only identified by its alias.
+    pub fn aliased(alias: u64, value: Value<'a>) -> Self {
+        Self {
+            name: None,
+            alias: Some(alias),
+            timestamp: None,
+            value,
+        }
+    }
+
+    /// Creates the `bdSeq` metric, which must be included in every NBIRTH and NDEATH.
+    pub fn bd_seq(bd_seq: u8) -> Self {
+        Self::new("bdSeq", Value::UInt64(bd_seq.into()))
+    }
+
+    /// Sets the alias of the metric.
+    pub fn with_alias(mut self, alias: u64) -> Self {
+        self.alias = Some(alias);
+        self
+    }
+
+    /// Sets the time the value was recorded.
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    fn encode(&self, out: &mut Output<'_>) {
+        if let Some(name) = self.name {
+            out.bytes_field(1, name.as_bytes());
+        }
+        if let Some(alias) = self.alias {
+            out.varint_field(2, alias);
+        }
+        if let Some(timestamp) = self.timestamp {
+            out.varint_field(3, timestamp);
+        }
+        out.varint_field(4, self.value.data_type().into());
+
+        match self.value {
+            // Signed integers are stored as their two's complement.
+            Value::Int32(v) => out.varint_field(10, (v as u32).into()),
+            Value::Int64(v) => out.varint_field(11, v as u64),
+            Value::UInt32(v) => out.varint_field(10, v.into()),
+            Value::UInt64(v) => out.varint_field(11, v),
+            Value::Float(v) => {
+                out.tag(12, WIRE_FIXED32);
+                out.bytes(&v.to_le_bytes());
+            }
+            Value::Double(v) => {
+                out.tag(13, WIRE_FIXED64);
+                out.bytes(&v.to_le_bytes());
+            }
+            Value::Boolean(v) => out.varint_field(14, v.into()),
+            Value::String(v) => out.bytes_field(15, v.as_bytes()),
+        }
+    }
+
+    fn encoded_len(&self) -> usize {
+        let mut out = Output::new(&mut []);
+        self.encode(&mut out);
+        out.len
+    }
+}
+
+/// Writes a Sparkplug B payload into a buffer.
+///
+/// Fields are written in the order they are added, [`Self::finish`] fails if the buffer was too
+/// small.
+pub struct PayloadWriter<'b> {
+    out: Output<'b>,
+}
+
+impl<'b> PayloadWriter<'b> {
+    /// Creates a new writer, which writes into `buffer`.
+    pub fn new(buffer: &'b mut [u8]) -> Self {
+        Self {
+            out: Output::new(buffer),
+        }
+    }
+
+    /// Writes the time the payload was created, in milliseconds since the Unix epoch.
+    pub fn timestamp(mut self, timestamp: u64) -> Self {
+        self.out.varint_field(1, timestamp);
+        self
+    }
+
+    /// Writes a metric.
+    pub fn metric(mut self, metric: &Metric<'_>) -> Self {
+        self.out.tag(2, WIRE_LEN);
+        self.out.varint(metric.encoded_len() as u64);
+        metric.encode(&mut self.out);
+        self
+    }
+
+    /// Writes the sequence number of the message.
+    pub fn seq(mut self, seq: u8) -> Self {
+        self.out.varint_field(3, seq.into());
+        self
+    }
+
+    /// Returns the encoded payload, or `None` if the buffer is too small.
+    pub fn finish(self) -> Option<&'b [u8]> {
+        let Output {
+            buffer,
+            len,
+            overflow,
+        } = self.out;
+        (!overflow).then(|| &buffer[..len])
+    }
+}
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_FIXED64: u8 = 1;
+const WIRE_LEN: u8 = 2;
+const WIRE_FIXED32: u8 = 5;
+
+/// Protobuf output, which keeps counting the length once the buffer is full.
+struct Output<'b> {
+    buffer: &'b mut [u8],
+    len: usize,
+    overflow: bool,
+}
+
+impl<'b> Output<'b> {
+    fn new(buffer: &'b mut [u8]) -> Self {
+        Self {
+            buffer,
+            len: 0,
+            overflow: false,
+        }
+    }
+
+    fn bytes(&mut self, data: &[u8]) {
+        match self.buffer.get_mut(self.len..self.len + data.len()) {
+            Some(dst) if !self.overflow => dst.copy_from_slice(data),
+            _ => self.overflow = true,
+        }
+        self.len += data.len();
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.bytes(&[byte]);
+                return;
+            }
+            self.bytes(&[byte | 0x80]);
+        }
+    }
+
+    fn tag(&mut self, field: u8, wire_type: u8) {
+        self.varint(u64::from((field << 3) | wire_type));
+    }
+
+    fn varint_field(&mut self, field: u8, value: u64) {
+        self.tag(field, WIRE_VARINT);
+        self.varint(value);
+    }
+
+    fn bytes_field(&mut self, field: u8, data: &[u8]) {
+        self.tag(field, WIRE_LEN);
+        self.varint(data.len() as u64);
+        self.bytes(data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_bd_seq() {
+        let mut buffer = [0; 32];
+        let payload = PayloadWriter::new(&mut buffer)
+            .timestamp(300)
+            .metric(&Metric::bd_seq(3))
+            .seq(0)
+            .finish()
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            // timestamp
+            0x08, 0xac, 0x02,
+            // metric, name `bdSeq`, datatype UInt64, long_value 3
+            0x12, 0x0b, 0x0a, 0x05, b'b', b'd', b'S', b'e', b'q', 0x20, 0x08, 0x58, 0x03,
+            // seq
+            0x18, 0x00,
+        ];
+        assert_eq!(payload, expected);
+    }
+
+    #[test]
+    fn test_payload_values() {
+        let mut buffer = [0; 64];
+        let payload = PayloadWriter::new(&mut buffer)
+            .metric(&Metric::aliased(1, Value::Int32(-1)))
+            .metric(&Metric::aliased(2, Value::Float(1.0)).with_timestamp(1))
+            .metric(&Metric::aliased(3, Value::Boolean(true)))
+            .finish()
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            // alias 1, datatype Int32, int_value -1 as two's complement
+            0x12, 0x0a, 0x10, 0x01, 0x20, 0x03, 0x50, 0xff, 0xff, 0xff, 0xff, 0x0f,
+            // alias 2, timestamp 1, datatype Float, float_value 1.0
+            0x12, 0x0b, 0x10, 0x02, 0x18, 0x01, 0x20, 0x09, 0x65, 0x00, 0x00, 0x80, 0x3f,
+            // alias 3, datatype Boolean, boolean_value true
+            0x12, 0x06, 0x10, 0x03, 0x20, 0x0b, 0x70, 0x01,
+        ];
+        assert_eq!(payload, expected);
+    }
+
+    #[test]
+    fn test_payload_too_small() {
+        let metric = Metric::new("temperature", Value::String("warm"));
+
+        let mut buffer = [0; 22];
+        let writer = PayloadWriter::new(&mut buffer).metric(&metric);
+        assert!(writer.finish().is_none());
+
+        let mut buffer = [0; 23];
+        let writer = PayloadWriter::new(&mut buffer).metric(&metric);
+        assert_eq!(writer.finish().map(|payload| payload.len()), Some(23));
+    }
+}
@@ -0,0 +1,356 @@
+//! Matching of topic names against topic filters.
+//!
+//! Spec: [4.7](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901241)
+
+use core::fmt;
+
+/// A fixed capacity tree, mapping topic filters to values.
+///
+/// Filters are split into their levels, filters sharing a common prefix share the nodes of the
+/// prefix. The tree stores up to `N` levels in total, it does not allocate.
+///
+/// Looking up a topic name yields the values of all matching filters, including filters with the
+/// single level (`+`) and multi level (`#`) wildcards. This can be used to route received
+/// messages to handlers.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::topic::TopicTrie;
+/// let mut routes = TopicTrie::<_, 8>::new();
+/// routes.insert("sensor/+/temperature", "temperature").unwrap();
+/// routes.insert("sensor/#", "all").unwrap();
+/// routes.insert("command/reboot", "reboot").unwrap();
+///
+/// let matches: Vec<_> = routes.matches("sensor/kitchen/temperature").collect();
+/// assert_eq!(matches, [&"temperature", &"all"]);
+/// assert_eq!(routes.matches("command/reboot").next(), Some(&"reboot"));
+/// assert_eq!(routes.matches("command/shutdown").next(), None);
+/// ```
+pub struct TopicTrie<'a, T, const N: usize> {
+    nodes: [Option<Node<'a, T>>; N],
+}
+
+struct Node<'a, T> {
+    /// The level of the filter, represented by this node.
+    level: &'a str,
+    /// Index of the parent node, `None` for the first level of a filter.
+    parent: Option<usize>,
+    /// Value of the filter, which ends at this node.
+    value: Option<T>,
+}
+
+impl<'a, T, const N: usize> TopicTrie<'a, T, N> {
+    /// Creates a new, empty tree.
+    pub fn new() -> Self {
+        Self {
+            nodes: core::array::from_fn(|_| None),
+        }
+    }
+
+    /// Returns `true` if the tree contains no filters.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.iter().flatten().all(|node| node.value.is_none())
+    }
+
+    /// Inserts a value for a topic filter.
+    ///
+    /// Returns the previous value of the filter. Fails if the filter is invalid or the tree does
+    /// not have enough space left for the levels of the filter.
+    pub fn insert(&mut self, filter: &'a str, value: T) -> Result<Option<T>, TopicTrieError> {
+        if crate::protocol::validate_topic_filter(filter).is_err() {
+            return Err(TopicTrieError::InvalidFilter);
+        }
+
+        let mut parent = None;
+        let mut levels = filter.split('/');
+        // Walk the existing prefix of the filter, then check there is enough space for the rest.
+        for level in levels.by_ref() {
+            match self.child(parent, level) {
+                Some(index) => parent = Some(index),
+                None => {
+                    let needed = 1 + levels.clone().count();
+                    let free = self.nodes.iter().filter(|node| node.is_none()).count();
+                    if needed > free {
+                        return Err(TopicTrieError::Full);
+                    }
+
+                    parent = Some(self.push(parent, level));
+                    break;
+                }
+            }
+        }
+        for level in levels {
+            parent = Some(self.push(parent, level));
+        }
+
+        // A filter has at least one level.
+        let node = self.node_mut(parent.unwrap());
+        Ok(node.value.replace(value))
+    }
+
+    /// Returns the value of a topic filter.
+    ///
+    /// Filters are compared literally, wildcards are not expanded.
+    pub fn get(&self, filter: &str) -> Option<&T> {
+        let index = self.find(filter)?;
+        self.nodes[index].as_ref()?.value.as_ref()
+    }
+
+    /// Removes a topic filter, returning its value.
+    pub fn remove(&mut self, filter: &str) -> Option<T> {
+        let index = self.find(filter)?;
+        let value = self.node_mut(index).value.take();
+
+        // Free all levels, which are no longer used by any filter.
+        let mut current = Some(index);
+        while let Some(index) = current {
+            let node = self.nodes[index].as_ref()?;
+            if node.value.is_some() || self.has_children(index) {
+                break;
+            }
+            current = node.parent;
+            self.nodes[index] = None;
+        }
+
+        value
+    }
+
+    /// Returns an iterator over the values of all filters matching the topic name.
+    pub fn matches<'t>(&'t self, topic: &'t str) -> Matches<'t, 'a, T, N> {
+        Matches {
+            trie: self,
+            topic,
+            levels: topic.split('/').count(),
+            index: 0,
+        }
+    }
+
+    /// Returns the index of the node matching the filter exactly.
+    fn find(&self, filter: &str) -> Option<usize> {
+        let mut parent = None;
+        for level in filter.split('/') {
+            parent = Some(self.child(parent, level)?);
+        }
+        parent
+    }
+
+    fn child(&self, parent: Option<usize>, level: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| {
+            node.as_ref()
+                .is_some_and(|node| node.parent == parent && node.level == level)
+        })
+    }
+
+    fn has_children(&self, index: usize) -> bool {
+        self.nodes
+            .iter()
+            .flatten()
+            .any(|node| node.parent == Some(index))
+    }
+
+    /// Adds a new node, there must be a free slot.
+    fn push(&mut self, parent: Option<usize>, level: &'a str) -> usize {
+        let index = self.nodes.iter().position(Option::is_none).unwrap();
+        self.nodes[index] = Some(Node {
+            level,
+            parent,
+            value: None,
+        });
+        index
+    }
+
+    fn node_mut(&mut self, index: usize) -> &mut Node<'a, T> {
+        // Indices are only ever taken from existing nodes.
+        self.nodes[index].as_mut().unwrap()
+    }
+
+    /// Returns `true` if the filter ending at `index` matches the topic name.
+    fn is_match(&self, index: usize, topic: &str, levels: usize) -> bool {
+        let mut depth = 0;
+        let mut current = Some(index);
+        while let Some(index) = current {
+            depth += 1;
+            current = self.nodes[index].as_ref().and_then(|node| node.parent);
+        }
+
+        let mut current = Some(index);
+        let mut level = depth;
+        while let Some(index) = current {
+            let Some(node) = &self.nodes[index] else {
+                return false;
+            };
+            level -= 1;
+
+            let matches = match node.level {
+                // Also matches the parent level, `a/#` matches `a`.
+                "#" => levels >= level,
+                "+" => level < levels,
+                name => topic.split('/').nth(level) == Some(name),
+            };
+            // Wildcards do not match topics starting with `$` on the first level.
+            let is_wildcard = node.level == "#" || node.level == "+";
+            if !matches || (level == 0 && is_wildcard && is_reserved(topic)) {
+                return false;
+            }
+            if level + 1 == depth && node.level != "#" && levels != depth {
+                return false;
+            }
+
+            current = node.parent;
+        }
+
+        true
+    }
+}
+
+impl<T, const N: usize> Default for TopicTrie<'_, T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for TopicTrie<'_, T, N>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TopicTrie")
+            .field("levels", &self.nodes.iter().flatten().count())
+            .field("capacity", &N)
+            .finish()
+    }
+}
+
+/// Iterator over the values of all filters matching a topic name, see [`TopicTrie::matches`].
+pub struct Matches<'t, 'a, T, const N: usize> {
+    trie: &'t TopicTrie<'a, T, N>,
+    topic: &'t str,
+    /// Amount of levels of the topic name.
+    levels: usize,
+    /// Index of the next node to check.
+    index: usize,
+}
+
+impl<'t, T, const N: usize> Iterator for Matches<'t, '_, T, N> {
+    type Item = &'t T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < N {
+            let index = self.index;
+            self.index += 1;
+
+            let Some(Node {
+                value: Some(value), ..
+            }) = &self.trie.nodes[index]
+            else {
+                continue;
+            };
+            if self.trie.is_match(index, self.topic, self.levels) {
+                return Some(value);
+            }
+        }
+
+        None
+    }
+}
+
+/// Returned when a filter cannot be inserted into a [`TopicTrie`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopicTrieError {
+    /// The tree has not enough space left for the levels of the filter.
+    Full,
+    /// The filter is not a valid topic filter.
+    InvalidFilter,
+}
+
+impl fmt::Display for TopicTrieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => f.write_str("topic tree is full"),
+            Self::InvalidFilter => f.write_str("invalid topic filter"),
+        }
+    }
+}
+
+impl core::error::Error for TopicTrieError {}
+
+/// Returns `true` if the topic is reserved for the server.
+///
+/// Topics starting with `$` are used by servers for their own purposes, like `$SYS`. Clients
+/// can subscribe to them, but servers usually reject or drop publishes to them.
+///
+/// Spec: [4.7.2](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901246)
+pub fn is_reserved(topic: &str) -> bool {
+    topic.starts_with('$')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches<const N: usize>(trie: &TopicTrie<'_, u8, N>, topic: &str) -> Vec<u8> {
+        let mut matches: Vec<_> = trie.matches(topic).copied().collect();
+        matches.sort();
+        matches
+    }
+
+    #[test]
+    fn test_topic_trie_wildcards() {
+        let mut trie = TopicTrie::<u8, 16>::new();
+        trie.insert("a/b/c", 1).unwrap();
+        trie.insert("a/+/c", 2).unwrap();
+        trie.insert("a/#", 3).unwrap();
+        trie.insert("#", 4).unwrap();
+        trie.insert("+/b", 5).unwrap();
+        trie.insert("a/b", 6).unwrap();
+
+        assert_eq!(matches(&trie, "a/b/c"), [1, 2, 3, 4]);
+        assert_eq!(matches(&trie, "a/x/c"), [2, 3, 4]);
+        assert_eq!(matches(&trie, "a/b"), [3, 4, 5, 6]);
+        assert_eq!(matches(&trie, "a"), [3, 4]);
+        assert_eq!(matches(&trie, "x/b/c"), [4]);
+        assert_eq!(matches(&trie, "a/b/c/d"), [3, 4]);
+        // Wildcards do not match topics starting with `$`.
+        assert_eq!(matches(&trie, "$SYS/b"), [0u8; 0]);
+    }
+
+    #[test]
+    fn test_topic_trie_shares_prefixes() {
+        let mut trie = TopicTrie::<u8, 4>::new();
+        assert_eq!(trie.insert("a/b/c", 1), Ok(None));
+        assert_eq!(trie.insert("a/b/d", 2), Ok(None));
+        assert_eq!(trie.insert("a/b", 3), Ok(None));
+        assert_eq!(trie.insert("a/b", 4), Ok(Some(3)));
+        // Needs two more levels, but there is only one left.
+        assert_eq!(trie.insert("a/x/y", 5), Err(TopicTrieError::Full));
+        assert_eq!(matches(&trie, "a/x/y"), [0u8; 0]);
+
+        assert_eq!(trie.get("a/b/c"), Some(&1));
+        assert_eq!(trie.get("a"), None);
+    }
+
+    #[test]
+    fn test_topic_trie_remove() {
+        let mut trie = TopicTrie::<u8, 3>::new();
+        trie.insert("a/b/c", 1).unwrap();
+        assert_eq!(trie.insert("x", 2), Err(TopicTrieError::Full));
+
+        assert_eq!(trie.remove("a/b"), None);
+        assert_eq!(trie.remove("a/b/c"), Some(1));
+        assert!(trie.is_empty());
+
+        // All levels were freed.
+        trie.insert("x/y/z", 2).unwrap();
+        assert_eq!(matches(&trie, "x/y/z"), [2]);
+    }
+
+    #[test]
+    fn test_topic_trie_invalid_filter() {
+        let mut trie = TopicTrie::<u8, 8>::new();
+        for filter in ["", "a/#/b", "a/b#", "a+/b"] {
+            assert_eq!(trie.insert(filter, 1), Err(TopicTrieError::InvalidFilter));
+        }
+        assert!(trie.is_empty());
+    }
+}
@@ -1,7 +1,17 @@
+use core::fmt;
+
 /// Returned by [`Buffer::try_resize`] if the buffer is not resizable.
 #[derive(Debug, Clone, Copy)]
 pub struct BufferNotResizable;
 
+impl fmt::Display for BufferNotResizable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Buffer is not resizable")
+    }
+}
+
+impl core::error::Error for BufferNotResizable {}
+
 /// A continuous buffer, which may be resizable.
 pub trait Buffer {
     /// Returns the entire contents of the buffer as continuous byte slice.
@@ -0,0 +1,453 @@
+//! Utilities to adapt different kinds of transports for use with a [`Connection`].
+//!
+//! The client is not tied to TCP, any transport implementing [`embedded_io_async::Read`] and
+//! [`embedded_io_async::Write`] can be used.
+//!
+//! [`Connection`]: crate::Connection
+
+use core::fmt;
+
+use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+
+use crate::client::{Direction, Frame, Frames};
+
+/// Joins a separate read and write half into a single transport.
+///
+/// Some transports, like QUIC streams, are split into a receiving and sending half, which
+/// can be joined into a single transport using a [`Duplex`].
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::transport::Duplex;
+/// # fn example<R, W>(recv: R, send: W)
+/// # where
+/// #     R: embedded_io_async::Read,
+/// #     W: embedded_io_async::Write<Error = R::Error>,
+/// # {
+/// let transport = Duplex::new(recv, send);
+/// let connection = miniqtt::Connection::new(transport, [0; 128]);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Duplex<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl<R, W> Duplex<R, W> {
+    /// Creates a new [`Duplex`] from a read and a write half.
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader, writer }
+    }
+
+    /// Returns a reference to the read half.
+    pub fn reader(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a reference to the write half.
+    pub fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns the read and write half.
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+}
+
+impl<R, W> ErrorType for Duplex<R, W>
+where
+    R: ErrorType,
+    W: ErrorType<Error = R::Error>,
+{
+    type Error = R::Error;
+}
+
+impl<R, W> Read for Duplex<R, W>
+where
+    R: Read,
+    W: ErrorType<Error = R::Error>,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.reader.read(buf).await
+    }
+}
+
+impl<R, W> Write for Duplex<R, W>
+where
+    R: ErrorType,
+    W: Write<Error = R::Error>,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.writer.write(buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.writer.flush().await
+    }
+}
+
+/// One of two transports, selected at runtime.
+///
+/// The async traits of [`embedded_io_async`] cannot be used as trait objects. To switch
+/// transports at runtime, e.g. between Ethernet and Wi-Fi, the transports can be combined into
+/// an [`Either`], instead of making the entire application generic over the transport.
+///
+/// More than two transports are supported by nesting [`Either`]s.
+///
+/// Errors of the transports are wrapped into an [`Either`] as well.
+///
+/// # Examples:
+///
+/// ```
+/// # use miniqtt::transport::Either;
+/// # fn example<E, W>(ethernet: Option<E>, wifi: W)
+/// # where
+/// #     E: embedded_io_async::Read + embedded_io_async::Write,
+/// #     W: embedded_io_async::Read + embedded_io_async::Write,
+/// # {
+/// let transport = match ethernet {
+///     Some(ethernet) => Either::Left(ethernet),
+///     None => Either::Right(wifi),
+/// };
+/// let connection = miniqtt::Connection::new(transport, [0; 128]);
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+impl<L, R> ErrorType for Either<L, R>
+where
+    L: ErrorType,
+    R: ErrorType,
+{
+    type Error = Either<L::Error, R::Error>;
+}
+
+impl<L, R> Read for Either<L, R>
+where
+    L: Read,
+    R: Read,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        match self {
+            Self::Left(inner) => inner.read(buf).await.map_err(Either::Left),
+            Self::Right(inner) => inner.read(buf).await.map_err(Either::Right),
+        }
+    }
+}
+
+impl<L, R> Write for Either<L, R>
+where
+    L: Write,
+    R: Write,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        match self {
+            Self::Left(inner) => inner.write(buf).await.map_err(Either::Left),
+            Self::Right(inner) => inner.write(buf).await.map_err(Either::Right),
+        }
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        match self {
+            Self::Left(inner) => inner.flush().await.map_err(Either::Left),
+            Self::Right(inner) => inner.flush().await.map_err(Either::Right),
+        }
+    }
+}
+
+impl<L, R> embedded_io_async::Error for Either<L, R>
+where
+    L: embedded_io_async::Error,
+    R: embedded_io_async::Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Left(err) => err.kind(),
+            Self::Right(err) => err.kind(),
+        }
+    }
+}
+
+impl<L, R> fmt::Display for Either<L, R>
+where
+    L: fmt::Display,
+    R: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Left(err) => err.fmt(f),
+            Self::Right(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<L, R> core::error::Error for Either<L, R>
+where
+    L: core::error::Error,
+    R: core::error::Error,
+{
+}
+
+/// Replays a session captured by a [`Recorder`].
+///
+/// Received frames are returned by reads, in the recorded order. Writes are compared against
+/// the recorded sent frames and fail with [`ReplayError::Diverged`], if the client sends
+/// something else or sends while a received frame is due. Once all frames are replayed, reads
+/// return end of file.
+///
+/// Captures from the field can be saved with [`Recorder::as_bytes`] and turned into regression
+/// tests, which run the client against the captured session deterministically.
+///
+/// [`Recorder`]: crate::client::Recorder
+/// [`Recorder::as_bytes`]: crate::client::Recorder::as_bytes
+///
+/// # Examples:
+///
+/// ```no_run
+/// # use miniqtt::client::Frames;
+/// # use miniqtt::transport::Replay;
+/// # async fn example() {
+/// let capture = std::fs::read("capture.bin").unwrap();
+///
+/// let transport = Replay::new(Frames::new(&capture));
+/// let mut client = miniqtt::Client::new(miniqtt::Connection::new(transport, Vec::new()));
+///
+/// client.connect("sensor-1").await.unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Replay<'a> {
+    frames: Frames<'a>,
+    /// The frame which is currently replayed and the amount of its data already replayed.
+    current: Option<(Frame<'a>, usize)>,
+}
+
+impl<'a> Replay<'a> {
+    /// Creates a new [`Replay`] of `frames`.
+    pub fn new(frames: Frames<'a>) -> Self {
+        Self {
+            frames,
+            current: None,
+        }
+    }
+
+    /// Returns the frame which is currently replayed and its remaining data.
+    fn current(&mut self) -> Result<Option<(Direction, &'a [u8])>, ReplayError> {
+        if self
+            .current
+            .is_none_or(|(frame, offset)| offset == frame.data.len())
+        {
+            self.current = self.frames.next().map(|frame| (frame, 0));
+        }
+
+        match self.current {
+            None => Ok(None),
+            Some((frame, _)) if frame.is_truncated() => Err(ReplayError::Truncated),
+            Some((frame, offset)) => Ok(Some((frame.direction, &frame.data[offset..]))),
+        }
+    }
+
+    fn advance(&mut self, n: usize) {
+        if let Some((_, offset)) = &mut self.current {
+            *offset += n;
+        }
+    }
+}
+
+impl ErrorType for Replay<'_> {
+    type Error = ReplayError;
+}
+
+impl Read for Replay<'_> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        match self.current()? {
+            None => Ok(0),
+            Some((Direction::Received, data)) => {
+                let n = data.len().min(buf.len());
+                buf[..n].copy_from_slice(&data[..n]);
+                self.advance(n);
+                Ok(n)
+            }
+            Some((Direction::Sent, _)) => Err(ReplayError::Diverged),
+        }
+    }
+}
+
+impl Write for Replay<'_> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        match self.current()? {
+            Some((Direction::Sent, data)) => {
+                let n = data.len().min(buf.len());
+                if buf[..n] != data[..n] {
+                    return Err(ReplayError::Diverged);
+                }
+                self.advance(n);
+                Ok(n)
+            }
+            _ => Err(ReplayError::Diverged),
+        }
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Error returned by a [`Replay`] transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The client did not send the recorded data.
+    Diverged,
+    /// The replayed frame was truncated by the recorder.
+    Truncated,
+}
+
+impl embedded_io_async::Error for ReplayError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Diverged => f.write_str("client diverged from the recorded session"),
+            Self::Truncated => f.write_str("recorded frame is truncated"),
+        }
+    }
+}
+
+impl core::error::Error for ReplayError {}
+
+/// Adapts a blocking [`std::io::Read`] and [`std::io::Write`] transport.
+///
+/// Reads and writes block the current thread, futures of a client using this transport
+/// complete without ever waiting for the transport. They can be driven with [`block_on`],
+/// without an async runtime.
+///
+/// Waiting for a message blocks until data is received. To stop waiting, configure a read
+/// timeout on the transport, e.g. with [`TcpStream::set_read_timeout`], which surfaces as an
+/// [`Error::Connection`](crate::client::Error::Connection).
+///
+/// [`TcpStream::set_read_timeout`]: std::net::TcpStream::set_read_timeout
+///
+/// # Examples:
+///
+/// ```no_run
+/// # use miniqtt::transport::{Blocking, block_on};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let stream = std::net::TcpStream::connect("localhost:1883")?;
+/// let connection = miniqtt::Connection::new(Blocking::new(stream), Vec::new());
+/// let mut client = miniqtt::Client::new(connection);
+///
+/// block_on(client.connect("miniqtt"))?;
+/// block_on(client.send("greeting", b"hello"))?;
+/// block_on(client.disconnect())?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Blocking<T> {
+    inner: T,
+}
+
+#[cfg(feature = "std")]
+impl<T> Blocking<T> {
+    /// Creates a new [`Blocking`] transport.
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Returns a reference to the wrapped transport.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped transport.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Returns the wrapped transport.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> ErrorType for Blocking<T> {
+    type Error = std::io::Error;
+}
+
+#[cfg(feature = "std")]
+impl<T> Read for Blocking<T>
+where
+    T: std::io::Read,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.inner.read(buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Write for Blocking<T>
+where
+    T: std::io::Write,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.write(buf)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
+
+/// Runs a future to completion on the current thread.
+///
+/// The thread is parked while the future waits, e.g. for a
+/// [timeout](crate::client::Timeout). See [`Blocking`].
+#[cfg(feature = "std")]
+pub fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    use std::thread::Thread;
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Arc::new(Unpark(std::thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = core::pin::pin!(future);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
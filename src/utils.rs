@@ -1,3 +1,5 @@
+use core::fmt;
+
 use crate::protocol::{Parse, ParseError};
 
 pub struct Cursor<'a> {
@@ -16,12 +18,22 @@ impl<'a> Cursor<'a> {
         Ok(result)
     }
 
+    pub fn peek_u8(&self) -> Option<u8> {
+        self.rem().first().copied()
+    }
+
     pub fn read_u16_be<T>(&mut self) -> Result<u16, ParseError<T>> {
         let msb = self.read_u8()?;
         let lsb = self.read_u8()?;
         Ok(u16::from_be_bytes([msb, lsb]))
     }
 
+    pub fn read_u32_be<T>(&mut self) -> Result<u32, ParseError<T>> {
+        let msb = self.read_u16_be()?;
+        let lsb = self.read_u16_be()?;
+        Ok(u32::from(msb) << 16 | u32::from(lsb))
+    }
+
     pub fn read<T: Parse<'a>>(&mut self) -> Result<T, ParseError<T::Error>> {
         let (len, packet) = T::parse(self.rem())?;
         self.position += len;
@@ -38,8 +50,14 @@ impl<'a> Cursor<'a> {
         self.position
     }
 
+    /// Returns the data which was not yet read.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.rem()
+    }
+
     fn rem(&self) -> &'a [u8] {
-        &self.buf[self.position..]
+        // The position never exceeds the buffer, `get` keeps this free of a panic path.
+        self.buf.get(self.position..).unwrap_or_default()
     }
 }
 
@@ -48,9 +66,53 @@ macro_rules! write_many {
         $(
             match ($v).write_to(&mut $sink).await {
                 Ok(()) => (),
-                Err(err) => return Err(err),
+                Err(err) => return Err(err.into()),
             }
         )*
     }};
 }
 pub(super) use write_many;
+
+/// A [`fmt::Write`] into a fixed size buffer, which fails once the buffer is
+/// full.
+pub struct SliceWriter<'b> {
+    pub buffer: &'b mut [u8],
+    pub len: usize,
+}
+
+impl fmt::Write for SliceWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buffer
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// A sink which discards all data, but counts the amount of bytes written.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct CountingSink(usize);
+
+#[cfg(test)]
+impl CountingSink {
+    pub fn count(&self) -> usize {
+        self.0
+    }
+}
+
+#[cfg(test)]
+impl embedded_io_async::ErrorType for CountingSink {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(test)]
+impl embedded_io_async::Write for CountingSink {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+}
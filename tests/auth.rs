@@ -0,0 +1,176 @@
+#![cfg(feature = "auth")]
+
+use miniqtt::client::{Authenticator, Error};
+use miniqtt::protocol::v5::{ConnAckReason, DisconnectReason};
+
+mod common;
+
+#[tokio::test]
+async fn test_reauthenticate() {
+    use miniqtt::protocol::v5::AuthReason;
+
+    let mock = common::Mock::new();
+    mock.push_rx([
+        0xf0, 0x0a, 0x18, 0x08, 0x15, 0x00, 0x01, b'm', 0x16, 0x00, 0x01, 0x02,
+    ]);
+    mock.push_rx([0xf0, 0x00]);
+    let mut client = mock.client();
+
+    let auth = client.reauthenticate("m", &[0x01]).await.unwrap();
+    assert_eq!(auth.reason, AuthReason::ContinueAuthentication);
+    assert_eq!(auth.properties.authentication_data(), Some(&[0x02][..]));
+    drop(auth);
+    assert_eq!(
+        mock.take_tx(),
+        [
+            0xf0, 0x0a, 0x19, 0x08, 0x15, 0x00, 0x01, b'm', 0x16, 0x00, 0x01, 0x01
+        ]
+    );
+
+    let auth = client.continue_authentication("m", &[0x03]).await.unwrap();
+    assert_eq!(auth.reason, AuthReason::Success);
+    drop(auth);
+    assert_eq!(mock.take_tx()[2], 0x18);
+}
+
+#[tokio::test]
+async fn test_reauthenticate_rejected() {
+    let mock = common::Mock::new();
+    // The server closes the connection with a DISCONNECT, Not authorized.
+    mock.push_rx([0xe0, 0x01, 0x87]);
+    let mut client = mock.client();
+
+    let err = client.reauthenticate("m", &[]).await.unwrap_err();
+    assert!(matches!(
+        err,
+        Error::ServerDisconnect(DisconnectReason::NotAuthorized)
+    ));
+}
+
+/// Responds to every challenge with the challenge incremented by one.
+struct Increment {
+    response: [u8; 1],
+    rounds: usize,
+}
+
+impl Authenticator for Increment {
+    fn method(&self) -> &'static str {
+        "m"
+    }
+
+    fn respond(&mut self, challenge: Option<&[u8]>) -> Option<&[u8]> {
+        if let Some(challenge) = challenge {
+            if self.rounds == 0 {
+                return None;
+            }
+            self.rounds -= 1;
+            self.response = [challenge[0] + 1];
+        }
+        Some(&self.response)
+    }
+
+    fn finish(&mut self, data: Option<&[u8]>) -> bool {
+        // The server proves itself with the last response incremented by one.
+        data == Some(&[self.response[0] + 1])
+    }
+}
+
+#[tokio::test]
+async fn test_connect_with_authentication() {
+    let mock = common::Mock::new();
+    // AUTH, continue authentication with method "m" and data `0x02`.
+    mock.push_rx([
+        0xf0, 0x0a, 0x18, 0x08, 0x15, 0x00, 0x01, b'm', 0x16, 0x00, 0x01, 0x02,
+    ]);
+    // CONNACK with the data `0x04`.
+    mock.push_rx([0x20, 0x07, 0x00, 0x00, 0x04, 0x16, 0x00, 0x01, 0x04]);
+    let mut client = mock.client();
+
+    let mut authenticator = Increment {
+        response: [0x01],
+        rounds: 1,
+    };
+    let response = client
+        .connect("miniqtt")
+        .with_authenticator(&mut authenticator)
+        .await
+        .unwrap();
+    assert!(response.successful());
+    assert!(client.is_connected());
+
+    assert_eq!(
+        mock.take_tx(),
+        [
+            // CONNECT with method "m" and data `0x01`.
+            &[
+                0x10, 0x1c, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x05, 0x02, 0x00, 0x00, 0x08, 0x15,
+                0x00, 0x01, b'm', 0x16, 0x00, 0x01, 0x01, 0x00, 0x07, b'm', b'i', b'n', b'i', b'q',
+                b't', b't',
+            ][..],
+            // AUTH, continue authentication with the response `0x03`.
+            &[
+                0xf0, 0x0a, 0x18, 0x08, 0x15, 0x00, 0x01, b'm', 0x16, 0x00, 0x01, 0x03,
+            ],
+        ]
+        .concat()
+    );
+}
+
+#[tokio::test]
+async fn test_connect_with_authentication_aborted() {
+    let mock = common::Mock::new();
+    mock.push_rx([
+        0xf0, 0x0a, 0x18, 0x08, 0x15, 0x00, 0x01, b'm', 0x16, 0x00, 0x01, 0x02,
+    ]);
+    let mut client = mock.client();
+
+    let mut authenticator = Increment {
+        response: [0x01],
+        rounds: 0,
+    };
+    let err = client
+        .connect("miniqtt")
+        .with_authenticator(&mut authenticator)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::AuthenticationAborted));
+    assert!(!client.is_connected());
+}
+
+#[tokio::test]
+async fn test_connect_with_authentication_unverified() {
+    let mock = common::Mock::new();
+    // CONNACK with the data `0x03`, the server did not prove itself.
+    mock.push_rx([0x20, 0x07, 0x00, 0x00, 0x04, 0x16, 0x00, 0x01, 0x03]);
+    let mut client = mock.client();
+
+    let err = client
+        .connect("miniqtt")
+        .with_authenticator(&mut Increment {
+            response: [0x01],
+            rounds: 0,
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::AuthenticationAborted));
+    assert!(!client.is_connected());
+}
+
+#[tokio::test]
+async fn test_connect_with_authentication_rejected() {
+    let mock = common::Mock::new();
+    // CONNACK, bad authentication method.
+    mock.push_rx([0x20, 0x03, 0x00, 0x8c, 0x00]);
+    let mut client = mock.client();
+
+    let response = client
+        .connect("miniqtt")
+        .with_authenticator(&mut Increment {
+            response: [0x01],
+            rounds: 0,
+        })
+        .await
+        .unwrap();
+    assert_eq!(response.reason(), ConnAckReason::BadAuthenticationMethod);
+    assert!(!client.is_connected());
+}
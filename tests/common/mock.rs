@@ -0,0 +1,84 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::rc::Rc;
+
+/// A scripted in-memory transport.
+///
+/// Every read returns the next chunk of queued data, once all chunks are consumed the
+/// connection is closed, unless the mock [stalls](Mock::stall). All written data is recorded.
+#[derive(Debug, Default, Clone)]
+pub struct Mock {
+    inner: Rc<RefCell<MockInner>>,
+}
+
+#[derive(Debug, Default)]
+struct MockInner {
+    rx: VecDeque<Vec<u8>>,
+    tx: Vec<u8>,
+    stall: bool,
+}
+
+impl Mock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a chunk of data, returned from a single read.
+    pub fn push_rx(&self, data: impl AsRef<[u8]>) {
+        self.inner.borrow_mut().rx.push_back(data.as_ref().to_vec());
+    }
+
+    /// Never completes reads once all queued data is consumed, instead of closing the connection.
+    pub fn stall(&self) {
+        self.inner.borrow_mut().stall = true;
+    }
+
+    /// Returns all data written so far and clears it.
+    pub fn take_tx(&self) -> Vec<u8> {
+        std::mem::take(&mut self.inner.borrow_mut().tx)
+    }
+
+    pub fn client(&self) -> miniqtt::Client<Mock, Vec<u8>> {
+        let connection = miniqtt::Connection::new(self.clone(), Vec::new());
+        miniqtt::Client::new(connection)
+    }
+}
+
+impl embedded_io_async::ErrorType for Mock {
+    type Error = Infallible;
+}
+
+impl embedded_io_async::Read for Mock {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let stall = {
+            let inner = self.inner.borrow();
+            inner.stall && inner.rx.is_empty()
+        };
+        if stall {
+            std::future::pending::<()>().await;
+        }
+
+        let mut inner = self.inner.borrow_mut();
+
+        let Some(chunk) = inner.rx.front_mut() else {
+            return Ok(0);
+        };
+
+        let len = buf.len().min(chunk.len());
+        buf[..len].copy_from_slice(&chunk[..len]);
+        chunk.drain(..len);
+        if chunk.is_empty() {
+            inner.rx.pop_front();
+        }
+
+        Ok(len)
+    }
+}
+
+impl embedded_io_async::Write for Mock {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.borrow_mut().tx.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
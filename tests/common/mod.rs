@@ -1,3 +1,13 @@
+#![allow(
+    dead_code,
+    unused_imports,
+    reason = "not every test uses all utilities"
+)]
+
+mod mock;
 mod mosquitto;
+mod sim;
 
+pub use mock::*;
 pub use mosquitto::*;
+pub use sim::*;
@@ -29,7 +29,10 @@ impl Mosquitto {
         self.client_with_buffer(Vec::new()).await
     }
 
-    pub async fn client_with_buffer<B>(&self, buffer: B) -> miniqtt::Client<Connection, B> {
+    pub async fn client_with_buffer<B>(&self, buffer: B) -> miniqtt::Client<Connection, B>
+    where
+        B: miniqtt::client::Buffer,
+    {
         let addr = ("127.0.0.1", self.config.port);
         let stream = wait_available(addr, MAX_WAIT).await.unwrap();
         let stream = embedded_io_adapters::tokio_1::FromTokio::new(stream);
@@ -0,0 +1,218 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// A deterministic simulated network with a virtual clock.
+///
+/// Data received by the client is delivered after a simulated latency and may be dropped,
+/// both are derived from a seed, every run with the same seed behaves identically.
+///
+/// Futures are driven with [`Sim::run`], which advances the virtual clock to the next event
+/// whenever the future cannot make progress, simulated time passes instantly.
+#[derive(Debug, Clone)]
+pub struct Sim {
+    inner: Rc<RefCell<SimInner>>,
+}
+
+#[derive(Debug)]
+struct SimInner {
+    now: Duration,
+    rng: u64,
+    latency: (Duration, Duration),
+    drop_rate: u32,
+    /// Queued data and the time it is delivered.
+    rx: VecDeque<(Duration, Vec<u8>)>,
+    tx: Vec<u8>,
+    /// Deadlines of all pending delays.
+    timers: Vec<Duration>,
+    closed: bool,
+}
+
+impl Sim {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(SimInner {
+                now: Duration::ZERO,
+                rng: seed.max(1),
+                latency: (Duration::ZERO, Duration::ZERO),
+                drop_rate: 0,
+                rx: VecDeque::new(),
+                tx: Vec::new(),
+                timers: Vec::new(),
+                closed: false,
+            })),
+        }
+    }
+
+    /// Delays received data by a random latency between `min` and `max`.
+    pub fn latency(self, min: Duration, max: Duration) -> Self {
+        self.inner.borrow_mut().latency = (min, max);
+        self
+    }
+
+    /// Drops received data with a probability of `percent`.
+    pub fn drop_rate(self, percent: u32) -> Self {
+        self.inner.borrow_mut().drop_rate = percent;
+        self
+    }
+
+    /// Returns the current virtual time.
+    pub fn now(&self) -> Duration {
+        self.inner.borrow().now
+    }
+
+    /// Queues a chunk of data, which is delivered after the simulated latency or dropped.
+    ///
+    /// Returns `false` if the data was dropped.
+    pub fn push_rx(&self, data: impl AsRef<[u8]>) -> bool {
+        let mut inner = self.inner.borrow_mut();
+
+        if inner.next_u32() % 100 < inner.drop_rate {
+            return false;
+        }
+
+        let (min, max) = inner.latency;
+        let jitter = (max - min).as_micros() as u64;
+        let latency = min + Duration::from_micros(inner.next_u64() % (jitter + 1));
+
+        // Data is delivered in order, like on a stream.
+        let at = inner
+            .rx
+            .back()
+            .map_or(Duration::ZERO, |&(at, _)| at)
+            .max(inner.now + latency);
+        inner.rx.push_back((at, data.as_ref().to_vec()));
+
+        true
+    }
+
+    /// Closes the connection once all queued data is delivered.
+    pub fn close(&self) {
+        self.inner.borrow_mut().closed = true;
+    }
+
+    /// Returns all data written so far and clears it.
+    pub fn take_tx(&self) -> Vec<u8> {
+        std::mem::take(&mut self.inner.borrow_mut().tx)
+    }
+
+    /// Returns a delay, which waits for the virtual clock.
+    pub fn delay(&self) -> SimDelay {
+        SimDelay { sim: self.clone() }
+    }
+
+    /// Runs `future` to completion, advancing the virtual clock whenever it is pending.
+    ///
+    /// Panics if the future is pending without any future event.
+    pub fn run<F: Future>(&self, future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+
+            let mut inner = self.inner.borrow_mut();
+            let now = inner.now;
+            inner.timers.retain(|&deadline| deadline > now);
+
+            let next = inner
+                .timers
+                .iter()
+                .copied()
+                .chain(inner.rx.front().map(|&(at, _)| at))
+                .filter(|&at| at > now)
+                .min()
+                .expect("simulation stalled, no pending events");
+            inner.now = next;
+        }
+    }
+
+    fn sleep_until(&self, deadline: Duration) -> impl Future<Output = ()> {
+        self.inner.borrow_mut().timers.push(deadline);
+        std::future::poll_fn(move |_| match self.now() >= deadline {
+            true => Poll::Ready(()),
+            false => Poll::Pending,
+        })
+    }
+}
+
+impl SimInner {
+    fn next_u64(&mut self) -> u64 {
+        // xorshift64
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+}
+
+impl embedded_io_async::ErrorType for Sim {
+    type Error = Infallible;
+}
+
+impl embedded_io_async::Read for Sim {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        std::future::poll_fn(|_| {
+            let mut inner = self.inner.borrow_mut();
+            let now = inner.now;
+
+            let Some((at, chunk)) = inner.rx.front_mut() else {
+                return match inner.closed {
+                    true => Poll::Ready(Ok(0)),
+                    false => Poll::Pending,
+                };
+            };
+            if *at > now {
+                return Poll::Pending;
+            }
+
+            let len = buf.len().min(chunk.len());
+            buf[..len].copy_from_slice(&chunk[..len]);
+            chunk.drain(..len);
+            if chunk.is_empty() {
+                inner.rx.pop_front();
+            }
+
+            Poll::Ready(Ok(len))
+        })
+        .await
+    }
+}
+
+impl embedded_io_async::Write for Sim {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.borrow_mut().tx.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+/// A delay waiting for the virtual clock of a [`Sim`].
+#[derive(Debug, Clone)]
+pub struct SimDelay {
+    sim: Sim,
+}
+
+#[cfg(feature = "embedded-hal-async-1")]
+impl embedded_hal_async::delay::DelayNs for SimDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        let deadline = self.sim.now() + Duration::from_nanos(ns.into());
+        self.sim.sleep_until(deadline).await;
+    }
+}
+
+impl miniqtt::client::Clock for Sim {
+    fn now(&self) -> Duration {
+        Sim::now(self)
+    }
+}
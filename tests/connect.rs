@@ -1,4 +1,15 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
+#[cfg(feature = "properties")]
+use miniqtt::client::TimeSync;
+#[cfg(feature = "properties")]
+use miniqtt::protocol::QoS;
+use miniqtt::protocol::codec::TrailingData;
+#[cfg(feature = "properties")]
+use miniqtt::protocol::v5::ConnAckReason;
 use miniqtt::protocol::v5::ConnectProperty;
+use miniqtt::transport::Duplex;
 
 mod common;
 
@@ -57,6 +68,23 @@ async fn test_client_connect_credentials_valid() {
     assert!(res.successful());
 }
 
+#[tokio::test]
+async fn test_client_connect_credentials_binary() {
+    let mosquitto = common::Mosquitto::builder()
+        .credentials("foo", "bar")
+        .start();
+    let mut client = mosquitto.client().await;
+
+    let res = client
+        .connect("miniqtt")
+        .with_username("foo")
+        .with_password(b"bar")
+        .await
+        .unwrap();
+
+    assert!(res.successful());
+}
+
 #[tokio::test]
 async fn test_client_connect_credentials_invalid() {
     let mosquitto = common::Mosquitto::builder()
@@ -107,3 +135,313 @@ async fn test_client_connect_resume_session() {
     // Note: Just because the client sets the correct flags and asserts the resumption, it does not
     // mean it implements proper session resumptions.
 }
+
+#[tokio::test]
+async fn test_connect_unsupported_protocol_version() {
+    let mock = common::Mock::new();
+    // MQTT 5 CONNACK, unsupported protocol version
+    mock.push_rx([0x20, 0x03, 0x00, 0x84, 0x00]);
+    let mut client = mock.client();
+
+    let err = client.connect("miniqtt").await.unwrap_err();
+    assert!(matches!(
+        err,
+        miniqtt::client::Error::UnsupportedProtocolVersion
+    ));
+    assert!(!client.is_connected());
+}
+
+#[tokio::test]
+async fn test_connect_unsupported_protocol_version_v311() {
+    let mock = common::Mock::new();
+    // MQTT 3.1.1 CONNACK, unacceptable protocol version
+    mock.push_rx([0x20, 0x02, 0x00, 0x01]);
+    let mut client = mock.client();
+
+    let err = client.connect("miniqtt").await.unwrap_err();
+    assert!(matches!(
+        err,
+        miniqtt::client::Error::UnsupportedProtocolVersion
+    ));
+}
+
+#[tokio::test]
+async fn test_connect_trailing_data() {
+    let mock = common::Mock::new();
+    // CONNACK with a stray byte after the properties.
+    mock.push_rx([0x20, 0x04, 0x00, 0x00, 0x00, 0xff]);
+    let mut client = mock.client();
+
+    let err = client.connect("miniqtt").await.unwrap_err();
+    assert!(matches!(err, miniqtt::client::Error::Protocol));
+}
+
+#[tokio::test]
+async fn test_connect_trailing_data_ignored() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x20, 0x04, 0x00, 0x00, 0x00, 0xff]);
+    let connection = miniqtt::Connection::builder(mock, Vec::new())
+        .trailing_data(TrailingData::Ignore)
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+
+    let res = client.connect("miniqtt").await.unwrap();
+    assert!(res.successful());
+}
+
+#[tokio::test]
+async fn test_connect_binary_password() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    let mut client = mock.client();
+
+    let res = client
+        .connect("miniqtt")
+        .with_password(&[0xff, 0x00, b'x'])
+        .await
+        .unwrap();
+    assert!(res.successful());
+
+    let tx = mock.take_tx();
+    // Only the password flag is set in the connect flags.
+    assert_eq!(tx[9] & 0xc0, 0x40);
+    assert!(tx.ends_with(&[0x00, 0x03, 0xff, 0x00, b'x']));
+}
+
+#[tokio::test]
+async fn test_connect_no_password() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    let mut client = mock.client();
+
+    let res = client.connect("miniqtt").with_password(None).await.unwrap();
+    assert!(res.successful());
+
+    let tx = mock.take_tx();
+    assert_eq!(tx[9] & 0xc0, 0x00);
+    assert!(tx.ends_with(b"miniqtt"));
+}
+
+#[tokio::test]
+async fn test_connect_resume() {
+    let mock = common::Mock::new();
+    mock.stall();
+    let mut client = mock.client();
+
+    let res = tokio::time::timeout(Duration::from_millis(10), client.connect("miniqtt")).await;
+    assert!(res.is_err());
+    assert!(client.pending_connect());
+    assert!(!client.is_connected());
+
+    // CONNACK with a server keep alive of 30 seconds.
+    mock.push_rx([0x20, 0x06, 0x00, 0x00, 0x03, 0x13, 0x00, 0x1e]);
+    let res = client.resume_connect().await.unwrap().unwrap();
+    assert!(res.successful());
+    assert!(client.is_connected());
+    assert!(!client.pending_connect());
+    #[cfg(feature = "properties")]
+    assert_eq!(client.keep_alive(), 30);
+
+    // Nothing is pending anymore.
+    assert!(client.resume_connect().await.unwrap().is_none());
+    // The connect was only sent once.
+    let tx = mock.take_tx();
+    assert_eq!(tx[0], 0x10);
+    assert_eq!(tx.len(), 2 + usize::from(tx[1]));
+}
+
+/// Accepts no data, every write waits forever.
+struct StalledWriter;
+
+impl embedded_io_async::ErrorType for StalledWriter {
+    type Error = Infallible;
+}
+
+impl embedded_io_async::Write for StalledWriter {
+    async fn write(&mut self, _buf: &[u8]) -> Result<usize, Self::Error> {
+        std::future::pending().await
+    }
+}
+
+#[tokio::test]
+async fn test_connect_resume_interrupted_write() {
+    let transport = Duplex::new(common::Mock::new(), StalledWriter);
+    let mut client = miniqtt::Client::new(miniqtt::Connection::new(transport, Vec::new()));
+
+    let res = tokio::time::timeout(Duration::from_millis(10), client.connect("miniqtt")).await;
+    assert!(res.is_err());
+    assert!(client.pending_connect());
+
+    let err = client.resume_connect().await.unwrap_err();
+    assert!(matches!(err, miniqtt::client::Error::InterruptedWrite));
+}
+
+#[cfg(feature = "properties")]
+/// CONNACK with the user property `time=1700000000123`.
+const CONNACK_TIME: &[u8] = &[
+    0x20, 0x19, 0x00, 0x00, 0x16, 0x26, 0x00, 0x04, b't', b'i', b'm', b'e', 0x00, 0x0d, b'1', b'7',
+    b'0', b'0', b'0', b'0', b'0', b'0', b'0', b'0', b'1', b'2', b'3',
+];
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_connect_time_sync() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK_TIME);
+    let mut client = mock.client();
+
+    let time_sync = TimeSync::new("time");
+    let res = client
+        .connect("miniqtt")
+        .with_properties(&[time_sync.request()])
+        .with_time_sync(time_sync)
+        .await
+        .unwrap();
+    assert!(res.successful());
+    assert_eq!(res.broker_time(), Some(1_700_000_000_123));
+
+    // The time is requested with an empty user property.
+    let request = [0x26, 0x00, 0x04, b't', b'i', b'm', b'e', 0x00, 0x00];
+    let tx = mock.take_tx();
+    assert!(tx.windows(request.len()).any(|w| w == request));
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_connect_time_sync_not_configured() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK_TIME);
+    let mut client = mock.client();
+
+    let res = client.connect("miniqtt").await.unwrap();
+    assert!(res.successful());
+    assert_eq!(res.broker_time(), None);
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_connect_assigned_client_id() {
+    let mock = common::Mock::new();
+    // CONNACK with the assigned client identifier `auto-1`.
+    mock.push_rx([
+        0x20, 0x0c, 0x00, 0x00, 0x09, 0x12, 0x00, 0x06, b'a', b'u', b't', b'o', b'-', b'1',
+    ]);
+    mock.push_rx([0x20, 0x03, 0x01, 0x00, 0x00]);
+    let mut client = mock.client();
+    assert_eq!(client.assigned_client_id(), None);
+
+    let res = client.connect("").await.unwrap();
+    assert_eq!(
+        res.assigned_client_id().map(|id| id.as_str()),
+        Some("auto-1")
+    );
+    assert_eq!(
+        client.assigned_client_id().map(|id| id.as_str()),
+        Some("auto-1")
+    );
+
+    // The identifier survives the reuse of the receive buffer and a reconnect.
+    let client_id = client.assigned_client_id().copied().unwrap();
+    let res = client
+        .connect(&client_id)
+        .resume_session(true)
+        .reconnect(true)
+        .await
+        .unwrap();
+    assert!(res.session_present());
+    assert_eq!(res.assigned_client_id(), None);
+    assert_eq!(client.assigned_client_id(), Some(&client_id));
+}
+
+#[tokio::test]
+async fn test_connect_already_connected() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    let mut client = mock.client();
+
+    client.connect("miniqtt").await.unwrap();
+    mock.take_tx();
+
+    let err = client.connect("miniqtt").await.unwrap_err();
+    assert!(matches!(err, miniqtt::client::Error::AlreadyConnected));
+    assert!(mock.take_tx().is_empty());
+    assert!(client.is_connected());
+}
+
+#[tokio::test]
+async fn test_connect_reconnect() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    let mut client = mock.client();
+
+    client.connect("miniqtt").await.unwrap();
+    mock.take_tx();
+
+    client.connect("miniqtt").reconnect(true).await.unwrap();
+    let tx = mock.take_tx();
+    // DISCONNECT, normal disconnection without the will message, followed by the CONNECT.
+    assert_eq!(tx[..3], [0xe0, 0x01, 0x00]);
+    assert_eq!(tx[3], 0x10);
+    assert!(client.is_connected());
+}
+
+#[tokio::test]
+async fn test_connect_reconnect_discards_buffered() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    // Two messages read at once, the second one is still buffered.
+    mock.push_rx([
+        0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'b', 0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'c',
+    ]);
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    let mut client = mock.client();
+
+    client.connect("miniqtt").await.unwrap();
+    let message = client.receive().await.unwrap();
+    assert_eq!(message.payload, b"b");
+    drop(message);
+    assert_eq!(client.buffered_packets(), 1);
+
+    client.connect("miniqtt").reconnect(true).await.unwrap();
+    assert!(client.is_connected());
+    assert_eq!(client.buffered_packets(), 0);
+    assert!(client.try_receive().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_connect_after_server_disconnect() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    // DISCONNECT, server shutting down.
+    mock.push_rx([0xe0, 0x01, 0x8b]);
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    let mut client = mock.client();
+
+    client.connect("miniqtt").await.unwrap();
+    assert!(client.receive().await.is_err());
+    assert!(!client.is_connected());
+
+    // The connection was closed by the server, no DISCONNECT is necessary.
+    client.connect("miniqtt").await.unwrap();
+    assert!(client.is_connected());
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_connect_server_capabilities() {
+    let mock = common::Mock::new();
+    // CONNACK with receive maximum 10, maximum QoS 1 and maximum packet size 1024.
+    mock.push_rx([
+        0x20, 0x0d, 0x00, 0x00, 0x0a, 0x21, 0x00, 0x0a, 0x24, 0x01, 0x27, 0x00, 0x00, 0x04, 0x00,
+    ]);
+    let mut client = mock.client();
+
+    let res = client.connect("miniqtt").await.unwrap();
+    assert_eq!(res.reason(), ConnAckReason::Success);
+    assert_eq!(res.receive_maximum(), 10);
+    assert_eq!(res.maximum_qos(), QoS::AtLeastOnce);
+    assert!(res.retain_available());
+    assert_eq!(res.maximum_packet_size(), Some(1024));
+    assert_eq!(res.topic_alias_maximum(), 0);
+}
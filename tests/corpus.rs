@@ -0,0 +1,97 @@
+//! Runs the captured broker frames in `tests/corpus` through the decoder.
+//!
+//! Every file contains the hex encoded bytes received from a broker, lines starting with `#`
+//! are comments. Lines starting with `>` list the expected outcome of decoding the data
+//! packet by packet: the type of every decoded packet, followed by `incomplete` if data is
+//! left over, or the error which stopped decoding.
+#![cfg(all(feature = "auth", feature = "subscribe", feature = "unsubscribe"))]
+
+use std::path::Path;
+
+use miniqtt::protocol::{self, ParseError, v5::AnyPacket};
+
+struct Case {
+    data: Vec<u8>,
+    expected: Vec<String>,
+}
+
+fn load(path: &Path) -> Case {
+    let content = std::fs::read_to_string(path).unwrap();
+
+    let mut case = Case {
+        data: Vec::new(),
+        expected: Vec::new(),
+    };
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line.strip_prefix('>') {
+            Some(outcome) => case.expected.push(outcome.trim().to_owned()),
+            None => case.data.extend(line.split_whitespace().map(|byte| {
+                u8::from_str_radix(byte, 16)
+                    .unwrap_or_else(|_| panic!("{}: invalid byte {byte:?}", path.display()))
+            })),
+        }
+    }
+    case
+}
+
+fn outcome(mut data: &[u8]) -> Vec<String> {
+    let mut outcome = Vec::new();
+
+    while !data.is_empty() {
+        match protocol::decode(data) {
+            Ok((len, packet)) => {
+                let ty = match packet {
+                    AnyPacket::Auth(_) => "AUTH",
+                    AnyPacket::ConnAck(_) => "CONNACK",
+                    AnyPacket::Disconnect(_) => "DISCONNECT",
+                    AnyPacket::PingResp(_) => "PINGRESP",
+                    AnyPacket::PubAck(_) => "PUBACK",
+                    AnyPacket::PubComp(_) => "PUBCOMP",
+                    AnyPacket::PubRec(_) => "PUBREC",
+                    AnyPacket::PubRel(_) => "PUBREL",
+                    AnyPacket::Publish(_) => "PUBLISH",
+                    AnyPacket::SubAck(_) => "SUBACK",
+                    AnyPacket::UnsubAck(_) => "UNSUBACK",
+                };
+                outcome.push(ty.to_owned());
+                data = &data[len..];
+            }
+            Err(ParseError::NotEnoughData) => {
+                outcome.push("incomplete".to_owned());
+                break;
+            }
+            Err(ParseError::Error(err)) => {
+                outcome.push(format!("error {err:?}"));
+                break;
+            }
+        }
+    }
+
+    outcome
+}
+
+#[test]
+fn test_corpus() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let case = load(&path);
+        assert!(
+            !case.expected.is_empty(),
+            "{}: no expected outcome",
+            path.display()
+        );
+        assert_eq!(outcome(&case.data), case.expected, "{}", path.display());
+    }
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use miniqtt::protocol::v5;
+
+const CONNACK: &[u8] = &[0x20, 0x03, 0x00, 0x00, 0x00];
+#[cfg(feature = "std")]
+const DISCONNECT: &[u8] = &[0xe0, 0x01, 0x00];
+
+#[cfg(feature = "std")]
+#[tokio::test]
+async fn test_disconnect_on_drop() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+
+    let mut client = mock.client().disconnect_on_drop();
+    client.connect("miniqtt").await.unwrap();
+    assert!(client.is_connected());
+    mock.take_tx();
+
+    drop(client);
+    assert_eq!(mock.take_tx(), DISCONNECT);
+}
+
+#[cfg(feature = "std")]
+#[tokio::test]
+async fn test_disconnect_on_drop_not_connected() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+
+    let mut client = mock.client().disconnect_on_drop();
+    client.connect("miniqtt").await.unwrap();
+    client.disconnect().await.unwrap();
+    assert!(!client.is_connected());
+    mock.take_tx();
+
+    drop(client);
+    assert_eq!(mock.take_tx(), []);
+}
+
+#[cfg(feature = "std")]
+#[tokio::test]
+async fn test_disconnect_on_drop_into_inner() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+
+    let mut client = mock.client().disconnect_on_drop();
+    client.connect("miniqtt").await.unwrap();
+    mock.take_tx();
+
+    let client = client.into_inner();
+    assert!(client.is_connected());
+    assert_eq!(mock.take_tx(), []);
+}
+
+#[tokio::test]
+async fn test_disconnect_reason() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+
+    let mut client = mock.client();
+    client.connect("miniqtt").await.unwrap();
+    mock.take_tx();
+
+    client
+        .disconnect()
+        .reason(v5::DisconnectReason::NormalDisconnection)
+        .await
+        .unwrap();
+    assert!(!client.is_connected());
+    assert_eq!(mock.take_tx(), [0xe0, 0x01, 0x00]);
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_disconnect_properties() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+
+    let mut client = mock.client();
+    client.connect("miniqtt").await.unwrap();
+    mock.take_tx();
+
+    client
+        .disconnect()
+        .reason(v5::DisconnectReason::QuotaExceeded)
+        .with_properties(&[
+            v5::DisconnectProperty::ReasonString("bye"),
+            v5::DisconnectProperty::UserProperty {
+                key: "k",
+                value: "v",
+            },
+        ])
+        .await
+        .unwrap();
+    assert_eq!(
+        mock.take_tx(),
+        [
+            0xe0, 0x0f, 0x97, 0x0d, 0x1f, 0x00, 0x03, b'b', b'y', b'e', 0x26, 0x00, 0x01, b'k',
+            0x00, 0x01, b'v'
+        ]
+    );
+}
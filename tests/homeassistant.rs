@@ -0,0 +1,51 @@
+#![cfg(feature = "homeassistant")]
+
+use miniqtt::homeassistant::{Component, Entity};
+
+mod common;
+
+#[tokio::test]
+async fn test_homeassistant_switch() {
+    let mock = common::Mock::new();
+    // SUBACK, followed by the command `ON`.
+    mock.push_rx([0x90, 0x04, 0x4e, 0x20, 0x00, 0x00]);
+    mock.push_rx([0x30, 0x07, 0x00, 0x02, b's', b'/', 0x00, b'O', b'N']);
+    let mut client = mock.client();
+
+    let switch = Entity::new(Component::Switch, "s", "S")
+        .with_discovery_prefix("ha")
+        .with_state_topic("s")
+        .with_command_topic("s/");
+
+    let mut buffer = [0; 128];
+    switch.announce(&mut client, &mut buffer).await.unwrap();
+    let config = br#"{"name":"S","unique_id":"s","state_topic":"s","command_topic":"s/"}"#;
+    let tx = mock.take_tx();
+    assert_eq!(tx[0], 0x31);
+    assert_eq!(&tx[4..22], b"ha/switch/s/config");
+    assert_eq!(&tx[23..], config);
+
+    switch.subscribe_commands(&mut client).await.unwrap();
+    mock.take_tx();
+
+    let message = client.receive().await.unwrap();
+    assert_eq!(switch.command_on_off(&message), Some(true));
+    drop(message);
+
+    switch.publish_on_off(&mut client, true).await.unwrap();
+    assert_eq!(
+        mock.take_tx(),
+        [0x31, 0x06, 0x00, 0x01, b's', 0x00, b'O', b'N']
+    );
+}
+
+#[tokio::test]
+async fn test_homeassistant_without_topics() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    let sensor = Entity::new(Component::Sensor, "t", "T");
+    assert!(sensor.publish_state(&mut client, b"1").await.is_err());
+    assert!(sensor.subscribe_commands(&mut client).await.is_err());
+    assert!(mock.take_tx().is_empty());
+}
@@ -0,0 +1,218 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use miniqtt::client::Error;
+
+mod common;
+
+/// Successful CONNACK without properties.
+const CONNACK: &[u8] = &[0x20, 0x03, 0x00, 0x00, 0x00];
+#[cfg(feature = "properties")]
+/// Successful CONNACK with a server keep alive of 60 seconds.
+const CONNACK_SERVER_KEEP_ALIVE: &[u8] = &[0x20, 0x06, 0x00, 0x00, 0x03, 0x13, 0x00, 0x3c];
+#[cfg(feature = "properties")]
+/// Successful CONNACK with a server keep alive of 0 seconds.
+const CONNACK_SERVER_KEEP_ALIVE_ZERO: &[u8] = &[0x20, 0x06, 0x00, 0x00, 0x03, 0x13, 0x00, 0x00];
+
+/// Connects with the `requested` keep alive and the CONNACK `ack`.
+async fn connect(ack: &[u8], requested: u16) -> miniqtt::Client<common::Mock, Vec<u8>> {
+    let mock = common::Mock::new();
+    mock.push_rx(ack);
+    let mut client = mock.client();
+
+    let res = client
+        .connect("miniqtt")
+        .keep_alive(requested)
+        .await
+        .unwrap();
+    assert!(res.successful());
+    client
+}
+
+fn assert_disabled<C, B, T>(client: &miniqtt::Client<C, B, T>) {
+    assert_eq!(client.keep_alive(), 0);
+    assert_eq!(client.ping_due_in(Duration::ZERO), None);
+    assert_eq!(client.ping_due_in(Duration::from_secs(3600)), None);
+    assert!(!client.keep_alive_expired(Duration::from_secs(3600)));
+}
+
+fn assert_enabled<C, B, T>(client: &miniqtt::Client<C, B, T>, keep_alive: u64) {
+    let keep_alive = Duration::from_secs(keep_alive);
+    assert_eq!(client.ping_due_in(Duration::ZERO), Some(keep_alive));
+    assert_eq!(
+        client.ping_due_in(keep_alive / 2),
+        Some(keep_alive - keep_alive / 2)
+    );
+    assert_eq!(client.ping_due_in(keep_alive * 2), Some(Duration::ZERO));
+    assert!(!client.keep_alive_expired(keep_alive));
+    assert!(!client.keep_alive_expired(keep_alive * 3 / 2));
+    assert!(client.keep_alive_expired(keep_alive * 2));
+}
+
+#[tokio::test]
+async fn test_keep_alive_requested() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+    let mut client = mock.client();
+
+    assert_eq!(client.keep_alive(), 0);
+
+    let res = client.connect("miniqtt").keep_alive(10).await.unwrap();
+    assert!(res.successful());
+    assert_eq!(client.keep_alive(), 10);
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_keep_alive_server_assigned() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK_SERVER_KEEP_ALIVE);
+    let mut client = mock.client();
+
+    let res = client.connect("miniqtt").keep_alive(10).await.unwrap();
+    assert!(res.successful());
+    assert_eq!(client.keep_alive(), 60);
+}
+
+#[tokio::test]
+async fn test_keep_alive_disabled() {
+    let client = connect(CONNACK, 0).await;
+    assert_disabled(&client);
+}
+
+#[tokio::test]
+async fn test_keep_alive_enabled() {
+    let client = connect(CONNACK, 10).await;
+    assert_enabled(&client, 10);
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_keep_alive_enabled_by_server() {
+    let client = connect(CONNACK_SERVER_KEEP_ALIVE, 0).await;
+    assert_enabled(&client, 60);
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_keep_alive_disabled_by_server() {
+    let client = connect(CONNACK_SERVER_KEEP_ALIVE_ZERO, 10).await;
+    assert_disabled(&client);
+}
+
+#[tokio::test]
+async fn test_keep_alive_bounds_clamp_requested() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+    mock.push_rx(CONNACK);
+    let mut client = mock.client();
+
+    let res = client
+        .connect("miniqtt")
+        .keep_alive(5)
+        .keep_alive_bounds(30, 300)
+        .await
+        .unwrap();
+    assert!(!res.keep_alive_out_of_bounds());
+    assert_eq!(client.keep_alive(), 30);
+    // Keep alive in the CONNECT.
+    assert_eq!(&mock.take_tx()[10..12], [0x00, 0x1e]);
+
+    client
+        .connect("miniqtt")
+        .keep_alive_bounds(30, 300)
+        .keep_alive(u16::MAX)
+        .reconnect(true)
+        .await
+        .unwrap();
+    assert_eq!(client.keep_alive(), 300);
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_keep_alive_bounds_server_assigned() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK_SERVER_KEEP_ALIVE);
+    mock.push_rx(CONNACK_SERVER_KEEP_ALIVE);
+    let mut client = mock.client();
+
+    let res = client
+        .connect("miniqtt")
+        .keep_alive(10)
+        .keep_alive_bounds(10, 30)
+        .await
+        .unwrap();
+    assert_eq!(res.server_keep_alive(), Some(60));
+    assert!(res.keep_alive_out_of_bounds());
+    // The keep alive of the server is used regardless.
+    assert_eq!(client.keep_alive(), 60);
+
+    let res = client
+        .connect("miniqtt")
+        .keep_alive_bounds(10, 60)
+        .reconnect(true)
+        .await
+        .unwrap();
+    assert!(!res.keep_alive_out_of_bounds());
+}
+
+#[tokio::test]
+async fn test_ping() {
+    let mock = common::Mock::new();
+    mock.push_rx([0xd0, 0x00]);
+    let mut client = mock.client();
+
+    client.ping().await.unwrap();
+    assert_eq!(mock.take_tx(), [0xc0, 0x00]);
+}
+
+#[tokio::test]
+async fn test_ping_disconnected() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    assert!(matches!(client.ping().await, Err(Error::Disconnected)));
+}
+
+static NOW_SECS: AtomicU64 = AtomicU64::new(0);
+
+fn now() -> Duration {
+    Duration::from_secs(NOW_SECS.load(Ordering::Relaxed))
+}
+
+static CLOCK: fn() -> Duration = now;
+
+#[tokio::test]
+async fn test_keep_alive_tick() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+    let connection = miniqtt::Connection::builder(mock.clone(), Vec::new())
+        .with_clock(&CLOCK)
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+
+    client.connect("miniqtt").keep_alive(10).await.unwrap();
+    mock.take_tx();
+    assert_eq!(client.next_ping_in(), Some(Duration::from_secs(10)));
+    assert!(!client.keep_alive_tick().await.unwrap());
+
+    NOW_SECS.store(4, Ordering::Relaxed);
+    assert_eq!(client.next_ping_in(), Some(Duration::from_secs(6)));
+    assert!(!client.keep_alive_tick().await.unwrap());
+    assert!(mock.take_tx().is_empty());
+
+    NOW_SECS.store(10, Ordering::Relaxed);
+    assert_eq!(client.next_ping_in(), Some(Duration::ZERO));
+    assert!(client.keep_alive_tick().await.unwrap());
+    assert_eq!(mock.take_tx(), [0xc0, 0x00]);
+
+    // The ping restarts the interval.
+    assert_eq!(client.next_ping_in(), Some(Duration::from_secs(10)));
+}
+
+#[tokio::test]
+async fn test_keep_alive_tick_without_clock() {
+    let mut client = connect(CONNACK, 10).await;
+    assert_eq!(client.next_ping_in(), None);
+    assert!(!client.keep_alive_tick().await.unwrap());
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "will")]
+
+use miniqtt::client::Presence;
+
+mod common;
+
+/// Successful CONNACK without properties.
+const CONNACK: &[u8] = &[0x20, 0x03, 0x00, 0x00, 0x00];
+
+#[tokio::test]
+async fn test_presence_raw() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+    let mut client = mock.client();
+
+    let presence = Presence::new("p");
+    client.connect("c").with_presence(presence).await.unwrap();
+
+    #[rustfmt::skip]
+    let expected: &[u8] = &[
+        // CONNECT, will flag, will retain, clean start
+        0x10, 0x1b, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x05, 0x26, 0x00, 0x00, 0x00,
+        0x00, 0x01, b'c',
+        // will properties, topic and payload
+        0x00, 0x00, 0x01, b'p', 0x00, 0x07, b'o', b'f', b'f', b'l', b'i', b'n', b'e',
+        // retained PUBLISH
+        0x31, 0x0a, 0x00, 0x01, b'p', 0x00, b'o', b'n', b'l', b'i', b'n', b'e',
+    ];
+    assert_eq!(mock.take_tx(), expected);
+}
+
+#[tokio::test]
+async fn test_presence_json() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+    let mut client = mock.client();
+
+    let presence = Presence::new("p").json();
+    client.connect("c").with_presence(presence).await.unwrap();
+
+    let tx = mock.take_tx();
+    assert!(tx.ends_with(br#"{"online":true}"#));
+    assert!(
+        tx.windows(16)
+            .any(|window| window == br#"{"online":false}"#)
+    );
+}
+
+#[tokio::test]
+async fn test_presence_rejected() {
+    let mock = common::Mock::new();
+    // CONNACK, not authorized
+    mock.push_rx([0x20, 0x03, 0x00, 0x87, 0x00]);
+    let mut client = mock.client();
+
+    let presence = Presence::new("p");
+    let res = client.connect("c").with_presence(presence).await.unwrap();
+    assert!(!res.successful());
+
+    // Only the CONNECT was sent.
+    assert_eq!(mock.take_tx()[0], 0x10);
+    assert_eq!(mock.take_tx(), b"");
+}
@@ -0,0 +1,586 @@
+use core::sync::atomic::{AtomicU16, Ordering};
+use core::time::Duration;
+
+#[cfg(feature = "properties")]
+use miniqtt::client::ReplyTo;
+use miniqtt::client::{DeadLetter, Error, Staging, Stats, StatsReport};
+use miniqtt::protocol::types::PacketId;
+#[cfg(feature = "properties")]
+use miniqtt::protocol::v5::PublishProperty;
+use miniqtt::protocol::v5::{DisconnectReason, PubAckReason, PubCompReason, PubRecReason};
+use miniqtt::protocol::{QoS, ValidationError};
+
+mod common;
+
+#[tokio::test]
+async fn test_publish() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    client.publish("a", b"b").await.unwrap();
+    assert_eq!(mock.take_tx(), [0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'b']);
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_publish_retain_with_properties() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    client
+        .publish("a", b"b")
+        .retain(true)
+        .with_properties(&[PublishProperty::MessageExpiryInterval(60)])
+        .await
+        .unwrap();
+    assert_eq!(
+        mock.take_tx(),
+        [
+            0x31, 0x0a, 0x00, 0x01, b'a', 0x05, 0x02, 0x00, 0x00, 0x00, 0x3c, b'b'
+        ]
+    );
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_publish_builder_properties() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    client
+        .publish("a", b"b")
+        .with_properties(&[PublishProperty::UserProperty {
+            key: "k",
+            value: "v",
+        }])
+        .with_correlation_data(b"c")
+        .with_response_topic("r")
+        .with_content_type("t")
+        .with_message_expiry_interval(60)
+        .utf8_payload(true)
+        .await
+        .unwrap();
+    #[rustfmt::skip]
+    assert_eq!(
+        mock.take_tx(),
+        [
+            0x30, 0x1f, 0x00, 0x01, b'a',
+            0x1a,
+            // Payload format indicator.
+            0x01, 0x01,
+            // Message expiry interval.
+            0x02, 0x00, 0x00, 0x00, 0x3c,
+            // Content type.
+            0x03, 0x00, 0x01, b't',
+            // Response topic.
+            0x08, 0x00, 0x01, b'r',
+            // Correlation data.
+            0x09, 0x00, 0x01, b'c',
+            // User property, after the properties of the builder.
+            0x26, 0x00, 0x01, b'k', 0x00, 0x01, b'v',
+            b'b',
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_publish_qos1() {
+    let mock = common::Mock::new();
+    // PUBACK for the first identifier, without reason.
+    mock.push_rx([0x40, 0x02, 0x4e, 0x20]);
+    let mut client = mock.client();
+
+    client
+        .publish("a", b"b")
+        .qos(QoS::AtLeastOnce)
+        .await
+        .unwrap();
+    assert_eq!(
+        mock.take_tx(),
+        [0x32, 0x07, 0x00, 0x01, b'a', 0x4e, 0x20, 0x00, b'b']
+    );
+    assert_eq!(client.stats().reasons.get(0x00), 1);
+}
+
+#[tokio::test]
+async fn test_publish_qos1_interleaved_message() {
+    let mock = common::Mock::new();
+    // A message arrives before the PUBACK.
+    mock.push_rx([0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'm']);
+    mock.push_rx([0x40, 0x02, 0x4e, 0x20]);
+    let mut client = mock.client();
+
+    client
+        .publish("a", b"b")
+        .qos(QoS::AtLeastOnce)
+        .await
+        .unwrap();
+    assert_eq!(client.buffered_packets(), 1);
+
+    let message = client.receive().await.unwrap();
+    assert_eq!(message.topic, "a");
+    assert_eq!(message.payload, b"m");
+}
+
+#[tokio::test]
+async fn test_publish_qos1_rejected() {
+    let mock = common::Mock::new();
+    // PUBACK, quota exceeded.
+    mock.push_rx([0x40, 0x03, 0x4e, 0x20, 0x97]);
+    let mut client = mock.client();
+
+    let err = client
+        .publish("a", b"b")
+        .qos(QoS::AtLeastOnce)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::PublishRejected(PubAckReason::QuotaExceeded)
+    ));
+    assert_eq!(client.stats().reasons.failures(), 1);
+}
+
+#[tokio::test]
+async fn test_publish_qos1_server_disconnect() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    // DISCONNECT, quota exceeded, with reason string "q".
+    mock.push_rx([0xe0, 0x06, 0x97, 0x04, 0x1f, 0x00, 0x01, b'q']);
+    let mut client = mock.client();
+
+    client.connect("miniqtt").await.unwrap();
+    let err = client
+        .publish("a", b"b")
+        .qos(QoS::AtLeastOnce)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::ServerDisconnect(DisconnectReason::QuotaExceeded)
+    ));
+    assert!(!client.is_connected());
+    mock.take_tx();
+
+    // The connection is closed, nothing is sent anymore.
+    let err = client.publish("a", b"b").await.unwrap_err();
+    assert!(matches!(err, Error::Disconnected));
+    client.disconnect().await.unwrap();
+    assert_eq!(mock.take_tx(), []);
+}
+
+#[tokio::test]
+async fn test_publish_qos1_detach() {
+    static REJECTED: AtomicU16 = AtomicU16::new(0);
+    fn dead_letter(identifier: PacketId, reason: DeadLetter) {
+        assert_eq!(reason, DeadLetter::Rejected(PubAckReason::QuotaExceeded));
+        REJECTED.store(identifier.get(), Ordering::Relaxed);
+    }
+
+    let mock = common::Mock::new();
+    let connection = miniqtt::Connection::builder(mock.clone(), Vec::new())
+        .with_dead_letter(dead_letter)
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+
+    let mut identifiers = [None; 2];
+    for identifier in &mut identifiers {
+        *identifier = client
+            .publish("a", b"b")
+            .qos(QoS::AtLeastOnce)
+            .detach()
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        identifiers.map(|id| id.map(PacketId::get)),
+        [Some(0x4e20), Some(0x4e21)]
+    );
+    assert_eq!(client.pending_acks(), 2);
+    mock.take_tx();
+
+    // The PUBACK of the first detached publish arrives before the awaited one.
+    mock.push_rx([0x40, 0x02, 0x4e, 0x20]);
+    mock.push_rx([0x40, 0x02, 0x4e, 0x22]);
+    let identifier = client
+        .publish("a", b"b")
+        .qos(QoS::AtLeastOnce)
+        .await
+        .unwrap();
+    // Only detached publishes return their identifier.
+    assert_eq!(identifier, None);
+    assert_eq!(client.pending_acks(), 1);
+
+    // PUBACK, quota exceeded, skipped while receiving a message.
+    mock.push_rx([0x40, 0x03, 0x4e, 0x21, 0x97]);
+    mock.push_rx([0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'b']);
+    let message = client.receive().await.unwrap();
+    assert_eq!(message.payload, b"b");
+    drop(message);
+
+    assert_eq!(client.pending_acks(), 0);
+    assert_eq!(REJECTED.load(Ordering::Relaxed), 0x4e21);
+    assert_eq!(client.stats().reasons.get(0x00), 2);
+    assert_eq!(client.stats().reasons.failures(), 1);
+}
+
+#[tokio::test]
+async fn test_publish_qos1_detach_lost_on_connect() {
+    static LOST: AtomicU16 = AtomicU16::new(0);
+    fn dead_letter(_identifier: PacketId, reason: DeadLetter) {
+        assert_eq!(reason, DeadLetter::Lost);
+        LOST.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let mock = common::Mock::new();
+    let connection = miniqtt::Connection::builder(mock.clone(), Vec::new())
+        .with_dead_letter(dead_letter)
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+
+    for _ in 0..2 {
+        client
+            .publish("a", b"b")
+            .qos(QoS::AtLeastOnce)
+            .detach()
+            .await
+            .unwrap();
+    }
+    assert_eq!(client.pending_acks(), 2);
+
+    // The acknowledgements are lost with the previous connection.
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    client.connect("miniqtt").await.unwrap();
+    assert_eq!(client.pending_acks(), 0);
+    assert_eq!(LOST.load(Ordering::Relaxed), 2);
+}
+
+#[tokio::test]
+async fn test_publish_qos1_detach_unknown_identifier() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    client
+        .publish("a", b"b")
+        .qos(QoS::AtLeastOnce)
+        .detach()
+        .await
+        .unwrap();
+
+    // Only acknowledgements of detached publishes are skipped.
+    mock.push_rx([0x40, 0x02, 0x00, 0x01]);
+    let err = client.receive().await.unwrap_err();
+    assert!(matches!(err, Error::Protocol));
+    assert_eq!(client.pending_acks(), 1);
+}
+
+#[tokio::test]
+async fn test_publish_qos1_unexpected_identifier() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x40, 0x02, 0x00, 0x01]);
+    let mut client = mock.client();
+
+    let err = client
+        .publish("a", b"b")
+        .qos(QoS::AtLeastOnce)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Protocol));
+}
+
+#[tokio::test]
+async fn test_publish_qos2() {
+    let mock = common::Mock::new();
+    // PUBREC and PUBCOMP for the first identifier, without reason.
+    mock.push_rx([0x50, 0x02, 0x4e, 0x20]);
+    mock.push_rx([0x70, 0x02, 0x4e, 0x20]);
+    let mut client = mock.client();
+
+    client
+        .publish("a", b"b")
+        .qos(QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    #[rustfmt::skip]
+    let expected: &[u8] = &[
+        // PUBLISH
+        0x34, 0x07, 0x00, 0x01, b'a', 0x4e, 0x20, 0x00, b'b',
+        // PUBREL
+        0x62, 0x02, 0x4e, 0x20,
+    ];
+    assert_eq!(mock.take_tx(), expected);
+    assert_eq!(client.stats().reasons.get(0x00), 2);
+}
+
+#[tokio::test]
+async fn test_publish_qos2_interleaved_messages() {
+    let mock = common::Mock::new();
+    // Messages arrive before the PUBREC and before the PUBCOMP.
+    mock.push_rx([0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'1']);
+    mock.push_rx([0x50, 0x02, 0x4e, 0x20]);
+    mock.push_rx([0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'2']);
+    mock.push_rx([0x70, 0x02, 0x4e, 0x20]);
+    let mut client = mock.client();
+
+    client
+        .publish("a", b"b")
+        .qos(QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    assert_eq!(client.buffered_packets(), 2);
+
+    for payload in [b"1", b"2"] {
+        let message = client.receive().await.unwrap();
+        assert_eq!(message.payload, payload);
+    }
+}
+
+#[tokio::test]
+async fn test_publish_qos2_rejected() {
+    let mock = common::Mock::new();
+    // PUBREC, not authorized.
+    mock.push_rx([0x50, 0x03, 0x4e, 0x20, 0x87]);
+    let mut client = mock.client();
+
+    let err = client
+        .publish("a", b"b")
+        .qos(QoS::ExactlyOnce)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::PublishRejected(PubRecReason::NotAuthorized)
+    ));
+    // The rejected publish is not released.
+    assert_eq!(
+        mock.take_tx(),
+        [0x34, 0x07, 0x00, 0x01, b'a', 0x4e, 0x20, 0x00, b'b']
+    );
+}
+
+#[tokio::test]
+async fn test_publish_qos2_incomplete() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x50, 0x02, 0x4e, 0x20]);
+    // PUBCOMP, packet identifier not found.
+    mock.push_rx([0x70, 0x03, 0x4e, 0x20, 0x92]);
+    let mut client = mock.client();
+
+    let err = client
+        .publish("a", b"b")
+        .qos(QoS::ExactlyOnce)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::PublishIncomplete(PubCompReason::PacketIdentifierNotFound)
+    ));
+    assert_eq!(client.stats().reasons.get(0x92), 1);
+}
+
+#[tokio::test]
+async fn test_publish_vectored() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    client
+        .publish_vectored("a", &[b"he", b"", b"llo"])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        mock.take_tx(),
+        [
+            0x30, 0x09, 0x00, 0x01, b'a', 0x00, b'h', b'e', b'l', b'l', b'o'
+        ]
+    );
+}
+
+/// QoS 0 request on topic `a`, with the response topic `r`, correlation data `[1, 2]` and the
+/// payload `q`.
+#[cfg(feature = "properties")]
+const REQUEST: &[u8] = &[
+    0x30, 0x0e, 0x00, 0x01, b'a', 0x09, 0x08, 0x00, 0x01, b'r', 0x09, 0x00, 0x02, 0x01, 0x02, b'q',
+];
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_reply() {
+    let mock = common::Mock::new();
+    mock.push_rx(REQUEST);
+    let mut client = mock.client();
+
+    let message = client.receive().await.unwrap();
+    let reply_to = ReplyTo::<8>::new(&message).unwrap();
+    drop(message);
+    assert_eq!(reply_to.topic(), "r");
+    assert_eq!(reply_to.correlation_data(), Some(&[0x01, 0x02][..]));
+
+    client.reply(&reply_to, b"ok").await.unwrap();
+    assert_eq!(
+        mock.take_tx(),
+        [
+            0x30, 0x0b, 0x00, 0x01, b'r', 0x05, 0x09, 0x00, 0x02, 0x01, 0x02, b'o', b'k'
+        ]
+    );
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_reply_after_other_packets() {
+    let mock = common::Mock::new();
+    mock.push_rx(REQUEST);
+    mock.push_rx([0xd0, 0x00]);
+    let mut client = mock.client();
+
+    let message = client.receive().await.unwrap();
+    let reply_to = ReplyTo::<8>::new(&message).unwrap();
+    drop(message);
+
+    // The reply does not depend on the last received packet.
+    client.ping().await.unwrap();
+    mock.take_tx();
+
+    client.reply(&reply_to, b"ok").await.unwrap();
+    assert_eq!(&mock.take_tx()[..5], [0x30, 0x0b, 0x00, 0x01, b'r']);
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_reply_after_server_disconnect() {
+    let mock = common::Mock::new();
+    mock.push_rx([REQUEST, &[0xe0, 0x01, 0x8b]].concat());
+    let mut client = mock.client();
+
+    let message = client.receive().await.unwrap();
+    let reply_to = ReplyTo::<8>::new(&message).unwrap();
+    drop(message);
+    let err = client.receive().await.unwrap_err();
+    assert!(matches!(err, Error::ServerDisconnect(_)));
+
+    let err = client.reply(&reply_to, b"ok").await.unwrap_err();
+    assert!(matches!(err, Error::Disconnected));
+    assert!(mock.take_tx().is_empty());
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_reply_to_capacity() {
+    let mock = common::Mock::new();
+    mock.push_rx([REQUEST, &[0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'b']].concat());
+    let mut client = mock.client();
+
+    // The response topic and the correlation data need 3 bytes.
+    let message = client.receive().await.unwrap();
+    assert!(ReplyTo::<2>::new(&message).is_none());
+    assert!(ReplyTo::<3>::new(&message).is_some());
+    drop(message);
+
+    // Without a response topic, there is nobody to reply to.
+    let message = client.receive().await.unwrap();
+    assert!(ReplyTo::<8>::new(&message).is_none());
+}
+
+#[tokio::test]
+async fn test_send_staged() {
+    static STAGING: Staging<16> = Staging::new();
+
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    // Nothing is staged yet.
+    assert!(!client.send_staged(&STAGING).await.unwrap());
+
+    STAGING.handle().try_publish_qos0("a", b"b").unwrap();
+    assert!(client.send_staged(&STAGING).await.unwrap());
+    assert!(!STAGING.is_pending());
+    assert_eq!(mock.take_tx(), [0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'b']);
+}
+
+#[tokio::test]
+async fn test_publish_reserved_topic() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    let err = client.send("$SYS/broker", b"x").await.unwrap_err();
+    assert!(matches!(err, Error::ReservedTopic));
+    let err = client
+        .publish_vectored("$share", &[b"x"])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::ReservedTopic));
+    assert!(mock.take_tx().is_empty());
+
+    // Only topics starting with `$` are reserved.
+    client.send("a/$b", b"x").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_publish_invalid_topic() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    let err = client.send("", b"x").await.unwrap_err();
+    assert!(matches!(err, Error::Invalid(ValidationError::Empty)));
+    let err = client.send("a/+", b"x").await.unwrap_err();
+    assert!(matches!(err, Error::Invalid(ValidationError::Wildcard)));
+    assert!(mock.take_tx().is_empty());
+}
+
+#[tokio::test]
+async fn test_publish_reserved_topic_allowed() {
+    let mock = common::Mock::new();
+    let connection = miniqtt::Connection::builder(mock.clone(), Vec::new())
+        .allow_reserved_topics(true)
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+
+    client.send("$a", b"x").await.unwrap();
+    assert_eq!(
+        mock.take_tx(),
+        [0x30, 0x06, 0x00, 0x02, b'$', b'a', 0x00, b'x']
+    );
+}
+
+#[tokio::test]
+async fn test_publish_stats() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    let connection = miniqtt::Connection::builder(mock.clone(), Vec::new())
+        .with_stats(Stats {
+            connects: 1,
+            ..Default::default()
+        })
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+
+    client.connect("miniqtt").await.unwrap();
+    assert_eq!(client.stats().connects, 2);
+    assert_eq!(client.stats().reconnects(), 1);
+    assert_eq!(client.stats().rx_high_watermark, 5);
+    mock.take_tx();
+
+    let report = StatsReport::new("s", Duration::from_secs(60));
+    client
+        .publish_stats(&report, Duration::from_secs(7))
+        .await
+        .unwrap();
+
+    let payload = br#"{"uptime":7,"connects":2,"reconnects":1,"dropped":0,"rx_high_watermark":5}"#;
+    let tx = mock.take_tx();
+    assert_eq!(&tx[..5], [0x30, 0x4e, 0x00, 0x01, b's']);
+    assert_eq!(&tx[6..], payload);
+}
+
+#[tokio::test]
+async fn test_publish_stats_reserved_topic() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    let report = StatsReport::new("$SYS/stats", Duration::from_secs(60));
+    let err = client
+        .publish_stats(&report, Duration::ZERO)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::ReservedTopic));
+}
@@ -0,0 +1,479 @@
+use std::sync::Mutex;
+
+use miniqtt::client::{Compaction, Error, Event, Message, ReceiveOr, Watermark};
+use miniqtt::protocol::QoS;
+use miniqtt::protocol::v5::DisconnectReason;
+
+mod common;
+
+/// QoS 0 publish on topic `a` with the payload `b`.
+const PUBLISH: &[u8] = &[0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'b'];
+
+#[tokio::test]
+async fn test_try_receive_buffered() {
+    let mock = common::Mock::new();
+    mock.push_rx([PUBLISH, PUBLISH, &PUBLISH[..3]].concat());
+    let mut client = mock.client();
+
+    // Nothing has been read from the connection yet.
+    assert!(client.try_receive().unwrap().is_none());
+
+    client.receive().await.unwrap();
+
+    // The second message was received with the first one.
+    assert!(client.try_receive().unwrap().is_some());
+    // The third message is incomplete.
+    assert!(client.try_receive().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_receive_message() {
+    let mock = common::Mock::new();
+    mock.push_rx([PUBLISH, PUBLISH].concat());
+    let mut client = mock.client();
+
+    let message = client.receive().await.unwrap();
+    assert_eq!(message.topic, "a");
+    assert_eq!(message.payload, b"b");
+    drop(message);
+
+    let message = client.receive().await.unwrap();
+    assert_eq!(message.topic, "a");
+    drop(message);
+
+    assert!(client.try_receive().unwrap().is_none());
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_receive_dedup_correlation_data() {
+    /// QoS 0 publish on topic `a` with the payload `b` and the Correlation Data `x`.
+    const REQUEST: &[u8] = &[
+        0x30, 0x09, 0x00, 0x01, b'a', 0x04, 0x09, 0x00, 0x01, b'x', b'b',
+    ];
+
+    let mock = common::Mock::new();
+    mock.push_rx([REQUEST, PUBLISH, REQUEST, PUBLISH].concat());
+    let mut client = mock.client();
+    let mut dedup = miniqtt::client::Dedup::<4>::new();
+
+    let mut duplicates = Vec::new();
+    for _ in 0..4 {
+        let message = client.receive().await.unwrap();
+        duplicates.push(dedup.is_duplicate(&message));
+    }
+    // Messages without Correlation Data are never duplicates.
+    assert_eq!(duplicates, [false, false, true, false]);
+    assert_eq!(dedup.len(), 1);
+}
+
+#[tokio::test]
+async fn test_receive_message_flags() {
+    let mock = common::Mock::new();
+    // Duplicate QoS 1 publish with the retain flag and identifier 0x4e20.
+    mock.push_rx([0x3b, 0x07, 0x00, 0x01, b'a', 0x4e, 0x20, 0x00, b'b']);
+    let mut client = mock.client();
+
+    let message: Message<'_> = client.receive().await.unwrap();
+    assert_eq!(message.topic, "a");
+    assert_eq!(message.payload, b"b");
+    assert_eq!(message.qos, QoS::AtLeastOnce);
+    assert!(message.retain);
+    assert!(message.dup);
+    assert_eq!(message.identifier.map(|id| id.get()), Some(0x4e20));
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_receive_message_properties() {
+    let mock = common::Mock::new();
+    #[rustfmt::skip]
+    mock.push_rx([
+        0x30, 0x23, 0x00, 0x01, b'a',
+        0x1e,
+        0x01, 0x01,
+        0x02, 0x00, 0x00, 0x00, 0x3c,
+        0x03, 0x00, 0x01, b't',
+        0x08, 0x00, 0x01, b'r',
+        0x09, 0x00, 0x01, b'c',
+        0x0b, 0x01,
+        0x0b, 0x02,
+        0x26, 0x00, 0x01, b'k', 0x00, 0x01, b'v',
+        b'b',
+    ]);
+    let mut client = mock.client();
+
+    let message = client.receive().await.unwrap();
+    let properties = message.properties;
+    assert!(properties.utf8_payload());
+    assert_eq!(properties.message_expiry_interval(), Some(60));
+    assert_eq!(properties.content_type(), Some("t"));
+    assert_eq!(properties.response_topic(), Some("r"));
+    assert_eq!(properties.correlation_data(), Some(&b"c"[..]));
+    assert!(properties.subscription_identifiers().eq([1, 2]));
+    assert!(properties.user_properties().eq([("k", "v")]));
+    assert_eq!(message.payload, b"b");
+}
+
+#[tokio::test]
+async fn test_receive_message_leaked() {
+    let mock = common::Mock::new();
+    mock.push_rx(PUBLISH);
+    let mut client = mock.client();
+
+    // A leaked message is never released and received again.
+    std::mem::forget(client.receive().await.unwrap());
+    assert_eq!(client.receive().await.unwrap().topic, "a");
+    assert!(client.try_receive().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_receive_or_message() {
+    let mock = common::Mock::new();
+    mock.push_rx(PUBLISH);
+    let mut client = mock.client();
+
+    // Messages take precedence over a ready future.
+    let result = client.receive_or(std::future::ready(())).await.unwrap();
+    assert!(matches!(result, ReceiveOr::Message(message) if message.topic == "a"));
+}
+
+#[tokio::test]
+async fn test_receive_or_other() {
+    let mock = common::Mock::new();
+    mock.push_rx(&PUBLISH[..3]);
+    mock.stall();
+    let mut client = mock.client();
+
+    // The incomplete message is buffered, while the other future completes.
+    let result = client.receive_or(std::future::ready(42)).await.unwrap();
+    assert!(matches!(result, ReceiveOr::Other(42)));
+    drop(result);
+
+    mock.push_rx(&PUBLISH[3..]);
+    assert_eq!(client.receive().await.unwrap().payload, b"b");
+}
+
+#[tokio::test]
+async fn test_receive_watermarks() {
+    static WATERMARKS: Mutex<Vec<Watermark>> = Mutex::new(Vec::new());
+
+    fn record(watermark: Watermark) {
+        WATERMARKS.lock().unwrap().push(watermark);
+    }
+
+    let mock = common::Mock::new();
+    mock.push_rx(&PUBLISH[..5]);
+    mock.push_rx(&PUBLISH[5..]);
+
+    let connection = miniqtt::Connection::builder(mock, [0; 10])
+        .with_watermarks(&[50, 90], record)
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+
+    client.receive().await.unwrap();
+
+    assert_eq!(
+        *WATERMARKS.lock().unwrap(),
+        [Watermark {
+            threshold: 50,
+            used: 5,
+            capacity: 10
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_receive_max_packet_size() {
+    let mock = common::Mock::new();
+    mock.push_rx(PUBLISH);
+
+    let connection = miniqtt::Connection::builder(mock, Vec::new())
+        .max_packet_size(PUBLISH.len() - 1)
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+
+    assert!(matches!(client.receive().await, Err(Error::PacketTooLarge)));
+}
+
+#[tokio::test]
+async fn test_receive_lazy_compaction() {
+    let mock = common::Mock::new();
+    mock.push_rx([PUBLISH, PUBLISH, PUBLISH].concat());
+
+    // The buffer only fits a single packet, it is compacted once it runs out of space.
+    let connection = miniqtt::Connection::builder(mock, [0; 10])
+        .compaction(Compaction::Lazy)
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+
+    for _ in 0..3 {
+        assert_eq!(client.receive().await.unwrap().payload, b"b");
+    }
+    assert!(client.try_receive().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_receive_threshold_compaction() {
+    let mock = common::Mock::new();
+    mock.push_rx([PUBLISH, PUBLISH, &PUBLISH[..3]].concat());
+    mock.push_rx(&PUBLISH[3..]);
+    mock.push_rx([PUBLISH, &PUBLISH[..3]].concat());
+    mock.push_rx(&PUBLISH[3..]);
+
+    // Compacts once more than a single packet was consumed.
+    let connection = miniqtt::Connection::builder(mock, [0; 32])
+        .compaction(Compaction::Threshold(PUBLISH.len()))
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+
+    for _ in 0..5 {
+        assert_eq!(client.receive().await.unwrap().payload, b"b");
+    }
+
+    let stats = client.stats();
+    assert_eq!(stats.compactions, 1);
+    assert_eq!(stats.compactions_skipped, 1);
+}
+
+#[tokio::test]
+async fn test_receive_skips_ping_response() {
+    let mock = common::Mock::new();
+    mock.push_rx([&[0xd0, 0x00][..], PUBLISH, &[0xd0, 0x00]].concat());
+    let mut client = mock.client();
+
+    assert_eq!(client.receive().await.unwrap().payload, b"b");
+    assert!(client.try_receive().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_receive_acknowledges_released_message() {
+    let mock = common::Mock::new();
+    // QoS 1 and QoS 2 messages with the identifiers 0x4e20 and 0x4e21.
+    mock.push_rx([0x32, 0x07, 0x00, 0x01, b'a', 0x4e, 0x20, 0x00, b'b']);
+    mock.push_rx([0x34, 0x07, 0x00, 0x01, b'a', 0x4e, 0x21, 0x00, b'b']);
+    let mut client = mock.client();
+
+    let message = client.receive().await.unwrap();
+    assert_eq!(message.qos, QoS::AtLeastOnce);
+    // The message is only acknowledged once it is released.
+    assert!(mock.take_tx().is_empty());
+    drop(message);
+
+    let message = client.receive().await.unwrap();
+    assert_eq!(message.qos, QoS::ExactlyOnce);
+    // PUBACK
+    assert_eq!(mock.take_tx(), [0x40, 0x02, 0x4e, 0x20]);
+    drop(message);
+
+    // The PUBREC is sent before the next packet.
+    client.publish("a", b"b").await.unwrap();
+    assert_eq!(
+        mock.take_tx(),
+        [&[0x50, 0x02, 0x4e, 0x21][..], PUBLISH].concat()
+    );
+}
+
+#[tokio::test]
+async fn test_try_receive_acknowledges_first() {
+    let mock = common::Mock::new();
+    mock.push_rx(
+        [
+            &[0x32, 0x07, 0x00, 0x01, b'a', 0x00, 0x01, 0x00, b'b'][..],
+            &[0x32, 0x07, 0x00, 0x01, b'a', 0x00, 0x02, 0x00, b'b'],
+        ]
+        .concat(),
+    );
+    let mut client = mock.client();
+
+    assert!(client.receive().await.is_ok());
+    // The next message is only returned once the previous one is acknowledged.
+    assert!(client.try_receive().unwrap().is_none());
+    assert_eq!(client.buffered_packets(), 1);
+
+    assert!(client.receive().await.is_ok());
+    assert_eq!(mock.take_tx(), [0x40, 0x02, 0x00, 0x01]);
+}
+
+#[tokio::test]
+async fn test_receive_exactly_once() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x34, 0x07, 0x00, 0x01, b'a', 0x00, 0x01, 0x00, b'b']);
+    #[rustfmt::skip]
+    mock.push_rx([
+        // Duplicate of the first message.
+        0x3c, 0x07, 0x00, 0x01, b'a', 0x00, 0x01, 0x00, b'b',
+        // PUBREL
+        0x62, 0x02, 0x00, 0x01,
+        // A new message, re-using the released identifier.
+        0x34, 0x07, 0x00, 0x01, b'a', 0x00, 0x01, 0x00, b'c',
+    ]);
+    let mut client = mock.client();
+
+    assert_eq!(client.receive().await.unwrap().payload, b"b");
+    // The duplicate is acknowledged again, but not delivered.
+    assert_eq!(client.receive().await.unwrap().payload, b"c");
+    #[rustfmt::skip]
+    let expected: &[u8] = &[
+        // PUBREC
+        0x50, 0x02, 0x00, 0x01,
+        0x50, 0x02, 0x00, 0x01,
+        // PUBCOMP
+        0x70, 0x02, 0x00, 0x01,
+    ];
+    assert_eq!(mock.take_tx(), expected);
+}
+
+#[tokio::test]
+async fn test_receive_completes_release() {
+    let mock = common::Mock::new();
+    // PUBRELs of a resumed session, followed by a message.
+    mock.push_rx(
+        [
+            &[0x62, 0x02, 0x00, 0x01, 0x62, 0x02, 0x00, 0x02][..],
+            PUBLISH,
+        ]
+        .concat(),
+    );
+    let mut client = mock.client();
+
+    assert_eq!(client.receive().await.unwrap().payload, b"b");
+    assert_eq!(mock.take_tx(), [0x70, 0x02, 0x00, 0x01]);
+
+    // The second release is answered before the next packet is sent.
+    client.publish("a", b"b").await.unwrap();
+    assert_eq!(
+        mock.take_tx(),
+        [&[0x70, 0x02, 0x00, 0x02][..], PUBLISH].concat()
+    );
+}
+
+#[tokio::test]
+async fn test_events_complete_release() {
+    let mock = common::Mock::new();
+    mock.push_rx([&[0x62, 0x02, 0x00, 0x01][..], PUBLISH].concat());
+    let mut client = mock.client();
+
+    let mut events = client.events();
+    assert!(matches!(&*events.next().await.unwrap(), Event::Publish(_)));
+    // Nothing is read afterwards, the release is answered once parsing continues.
+    assert!(events.try_next().unwrap().is_none());
+
+    client.publish("a", b"b").await.unwrap();
+    assert_eq!(
+        mock.take_tx(),
+        [&[0x70, 0x02, 0x00, 0x01][..], PUBLISH].concat()
+    );
+}
+
+#[tokio::test]
+async fn test_receive_skips_unexpected_completion() {
+    let mock = common::Mock::new();
+    mock.push_rx([&[0x70, 0x02, 0x00, 0x01][..], PUBLISH].concat());
+    let mut client = mock.client();
+
+    assert_eq!(client.receive().await.unwrap().payload, b"b");
+    assert!(mock.take_tx().is_empty());
+}
+
+#[tokio::test]
+async fn test_receive_multiple_packets_in_one_read() {
+    let mock = common::Mock::new();
+    mock.push_rx([PUBLISH, PUBLISH, PUBLISH, &PUBLISH[..3]].concat());
+    let mut client = mock.client();
+
+    assert_eq!(client.buffered_packets(), 0);
+    assert_eq!(client.receive().await.unwrap().payload, b"b");
+    assert_eq!(client.buffered_packets(), 2);
+
+    // Buffered packets are received without reading, the connection is already closed.
+    for remaining in (0..2).rev() {
+        assert_eq!(client.receive().await.unwrap().payload, b"b");
+        assert_eq!(client.buffered_packets(), remaining);
+    }
+    assert!(matches!(client.receive().await, Err(Error::Disconnected)));
+}
+
+#[tokio::test]
+async fn test_receive_multiple_packets_fixed_buffer() {
+    let mock = common::Mock::new();
+    mock.push_rx([PUBLISH, PUBLISH].concat());
+    mock.push_rx(PUBLISH);
+
+    // Complete packets are parsed in place, the buffer is compacted before reading again.
+    let connection = miniqtt::Connection::builder(mock, [0; 16]).build();
+    let mut client = miniqtt::Client::new(connection);
+
+    for _ in 0..3 {
+        assert_eq!(client.receive().await.unwrap().payload, b"b");
+    }
+    assert_eq!(client.buffered_packets(), 0);
+}
+
+#[tokio::test]
+async fn test_events() {
+    let mock = common::Mock::new();
+    // PINGRESP, PUBLISH, DISCONNECT with Server shutting down.
+    mock.push_rx([&[0xd0, 0x00][..], PUBLISH, &[0xe0, 0x01, 0x8b]].concat());
+    let mut client = mock.client();
+    let mut events = client.events();
+
+    assert!(matches!(*events.next().await.unwrap(), Event::PingResp(_)));
+    match &*events.next().await.unwrap() {
+        Event::Publish(message) => assert_eq!(message.payload, b"b"),
+        event => panic!("expected a publish, got: {event:?}"),
+    }
+    match &*events.next().await.unwrap() {
+        Event::Disconnect(disconnect) => {
+            assert_eq!(disconnect.reason, DisconnectReason::ServerShuttingDown)
+        }
+        event => panic!("expected a disconnect, got: {event:?}"),
+    }
+    assert!(events.try_next().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_events_server_shutdown() {
+    let mock = common::Mock::new();
+    // The server closes the connection right after the DISCONNECT, nothing must be read anymore.
+    mock.push_rx([PUBLISH, &[0xe0, 0x01, 0x8b]].concat());
+    mock.stall();
+    let mut client = mock.client();
+    let mut events = client.events();
+
+    assert!(matches!(*events.next().await.unwrap(), Event::Publish(_)));
+    match &*events.next().await.unwrap() {
+        Event::Disconnect(disconnect) => {
+            assert_eq!(disconnect.reason, DisconnectReason::ServerShuttingDown)
+        }
+        event => panic!("expected a disconnect, got: {event:?}"),
+    }
+    assert!(matches!(events.next().await, Err(Error::Disconnected)));
+}
+
+#[tokio::test]
+async fn test_receive_server_shutdown() {
+    let mock = common::Mock::new();
+    mock.push_rx([PUBLISH, &[0xe0, 0x01, 0x8b]].concat());
+    mock.stall();
+    let mut client = mock.client();
+
+    assert_eq!(client.receive().await.unwrap().payload, b"b");
+    assert!(matches!(
+        client.receive().await,
+        Err(Error::ServerDisconnect(
+            DisconnectReason::ServerShuttingDown
+        ))
+    ));
+    assert!(matches!(client.receive().await, Err(Error::Disconnected)));
+}
+
+#[tokio::test]
+async fn test_events_unexpected_ack() {
+    let mock = common::Mock::new();
+    // SUBACK, without a pending subscription.
+    mock.push_rx([0x90, 0x04, 0x00, 0x01, 0x00, 0x00]);
+    let mut client = mock.client();
+
+    assert!(matches!(client.events().next().await, Err(Error::Protocol)));
+}
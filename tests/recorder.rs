@@ -0,0 +1,120 @@
+use miniqtt::client::{Direction, Error, Frames, Recorder};
+use miniqtt::transport::{Replay, ReplayError};
+
+mod common;
+
+/// Successful CONNACK without properties.
+const CONNACK: &[u8] = &[0x20, 0x03, 0x00, 0x00, 0x00];
+
+fn client(mock: &common::Mock) -> miniqtt::Client<common::Mock, Vec<u8>> {
+    let recorder = Recorder::new(Box::leak(Box::new([0; 256])), || 42);
+    let connection = miniqtt::Connection::builder(mock.clone(), Vec::new())
+        .with_recorder(recorder)
+        .build();
+    miniqtt::Client::new(connection)
+}
+
+#[tokio::test]
+async fn test_recorder_frames() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+    let mut client = client(&mock);
+
+    client.connect("miniqtt").await.unwrap();
+
+    let frames: Vec<_> = client.recorder().unwrap().frames().collect();
+    assert_eq!(frames.len(), 2);
+
+    assert_eq!(frames[0].direction, Direction::Sent);
+    assert_eq!(frames[0].data, mock.take_tx());
+    assert_eq!(frames[1].direction, Direction::Received);
+    assert_eq!(frames[1].data, CONNACK);
+
+    assert!(
+        frames
+            .iter()
+            .all(|f| f.timestamp == 42 && !f.is_truncated())
+    );
+}
+
+#[tokio::test]
+async fn test_recorder_protocol_error() {
+    let mock = common::Mock::new();
+    // CONNACK with an invalid reason code.
+    mock.push_rx([0x20, 0x03, 0x00, 0xff, 0x00]);
+    let mut client = client(&mock);
+
+    let err = client.connect("miniqtt").await.unwrap_err();
+    assert!(matches!(err, Error::Protocol));
+
+    let frame = client.recorder().unwrap().frames().last().unwrap();
+    assert_eq!(frame.direction, Direction::Received);
+    assert_eq!(frame.data, [0x20, 0x03, 0x00, 0xff, 0x00]);
+}
+
+/// Records a session, which connects and receives a single message.
+async fn record_session() -> Vec<u8> {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+    mock.push_rx([0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'b']);
+    let mut client = client(&mock);
+
+    client.connect("miniqtt").await.unwrap();
+    assert_eq!(client.receive().await.unwrap().payload, b"b");
+
+    client.recorder().unwrap().as_bytes().to_vec()
+}
+
+#[tokio::test]
+async fn test_recorder_replay() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), record_session().await).unwrap();
+    let capture = std::fs::read(file.path()).unwrap();
+
+    let connection = miniqtt::Connection::new(Replay::new(Frames::new(&capture)), Vec::new());
+    let mut client = miniqtt::Client::new(connection);
+
+    client.connect("miniqtt").await.unwrap();
+    assert_eq!(client.receive().await.unwrap().payload, b"b");
+    assert!(matches!(client.receive().await, Err(Error::Disconnected)));
+}
+
+#[tokio::test]
+async fn test_recorder_replay_diverged() {
+    let capture = record_session().await;
+
+    let connection = miniqtt::Connection::new(Replay::new(Frames::new(&capture)), Vec::new());
+    let mut client = miniqtt::Client::new(connection);
+
+    let err = client.connect("other").await.unwrap_err();
+    // Only the packet type matches the recorded connect, the packet was partially sent.
+    assert!(matches!(
+        err,
+        Error::Write {
+            packet_type: 1,
+            written: 1,
+            error: ReplayError::Diverged
+        }
+    ));
+}
+
+#[tokio::test]
+async fn test_recorder_replay_nothing_sent() {
+    let capture = record_session().await;
+
+    let connection = miniqtt::Connection::new(Replay::new(Frames::new(&capture)), Vec::new());
+    let mut client = miniqtt::Client::new(connection);
+
+    client.connect("miniqtt").await.unwrap();
+    assert_eq!(client.receive().await.unwrap().payload, b"b");
+
+    let err = client.publish("a", b"c").await.unwrap_err();
+    assert!(matches!(
+        err,
+        Error::Write {
+            packet_type: 3,
+            written: 0,
+            error: ReplayError::Diverged
+        }
+    ));
+}
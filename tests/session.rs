@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use miniqtt::protocol::v5::ConnectProperty;
+
+mod common;
+
+/// Successful CONNACK without properties.
+const CONNACK: &[u8] = &[0x20, 0x03, 0x00, 0x00, 0x00];
+#[cfg(feature = "properties")]
+/// Successful CONNACK with a session expiry interval of 300 seconds.
+const CONNACK_SESSION_EXPIRY: &[u8] = &[0x20, 0x08, 0x00, 0x00, 0x05, 0x11, 0x00, 0x00, 0x01, 0x2c];
+
+#[tokio::test]
+async fn test_session_expiry_default() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+    let mut client = mock.client();
+
+    client.connect("miniqtt").await.unwrap();
+    assert_eq!(client.session_expiry_interval(), 0);
+    assert_eq!(
+        client.session_expires_in(Duration::ZERO),
+        Some(Duration::ZERO)
+    );
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_session_expiry_requested() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+    let mut client = mock.client();
+
+    let properties = [ConnectProperty::SessionExpiryInterval(120)];
+    client
+        .connect("miniqtt")
+        .with_properties(&properties)
+        .await
+        .unwrap();
+
+    assert_eq!(client.session_expiry_interval(), 120);
+    assert_eq!(
+        client.session_expires_in(Duration::from_secs(20)),
+        Some(Duration::from_secs(100))
+    );
+    assert_eq!(
+        client.session_expires_in(Duration::from_secs(200)),
+        Some(Duration::ZERO)
+    );
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_session_expiry_server_assigned() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK_SESSION_EXPIRY);
+    let mut client = mock.client();
+
+    let properties = [ConnectProperty::SessionExpiryInterval(u32::MAX)];
+    client
+        .connect("miniqtt")
+        .with_properties(&properties)
+        .await
+        .unwrap();
+
+    assert_eq!(client.session_expiry_interval(), 300);
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_session_expiry_never() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+    let mut client = mock.client();
+
+    let properties = [ConnectProperty::SessionExpiryInterval(u32::MAX)];
+    client
+        .connect("miniqtt")
+        .with_properties(&properties)
+        .await
+        .unwrap();
+
+    assert_eq!(client.session_expires_in(Duration::from_secs(3600)), None);
+}
+
+#[cfg(not(feature = "properties"))]
+#[tokio::test]
+async fn test_session_expiry_without_properties() {
+    let mock = common::Mock::new();
+    mock.push_rx(CONNACK);
+    let mut client = mock.client();
+
+    // The session expiry interval is never sent, the server ends the session with the connection.
+    let properties = [ConnectProperty::SessionExpiryInterval(120)];
+    client
+        .connect("miniqtt")
+        .with_properties(&properties)
+        .await
+        .unwrap();
+
+    assert_eq!(client.session_expiry_interval(), 0);
+}
@@ -0,0 +1,36 @@
+#![cfg(feature = "shadow")]
+
+use std::time::Duration;
+
+use miniqtt::shadow::{Shadow, TopicScheme};
+
+mod common;
+
+#[tokio::test]
+async fn test_shadow_report_and_desired() {
+    let mock = common::Mock::new();
+    // PUBLISH on the desired topic `d/desired`, followed by one on an unrelated topic.
+    mock.push_rx([
+        0x30, 0x0e, 0x00, 0x09, b'd', b'/', b'd', b'e', b's', b'i', b'r', b'e', b'd', 0x00, b'o',
+        b'n',
+    ]);
+    mock.push_rx([0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'b']);
+    let mut client = mock.client();
+
+    let shadow = Shadow::<64>::new("d", TopicScheme::new(), Duration::from_secs(60)).unwrap();
+    shadow.report(&mut client, b"off").await.unwrap();
+    assert_eq!(
+        mock.take_tx(),
+        [
+            0x31, 0x10, 0x00, 0x0a, b'd', b'/', b'r', b'e', b'p', b'o', b'r', b't', b'e', b'd',
+            0x00, b'o', b'f', b'f'
+        ]
+    );
+
+    let message = client.receive().await.unwrap();
+    assert_eq!(shadow.desired(&message), Some(&b"on"[..]));
+    drop(message);
+
+    let message = client.receive().await.unwrap();
+    assert_eq!(shadow.desired(&message), None);
+}
@@ -0,0 +1,144 @@
+#![cfg(feature = "embedded-hal-async-1")]
+
+use std::time::{Duration, Instant};
+
+use miniqtt::client::{DelayTimeout, Error};
+
+mod common;
+
+/// Successful CONNACK without properties.
+const CONNACK: &[u8] = &[0x20, 0x03, 0x00, 0x00, 0x00];
+
+fn client(
+    sim: &common::Sim,
+    timeout_ms: u32,
+) -> miniqtt::Client<common::Sim, Vec<u8>, DelayTimeout<common::SimDelay>> {
+    let connection = miniqtt::Connection::new(sim.clone(), Vec::new());
+    miniqtt::Client::with_timeout(connection, DelayTimeout::new(sim.delay(), timeout_ms))
+}
+
+/// Connects until the CONNACK is not dropped, returns the amount of attempts.
+fn connect_with_retries(sim: &common::Sim) -> u32 {
+    let mut client = client(sim, 1000);
+
+    sim.run(async {
+        for attempt in 1.. {
+            sim.push_rx(CONNACK);
+            match client.connect("miniqtt").await {
+                Ok(res) if res.successful() => return attempt,
+                Err(Error::Timeout) => continue,
+                res => panic!("unexpected connect result: {res:?}"),
+            }
+        }
+        unreachable!()
+    })
+}
+
+#[test]
+fn test_sim_timeout_virtual_time() {
+    let sim = common::Sim::new(1);
+    let mut client = client(&sim, 30_000);
+
+    let start = Instant::now();
+    let res = sim.run(client.connect("miniqtt"));
+
+    assert!(matches!(res, Err(Error::Timeout)));
+    assert_eq!(sim.now(), Duration::from_secs(30));
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn test_sim_latency() {
+    let sim = common::Sim::new(1).latency(Duration::from_millis(100), Duration::from_millis(300));
+    let mut client = client(&sim, 1000);
+
+    sim.push_rx(CONNACK);
+    let res = sim.run(client.connect("miniqtt")).unwrap();
+
+    assert!(res.successful());
+    assert!((Duration::from_millis(100)..=Duration::from_millis(300)).contains(&sim.now()));
+}
+
+#[test]
+fn test_sim_drops_reproducible() {
+    let sim = |seed| {
+        common::Sim::new(seed)
+            .latency(Duration::from_millis(50), Duration::from_millis(150))
+            .drop_rate(50)
+    };
+
+    let runs: Vec<_> = (1..=10)
+        .map(|seed| {
+            let sim = sim(seed);
+            (connect_with_retries(&sim), sim.now())
+        })
+        .collect();
+
+    // The same seed yields the same outcome.
+    for (seed, run) in (1..=10).zip(&runs) {
+        let sim = sim(seed);
+        assert_eq!((connect_with_retries(&sim), sim.now()), *run);
+    }
+
+    // Every dropped CONNACK costs a timeout.
+    assert!(runs.iter().any(|&(attempts, _)| attempts > 1));
+    for &(attempts, now) in &runs {
+        assert!(now >= Duration::from_secs(u64::from(attempts - 1)));
+    }
+}
+
+#[test]
+fn test_sim_receive_with_pings() {
+    let sim = common::Sim::new(1);
+    let mut client = client(&sim, 1000);
+
+    sim.push_rx(CONNACK);
+    let res = sim.run(client.connect("miniqtt").keep_alive(10)).unwrap();
+    assert!(res.successful());
+    sim.take_tx();
+
+    // The PINGRESP is skipped and the message arrives after two keep alive intervals.
+    let sim = sim.latency(Duration::from_secs(25), Duration::from_secs(25));
+    sim.push_rx([0xd0, 0x00, 0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'b']);
+
+    let mut delay = sim.delay();
+    let message = sim.run(client.receive_with_pings(&mut delay)).unwrap();
+    assert_eq!(message.payload, b"b");
+    assert_eq!(sim.now(), Duration::from_secs(25));
+    assert_eq!(sim.take_tx(), [0xc0, 0x00, 0xc0, 0x00]);
+}
+
+#[test]
+fn test_sim_receive_with_pings_clock() {
+    let sim = common::Sim::new(1);
+    let connection = miniqtt::Connection::builder(sim.clone(), Vec::new())
+        .with_clock(Box::leak(Box::new(sim.clone())))
+        .build();
+    let mut client = miniqtt::Client::new(connection);
+    assert_eq!(client.last_tx_at(), None);
+    assert_eq!(client.last_rx_at(), None);
+
+    sim.push_rx(CONNACK);
+    let res = sim.run(client.connect("miniqtt").keep_alive(10)).unwrap();
+    assert!(res.successful());
+    assert_eq!(client.last_tx_at(), Some(Duration::ZERO));
+    assert_eq!(client.last_rx_at(), Some(Duration::ZERO));
+    sim.take_tx();
+
+    let mut delay = sim.delay();
+    sim.run(embedded_hal_async::delay::DelayNs::delay_ms(
+        &mut delay, 4000,
+    ));
+
+    // Pings are due one keep alive after the CONNECT, not after the start of the call.
+    let sim = sim.latency(Duration::from_secs(18), Duration::from_secs(18));
+    sim.push_rx([0xd0, 0x00, 0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'b']);
+
+    let message = sim.run(client.receive_with_pings(&mut delay)).unwrap();
+    assert_eq!(message.payload, b"b");
+    drop(message);
+    assert_eq!(sim.now(), Duration::from_secs(22));
+    assert_eq!(sim.take_tx(), [0xc0, 0x00, 0xc0, 0x00]);
+    assert_eq!(client.last_tx_at(), Some(Duration::from_secs(20)));
+    assert_eq!(client.last_rx_at(), Some(Duration::from_secs(22)));
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "sparkplug")]
+
+use miniqtt::client::Error;
+use miniqtt::protocol::ValidationError;
+use miniqtt::sparkplug::{EdgeNode, MessageType};
+
+mod common;
+
+#[tokio::test]
+async fn test_sparkplug_publish() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    let node = EdgeNode::<32>::new("g", "n").unwrap();
+    node.publish(&mut client, MessageType::NBirth, None, b"b")
+        .await
+        .unwrap();
+    node.publish(&mut client, MessageType::DData, Some("d"), b"x")
+        .await
+        .unwrap();
+
+    #[rustfmt::skip]
+    let expected: &[u8] = &[
+        0x30, 0x16, 0x00, 0x12,
+        b's', b'p', b'B', b'v', b'1', b'.', b'0', b'/', b'g', b'/',
+        b'N', b'B', b'I', b'R', b'T', b'H', b'/', b'n',
+        0x00, b'b',
+        0x30, 0x17, 0x00, 0x13,
+        b's', b'p', b'B', b'v', b'1', b'.', b'0', b'/', b'g', b'/',
+        b'D', b'D', b'A', b'T', b'A', b'/', b'n', b'/', b'd',
+        0x00, b'x',
+    ];
+    assert_eq!(mock.take_tx(), expected);
+}
+
+#[tokio::test]
+async fn test_sparkplug_publish_invalid_device() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    let node = EdgeNode::<32>::new("g", "n").unwrap();
+    let err = node
+        .publish(&mut client, MessageType::DData, None, b"x")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Invalid(ValidationError::Empty)));
+    let err = node
+        .publish(&mut client, MessageType::DData, Some("d/+"), b"x")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Invalid(ValidationError::Wildcard)));
+    assert!(mock.take_tx().is_empty());
+}
+
+#[tokio::test]
+async fn test_sparkplug_death_will() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    let mut client = mock.client();
+
+    let node = EdgeNode::<32>::new("g", "n").unwrap();
+    client
+        .connect("n")
+        .with_will(node.death(b"d"))
+        .await
+        .unwrap();
+
+    // Will flag with QoS 1, without retain, followed by the will topic and payload.
+    let tx = mock.take_tx();
+    assert_eq!(tx[9], 0x0e);
+    assert!(tx.ends_with(b"spBv1.0/g/NDEATH/n\x00\x01d"));
+}
@@ -0,0 +1,342 @@
+#![cfg(all(feature = "subscribe", feature = "unsubscribe"))]
+
+use std::time::Duration;
+
+use miniqtt::client::Error;
+use miniqtt::entropy::EntropySource;
+use miniqtt::protocol::types::PacketId;
+#[cfg(feature = "properties")]
+use miniqtt::protocol::v5::SubscribeProperty;
+use miniqtt::protocol::v5::{RetainHandling, SubAckReason, TopicFilter, UnsubAckReason};
+use miniqtt::protocol::{QoS, ValidationError};
+
+mod common;
+
+/// SUBACK for the first identifier used by a client with the passed reason code.
+fn sub_ack(reason: u8) -> [u8; 6] {
+    [0x90, 0x04, 0x4e, 0x20, 0x00, reason]
+}
+
+#[tokio::test]
+async fn test_subscribe_granted() {
+    let mock = common::Mock::new();
+    mock.push_rx(sub_ack(0x00));
+    let mut client = mock.client();
+
+    let reason = client.subscribe("a").await.unwrap();
+    assert_eq!(reason, SubAckReason::GrantedQoS0);
+    assert_eq!(reason.granted_qos(), Some(QoS::AtMostOnce));
+}
+
+#[tokio::test]
+async fn test_subscribe_options() {
+    let mock = common::Mock::new();
+    mock.push_rx(sub_ack(0x01));
+    let mut client = mock.client();
+
+    let reason = client
+        .subscribe("a")
+        .qos(QoS::AtLeastOnce)
+        .no_local(true)
+        .retain_as_published(true)
+        .retain_handling(RetainHandling::DoNotSendRetained)
+        .await
+        .unwrap();
+    assert_eq!(reason.granted_qos(), Some(QoS::AtLeastOnce));
+
+    // Identifier, empty properties, topic `a` and the subscription options.
+    assert_eq!(
+        mock.take_tx(),
+        [0x82, 0x07, 0x4e, 0x20, 0x00, 0x00, 0x01, b'a', 0x2d]
+    );
+}
+
+#[cfg(feature = "properties")]
+#[tokio::test]
+async fn test_subscribe_properties() {
+    let mock = common::Mock::new();
+    mock.push_rx(sub_ack(0x00));
+    let mut client = mock.client();
+
+    client
+        .subscribe("a")
+        .with_properties(&[SubscribeProperty::SubscriptionIdentifier(5)])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        mock.take_tx(),
+        [
+            0x82, 0x09, 0x4e, 0x20, 0x02, 0x0b, 0x05, 0x00, 0x01, b'a', 0x00
+        ]
+    );
+}
+
+fn filter(name: &str) -> TopicFilter<'_> {
+    TopicFilter {
+        name,
+        qos: QoS::AtLeastOnce,
+        no_local: false,
+        retain_as_published: false,
+        retain: RetainHandling::SendRetained,
+    }
+}
+
+#[tokio::test]
+async fn test_subscribe_many() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x90, 0x05, 0x4e, 0x20, 0x00, 0x01, 0x87]);
+    let mut client = mock.client();
+
+    let granted = client
+        .subscribe_many(&[filter("a"), filter("b")])
+        .await
+        .unwrap();
+    assert_eq!(
+        granted,
+        [Ok(QoS::AtLeastOnce), Err(SubAckReason::NotAuthorized)]
+    );
+
+    // Both topic filters are sent in a single SUBSCRIBE.
+    assert_eq!(
+        mock.take_tx(),
+        [
+            0x82, 0x0b, 0x4e, 0x20, 0x00, 0x00, 0x01, b'a', 0x01, 0x00, 0x01, b'b', 0x01
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_subscribe_many_reason_mismatch() {
+    let mock = common::Mock::new();
+    // Only a single reason for two topic filters.
+    mock.push_rx(sub_ack(0x01));
+    let mut client = mock.client();
+
+    let err = client
+        .subscribe_many(&[filter("a"), filter("b")])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Protocol));
+}
+
+#[tokio::test]
+async fn test_subscribe_many_empty() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    let err = client.subscribe_many(&[]).await.unwrap_err();
+    assert!(matches!(err, Error::Invalid(_)));
+    assert!(mock.take_tx().is_empty());
+}
+
+#[tokio::test]
+async fn test_subscribe_rejected() {
+    let mock = common::Mock::new();
+    mock.push_rx(sub_ack(0x8f));
+    let mut client = mock.client();
+
+    let err = client.subscribe("a").await.unwrap_err();
+    assert!(matches!(
+        err,
+        Error::SubscriptionRejected(SubAckReason::TopicFilterInvalid)
+    ));
+}
+
+#[tokio::test]
+async fn test_subscribe_rejected_transient() {
+    let mock = common::Mock::new();
+    mock.push_rx(sub_ack(0x97));
+    let mut client = mock.client();
+
+    let Err(Error::SubscriptionRejected(reason)) = client.subscribe("a").await else {
+        panic!("subscription must be rejected");
+    };
+    assert_eq!(reason, SubAckReason::QuotaExceeded);
+    assert!(reason.is_transient());
+}
+
+#[tokio::test]
+async fn test_subscribe_identifier_mismatch() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x90, 0x04, 0x00, 0x01, 0x00, 0x00]);
+    let mut client = mock.client();
+
+    let err = client.subscribe("a").await.unwrap_err();
+    assert!(matches!(err, Error::Protocol));
+}
+
+#[tokio::test]
+async fn test_subscribe_seeded_identifier() {
+    struct Fixed;
+
+    impl EntropySource for Fixed {
+        fn next_u32(&mut self) -> u32 {
+            0x1234_0042
+        }
+    }
+
+    let mock = common::Mock::new();
+    mock.push_rx([0x90, 0x04, 0x00, 0x42, 0x00, 0x00]);
+    let mut client = mock.client();
+    client.seed_identifiers(Fixed);
+
+    client.subscribe("a").await.unwrap();
+    assert_eq!(mock.take_tx()[2..4], [0x00, 0x42]);
+}
+
+#[tokio::test]
+async fn test_subscribe_resume() {
+    let mock = common::Mock::new();
+    mock.stall();
+    let mut client = mock.client();
+
+    let res = tokio::time::timeout(Duration::from_millis(10), client.subscribe("a")).await;
+    assert!(res.is_err());
+    assert_eq!(client.pending_subscribe().map(PacketId::get), Some(0x4e20));
+
+    mock.push_rx(sub_ack(0x01));
+    assert_eq!(
+        client.resume_subscribe().await.unwrap(),
+        Some(SubAckReason::GrantedQoS1)
+    );
+    assert_eq!(client.pending_subscribe(), None);
+
+    // Nothing is pending anymore.
+    assert_eq!(client.resume_subscribe().await.unwrap(), None);
+    // The subscription was only sent once.
+    let tx = mock.take_tx();
+    assert_eq!(tx[0], 0x82);
+    assert_eq!(tx.len(), 2 + usize::from(tx[1]));
+}
+
+#[tokio::test]
+async fn test_subscribe_after_cancelled() {
+    let mock = common::Mock::new();
+    mock.stall();
+    let mut client = mock.client();
+
+    let res = tokio::time::timeout(Duration::from_millis(10), client.subscribe("a")).await;
+    assert!(res.is_err());
+
+    // The acknowledgement of the cancelled subscription is skipped.
+    mock.push_rx([&sub_ack(0x8f)[..], &[0x90, 0x04, 0x4e, 0x21, 0x00, 0x00]].concat());
+    client.subscribe("b").await.unwrap();
+    assert_eq!(client.pending_subscribe(), None);
+}
+
+#[tokio::test]
+async fn test_subscribe_cancelled_then_receive() {
+    let mock = common::Mock::new();
+    mock.stall();
+    let mut client = mock.client();
+
+    let res = tokio::time::timeout(Duration::from_millis(10), client.subscribe("a")).await;
+    assert!(res.is_err());
+
+    // The late acknowledgement is consumed while receiving, instead of failing every receive.
+    mock.push_rx(
+        [
+            &sub_ack(0x01)[..],
+            &[0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'b'],
+        ]
+        .concat(),
+    );
+    let message = client.receive().await.unwrap();
+    assert_eq!(message.topic, "a");
+    assert_eq!(message.payload, b"b");
+    drop(message);
+
+    // The result stays available, without reading from the connection.
+    assert_eq!(client.pending_subscribe().map(PacketId::get), Some(0x4e20));
+    assert_eq!(
+        client.resume_subscribe().await.unwrap(),
+        Some(SubAckReason::GrantedQoS1)
+    );
+    assert_eq!(client.pending_subscribe(), None);
+}
+
+#[tokio::test]
+async fn test_unsubscribe() {
+    let mock = common::Mock::new();
+    mock.push_rx([0xb0, 0x04, 0x4e, 0x20, 0x00, 0x11]);
+    let mut client = mock.client();
+
+    // Not being subscribed is not an error.
+    client.unsubscribe("a").await.unwrap();
+    assert_eq!(
+        mock.take_tx(),
+        [0xa2, 0x06, 0x4e, 0x20, 0x00, 0x00, 0x01, b'a']
+    );
+}
+
+#[tokio::test]
+async fn test_unsubscribe_rejected() {
+    let mock = common::Mock::new();
+    mock.push_rx([0xb0, 0x04, 0x4e, 0x20, 0x00, 0x87]);
+    let mut client = mock.client();
+
+    let err = client.unsubscribe("a").await.unwrap_err();
+    assert!(matches!(
+        err,
+        Error::UnsubscribeRejected(UnsubAckReason::NotAuthorized)
+    ));
+}
+
+#[tokio::test]
+async fn test_reason_stats() {
+    let mock = common::Mock::new();
+    mock.push_rx([0x20, 0x03, 0x00, 0x00, 0x00]);
+    mock.push_rx(sub_ack(0x97));
+    mock.push_rx([0x90, 0x04, 0x4e, 0x21, 0x00, 0x00]);
+    mock.push_rx([0xb0, 0x04, 0x4e, 0x22, 0x00, 0x87]);
+    let mut client = mock.client();
+
+    client.connect("miniqtt").await.unwrap();
+    client.subscribe("a").await.unwrap_err();
+    client.subscribe("a").await.unwrap();
+    client.unsubscribe("a").await.unwrap_err();
+
+    let reasons = client.stats().reasons;
+    assert_eq!(
+        reasons.iter().collect::<Vec<_>>(),
+        [(0x00, 2), (0x87, 1), (0x97, 1)]
+    );
+    assert_eq!(reasons.failures(), 2);
+}
+
+#[tokio::test]
+async fn test_fetch_retained() {
+    let mock = common::Mock::new();
+    mock.push_rx(sub_ack(0x00));
+    // A live message on another topic is dropped.
+    mock.push_rx([0x30, 0x05, 0x00, 0x01, b'b', 0x00, b'x']);
+    mock.push_rx([0x31, 0x06, 0x00, 0x01, b'a', 0x00, b'o', b'k']);
+    mock.push_rx([0xb0, 0x04, 0x4e, 0x21, 0x00, 0x00]);
+    let mut client = mock.client();
+
+    let config = client
+        .fetch_retained("a", |payload| payload.to_vec())
+        .await
+        .unwrap();
+    assert_eq!(config.as_deref(), Some(&b"ok"[..]));
+
+    // Subscribed and unsubscribed again.
+    let tx = mock.take_tx();
+    assert_eq!(tx[0], 0x82);
+    assert_eq!(tx[2 + usize::from(tx[1])], 0xa2);
+}
+
+#[tokio::test]
+async fn test_fetch_retained_wildcard() {
+    let mock = common::Mock::new();
+    let mut client = mock.client();
+
+    // A wildcard filter never matches the topic of a message.
+    let err = client
+        .fetch_retained("a/+", |payload| payload.to_vec())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Invalid(ValidationError::Wildcard)));
+    assert!(mock.take_tx().is_empty());
+}
@@ -0,0 +1,30 @@
+#![cfg(feature = "embedded-hal-async-1")]
+
+use std::time::Duration;
+
+use miniqtt::client::{DelayTimeout, Error};
+
+mod common;
+
+struct TokioDelay;
+
+impl embedded_hal_async::delay::DelayNs for TokioDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        tokio::time::sleep(Duration::from_nanos(ns.into())).await;
+    }
+}
+
+#[tokio::test]
+async fn test_timeout_connect() {
+    let mock = common::Mock::new();
+    mock.stall();
+
+    let connection = miniqtt::Connection::new(mock.clone(), Vec::new());
+    let mut client = miniqtt::Client::with_timeout(connection, DelayTimeout::new(TokioDelay, 10));
+
+    let res = client.connect("miniqtt").await;
+    assert!(matches!(res, Err(Error::Timeout)));
+
+    // The connect packet was still sent.
+    assert_eq!(mock.take_tx()[0], 0x10);
+}
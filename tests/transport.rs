@@ -0,0 +1,72 @@
+use miniqtt::transport::Either;
+
+mod common;
+
+/// Successful CONNACK without properties.
+const CONNACK: &[u8] = &[0x20, 0x03, 0x00, 0x00, 0x00];
+
+#[tokio::test]
+async fn test_either_transport() {
+    let fallback = common::Mock::new();
+    fallback.push_rx(CONNACK);
+
+    let transport = Either::<common::Mock, _>::Right(fallback.clone());
+    let connection = miniqtt::Connection::new(transport, Vec::new());
+    let mut client = miniqtt::Client::new(connection);
+
+    let res = client.connect("miniqtt").await.unwrap();
+    assert!(res.successful());
+
+    assert_eq!(fallback.take_tx()[0], 0x10);
+}
+
+/// A blocking transport, which reads from a fixed input and collects everything written.
+#[cfg(feature = "std")]
+struct StdIo {
+    rx: std::io::Cursor<Vec<u8>>,
+    tx: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl std::io::Read for StdIo {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.rx.read(buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for StdIo {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tx.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_blocking_transport() {
+    use miniqtt::transport::{Blocking, block_on};
+
+    let mut rx = CONNACK.to_vec();
+    rx.extend([0x30, 0x05, 0x00, 0x01, b'a', 0x00, b'x']);
+    let transport = Blocking::new(StdIo {
+        rx: std::io::Cursor::new(rx),
+        tx: Vec::new(),
+    });
+    let mut client = miniqtt::Client::new(miniqtt::Connection::new(transport, Vec::new()));
+
+    let res = block_on(client.connect("miniqtt")).unwrap();
+    assert!(res.successful());
+
+    let message = block_on(client.receive()).unwrap();
+    assert_eq!(message.topic, "a");
+    assert_eq!(message.payload, b"x");
+    drop(message);
+
+    // The input is exhausted, which reads like a closed connection.
+    let err = block_on(client.receive()).unwrap_err();
+    assert!(matches!(err, miniqtt::client::Error::Disconnected));
+}